@@ -0,0 +1,96 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# dev.sh — developer tasks for safe-bash-hook.
+#
+# Usage:
+#   ./dev.sh gen-pattern-tests '<reason>' '<blocked command>' '<allowed command>'
+#
+# gen-pattern-tests scaffolds coverage for a DenyPattern you have just added to
+# src/patterns.rs (matched by its reason string):
+#   - an EXAMPLES entry, checked by the every_rule_has_block_and_allow_examples meta-test
+#   - <slug>_blocked / <slug>_no_false_positive test functions in the patterns.rs tests
+#   - a block and an allow case in test.sh
+# Review the generated code, then run: cargo test && ./test.sh target/debug/safe-bash-hook
+
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+PATTERNS="${SCRIPT_DIR}/src/patterns.rs"
+TESTS_SH="${SCRIPT_DIR}/test.sh"
+
+info()  { printf '\033[1;34m==>\033[0m %s\n' "$*"; }
+ok()    { printf '\033[1;32m  ✓\033[0m %s\n' "$*"; }
+die()   { printf '\033[1;31merror:\033[0m %s\n' "$*" >&2; exit 1; }
+
+usage() {
+  sed -n '4,7p' "$0" | sed 's/^# \{0,1\}//' >&2
+  exit 1
+}
+
+# Rust string literal
+rust_str() {
+  printf '"%s"' "$(printf '%s' "$1" | sed 's/\\/\\\\/g; s/"/\\"/g')"
+}
+
+# Single-quoted shell word
+sh_str() {
+  printf "'%s'" "$(printf '%s' "$1" | sed "s/'/'\\\\''/g")"
+}
+
+gen_pattern_tests() {
+  [ $# -eq 3 ] || usage
+  local reason="$1" blocked="$2" allowed="$3"
+
+  case "$reason$blocked$allowed" in
+    *$'\n'*|*$'\t'*) die "examples must be single-line without tabs (test.sh builds JSON with sed)" ;;
+  esac
+  grep -qF "$(rust_str "$reason"))," "$PATTERNS" \
+    || die "no DenyPattern with reason $(rust_str "$reason") in src/patterns.rs — add the rule first"
+  grep -qF "        ($(rust_str "$reason"), " "$PATTERNS" \
+    && die "EXAMPLES already has an entry for $(rust_str "$reason")"
+
+  local slug
+  slug="$(printf '%s' "$reason" | tr '[:upper:]' '[:lower:]' | sed 's/[^a-z0-9]\{1,\}/_/g; s/^_//; s/_$//')"
+  grep -q "fn ${slug}_blocked()" "$PATTERNS" && die "fn ${slug}_blocked already exists"
+
+  info "Scaffolding tests for $(rust_str "$reason")"
+
+  EXAMPLE="        ($(rust_str "$reason"), &[$(rust_str "$blocked")], &[$(rust_str "$allowed")]),"
+  TESTS="    #[test]
+    fn ${slug}_blocked() {
+        assert!(is_blocked($(rust_str "$blocked")));
+    }
+
+    #[test]
+    fn ${slug}_no_false_positive() {
+        assert!(is_allowed($(rust_str "$allowed")));
+    }
+"
+  export EXAMPLE TESTS
+  awk '
+    /^    \/\/ --- Rule coverage ---$/ { print ENVIRON["TESTS"] }
+    /^        \/\/ dev\.sh gen-pattern-tests: new entries go above this line$/ { print ENVIRON["EXAMPLE"] }
+    { print }
+  ' "$PATTERNS" > "$PATTERNS.tmp" && mv "$PATTERNS.tmp" "$PATTERNS"
+  ok "src/patterns.rs: EXAMPLES entry, ${slug}_blocked, ${slug}_no_false_positive"
+
+  # Append to the end of the BLOCK and ALLOW lists (the first blank line after their run_test lines)
+  BLOCK_CASE="$(printf 'run_test %-38s 2 %s' "$(sh_str "$reason")" "$(sh_str "$blocked")")"
+  ALLOW_CASE="$(printf 'run_test %-38s 0 %s' "$(sh_str "$reason (near miss)")" "$(sh_str "$allowed")")"
+  export BLOCK_CASE ALLOW_CASE
+  awk '
+    /^printf .Should BLOCK/ { section = "block"; seen = 0 }
+    /^printf .Should ALLOW/ { section = "allow"; seen = 0 }
+    section != "" && /^run_test / { seen = 1 }
+    section != "" && seen && /^$/ {
+      print (section == "block" ? ENVIRON["BLOCK_CASE"] : ENVIRON["ALLOW_CASE"])
+      section = ""
+    }
+    { print }
+  ' "$TESTS_SH" > "$TESTS_SH.tmp" && cat "$TESTS_SH.tmp" > "$TESTS_SH" && rm "$TESTS_SH.tmp"
+  ok "test.sh: block and allow cases"
+}
+
+case "${1:-}" in
+  gen-pattern-tests) shift; gen_pattern_tests "$@" ;;
+  *) usage ;;
+esac
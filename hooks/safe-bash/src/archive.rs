@@ -0,0 +1,342 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directories an archive should never be unpacked into.
+const SENSITIVE_DIRS: &[&str] = &[
+    "/etc", "/usr", "/bin", "/sbin", "/lib", "/lib64", "/boot", "/dev", "/sys", "/proc",
+];
+
+/// Home subdirectories where unpacked files change credentials or tool behavior.
+const SENSITIVE_HOME_DIRS: &[&str] =
+    &[".ssh", ".aws", ".gnupg", ".config", ".claude", ".local/bin"];
+
+/// Skip listing archives larger than this — peeking must stay fast.
+const MAX_PEEK_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Flag a zip whose contents expand past this many bytes...
+const BOMB_MIN_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// ...at more than this compression ratio.
+const BOMB_MIN_RATIO: u64 = 100;
+
+/// What an extraction command asks for.
+#[derive(Debug, Default, PartialEq)]
+struct Extraction {
+    tool: &'static str,
+    archive: Option<String>,
+    dest: Option<String>,
+    /// `tar -P`/`--absolute-names` or `unzip -:` keep `/` and `..` in member names.
+    keeps_unsafe_paths: bool,
+}
+
+/// Check tar/unzip extractions: unsafe-path flags, sensitive destinations, and — when the
+/// archive already exists — members with absolute or `..` paths and zip bombs.
+pub fn check(cmd: &str, cwd: &Path, wrappers: &[String]) -> Option<Hit> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        let Some(x) = parse_extraction(args) else {
+            continue;
+        };
+
+        if x.keeps_unsafe_paths {
+            return Some(hit(format!(
+                "Archive: {} keeps absolute and `..` member paths",
+                x.tool
+            )));
+        }
+        if let Some(dest) = &x.dest {
+            let resolved = crate::perms::resolve(dest, cwd, home.as_deref());
+            if is_sensitive_dest(&resolved, home.as_deref()) {
+                return Some(hit(format!("Archive: {} extracts into {}", x.tool, dest)));
+            }
+        }
+        if let Some(archive) = &x.archive {
+            let path = cwd.join(archive);
+            if let Some(reason) = peek(x.tool, &path) {
+                return Some(hit(format!("Archive: {} {}", archive, reason)));
+            }
+        }
+    }
+    None
+}
+
+fn hit(reason: String) -> Hit {
+    Hit {
+        id: "ARC101",
+        reason,
+        category: Category::Archive,
+    }
+}
+
+fn parse_extraction(args: &[&str]) -> Option<Extraction> {
+    let program = args.first()?;
+    match program.rsplit('/').next()? {
+        "tar" | "gtar" | "bsdtar" => parse_tar(&args[1..]),
+        "unzip" => parse_unzip(&args[1..]),
+        _ => None,
+    }
+}
+
+/// tar in old (`xzf a.tgz`), short (`-xzf a.tgz -C /`) or long (`--extract --file=a.tgz`) style.
+fn parse_tar(args: &[&str]) -> Option<Extraction> {
+    let mut x = Extraction {
+        tool: "tar",
+        ..Extraction::default()
+    };
+    let mut extract = false;
+    let mut i = 0;
+    while i < args.len() {
+        let a = args[i];
+        let next = args.get(i + 1).map(|s| s.to_string());
+        if let Some(long) = a.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((n, v)) => (n, Some(v.to_string())),
+                None => (long, None),
+            };
+            match name {
+                "extract" | "get" => extract = true,
+                "absolute-names" => x.keeps_unsafe_paths = true,
+                "directory" | "file" => {
+                    let value = value.or_else(|| {
+                        i += 1;
+                        next
+                    });
+                    if name == "directory" {
+                        x.dest = value;
+                    } else {
+                        x.archive = value;
+                    }
+                }
+                _ => {}
+            }
+        } else if a.starts_with('-') || i == 0 {
+            // Short bundle; the first word may omit the dash (old style), in which case
+            // every argument-taking letter consumes the next word in order
+            let old_style = !a.starts_with('-');
+            let bundle = a.trim_start_matches('-');
+            for (pos, c) in bundle.char_indices() {
+                match c {
+                    'x' => extract = true,
+                    'P' => x.keeps_unsafe_paths = true,
+                    'f' | 'C' => {
+                        let rest = &bundle[pos + 1..];
+                        let value = if old_style || rest.is_empty() {
+                            i += 1;
+                            args.get(i).map(|s| s.to_string())
+                        } else {
+                            Some(rest.to_string())
+                        };
+                        if c == 'f' {
+                            x.archive = value;
+                        } else {
+                            x.dest = value;
+                        }
+                        if !old_style {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        i += 1;
+    }
+    extract.then_some(x)
+}
+
+fn parse_unzip(args: &[&str]) -> Option<Extraction> {
+    let mut x = Extraction {
+        tool: "unzip",
+        ..Extraction::default()
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let a = args[i];
+        if a == "-d" {
+            i += 1;
+            x.dest = args.get(i).map(|s| s.to_string());
+        } else if let Some(dir) = a.strip_prefix("-d") {
+            x.dest = Some(dir.to_string());
+        } else if a.starts_with('-') {
+            // -l / -t / -v / -Z only list or test
+            if a.contains(['l', 't', 'v', 'Z']) && !a.contains(':') {
+                return None;
+            }
+            x.keeps_unsafe_paths |= a.contains(':');
+        } else if x.archive.is_none() {
+            x.archive = Some(a.to_string());
+        }
+        i += 1;
+    }
+    Some(x)
+}
+
+fn is_sensitive_dest(dest: &Path, home: Option<&Path>) -> bool {
+    if dest == Path::new("/") || SENSITIVE_DIRS.iter().any(|d| dest.starts_with(d)) {
+        return true;
+    }
+    let Some(home) = home else { return false };
+    dest == home
+        || SENSITIVE_HOME_DIRS
+            .iter()
+            .any(|d| dest.starts_with(home.join(d)))
+}
+
+/// List an existing archive and describe the first problem: a member that escapes the
+/// destination, or (for zip) an expansion ratio that looks like a bomb.
+fn peek(tool: &str, path: &Path) -> Option<String> {
+    let meta = fs::metadata(path)
+        .ok()
+        .filter(|m| m.is_file() && m.len() <= MAX_PEEK_BYTES)?;
+    let listing = match tool {
+        "unzip" => Command::new("unzip").arg("-Z1").arg(path).output(),
+        _ => Command::new("tar").arg("-tf").arg(path).output(),
+    }
+    .ok()
+    .filter(|o| o.status.success())?;
+
+    let listing = String::from_utf8_lossy(&listing.stdout);
+    if let Some(entry) = listing.lines().find(|e| is_unsafe_member(e)) {
+        return Some(format!("contains path-traversal entry {}", entry));
+    }
+
+    if tool == "unzip" {
+        let total = unzip_total_size(path)?;
+        let ratio = total / meta.len().max(1);
+        if total >= BOMB_MIN_BYTES && ratio >= BOMB_MIN_RATIO {
+            return Some(format!(
+                "expands to {} MiB ({}x its size) — possible zip bomb",
+                total >> 20,
+                ratio
+            ));
+        }
+    }
+    None
+}
+
+fn is_unsafe_member(entry: &str) -> bool {
+    entry.starts_with('/') || entry.split(['/', '\\']).any(|c| c == "..")
+}
+
+/// Total uncompressed size from the last line of `unzip -l`.
+fn unzip_total_size(path: &Path) -> Option<u64> {
+    let out = Command::new("unzip").arg("-l").arg(path).output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    text.lines().last()?.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn check_in(cmd: &str, cwd: &Path) -> Option<String> {
+        check(cmd, cwd, &[]).map(|h| h.reason)
+    }
+
+    /// A ustar archive with one empty regular file per name (names are written verbatim).
+    fn write_tar(path: &Path, names: &[&str]) {
+        let mut out = Vec::new();
+        for name in names {
+            let mut h = [0u8; 512];
+            h[..name.len()].copy_from_slice(name.as_bytes());
+            h[100..108].copy_from_slice(b"0000644\0");
+            h[108..116].copy_from_slice(b"0000000\0");
+            h[116..124].copy_from_slice(b"0000000\0");
+            h[124..136].copy_from_slice(b"00000000000\0");
+            h[136..148].copy_from_slice(b"00000000000\0");
+            h[156] = b'0';
+            h[257..263].copy_from_slice(b"ustar\0");
+            h[263..265].copy_from_slice(b"00");
+            h[148..156].copy_from_slice(b"        ");
+            let sum: u32 = h.iter().map(|b| *b as u32).sum();
+            h[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+            out.extend_from_slice(&h);
+        }
+        out.extend_from_slice(&[0u8; 1024]);
+        fs::write(path, out).unwrap();
+    }
+
+    #[test]
+    fn parses_tar_styles() {
+        let x = parse_extraction(&["tar", "xzf", "a.tgz", "-C", "/"]).unwrap();
+        assert_eq!(
+            (x.archive.as_deref(), x.dest.as_deref()),
+            (Some("a.tgz"), Some("/"))
+        );
+        let x = parse_extraction(&["tar", "-xvf", "a.tar", "--directory=/etc"]).unwrap();
+        assert_eq!(
+            (x.archive.as_deref(), x.dest.as_deref()),
+            (Some("a.tar"), Some("/etc"))
+        );
+        let x =
+            parse_extraction(&["tar", "--extract", "--file", "a.tar", "-C/opt/xfiles"]).unwrap();
+        assert_eq!(
+            (x.archive.as_deref(), x.dest.as_deref()),
+            (Some("a.tar"), Some("/opt/xfiles"))
+        );
+        assert!(parse_extraction(&["tar", "-czf", "out.tgz", "src"]).is_none());
+        assert!(parse_extraction(&["tar", "-tf", "a.tar"]).is_none());
+    }
+
+    #[test]
+    fn flags_unsafe_path_options() {
+        let cwd = Path::new("/tmp");
+        assert!(check_in("tar -xPf a.tar", cwd)
+            .unwrap()
+            .contains("keeps absolute"));
+        assert!(check_in("tar --absolute-names -xf a.tar", cwd).is_some());
+        assert!(check_in("unzip -: a.zip", cwd).is_some());
+    }
+
+    #[test]
+    fn flags_sensitive_destinations() {
+        let cwd = Path::new("/tmp");
+        assert!(check_in("tar -C / -xzf rootfs.tgz", cwd)
+            .unwrap()
+            .contains("extracts into /"));
+        assert!(check_in("unzip -o archive.zip -d /etc", cwd).is_some());
+        assert!(check_in("sudo tar xf backup.tar -C /usr/local", cwd).is_some());
+        assert!(check_in("tar xf keys.tar -C ~/.ssh", cwd).is_some());
+    }
+
+    #[test]
+    fn allows_ordinary_extraction() {
+        let dir = TempDir::new().unwrap();
+        assert!(check_in("tar -xzf deps.tgz -C vendor", dir.path()).is_none());
+        assert!(check_in("unzip -o site.zip -d public", dir.path()).is_none());
+        assert!(check_in("unzip -l /etc/whatever.zip", dir.path()).is_none());
+        assert!(check_in("tar -czf out.tgz -C / etc", dir.path()).is_none());
+    }
+
+    #[test]
+    fn peeks_for_path_traversal_members() {
+        let dir = TempDir::new().unwrap();
+        write_tar(&dir.path().join("ok.tar"), &["a.txt", "dir/b.txt"]);
+        write_tar(&dir.path().join("evil.tar"), &["a.txt", "../../.bashrc"]);
+        if Command::new("tar").arg("--version").output().is_err() {
+            return; // tar not installed
+        }
+        assert!(check_in("tar xf ok.tar", dir.path()).is_none());
+        let reason = check_in("tar -xf evil.tar -C out", dir.path()).unwrap();
+        assert!(
+            reason.contains("path-traversal entry ../../.bashrc"),
+            "{}",
+            reason
+        );
+    }
+
+    #[test]
+    fn unsafe_member_names() {
+        assert!(is_unsafe_member("/etc/passwd"));
+        assert!(is_unsafe_member("a/../../b"));
+        assert!(is_unsafe_member("..\\evil.dll"));
+        assert!(!is_unsafe_member("a/..b/c"));
+    }
+}
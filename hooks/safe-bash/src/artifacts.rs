@@ -0,0 +1,123 @@
+use std::path::Path;
+
+/// Directory names build tools regenerate, whose recursive deletion is routine. `artifact_dirs`
+/// in the config adds more; entries may be globs (`*.egg-info`).
+pub const ARTIFACT_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    ".next",
+    ".nuxt",
+    ".svelte-kit",
+    ".turbo",
+    ".parcel-cache",
+    ".gradle",
+    "__pycache__",
+    ".pytest_cache",
+    ".mypy_cache",
+    ".ruff_cache",
+    ".tox",
+    "*.egg-info",
+    "coverage",
+];
+
+/// Whether a command is made only of `rm -r` calls on build artifacts inside `cwd`
+/// (`rm -rf node_modules`, `rm -rf dist build .next`, `rm -r web/node_modules/`), which the
+/// generic rm rules then let through. False when any part of the command is something else —
+/// another directory, an absolute, `~`, `$` or `..` path, a wildcard, a path that leaves `cwd`
+/// through a symlink, another command — so those keep the generic rm rules.
+pub fn check(cmd: &str, cwd: &Path, wrappers: &[String], extra: &[String]) -> bool {
+    let ast = crate::shell::parse(cmd);
+    let mut targets = 0;
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().and_then(|p| p.rsplit('/').next()) != Some("rm") {
+            return false;
+        }
+        let mut options_done = false;
+        let mut recursive = false;
+        for arg in &args[1..] {
+            if !options_done && arg.starts_with('-') {
+                options_done = *arg == "--";
+                recursive |=
+                    *arg == "--recursive" || (!arg.starts_with("--") && arg.contains(['r', 'R']));
+            } else if is_artifact(arg, cwd, extra) {
+                targets += 1;
+            } else {
+                return false;
+            }
+        }
+        if !recursive {
+            return false;
+        }
+    }
+    targets > 0
+}
+
+fn is_artifact(arg: &str, cwd: &Path, extra: &[String]) -> bool {
+    let path = arg.trim_end_matches('/');
+    let named = !path.starts_with(['/', '~', '$', '`'])
+        && !path.contains(['*', '?', '[', '{'])
+        && !path.split('/').any(|c| c == "..")
+        && path.rsplit('/').next().is_some_and(|name| {
+            ARTIFACT_DIRS
+                .iter()
+                .copied()
+                .chain(extra.iter().map(String::as_str))
+                .any(|p| crate::blast::glob_match(p, name))
+        });
+    if !named {
+        return false;
+    }
+    // A symlinked parent or `node_modules -> ~` would take the delete out of the project; a
+    // path that does not exist deletes nothing
+    match (cwd.join(path).canonicalize(), cwd.canonicalize()) {
+        (Ok(real), Ok(root)) => real.starts_with(root),
+        (Err(_), _) => true,
+        (Ok(_), Err(_)) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifact_deletes_inside_cwd() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("web/node_modules")).unwrap();
+        let check = |cmd: &str| check(cmd, dir.path(), &[], &["vendor".to_string()]);
+        assert!(check("rm -rf node_modules"));
+        assert!(check(
+            "rm -rf dist build .next && rm -r -f web/node_modules/"
+        ));
+        assert!(check("sudo rm -rf target ./pkg.egg-info"));
+        assert!(check("rm -rf vendor"), "configured name");
+    }
+
+    #[test]
+    fn other_targets_keep_generic_rules() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let outside = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(outside.path().join("build")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("build"), dir.path().join("dist")).unwrap();
+        for cmd in [
+            "rm -rf node_modules src",
+            "rm -rf /tmp/node_modules",
+            "rm -rf ~/build",
+            "rm -rf ../target",
+            "rm -rf $OUT/dist",
+            "rm -rf dist/*",
+            "rm node_modules",
+            "rm -rf build && make",
+            "rm -rf link/build",
+            "rm -rf dist/",
+            "rm -rf vendor",
+        ] {
+            assert!(!check(cmd, dir.path(), &[], &[]), "{}", cmd);
+        }
+    }
+}
@@ -0,0 +1,506 @@
+use crate::config::AuditRetention;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory holding one append-only audit log per project (one JSON object per line).
+pub fn audit_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-audit")
+}
+
+/// The audit log for a project, keyed by a hash of its workspace root.
+pub fn project_log_path(state_dir: &Path, project: &Path) -> PathBuf {
+    let hash = crate::inventory::fnv1a(project.to_string_lossy().as_bytes());
+    audit_dir(state_dir).join(format!("{:016x}.jsonl", hash))
+}
+
+/// The single mixed log written before per-project logs; still read and purged.
+fn legacy_log_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-audit.jsonl")
+}
+
+/// Every audit log on disk: the per-project logs and the legacy mixed log.
+pub fn log_files(state_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(audit_dir(state_dir))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    let legacy = legacy_log_path(state_dir);
+    if legacy.exists() {
+        files.push(legacy);
+    }
+    files
+}
+
+/// A single hook decision worth recording.
+pub struct Entry<'a> {
+    pub decision: &'a str,
+    pub reason: &'a str,
+    pub command: &'a str,
+    /// The command's shape (see fingerprint.rs), for grouping similar commands.
+    pub fingerprint: &'a str,
+    pub privilege: &'a str,
+    /// The workspace root (or cwd outside a git workspace) the command ran in.
+    pub project: &'a Path,
+    /// A command that undoes this one, captured before it ran (see rollback.rs).
+    pub rollback: Option<&'a str>,
+    /// The safer command run in place of this one, with the user's approval (see rewrite.rs).
+    pub rewritten: Option<&'a str>,
+    /// Backups of the files the command edits in place (see sedbackup.rs).
+    pub backups: &'a [String],
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Append an entry to its project's audit log, apply the retention policy, and return the
+/// entry's id (`<ts>-<pid>`). Failures warn but never affect the decision.
+pub fn record(state_dir: &Path, entry: &Entry, retention: &AuditRetention) -> String {
+    let ts = now_secs();
+    let id = format!("{}-{}", ts, std::process::id());
+    let mut line = json!({
+        "id": id,
+        "ts": ts,
+        "decision": entry.decision,
+        "reason": entry.reason,
+        "command": entry.command,
+        "fingerprint": entry.fingerprint,
+        "privilege": entry.privilege,
+        "project": entry.project.to_string_lossy(),
+    });
+    if let Some(rollback) = entry.rollback {
+        line["rollback"] = json!(rollback);
+    }
+    if let Some(rewritten) = entry.rewritten {
+        line["rewritten"] = json!(rewritten);
+    }
+    if !entry.backups.is_empty() {
+        line["backups"] = json!(entry.backups);
+    }
+    append(state_dir, entry.project, &line, retention, ts);
+    id
+}
+
+/// Append a `session-summary` entry with a session's tally (see session.rs) to its project's
+/// audit log.
+pub fn record_summary(
+    state_dir: &Path,
+    project: &Path,
+    session: &str,
+    tally: &crate::session::Tally,
+    summary: &str,
+    retention: &AuditRetention,
+) {
+    let ts = now_secs();
+    let line = json!({
+        "id": format!("{}-{}", ts, std::process::id()),
+        "ts": ts,
+        "decision": "session-summary",
+        "reason": summary,
+        "session": session,
+        "summary": tally,
+        "project": project.to_string_lossy(),
+    });
+    append(state_dir, project, &line, retention, ts);
+}
+
+fn append(
+    state_dir: &Path,
+    project: &Path,
+    line: &serde_json::Value,
+    retention: &AuditRetention,
+    ts: u64,
+) {
+    let path = project_log_path(state_dir, project);
+    // Only the audit dir itself is created: a missing state dir stays a write failure
+    let dir = audit_dir(state_dir);
+    let result = (if dir.is_dir() {
+        Ok(())
+    } else {
+        fs::create_dir(&dir)
+    })
+    .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+    .and_then(|mut f| writeln!(f, "{}", line));
+    if let Err(e) = result {
+        crate::warnings::warn(
+            "audit-write",
+            &format!("could not write audit log {}: {}", path.display(), e),
+        );
+        return;
+    }
+    if over_retention(&path, retention, ts) {
+        prune(&path, retention, ts);
+    }
+}
+
+/// Cheap check before rewriting: the log is over the size limit or its oldest entry has expired.
+fn over_retention(path: &Path, retention: &AuditRetention, now: u64) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    if file.metadata().is_ok_and(|m| m.len() > retention.max_bytes) {
+        return true;
+    }
+    let mut first = String::new();
+    let _ = BufReader::new(file).read_line(&mut first);
+    entry_ts(&first).is_some_and(|ts| ts < cutoff(retention, now))
+}
+
+fn cutoff(retention: &AuditRetention, now: u64) -> u64 {
+    now.saturating_sub(retention.max_age_days.saturating_mul(86_400))
+}
+
+fn entry_ts(line: &str) -> Option<u64> {
+    serde_json::from_str::<serde_json::Value>(line).ok()?["ts"].as_u64()
+}
+
+/// Drop expired entries, then the oldest ones until the log fits `max_bytes`. Returns how
+/// many entries were removed; a log left empty is deleted.
+pub fn prune(path: &Path, retention: &AuditRetention, now: u64) -> usize {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return 0;
+    };
+    let cutoff = cutoff(retention, now);
+    let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut kept: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|l| entry_ts(l).is_none_or(|ts| ts >= cutoff))
+        .collect();
+    let mut size: u64 = kept.iter().map(|l| l.len() as u64 + 1).sum();
+    let mut drop = 0;
+    while size > retention.max_bytes && drop < kept.len() {
+        size -= kept[drop].len() as u64 + 1;
+        drop += 1;
+    }
+    kept.drain(..drop);
+    let removed = lines.len() - kept.len();
+    if removed == 0 {
+        return 0;
+    }
+
+    let result = if kept.is_empty() {
+        fs::remove_file(path)
+    } else {
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, kept.join("\n") + "\n").and_then(|_| fs::rename(&tmp, path))
+    };
+    if let Err(e) = result {
+        crate::warnings::warn(
+            "audit-write",
+            &format!("could not prune audit log {}: {}", path.display(), e),
+        );
+        return 0;
+    }
+    removed
+}
+
+/// `safe-bash-hook purge`: apply the retention policy to every audit log now. Returns
+/// (log, entries removed) for each log that changed.
+pub fn purge_all(state_dir: &Path, retention: &AuditRetention) -> Vec<(PathBuf, usize)> {
+    let now = now_secs();
+    log_files(state_dir)
+        .into_iter()
+        .map(|path| {
+            let removed = prune(&path, retention, now);
+            (path, removed)
+        })
+        .filter(|(_, removed)| *removed > 0)
+        .collect()
+}
+
+/// `safe-bash-hook purge <project>`: delete one project's audit log. Returns how many
+/// entries it held, or None if there was no log.
+pub fn purge_project(state_dir: &Path, project: &Path) -> Option<usize> {
+    let path = project_log_path(state_dir, project);
+    let entries = fs::read_to_string(&path)
+        .ok()?
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .count();
+    fs::remove_file(&path).ok()?;
+    Some(entries)
+}
+
+/// `safe-bash-hook stats`: how often each command shape was denied, asked or warned about,
+/// most frequent first, across every log or only `log`. Entries written before fingerprints
+/// were recorded are fingerprinted from their command.
+pub fn stats(state_dir: &Path, log: Option<&Path>) -> Vec<(String, BTreeMap<String, usize>)> {
+    let logs = match log {
+        Some(log) => vec![log.to_path_buf()],
+        None => log_files(state_dir),
+    };
+    let mut counts: HashMap<String, BTreeMap<String, usize>> = HashMap::new();
+    for log in logs {
+        let Ok(contents) = fs::read_to_string(&log) else {
+            continue;
+        };
+        for v in contents
+            .lines()
+            .filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+        {
+            if v["decision"] == "session-summary" {
+                continue;
+            }
+            let shape = match v["fingerprint"].as_str() {
+                Some(shape) => shape.to_string(),
+                None => crate::fingerprint::of(v["command"].as_str().unwrap_or(""), &[]).shape,
+            };
+            let decision = v["decision"].as_str().unwrap_or("?").to_string();
+            *counts
+                .entry(shape)
+                .or_default()
+                .entry(decision)
+                .or_default() += 1;
+        }
+    }
+    let mut rows: Vec<_> = counts.into_iter().collect();
+    rows.sort_by(|a, b| {
+        b.1.values()
+            .sum::<usize>()
+            .cmp(&a.1.values().sum::<usize>())
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    rows
+}
+
+/// Find an audit entry by id in any log.
+pub fn lookup(state_dir: &Path, id: &str) -> Option<serde_json::Value> {
+    log_files(state_dir).iter().find_map(|log| {
+        fs::read_to_string(log)
+            .ok()?
+            .lines()
+            .filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+            .find(|v| v["id"] == id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry<'a>(command: &'a str, project: &'a Path) -> Entry<'a> {
+        Entry {
+            decision: "deny",
+            reason: "test",
+            command,
+            fingerprint: "",
+            privilege: "root",
+            project,
+            rollback: None,
+            rewritten: None,
+            backups: &[],
+        }
+    }
+
+    fn read_log(path: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn record_appends_json_lines_per_project() {
+        let dir = TempDir::new().unwrap();
+        let (a, b) = (Path::new("/work/a"), Path::new("/work/b"));
+        record(
+            dir.path(),
+            &entry("rm -rf /", a),
+            &AuditRetention::default(),
+        );
+        record(dir.path(), &entry("sudo ls", a), &AuditRetention::default());
+        record(
+            dir.path(),
+            &entry("git push -f", b),
+            &AuditRetention::default(),
+        );
+        let lines = read_log(&project_log_path(dir.path(), a));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["command"], "rm -rf /");
+        assert_eq!(lines[0]["privilege"], "root");
+        assert_eq!(lines[0]["project"], "/work/a");
+        assert!(lines[0].get("rollback").is_none());
+        assert!(lines[0].get("rewritten").is_none());
+        assert_eq!(read_log(&project_log_path(dir.path(), b)).len(), 1);
+        assert_eq!(log_files(dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn record_returns_id_and_stores_rollback() {
+        let dir = TempDir::new().unwrap();
+        let project = Path::new("/work/a");
+        let id = record(
+            dir.path(),
+            &Entry {
+                rollback: Some("git reset --hard abc"),
+                ..entry("git reset --hard", project)
+            },
+            &AuditRetention::default(),
+        );
+        let v = lookup(dir.path(), &id).unwrap();
+        assert_eq!(v["rollback"], "git reset --hard abc");
+        assert!(lookup(dir.path(), "0-0").is_none());
+    }
+
+    #[test]
+    fn record_stores_rewritten_command_and_backups() {
+        let dir = TempDir::new().unwrap();
+        let project = Path::new("/work/a");
+        let asked = Entry {
+            decision: "ask",
+            rewritten: Some("rm -i -r build"),
+            ..entry("rm -rf build", project)
+        };
+        let id = record(dir.path(), &asked, &AuditRetention::default());
+        let v = lookup(dir.path(), &id).unwrap();
+        assert_eq!(v["command"], "rm -rf build");
+        assert_eq!(v["rewritten"], "rm -i -r build");
+        assert!(v.get("backups").is_none());
+
+        let backups = ["/work/a/f.safe-bash.bak".to_string()];
+        let backed = Entry {
+            decision: "backup",
+            rewritten: Some("sed -i.safe-bash.bak s/a/b/ f"),
+            backups: &backups,
+            ..entry("sed -i s/a/b/ f", project)
+        };
+        // Same second, same process: the same id, so a separate state dir
+        let dir = TempDir::new().unwrap();
+        let id = record(dir.path(), &backed, &AuditRetention::default());
+        assert_eq!(
+            lookup(dir.path(), &id).unwrap()["backups"],
+            serde_json::json!(["/work/a/f.safe-bash.bak"])
+        );
+    }
+
+    #[test]
+    fn lookup_also_reads_legacy_log() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            legacy_log_path(dir.path()),
+            "{\"id\":\"1-2\",\"rollback\":\"x\"}\nnot json\n",
+        )
+        .unwrap();
+        assert_eq!(lookup(dir.path(), "1-2").unwrap()["rollback"], "x");
+    }
+
+    #[test]
+    fn prune_drops_expired_then_oldest_entries() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("p.jsonl");
+        let day = 86_400;
+        let now = 100 * day;
+        let line = |ts: u64| format!("{{\"ts\":{},\"command\":\"x\"}}", ts);
+        let contents: Vec<String> = [
+            now - 40 * day,
+            now - 20 * day,
+            now - 2 * day,
+            now - day,
+            now,
+        ]
+        .iter()
+        .map(|t| line(*t))
+        .collect();
+        fs::write(&log, contents.join("\n") + "\n").unwrap();
+
+        let retention = AuditRetention {
+            max_age_days: 30,
+            max_bytes: 10_000,
+        };
+        assert!(over_retention(&log, &retention, now));
+        assert_eq!(prune(&log, &retention, now), 1);
+        assert!(!over_retention(&log, &retention, now));
+
+        // Room for two entries: the newest two are kept
+        let retention = AuditRetention {
+            max_age_days: 30,
+            max_bytes: 2 * (line(now).len() as u64 + 1),
+        };
+        assert_eq!(prune(&log, &retention, now), 2);
+        let kept: Vec<u64> = read_log(&log)
+            .iter()
+            .map(|v| v["ts"].as_u64().unwrap())
+            .collect();
+        assert_eq!(kept, [now - day, now]);
+    }
+
+    #[test]
+    fn purge_project_deletes_only_that_log() {
+        let dir = TempDir::new().unwrap();
+        let (a, b) = (Path::new("/work/a"), Path::new("/work/b"));
+        record(dir.path(), &entry("ls", a), &AuditRetention::default());
+        record(dir.path(), &entry("ls", b), &AuditRetention::default());
+        assert_eq!(purge_project(dir.path(), a), Some(1));
+        assert_eq!(purge_project(dir.path(), a), None);
+        assert!(project_log_path(dir.path(), b).exists());
+    }
+
+    #[test]
+    fn stats_group_by_shape() {
+        let dir = TempDir::new().unwrap();
+        let project = Path::new("/work/a");
+        let shape = "rm -f -r";
+        record(
+            dir.path(),
+            &Entry {
+                fingerprint: shape,
+                ..entry("rm -rf ./a", project)
+            },
+            &AuditRetention::default(),
+        );
+        record(
+            dir.path(),
+            &Entry {
+                fingerprint: shape,
+                decision: "ask",
+                ..entry("rm -fr ./b", project)
+            },
+            &AuditRetention::default(),
+        );
+        fs::write(
+            legacy_log_path(dir.path()),
+            "{\"decision\":\"deny\",\"command\":\"rm -r -f \
+             c\"}\n{\"decision\":\"warn\",\"command\":\"sed -i s/a/b/ f\"}\n",
+        )
+        .unwrap();
+        let rows = stats(dir.path(), None);
+        assert_eq!(rows[0].0, shape);
+        assert_eq!(
+            rows[0].1,
+            BTreeMap::from([("ask".to_string(), 1), ("deny".to_string(), 2)])
+        );
+        assert_eq!(rows[1].0, "sed -i");
+        assert_eq!(
+            stats(dir.path(), Some(&project_log_path(dir.path(), project))).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn record_does_not_panic_on_bad_path() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("missing/hooks");
+        record(
+            &missing,
+            &entry("ls", Path::new("/")),
+            &AuditRetention::default(),
+        );
+        assert!(!missing.exists());
+    }
+}
@@ -1,19 +1,21 @@
+use crate::dirs::Dirs;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-const UPDATE_URL: &str = "https://raw.githubusercontent.com/apollo-com-ph/apollo-claude/main/safe-bash-patterns.json";
+const UPDATE_URL: &str =
+    "https://raw.githubusercontent.com/apollo-com-ph/apollo-claude/main/safe-bash-patterns.json";
 const UPDATE_INTERVAL_SECS: u64 = 3600; // 1 hour
 
 /// Path to the timestamp file that tracks the last update check.
-pub fn last_update_path(hooks_dir: &Path) -> PathBuf {
-    hooks_dir.join("safe-bash-patterns.last_update")
+pub fn last_update_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-patterns.last_update")
 }
 
 /// Path to the patterns file.
-pub fn patterns_path(hooks_dir: &Path) -> PathBuf {
-    hooks_dir.join("safe-bash-patterns.json")
+pub fn patterns_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("safe-bash-patterns.json")
 }
 
 /// Returns true if an update should be triggered (file missing or mtime > interval).
@@ -37,7 +39,14 @@ pub fn update_needed(timestamp_path: &Path) -> bool {
 /// Touch the timestamp file (create or update mtime).
 pub fn touch_timestamp(timestamp_path: &Path) {
     if let Err(e) = fs::write(timestamp_path, format!("{}", now_secs())) {
-        eprintln!("safe-bash-hook: warn: could not write timestamp {}: {}", timestamp_path.display(), e);
+        crate::warnings::warn(
+            "timestamp-write",
+            &format!(
+                "could not write timestamp {}: {}",
+                timestamp_path.display(),
+                e
+            ),
+        );
     }
 }
 
@@ -50,44 +59,87 @@ fn now_secs() -> u64 {
 
 /// Spawn a detached background curl to fetch the latest patterns file.
 /// Never blocks — the child process is fully detached.
+/// The fetch first records the source's URL and public key (see `tofu`); with `refuse_changed`
+/// and a trusted source on record, curl only accepts that key, and a changed URL is not fetched;
+/// nothing is while a source trusted on another machine waits to be confirmed on this one.
 /// Returns Ok(()) if the spawn succeeded, Err(msg) if curl is unavailable or spawn failed.
-pub fn spawn_background_update(hooks_dir: &Path) -> Result<(), String> {
-    let target = patterns_path(hooks_dir);
-    let tmpfile = format!("{}.tmp", target.display());
+pub fn spawn_background_update(dirs: &Dirs, refuse_changed: bool) -> Result<(), String> {
+    let target = patterns_path(&dirs.cache);
+    let observe = crate::tofu::observe_script(UPDATE_URL, &crate::tofu::observed_path(&dirs.state));
+    let pin = match crate::tofu::trusted(&dirs.state) {
+        _ if !refuse_changed => String::new(),
+        Err(why) => {
+            crate::warnings::warn(
+                "source-host",
+                &format!("not fetching {}: {}", UPDATE_URL, why),
+            );
+            return Ok(());
+        }
+        Ok(Some(trusted)) if trusted.url != UPDATE_URL => {
+            crate::warnings::warn(
+                "source-url",
+                &format!(
+                    "not fetching {}: the trusted policy source is {}",
+                    UPDATE_URL, trusted.url
+                ),
+            );
+            return Ok(());
+        }
+        Ok(Some(trusted)) => format!(" --pinnedpubkey {}", shell_quote(&trusted.key)),
+        Ok(None) => String::new(),
+    };
+
+    spawn(&format!(
+        "{}; {}",
+        observe,
+        fetch_script(&pin, UPDATE_URL, &target)
+    ))
+}
 
-    // Build: curl -fsSL <url> -o <tmp> && jq empty <tmp> 2>/dev/null && mv <tmp> <target>
-    // The jq validation ensures we never replace the patterns file with corrupted/truncated content.
-    // If jq is not installed, validation fails and the existing patterns file is preserved (safe default).
-    let script = format!(
-        "curl -fsSL {} -o {} && jq empty {} 2>/dev/null && mv {} {} || rm -f {}",
-        UPDATE_URL,
+/// Spawn a detached background curl that replaces `target` with the JSON at `url`, for
+/// config files named in `extends`. Never blocks.
+pub fn spawn_fetch(url: &str, target: &Path) -> Result<(), String> {
+    spawn(&fetch_script("", &shell_quote(url), target))
+}
+
+/// curl -fsSL <url> -o <tmp> && jq empty <tmp> 2>/dev/null && mv <tmp> <target>
+/// The jq validation ensures we never replace the file with corrupted/truncated content.
+/// If jq is not installed, validation fails and the existing file is preserved (safe default).
+fn fetch_script(curl_options: &str, url: &str, target: &Path) -> String {
+    let tmpfile = format!("{}.tmp", target.display());
+    format!(
+        "curl -fsSL{} {} -o {} && jq empty {} 2>/dev/null && mv {} {} || rm -f {}",
+        curl_options,
+        url,
         shell_quote(&tmpfile),
         shell_quote(&tmpfile),
         shell_quote(&tmpfile),
         shell_quote(target.to_str().unwrap_or("")),
         shell_quote(&tmpfile),
-    );
+    )
+}
 
-    // Spawn detached via sh -c "..." &
+/// Run `script` detached via sh -c "..." &
+fn spawn(script: &str) -> Result<(), String> {
     let result = Command::new("sh")
         .arg("-c")
-        .arg(&format!("{} >/dev/null 2>&1 &", script))
+        .arg(format!("{{ {}; }} >/dev/null 2>&1 &", script))
         .spawn();
 
     match result {
         Ok(_) => Ok(()),
-        Err(e) => Err(format!("safe-bash-hook: warn: could not spawn update: {}", e)),
+        Err(e) => Err(format!("could not spawn update: {}", e)),
     }
 }
 
-fn shell_quote(s: &str) -> String {
+pub fn shell_quote(s: &str) -> String {
     format!("'{}'", s.replace('\'', "'\\''"))
 }
 
 /// Check if update is needed and, if so, touch the timestamp and spawn the background fetch.
 /// This function is intentionally non-blocking and failure-tolerant.
-pub fn maybe_update(hooks_dir: &Path) {
-    let ts_path = last_update_path(hooks_dir);
+pub fn maybe_update(dirs: &Dirs, refuse_changed: bool) {
+    let ts_path = last_update_path(&dirs.state);
 
     if !update_needed(&ts_path) {
         return;
@@ -95,8 +147,8 @@ pub fn maybe_update(hooks_dir: &Path) {
 
     touch_timestamp(&ts_path);
 
-    if let Err(warn) = spawn_background_update(hooks_dir) {
-        eprintln!("{}", warn);
+    if let Err(warn) = spawn_background_update(dirs, refuse_changed) {
+        crate::warnings::warn("update-spawn", &warn);
     }
 }
 
@@ -151,13 +203,13 @@ mod tests {
         // without hanging. We don't assert the network result.
         let dir = TempDir::new().unwrap();
         let start = std::time::Instant::now();
-        let _ = spawn_background_update(dir.path());
+        let _ = spawn_background_update(&Dirs::single(dir.path()), false);
         assert!(start.elapsed() < StdDuration::from_secs(1));
     }
 
     #[test]
     fn maybe_update_does_not_panic_on_bad_path() {
         // Non-writable path — should warn but not panic
-        maybe_update(Path::new("/nonexistent/path/hooks"));
+        maybe_update(&Dirs::single(Path::new("/nonexistent/path/hooks")), false);
     }
 }
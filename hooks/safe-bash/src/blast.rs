@@ -0,0 +1,561 @@
+use crate::inventory::{self, Inventory};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Stop counting glob matches after this many entries — the estimate only needs an order of
+/// magnitude.
+const MAX_GLOB_MATCHES: usize = 10_000;
+
+/// Branch names treated as protected when describing git commands, unless the config names
+/// its own (`protected_branches`).
+const PROTECTED_BRANCHES: &[&str] = &["main", "master", "production", "release/*"];
+
+/// Build a short human-readable summary of what a command would touch:
+/// how many entries its globs match, whether git targets a protected branch,
+/// and whether a target looks like production. Returns None when there is nothing to add.
+/// Inside a git workspace, glob counts come from the cached inventory under `cache_dir`.
+/// `protected` lists the protected branch globs, the built-in ones when empty.
+pub fn describe(
+    cmd: &str,
+    cwd: Option<&Path>,
+    cache_dir: &Path,
+    protected: &[String],
+) -> Option<String> {
+    let is_protected_branch = |branch: &str| match protected {
+        [] => PROTECTED_BRANCHES.iter().any(|p| glob_match(p, branch)),
+        _ => protected.iter().any(|p| glob_match(p, branch)),
+    };
+    let mut facts: Vec<String> = Vec::new();
+    let mut workspace: Option<Option<Inventory>> = None;
+
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        let words: Vec<String> = command.args().iter().map(|s| s.to_string()).collect();
+        if words.is_empty() {
+            continue;
+        }
+
+        if let Some(base) = cwd {
+            for word in words.iter().skip(1).filter(|w| is_glob(w)) {
+                let inventory = workspace
+                    .get_or_insert_with(|| {
+                        workspace_root(base)
+                            .map(|root| inventory::load(cache_dir, &root))
+                            .filter(|inv| !inv.truncated)
+                    })
+                    .as_ref();
+                facts.push(describe_glob(word, base, inventory));
+            }
+        }
+
+        if words[0] == "git" {
+            let targets = if words.get(1).map(|s| s.as_str()) == Some("push") {
+                Some(push_targets(&words[2..]))
+            } else {
+                None
+            };
+            // A push only goes to the current branch when it names no refspec or HEAD
+            let on_current = targets
+                .as_ref()
+                .is_none_or(|t| t.is_empty() || t.iter().any(|t| t == "HEAD"));
+            if let Some(branch) = cwd.filter(|_| on_current).and_then(current_branch) {
+                if is_protected_branch(&branch) {
+                    facts.push(format!("current branch {} is protected", branch));
+                }
+            }
+            for target in targets.unwrap_or_default() {
+                if is_protected_branch(&target) {
+                    facts.push(format!("push targets protected branch {}", target));
+                }
+            }
+        }
+
+        for word in words.iter().skip(1) {
+            if looks_like_production(word) {
+                facts.push(format!("target {} looks like production", word));
+            }
+        }
+    }
+
+    facts.dedup();
+    if facts.is_empty() {
+        None
+    } else {
+        Some(facts.join("; "))
+    }
+}
+
+/// "`pattern` matches N entries", with the git-tracked share when the inventory can answer.
+/// The inventory does not look inside symlinked directories, so a glob under one is counted
+/// on disk and the link named.
+fn describe_glob(word: &str, cwd: &Path, inventory: Option<&Inventory>) -> String {
+    if let Some((link, target)) = symlinked_dir(word, cwd) {
+        let n = count_glob_matches(cwd, word);
+        let shown = if n >= MAX_GLOB_MATCHES {
+            format!("{}+", n)
+        } else {
+            n.to_string()
+        };
+        return format!(
+            "`{}` matches {} entries (through symlink {} -> {})",
+            word,
+            shown,
+            link,
+            target.display()
+        );
+    }
+    if let Some((inv, rel)) =
+        inventory.and_then(|inv| Some((inv, workspace_relative(word, cwd, &inv.root)?)))
+    {
+        let matches = inv.glob_matches(&rel);
+        let tracked = matches.iter().filter(|m| inv.is_tracked(m)).count();
+        if tracked > 0 {
+            return format!(
+                "`{}` matches {} entries ({} tracked by git)",
+                word,
+                matches.len(),
+                tracked
+            );
+        }
+        return format!("`{}` matches {} entries", word, matches.len());
+    }
+    let n = count_glob_matches(cwd, word);
+    let shown = if n >= MAX_GLOB_MATCHES {
+        format!("{}+", n)
+    } else {
+        n.to_string()
+    };
+    format!("`{}` matches {} entries", word, shown)
+}
+
+/// The first symlink among the directories a glob's literal leading components name
+/// (`dist` in `dist/*`), with its target.
+fn symlinked_dir(pattern: &str, cwd: &Path) -> Option<(String, PathBuf)> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let literal = components
+        .iter()
+        .position(|c| is_glob(c))
+        .unwrap_or(components.len());
+    let base = if pattern.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        cwd.to_path_buf()
+    };
+    let mut dir = base;
+    for (i, component) in components[..literal]
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_empty())
+    {
+        dir.push(component);
+        if dir
+            .symlink_metadata()
+            .is_ok_and(|m| m.file_type().is_symlink())
+        {
+            return Some((components[..=i].join("/"), fs::read_link(&dir).ok()?));
+        }
+    }
+    None
+}
+
+/// A cwd-relative glob rewritten relative to the workspace root. None for patterns that
+/// can leave the workspace (absolute, `~`, `..`).
+fn workspace_relative(pattern: &str, cwd: &Path, root: &Path) -> Option<String> {
+    if pattern.starts_with('/') || pattern.starts_with('~') || pattern.split('/').any(|c| c == "..")
+    {
+        return None;
+    }
+    let prefix = cwd.strip_prefix(root).ok()?.to_string_lossy().to_string();
+    if prefix.is_empty() {
+        Some(pattern.to_string())
+    } else {
+        Some(format!("{}/{}", prefix, pattern))
+    }
+}
+
+/// The nearest ancestor of `start` containing `.git` (directory, or file for worktrees).
+pub fn workspace_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|d| d.join(".git").exists())
+        .map(|d| d.to_path_buf())
+}
+
+fn is_glob(word: &str) -> bool {
+    word.contains(['*', '?', '['])
+}
+
+/// Count filesystem entries matched by a shell glob, resolved relative to `base`.
+pub fn count_glob_matches(base: &Path, pattern: &str) -> usize {
+    glob_paths(base, pattern).len()
+}
+
+/// Filesystem entries matched by a shell glob, resolved relative to `base`
+/// (at most MAX_GLOB_MATCHES per component).
+pub fn glob_paths(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    let (root, rest) = if let Some(stripped) = pattern.strip_prefix('/') {
+        (PathBuf::from("/"), stripped.to_string())
+    } else if let Some(stripped) = pattern.strip_prefix("~/") {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        (PathBuf::from(home), stripped.to_string())
+    } else {
+        (base.to_path_buf(), pattern.to_string())
+    };
+
+    let components: Vec<&str> = rest
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+    let mut current = vec![root];
+    for component in components {
+        let mut next = Vec::new();
+        for dir in &current {
+            if !is_glob(component) {
+                let p = dir.join(component);
+                if p.symlink_metadata().is_ok() {
+                    next.push(p);
+                }
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                // Shell globs don't match dotfiles unless the pattern starts with a dot
+                if name.starts_with('.') && !component.starts_with('.') {
+                    continue;
+                }
+                if glob_match(component, &name) {
+                    next.push(entry.path());
+                    if next.len() >= MAX_GLOB_MATCHES {
+                        break;
+                    }
+                }
+            }
+        }
+        current = next;
+        if current.is_empty() {
+            break;
+        }
+    }
+    current
+}
+
+/// One element of a glob.
+enum Token {
+    Star,
+    Any,
+    /// `[...]`: whether it is negated, and its characters as ranges.
+    Class(bool, Vec<(char, char)>),
+    Literal(char),
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Star | Token::Any => true,
+            Token::Class(negate, ranges) => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negate
+            }
+            Token::Literal(l) => *l == c,
+        }
+    }
+}
+
+fn tokens(pattern: &[char]) -> Vec<Token> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < pattern.len() {
+        let token = match pattern[i] {
+            '*' => Token::Star,
+            '?' => Token::Any,
+            // A `[` without a closing `]` is literal
+            '[' => match pattern[i + 1..]
+                .iter()
+                .position(|&c| c == ']')
+                .map(|k| i + 1 + k)
+            {
+                Some(close) => {
+                    let mut class = &pattern[i + 1..close];
+                    let negate = matches!(class.first(), Some('!') | Some('^'));
+                    if negate {
+                        class = &class[1..];
+                    }
+                    let mut ranges = Vec::new();
+                    let mut k = 0;
+                    while k < class.len() {
+                        if k + 2 < class.len() && class[k + 1] == '-' {
+                            ranges.push((class[k], class[k + 2]));
+                            k += 3;
+                        } else {
+                            ranges.push((class[k], class[k]));
+                            k += 1;
+                        }
+                    }
+                    i = close;
+                    Token::Class(negate, ranges)
+                }
+                None => Token::Literal('['),
+            },
+            c => Token::Literal(c),
+        };
+        out.push(token);
+        i += 1;
+    }
+    out
+}
+
+/// Match a single path component against a glob (`*`, `?`, `[...]`). Patterns come from the
+/// command being checked, so this stays linear per `*`: on a mismatch only the last `*` seen
+/// takes one more character, which is enough since any earlier one could not do better.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p = tokens(&pattern.chars().collect::<Vec<_>>());
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Where the last `*` is in the pattern, and where the name resumes after what it took
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        match p.get(pi) {
+            Some(Token::Star) => {
+                star = Some((pi, ni));
+                pi += 1;
+            }
+            Some(token) if token.matches(n[ni]) => {
+                pi += 1;
+                ni += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    pi = sp + 1;
+                    ni = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|t| matches!(t, Token::Star))
+}
+
+/// Read the checked-out branch from .git/HEAD without spawning git.
+pub fn current_branch(cwd: &Path) -> Option<String> {
+    let git_dir = find_git_dir(cwd)?;
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(|s| s.to_string())
+}
+
+/// Walk up from `start` to the nearest .git directory (or gitdir file, for worktrees).
+pub fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(dot_git);
+        }
+        if dot_git.is_file() {
+            let contents = fs::read_to_string(&dot_git).ok()?;
+            let target = contents.trim().strip_prefix("gitdir:")?.trim();
+            return Some(dir.join(target));
+        }
+    }
+    None
+}
+
+/// Destination branch names from `git push` arguments (`origin main`, `origin +HEAD:main`).
+fn push_targets(args: &[String]) -> Vec<String> {
+    let positional: Vec<&String> = args.iter().filter(|a| !a.starts_with('-')).collect();
+    positional
+        .iter()
+        .skip(1)
+        .map(|refspec| {
+            let spec = refspec.trim_start_matches('+');
+            let dst = spec.rsplit(':').next().unwrap_or(spec);
+            dst.trim_start_matches("refs/heads/").to_string()
+        })
+        .collect()
+}
+
+fn looks_like_production(word: &str) -> bool {
+    static PROD: OnceLock<Regex> = OnceLock::new();
+    let prod = PROD.get_or_init(|| {
+        Regex::new(r"(?i)(^|[^a-z0-9])(prod|production|prd)([^a-z0-9]|$)")
+            .expect("invalid prod regex")
+    });
+    let is_target = word.contains("://")
+        || word.contains('@')
+        || (word.contains('.') && !word.starts_with('.'));
+    is_target && prod.is_match(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn glob_match_basics() {
+        assert!(glob_match("*.log", "app.log"));
+        assert!(!glob_match("*.log", "app.txt"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(glob_match("[a-c]x", "bx"));
+        assert!(!glob_match("[!a-c]x", "bx"));
+        assert!(glob_match("release/*", "release/1.2"));
+        assert!(glob_match("*a*b", "xaybzb"));
+        assert!(!glob_match("*a*b", "xaybz"));
+        assert!(glob_match("[x", "[x"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn pathological_glob_matches_fast() {
+        let name = "a".repeat(60);
+        let pattern = format!("{}b", "*a".repeat(14));
+        let started = std::time::Instant::now();
+        assert!(!glob_match(&pattern, &name));
+        assert!(glob_match(&format!("{}*", "*a".repeat(14)), &name));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn counts_glob_matches_in_cwd() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.log", "b.log", "c.txt", ".hidden.log"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert_eq!(count_glob_matches(dir.path(), "*.log"), 2);
+        assert_eq!(count_glob_matches(dir.path(), "./*"), 3);
+        assert_eq!(count_glob_matches(dir.path(), "missing/*"), 0);
+    }
+
+    #[test]
+    fn describe_reports_glob_counts() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/x.o"), "").unwrap();
+        let ctx = describe("rm -rf build/*", Some(dir.path()), dir.path(), &[]).unwrap();
+        assert!(ctx.contains("`build/*` matches 1 entries"), "{}", ctx);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn describe_counts_through_symlinked_dirs() {
+        let dir = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        for name in ["a", "b"] {
+            fs::write(target.path().join(name), "").unwrap();
+        }
+        std::os::unix::fs::symlink(target.path(), dir.path().join("dist")).unwrap();
+        let ctx = describe("rm -rf dist/*", Some(dir.path()), dir.path(), &[]).unwrap();
+        let expected = format!(
+            "`dist/*` matches 2 entries (through symlink dist -> {})",
+            target.path().display()
+        );
+        assert!(ctx.contains(&expected), "{}", ctx);
+    }
+
+    #[test]
+    fn describe_uses_inventory_inside_workspace() {
+        let dir = TempDir::new().unwrap();
+        let hooks = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("pkg/build")).unwrap();
+        fs::write(dir.path().join("pkg/build/x.o"), "").unwrap();
+        fs::write(dir.path().join("pkg/build/y.o"), "").unwrap();
+        let ctx = describe(
+            "rm -rf build/*",
+            Some(&dir.path().join("pkg")),
+            hooks.path(),
+            &[],
+        )
+        .unwrap();
+        assert!(ctx.contains("`build/*` matches 2 entries"), "{}", ctx);
+        assert!(inventory::cache_path(hooks.path(), dir.path()).exists());
+    }
+
+    #[test]
+    fn workspace_relative_rejects_escaping_patterns() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            workspace_relative("*.o", Path::new("/repo/a"), root),
+            Some("a/*.o".to_string())
+        );
+        assert_eq!(
+            workspace_relative("*.o", root, root),
+            Some("*.o".to_string())
+        );
+        assert_eq!(workspace_relative("../*", Path::new("/repo/a"), root), None);
+        assert_eq!(workspace_relative("/tmp/*", root, root), None);
+    }
+
+    #[test]
+    fn describe_reports_protected_branch() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        let ctx = describe("git reset --hard HEAD~1", Some(dir.path()), dir.path(), &[]).unwrap();
+        assert!(ctx.contains("current branch main is protected"));
+        // A push goes to the current branch only without a refspec or with HEAD
+        for cmd in [
+            "git push -f",
+            "git push -f origin",
+            "git push -f origin HEAD",
+        ] {
+            assert_eq!(
+                describe(cmd, Some(dir.path()), dir.path(), &[]).as_deref(),
+                Some("current branch main is protected"),
+                "{}",
+                cmd
+            );
+        }
+        assert!(describe(
+            "git push -f origin feature",
+            Some(dir.path()),
+            dir.path(),
+            &[]
+        )
+        .is_none());
+        let ctx = describe(
+            "git push -f origin HEAD:release/2.0",
+            Some(dir.path()),
+            dir.path(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(ctx, "push targets protected branch release/2.0");
+    }
+
+    #[test]
+    fn describe_reports_push_target() {
+        let ctx = describe(
+            "git push --force origin +HEAD:release/2.0",
+            None,
+            Path::new("."),
+            &[],
+        )
+        .unwrap();
+        assert!(ctx.contains("push targets protected branch release/2.0"));
+    }
+
+    #[test]
+    fn describe_reports_production_target() {
+        let ctx = describe(
+            "aws s3 rm --recursive s3://prod-assets/",
+            None,
+            Path::new("."),
+            &[],
+        )
+        .unwrap();
+        assert!(ctx.contains("s3://prod-assets/ looks like production"));
+        assert!(describe("aws s3 ls s3://staging-assets/", None, Path::new("."), &[]).is_none());
+    }
+
+    #[test]
+    fn describe_none_for_plain_command() {
+        assert!(describe("ls -la", None, Path::new("."), &[]).is_none());
+    }
+}
@@ -0,0 +1,327 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::{Category, Severities};
+use std::path::Path;
+
+/// The hardcoded force-push rules a push to unprotected branches is let off.
+pub const FORCE_PUSH_RULES: &[&str] = &["GIT001", "GIT004"];
+
+/// `git push` options whose value is the next word.
+const PUSH_OPTIONS_WITH_ARG: &[&str] =
+    &["-o", "--push-option", "--repo", "--receive-pack", "--exec"];
+
+/// Where a command's force pushes and branch deletions go, with `protected_branches` set.
+#[derive(Debug, PartialEq)]
+pub enum Push {
+    /// One reaches a protected branch: always blocked.
+    Protected(Hit),
+    /// Every one reaches a branch outside the list.
+    Unprotected,
+}
+
+/// What one push does to one destination.
+enum Change {
+    Force,
+    Delete,
+}
+
+/// Judge the force pushes (`-f`, `--force`, `+refspec`) and remote branch deletions (`-d`,
+/// `--delete`, `:branch`) in `cmd` by their destination branches, read from the refspecs, the
+/// current branch for a bare `git push` or `HEAD`, and `-C` for where git runs. None when the
+/// command has none, or when one goes somewhere the refspecs do not tell (`--all`, `--mirror`,
+/// a detached HEAD, a pattern refspec): the generic rules decide it.
+pub fn check(cmd: &str, cwd: &Path, wrappers: &[String], protected: &[String]) -> Option<Push> {
+    let ast = crate::shell::parse(cmd);
+    let mut pushes = 0;
+    let mut undecided = false;
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().and_then(|p| p.rsplit('/').next()) != Some("git") {
+            continue;
+        }
+        let mut dir = cwd.to_path_buf();
+        let mut i = 1;
+        while let Some(arg) = args.get(i) {
+            if crate::gitalias::OPTIONS_WITH_ARG.contains(arg) {
+                if *arg == "-C" {
+                    dir = dir.join(args.get(i + 1).copied().unwrap_or("."));
+                }
+                i += 2;
+            } else if arg.starts_with('-') {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        if args.get(i) != Some(&"push") {
+            continue;
+        }
+        let (mut force, mut delete, mut every) = (false, false, false);
+        let mut positional: Vec<&str> = Vec::new();
+        let mut rest = args[i + 1..].iter();
+        while let Some(arg) = rest.next() {
+            match *arg {
+                "--" => positional.extend(rest.by_ref().copied()),
+                a if PUSH_OPTIONS_WITH_ARG.contains(&a) => {
+                    rest.next();
+                }
+                "--force" => force = true,
+                "--delete" => delete = true,
+                // A mirror push force-updates and deletes to match
+                "--mirror" => (force, every) = (true, true),
+                "--all" | "--branches" | "--prune" => every = true,
+                a if a.starts_with("--") => {}
+                a if a.starts_with('-') => {
+                    force |= a.contains('f');
+                    delete |= a.contains('d');
+                }
+                a => positional.push(a),
+            }
+        }
+        // The first operand is the remote; the rest are refspecs
+        let refspecs = positional.get(1..).unwrap_or_default();
+        let mut changes: Vec<(Change, Option<String>)> = Vec::new();
+        if refspecs.is_empty() && (force || delete) {
+            let branch = if every {
+                None
+            } else {
+                crate::blast::current_branch(&dir)
+            };
+            changes.push((
+                if delete {
+                    Change::Delete
+                } else {
+                    Change::Force
+                },
+                branch,
+            ));
+        }
+        for spec in refspecs {
+            let plus = spec.starts_with('+');
+            let spec = spec.trim_start_matches('+');
+            let (src, dst) = spec.split_once(':').unwrap_or((spec, spec));
+            let change = if delete || src.is_empty() {
+                Change::Delete
+            } else if force || plus {
+                Change::Force
+            } else {
+                continue;
+            };
+            let dst = dst.strip_prefix("refs/heads/").unwrap_or(dst);
+            let branch = match dst {
+                "HEAD" => crate::blast::current_branch(&dir),
+                _ if dst.contains('*') || dst.starts_with("refs/") || dst.is_empty() => None,
+                _ => Some(dst.to_string()),
+            };
+            changes.push((change, branch));
+        }
+        for (change, branch) in changes {
+            pushes += 1;
+            let Some(branch) = branch else {
+                undecided = true;
+                continue;
+            };
+            if protected
+                .iter()
+                .any(|p| crate::blast::glob_match(p, &branch))
+            {
+                let (id, reason) = match change {
+                    Change::Force => (
+                        "GIT101",
+                        format!("Destructive: force push to protected branch {}", branch),
+                    ),
+                    Change::Delete => (
+                        "GIT102",
+                        format!("Destructive: deleting protected branch {}", branch),
+                    ),
+                };
+                return Some(Push::Protected(Hit {
+                    id,
+                    reason,
+                    category: Category::GitDestructive,
+                }));
+            }
+        }
+    }
+    (pushes > 0 && !undecided).then_some(Push::Unprotected)
+}
+
+/// Check the forms of a command against the hardcoded patterns with its pushes judged where
+/// they are: the full command and every segment the patterns see (`sh -c` scripts,
+/// substitutions, forms with variables resolved) block when they reach a protected branch, and
+/// are let off the force-push rules only when `check` finds that text reaches none.
+pub fn check_forms(
+    forms: &[&str],
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+    cwd: &Path,
+    protected: &[String],
+) -> CheckResult {
+    let judge = |text: &str| {
+        check(
+            text.strip_prefix("| ").unwrap_or(text),
+            cwd,
+            wrappers,
+            protected,
+        )
+    };
+    let mut result = CheckResult::Allow;
+    for form in forms {
+        let mut texts = Vec::new();
+        collect_texts(form, wrappers, 0, &mut texts);
+        for text in &texts {
+            if let Some(Push::Protected(hit)) = judge(text) {
+                return CheckResult::Deny(hit);
+            }
+        }
+        let exempt = |text: &str| {
+            if judge(text) == Some(Push::Unprotected) {
+                FORCE_PUSH_RULES
+            } else {
+                &[]
+            }
+        };
+        result = result.or_worse(crate::patterns::check_command_except(
+            form, patterns, severities, wrappers, &exempt,
+        ));
+    }
+    result
+}
+
+/// A command, its segments and, recursively, the `sh -c` scripts among them, which the patterns
+/// only see as part of the text of the segment running them.
+fn collect_texts(cmd: &str, wrappers: &[String], depth: usize, out: &mut Vec<String>) {
+    out.push(cmd.to_string());
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        if depth < crate::patterns::MAX_NESTING {
+            for command in crate::shell::parse(segment.strip_prefix("| ").unwrap_or(&segment))
+                .simple_commands()
+            {
+                if let Some(script) = crate::patterns::shell_script(&command.args(), wrappers) {
+                    collect_texts(script, wrappers, depth + 1, out);
+                }
+            }
+        }
+        out.push(segment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn judges_pushes_by_destination() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("app/.git")).unwrap();
+        std::fs::write(
+            dir.path().join("app/.git/HEAD"),
+            "ref: refs/heads/release/2.0\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("lib/.git")).unwrap();
+        std::fs::write(
+            dir.path().join("lib/.git/HEAD"),
+            "ref: refs/heads/ana/fix\n",
+        )
+        .unwrap();
+        let protected = ["main".to_string(), "release/*".to_string()];
+        let check = |cmd: &str| match check(cmd, dir.path(), &[], &protected) {
+            Some(Push::Protected(hit)) => format!("{} {}", hit.id, hit.reason),
+            Some(Push::Unprotected) => "unprotected".to_string(),
+            None => "undecided".to_string(),
+        };
+        assert_eq!(
+            check("git push --force origin main"),
+            "GIT101 Destructive: force push to protected branch main"
+        );
+        assert_eq!(
+            check("git push origin +feature:refs/heads/release/3.1"),
+            "GIT101 Destructive: force push to protected branch release/3.1"
+        );
+        assert_eq!(check("git push -f origin ana/feature"), "unprotected");
+        assert_eq!(
+            check("git push -fu origin HEAD:ana/feature && git push origin +ana/other"),
+            "unprotected"
+        );
+        assert_eq!(
+            check("git push origin ana/x && git push -f origin main"),
+            "GIT101 Destructive: force push to protected branch main"
+        );
+        // Deletions
+        assert_eq!(
+            check("git push origin --delete main"),
+            "GIT102 Destructive: deleting protected branch main"
+        );
+        assert_eq!(
+            check("git push origin :release/1.0"),
+            "GIT102 Destructive: deleting protected branch release/1.0"
+        );
+        assert_eq!(check("git push origin -d ana/old"), "unprotected");
+        // The current branch of the repository git runs in
+        assert_eq!(
+            check("git -C app push --force"),
+            "GIT101 Destructive: force push to protected branch release/2.0"
+        );
+        assert_eq!(check("git -C lib push --force origin HEAD"), "unprotected");
+        assert_eq!(
+            check("sudo git -C app push -f origin HEAD"),
+            "GIT101 Destructive: force push to protected branch release/2.0"
+        );
+        // Where the refspecs do not tell, or there is nothing to judge
+        for cmd in [
+            "git push --force",
+            "git push --mirror --force origin",
+            "git push -f origin 'refs/heads/*:refs/heads/*'",
+            "git push origin main",
+            "git status",
+        ] {
+            assert_eq!(check(cmd), "undecided", "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn pushes_are_judged_in_every_segment() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (patterns, severities) = (
+            crate::patterns::hardcoded_deny_patterns(),
+            Severities::default(),
+        );
+        let protected = ["main".to_string()];
+        let id = |forms: &[&str]| match check_forms(
+            forms,
+            &patterns,
+            &severities,
+            &[],
+            dir.path(),
+            &protected,
+        ) {
+            CheckResult::Deny(hit) => hit.id,
+            CheckResult::Allow => "allow",
+            _ => "other",
+        };
+        assert_eq!(id(&["git push --force origin feature"]), "allow");
+        assert_eq!(
+            id(&["git push --force origin feature && git -C . push -f origin other"]),
+            "allow"
+        );
+        assert_eq!(
+            id(&[r#"git push --force origin feature; bash -c "git push --force origin main""#]),
+            "GIT101"
+        );
+        // The form with the variable resolved is judged too
+        assert_eq!(
+            id(&[
+                "git push -f origin feature; c=git; $c push --force origin main",
+                "c=git; git push -f origin feature; git push --force origin main"
+            ]),
+            "GIT101"
+        );
+        // A script whose destination nothing tells keeps the generic rule
+        assert_eq!(
+            id(&[r#"git push --force origin feature; sh -c "git push --force --all origin""#]),
+            "GIT001"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+use std::path::{Path, PathBuf};
+
+/// A target directory with more entries than this takes long enough to rebuild to warn about.
+const HUGE_TARGET_ENTRIES: usize = 5000;
+
+/// Flag a full `cargo clean` whose target directory is huge: nothing is lost, but the next
+/// build starts from scratch. Partial cleans (`-p`, `--doc`) and dry runs are left alone.
+pub fn check_clean(cmd: &str, cwd: &Path, wrappers: &[String]) -> Option<Hit> {
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().and_then(|p| p.rsplit('/').next()) != Some("cargo")
+            || args.get(1) != Some(&"clean")
+        {
+            continue;
+        }
+        let rest = &args[2..];
+        if rest.iter().any(|a| {
+            matches!(*a, "-p" | "--package" | "--doc" | "-n" | "--dry-run")
+                || a.starts_with("--package=")
+        }) {
+            continue;
+        }
+        let explicit = rest
+            .iter()
+            .position(|a| *a == "--target-dir")
+            .and_then(|i| rest.get(i + 1).copied())
+            .or_else(|| rest.iter().find_map(|a| a.strip_prefix("--target-dir=")));
+        let Some(target) = explicit
+            .map(|d| cwd.join(d))
+            .or_else(|| default_target_dir(cwd))
+        else {
+            continue;
+        };
+        let entries = crate::scope::count_tree(&target, HUGE_TARGET_ENTRIES + 1);
+        if entries > HUGE_TARGET_ENTRIES {
+            let reason = format!(
+                "Rust toolchain: cargo clean deletes {} (more than {} files) — the next build \
+                 starts from scratch",
+                target.display(),
+                HUGE_TARGET_ENTRIES
+            );
+            return Some(Hit {
+                id: "RUST101",
+                reason,
+                category: Category::RustToolchain,
+            });
+        }
+    }
+    None
+}
+
+/// The `target` directory of the outermost Cargo workspace containing `cwd`.
+fn default_target_dir(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .filter(|d| d.join("Cargo.toml").is_file() && d.join("target").is_dir())
+        .last()
+        .map(|d| d.join("target"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn workspace(files: usize) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[workspace]\n").unwrap();
+        fs::create_dir_all(dir.path().join("crates/a")).unwrap();
+        fs::write(dir.path().join("crates/a/Cargo.toml"), "[package]\n").unwrap();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        for i in 0..files {
+            fs::write(dir.path().join(format!("target/debug/{}.o", i)), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn huge_target_dir_warns_from_any_member() {
+        let dir = workspace(HUGE_TARGET_ENTRIES);
+        let hit = check_clean("cargo clean", &dir.path().join("crates/a"), &[]).unwrap();
+        assert_eq!(hit.category, Category::RustToolchain);
+        assert!(
+            hit.reason
+                .contains(&format!("more than {} files", HUGE_TARGET_ENTRIES)),
+            "{}",
+            hit.reason
+        );
+        assert!(check_clean("cargo clean -p a", dir.path(), &[]).is_none());
+        assert!(check_clean("cargo clean --dry-run", dir.path(), &[]).is_none());
+    }
+
+    #[test]
+    fn small_or_missing_target_dir_is_fine() {
+        let dir = workspace(10);
+        assert!(check_clean("cargo clean", dir.path(), &[]).is_none());
+        assert!(check_clean("cargo clean --target-dir elsewhere", dir.path(), &[]).is_none());
+        assert!(check_clean("cargo build", dir.path(), &[]).is_none());
+    }
+}
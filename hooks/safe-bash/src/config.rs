@@ -1,131 +1,2097 @@
+use crate::dirs::Dirs;
+use crate::matcher::{CommandRule, Matcher, Unless};
+use crate::severity::{Category, Profile, Severities, Severity};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-/// A single pattern entry from the config file.
-#[derive(Deserialize, Debug)]
+/// A single pattern entry from the config file: a regex `pattern`, or a `command` rule on the
+/// parsed words of each simple command.
+#[derive(Deserialize, Debug, Clone)]
 pub struct ConfigPattern {
-    pub pattern: String,
+    /// The rule's ID, cited in block messages and named by `# safe-bash: allow` comments.
+    /// Rules without one are numbered CFG001, CFG002, … in load order, and cannot be suppressible.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// How `pattern` is read: "regex" (default), "fancy-regex", "glob", "contains" or "exact";
+    /// or "sha256", matching the one command whose hash is `value`.
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+    /// The hex SHA-256 of the exact command a "sha256" rule matches.
+    #[serde(default)]
+    pub value: Option<String>,
+    /// The program a command rule matches, e.g. "git".
+    #[serde(default)]
+    pub command: Option<String>,
+    /// The command rule's subcommand, e.g. "push".
+    #[serde(default)]
+    pub subcommand: Option<String>,
+    /// Flags of which a command rule needs at least one, e.g. ["--force", "-f"].
+    #[serde(default)]
+    pub flags_any: Vec<String>,
+    /// Exceptions to a deny rule; it does not apply to a simple command any of them holds for.
+    #[serde(default)]
+    pub unless: Vec<UnlessConfig>,
     pub reason: String,
+    /// What to do instead, shown under the reason when the deny rule blocks or asks, e.g.
+    /// "run ./deploy.sh --dry-run, or ask for a CI deploy".
+    #[serde(default)]
+    pub suggestion: Option<String>,
+    /// A deny rule no allow pattern can lift, in this layer or a later one.
+    #[serde(default)]
+    pub locked: bool,
+    /// What a deny rule does when it matches: "deny" (default), "ask", "warn" or "audit".
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// A `# safe-bash: allow <id>` comment or `SAFE_BASH_ALLOW=<id>` prefix lifts the deny rule
+    /// for that one command.
+    #[serde(default)]
+    pub suppressible: bool,
+    /// Example commands the merged policy must block, checked by `safe-bash-hook test-config`.
+    #[serde(default)]
+    pub should_block: Vec<String>,
+    /// Example commands the merged policy must let run.
+    #[serde(default)]
+    pub should_allow: Vec<String>,
+    /// The tools whose calls the rule checks, e.g. ["Write", "Edit"] (default ["Bash"]). A name
+    /// with `*` is a glob, e.g. "mcp__github__*".
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// A JSON path into another tool's input, e.g. "$.repo" or "$.files[*].path": the rule
+    /// matches the values there instead of the tool's usual subject.
+    #[serde(default)]
+    pub field: Option<String>,
+    /// The supervised hours of a deny rule, outside which it applies.
+    #[serde(default)]
+    pub schedule: Option<crate::schedule::ScheduleConfig>,
+    /// Zones a Bash rule's paths must lie in for it to match: "system", "home-config", "temp",
+    /// "project" or "outside-project". A deny rule matches when any path is in them, an allow
+    /// rule when every one is.
+    #[serde(default)]
+    pub zones: Vec<String>,
+    /// The paths `zones` looks at: "read", "write" or "delete" (default all of them).
+    #[serde(default)]
+    pub access: Option<String>,
+    /// The config file (or overlay) the rule is written in, recorded while the layers merge.
+    #[serde(default, rename = "_layer")]
+    pub layer: String,
+    /// That file's `policy`, recorded with it.
+    #[serde(default, rename = "_policy")]
+    pub policy: PolicyInfo,
+}
+
+/// One `unless` exception of a deny rule; every condition given must hold.
+#[derive(Deserialize, Debug, Clone)]
+pub struct UnlessConfig {
+    /// Regex the simple command's text matches.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Regex every operand of the simple command matches, e.g. "^(node_modules|target|dist)/".
+    #[serde(default)]
+    pub args_match: Option<String>,
+}
+
+/// Who owns the config file's rules, cited in block messages.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct PolicyInfo {
+    /// e.g. "ApolloTech org policy"
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Where to request an exception (URL, email, or channel).
+    #[serde(default)]
+    pub contact: Option<String>,
+}
+
+/// Where to send alerts for hook decisions (`alert_webhook` in the config).
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertWebhook {
+    pub url: String,
+    /// Decisions that raise an alert: "deny", "ask", "warn", "audit" (default: deny only).
+    #[serde(default = "default_alert_decisions")]
+    pub decisions: Vec<String>,
+}
+
+fn default_alert_decisions() -> Vec<String> {
+    vec!["deny".to_string()]
+}
+
+/// How long each project's audit log is kept (`audit_retention` in the config).
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditRetention {
+    /// Entries older than this are dropped (default 90).
+    #[serde(default = "default_max_age_days")]
+    pub max_age_days: u64,
+    /// A project log is trimmed to its newest entries past this size (default 10 MB).
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+}
+
+fn default_max_age_days() -> u64 {
+    90
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+impl Default for AuditRetention {
+    fn default() -> Self {
+        Self {
+            max_age_days: default_max_age_days(),
+            max_bytes: default_max_bytes(),
+        }
+    }
+}
+
+/// What to do when the same command keeps getting blocked in a session (`retry_loop` in the
+/// config).
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryLoop {
+    /// Blocks of one command a session gets before the message changes to "stop retrying" (default
+    /// 3).
+    #[serde(default = "default_max_repeats")]
+    pub max_repeats: u32,
+    /// Past `max_repeats`, ask the user instead of blocking again; blocks no config could lower
+    /// to an ask (root-wildcard and obfuscation rules, locked rules) keep blocking.
+    #[serde(default)]
+    pub escalate: bool,
+}
+
+fn default_max_repeats() -> u32 {
+    3
+}
+
+impl Default for RetryLoop {
+    fn default() -> Self {
+        Self {
+            max_repeats: default_max_repeats(),
+            escalate: false,
+        }
+    }
+}
+
+/// Running `sed -i` with a backup of each file instead of asking (`sed_backups` in the config).
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SedBackups {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Backups are deleted this many days after the edit that made them; kept forever if unset.
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+}
+
+/// A limit on how often matching commands may run (`quotas` in the config).
+#[derive(Deserialize, Debug, Clone)]
+pub struct QuotaConfig {
+    /// Regex matched against each simple command, with wrappers such as `sudo` peeled off.
+    pub pattern: String,
+    /// What the quota counts, for messages: "git push".
+    pub name: String,
+    /// Runs allowed per window.
+    pub max: u32,
+    /// "minute", "hour", "day" or "session" (default "hour").
+    #[serde(default = "default_quota_window")]
+    pub per: String,
+    /// "ask" or "deny" once the quota is used up (default "ask").
+    #[serde(default = "default_quota_exceed")]
+    pub exceed: String,
+}
+
+fn default_quota_window() -> String {
+    "hour".to_string()
+}
+
+fn default_quota_exceed() -> String {
+    "ask".to_string()
+}
+
+fn default_source_pinning() -> String {
+    "warn".to_string()
 }
 
 /// The structure of the optional ~/.claude/hooks/safe-bash-patterns.json file.
 #[derive(Deserialize, Debug, Default)]
 pub struct PatternsConfig {
     #[serde(default)]
+    #[allow(dead_code)]
     pub version: u32,
     #[serde(default)]
     pub deny: Vec<ConfigPattern>,
     #[serde(default)]
     pub allow: Vec<ConfigPattern>,
+    /// Apply root-only patterns and ignore allow overrides when running as root (default true).
+    #[serde(default)]
+    pub root_escalation: Option<bool>,
+    /// Built-in category defaults: "strict", "standard" (default) or "permissive".
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// "enforce" (default) or "report": decide and log as usual, but never block or ask.
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Default severity per hardcoded category, e.g. {"sensitive-read": "ask"}.
+    #[serde(default)]
+    pub category_severity: HashMap<String, String>,
+    /// Severity per hardcoded rule, keyed by its ID or reason, e.g. {"GIT002": "warn"}.
+    #[serde(default)]
+    pub rule_severity: HashMap<String, String>,
+    /// Hardcoded rules a `# safe-bash: allow <id>` comment or `SAFE_BASH_ALLOW=<id>` prefix may
+    /// lift for one command, e.g. ["GIT002"]; only down to the floor of the rule's category.
+    #[serde(default)]
+    pub suppressible_rules: Vec<String>,
+    /// Hardcoded rules whose commands are offered in a safer form instead of being blocked or
+    /// asked about as is, e.g. ["GIT001"] (see rewrite.rs).
+    #[serde(default)]
+    pub rewrite_rules: Vec<String>,
+    /// Extra prefix commands to look through, e.g. ["with-lock", "retry"].
+    #[serde(default)]
+    pub wrappers: Vec<String>,
+    /// Record an undo command (previous HEAD, remote tip) with ask decisions.
+    #[serde(default)]
+    pub rollback_hints: bool,
+    /// POST decisions to a webhook, queued while the endpoint is unreachable.
+    #[serde(default)]
+    pub alert_webhook: Option<AlertWebhook>,
+    /// Decide `rm -r` of relative globs by how many entries they expand to: block above
+    /// this many, warn at or below it.
+    #[serde(default)]
+    pub glob_delete_threshold: Option<usize>,
+    #[serde(default)]
+    pub audit_retention: AuditRetention,
+    /// Raise hardcoded matches in commands run over ssh/mosh one level (warn → ask → deny).
+    #[serde(default)]
+    pub remote_escalation: bool,
+    /// Built-in editor-state paths to leave unprotected, e.g. [".idea/"].
+    #[serde(default)]
+    pub unprotected_paths: Vec<String>,
+    /// Paths and globs no command may read, write or delete, e.g. ["~/.ssh", "/etc", ".git"];
+    /// relative entries are taken from the workspace root.
+    #[serde(default)]
+    pub protected_paths: Vec<String>,
+    /// Branch globs a force push or remote deletion may never reach, e.g. ["main", "release/*"];
+    /// with them set, force pushes to other branches are allowed.
+    #[serde(default)]
+    pub protected_branches: Vec<String>,
+    /// Directory names (or globs) to add to the built-in build artifacts a command made only of
+    /// `rm -r` calls inside the cwd may delete, e.g. ["vendor", ".terraform"].
+    #[serde(default)]
+    pub artifact_dirs: Vec<String>,
+    /// When a compound command is blocked, offer to run the segments before the blocked one.
+    #[serde(default)]
+    pub suggest_safe_prefix: bool,
+    /// Run `rm` of files inside the workspace as a move into the session's trash (see trash.rs).
+    #[serde(default)]
+    pub trash_deletes: bool,
+    #[serde(default)]
+    pub sed_backups: SedBackups,
+    /// Save what an allowed `rm` or `git reset --hard` may destroy before it runs (see
+    /// snapshot.rs).
+    #[serde(default)]
+    pub snapshots: bool,
+    #[serde(default)]
+    pub retry_loop: RetryLoop,
+    /// Rate limits on risky-but-legitimate commands, e.g. at most 3 `git push` per hour.
+    #[serde(default)]
+    pub quotas: Vec<QuotaConfig>,
+    /// "warn" (default) or "deny": what to do when the remote policy source's URL or server key
+    /// differs from the one seen on first use.
+    #[serde(default = "default_source_pinning")]
+    pub source_pinning: String,
+    /// Ask before running an npm/yarn/pnpm script whose body cannot be read from package.json.
+    #[serde(default)]
+    pub ask_unresolved_scripts: bool,
+    /// Resolve `make <target>` through the Makefile and check the recipe lines it runs.
+    #[serde(default)]
+    pub inspect_makefiles: bool,
+    /// Ask (or block, when already asking) when `tool_input.description` promises a read-only
+    /// look and the command changes something (default true).
+    #[serde(default)]
+    pub check_descriptions: Option<bool>,
+    /// Normalization stages to turn on or off, e.g. {"comments": true, "unicode": false}.
+    #[serde(default)]
+    pub normalization: HashMap<String, bool>,
+    /// Show the user a one-line summary of the session's decisions when it ends.
+    #[serde(default)]
+    pub session_summary: bool,
+    /// The engine for `"type": "regex"` rules: "regex" (default, linear time) or "fancy"
+    /// (lookaround and backreferences, as if every such rule were `"type": "fancy-regex"`).
+    #[serde(default)]
+    pub regex_engine: Option<String>,
 }
 
 /// A compiled config deny/allow entry.
 pub struct CompiledPattern {
-    pub re: Regex,
+    pub id: String,
+    pub matcher: Matcher,
     pub reason: String,
+    /// The safer alternative a deny rule suggests (`suggestion`).
+    pub suggestion: Option<String>,
+    pub severity: Severity,
+    pub unless: Vec<Unless>,
+    pub suppressible: bool,
+    /// Where in a tool call's input the rule looks (`field`); None for the usual subject.
+    pub field: Option<crate::tools::JsonPath>,
+    /// A deny rule's supervised hours (`schedule`); it is only in force outside them.
+    pub schedule: Option<crate::schedule::Schedule>,
+    /// Where the paths of a matching command must lie (`zones`, `access`).
+    pub zones: Option<crate::zones::ZoneRule>,
+    /// The config file the rule is written in, and its `policy`.
+    pub origin: Arc<Origin>,
 }
 
-/// Compiled result from loading the config file.
+/// Where a config or project rule is written: the file (or overlay) and that file's `policy`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Origin {
+    pub file: String,
+    pub policy: PolicyInfo,
+}
+
+impl CompiledPattern {
+    /// Whether the rule matches a command or segment: its matcher, then its zones.
+    pub fn is_match(&self, text: &str, wrappers: &[String]) -> bool {
+        self.matcher.is_match(text, wrappers)
+            && self.zones.as_ref().is_none_or(|z| z.holds(text, wrappers))
+    }
+
+    /// Whether the rule applies at this hour.
+    pub fn in_force(&self) -> bool {
+        self.schedule.as_ref().is_none_or(|s| s.applies_now())
+    }
+
+    /// The rule as a match; a scheduled rule's reason names the hours it gives way to.
+    pub fn found(&self) -> RuleMatch {
+        let reason = match &self.schedule {
+            Some(schedule) => format!("{} (outside {})", self.reason, schedule.describe()),
+            None => self.reason.clone(),
+        };
+        RuleMatch {
+            id: self.id.clone(),
+            reason,
+            suggestion: self.suggestion.clone(),
+            severity: self.severity,
+            origin: self.origin.clone(),
+        }
+    }
+}
+
+/// A config or project deny rule that matched a command, and what it asks the hook to do.
+#[derive(Debug, PartialEq)]
+pub struct RuleMatch {
+    pub id: String,
+    pub reason: String,
+    pub suggestion: Option<String>,
+    pub severity: Severity,
+    /// Where the rule is written, for the block message.
+    pub origin: Arc<Origin>,
+}
+
+impl RuleMatch {
+    /// The reason with the rule's ID: "deploys go through CI [CFG002]".
+    pub fn cited(&self) -> String {
+        format!("{} [{}]", self.reason, self.id)
+    }
+}
+
+/// The `should_block` and `should_allow` examples of one config rule.
+#[derive(Debug, PartialEq)]
+pub struct RuleExamples {
+    /// The rule, as test-config names it: `deny rule CFG002 "deploys go through CI"`.
+    pub rule: String,
+    /// The tools the examples are calls of, as the rule's `tools` (empty for Bash).
+    pub tools: Vec<String>,
+    pub should_block: Vec<String>,
+    pub should_allow: Vec<String>,
+}
+
+/// A compiled `quotas` entry.
+pub struct CompiledQuota {
+    pub re: Regex,
+    pub name: String,
+    pub max: u32,
+    pub window: crate::quota::Window,
+    /// Block rather than ask once the quota is used up.
+    pub deny: bool,
+}
+
+/// The config rules for the calls of one tool other than Bash, or of every tool a glob such as
+/// `mcp__github__*` names, matched against what a call touches (see `tools::subjects`).
 #[derive(Default)]
+pub struct ToolRules {
+    pub deny: Vec<CompiledPattern>,
+    pub locked_deny: Vec<CompiledPattern>,
+    pub allow: Vec<CompiledPattern>,
+}
+
+/// Compiled result from loading the config file.
 pub struct CompiledConfig {
     pub deny: Vec<CompiledPattern>,
+    /// Deny rules marked `locked`: checked before any allow pattern.
+    pub locked_deny: Vec<CompiledPattern>,
     pub allow: Vec<CompiledPattern>,
+    /// Rules for other tools' calls, by tool name or name glob.
+    pub tools: HashMap<String, ToolRules>,
+    pub root_escalation: bool,
+    pub severities: Severities,
+    /// IDs of the hardcoded rules that inline comments may suppress.
+    pub suppressible_rules: Vec<String>,
+    /// IDs of the hardcoded rules whose commands are rewritten into safer ones.
+    pub rewrite_rules: Vec<String>,
+    /// The files the rules were loaded from.
+    pub source: String,
+    /// Where an allow pattern overrides the config's denies: the user layer.
+    pub allow_file: String,
+    pub wrappers: Vec<String>,
+    pub rollback_hints: bool,
+    pub alert_webhook: Option<AlertWebhook>,
+    pub glob_delete_threshold: Option<usize>,
+    pub audit_retention: AuditRetention,
+    pub remote_escalation: bool,
+    pub unprotected_paths: Vec<String>,
+    pub protected_paths: Vec<String>,
+    pub protected_branches: Vec<String>,
+    pub artifact_dirs: Vec<String>,
+    pub suggest_safe_prefix: bool,
+    pub trash_deletes: bool,
+    pub sed_backups: SedBackups,
+    pub snapshots: bool,
+    /// Blocks and asks are only reported, and the command let through (`mode: "report"`).
+    pub report_only: bool,
+    pub retry_loop: RetryLoop,
+    pub quotas: Vec<CompiledQuota>,
+    /// Example commands of the rules that give any, in load order.
+    pub examples: Vec<RuleExamples>,
+    /// Refuse updates from a remote policy source that changed since first use.
+    pub refuse_changed_source: bool,
+    pub ask_unresolved_scripts: bool,
+    pub inspect_makefiles: bool,
+    pub check_descriptions: bool,
+    pub normalization: crate::normalize::Pipeline,
+    pub session_summary: bool,
+}
+
+impl Default for CompiledConfig {
+    fn default() -> Self {
+        Self {
+            deny: Vec::new(),
+            locked_deny: Vec::new(),
+            allow: Vec::new(),
+            tools: HashMap::new(),
+            root_escalation: true,
+            severities: Severities::default(),
+            suppressible_rules: Vec::new(),
+            rewrite_rules: Vec::new(),
+            source: String::new(),
+            allow_file: String::new(),
+            wrappers: Vec::new(),
+            rollback_hints: false,
+            alert_webhook: None,
+            glob_delete_threshold: None,
+            audit_retention: AuditRetention::default(),
+            remote_escalation: false,
+            unprotected_paths: Vec::new(),
+            protected_paths: Vec::new(),
+            protected_branches: Vec::new(),
+            artifact_dirs: Vec::new(),
+            suggest_safe_prefix: false,
+            trash_deletes: false,
+            sed_backups: SedBackups::default(),
+            snapshots: false,
+            report_only: false,
+            retry_loop: RetryLoop::default(),
+            quotas: Vec::new(),
+            examples: Vec::new(),
+            refuse_changed_source: false,
+            ask_unresolved_scripts: false,
+            inspect_makefiles: false,
+            check_descriptions: true,
+            normalization: crate::normalize::Pipeline::default(),
+            session_summary: false,
+        }
+    }
 }
 
-/// Load and compile patterns from the given path.
+impl CompiledConfig {
+    /// Who owns a config rule: the policy of the file it is written in, else that file.
+    fn owner(&self, found: &RuleMatch) -> String {
+        let file = if found.origin.file.is_empty() {
+            &self.source
+        } else {
+            &found.origin.file
+        };
+        found
+            .origin
+            .policy
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("config {}", file))
+    }
+
+    /// Where a config deny rule comes from and how to get past it.
+    pub fn layer_note(&self, found: &RuleMatch) -> String {
+        let name = self.owner(found);
+        match &found.origin.policy.contact {
+            Some(contact) => format!("rule from {} — request an exception via {}", name, contact),
+            None => format!(
+                "rule from {} — add an allow pattern in {} to override",
+                name, self.allow_file
+            ),
+        }
+    }
+
+    /// Put every scheduled deny rule in force, as outside its hours, so that test-config's
+    /// examples do not depend on when it runs.
+    pub fn ignore_schedules(&mut self) {
+        let tools = self
+            .tools
+            .values_mut()
+            .flat_map(|r| r.deny.iter_mut().chain(&mut r.locked_deny));
+        self.deny
+            .iter_mut()
+            .chain(&mut self.locked_deny)
+            .chain(tools)
+            .for_each(|p| p.schedule = None);
+    }
+
+    /// Where a locked config deny rule comes from; no allow pattern gets past it.
+    pub fn locked_note(&self, found: &RuleMatch) -> String {
+        let name = self.owner(found);
+        match &found.origin.policy.contact {
+            Some(contact) => format!(
+                "locked rule from {} — request an exception via {}",
+                name, contact
+            ),
+            None => format!(
+                "locked rule from {} — allow patterns cannot override it",
+                name
+            ),
+        }
+    }
+}
+
+/// Config layers by file stem, broadest first: the org policy fetched hourly from the remote
+/// source (in the cache directory), a team policy distributed alongside it, and the user's
+/// own file (both in the config directory).
+pub const LAYERS: &[(&str, &str)] = &[
+    ("org", "safe-bash-patterns"),
+    ("team", "safe-bash-team"),
+    ("user", "safe-bash-user"),
+];
+
+/// Extensions a hand-written config file may have, in the order they are looked for. All
+/// share one schema; TOML and YAML spare regexes a level of backslash escaping.
+const FORMATS: &[&str] = &["json", "toml", "yaml", "yml"];
+
+/// The file `<stem>.<ext>` under `dir` for the first of `formats` that exists, or the first
+/// format's name when none does.
+fn find_file(dir: &Path, stem: &str, formats: &[&str]) -> PathBuf {
+    let named = |ext: &&str| dir.join(format!("{}.{}", stem, ext));
+    formats
+        .iter()
+        .map(named)
+        .find(|p| p.is_file())
+        .unwrap_or_else(|| named(&formats[0]))
+}
+
+/// A config file's format by its extension: TOML for `.toml`, YAML for `.yaml`/`.yml`, JSON
+/// otherwise.
+fn format_of(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => "TOML",
+        Some("yaml" | "yml") => "YAML",
+        _ => "JSON",
+    }
+}
+
+/// Read and parse a config file in the format its extension names.
+pub fn read_file(path: &Path) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    parse(path, &text).map_err(|e| format!("malformed {}: {}", format_of(path), e))
+}
+
+/// Parse a config file in the format its extension names.
+fn parse(path: &Path, text: &str) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    match format_of(path) {
+        "TOML" => toml::from_str(text).map_err(|e| e.message().to_string()),
+        "YAML" => serde_yaml_ng::from_str(text).map_err(|e| e.to_string()),
+        _ => serde_json::from_str(text).map_err(|e| e.to_string()),
+    }
+}
+
+/// Settings whose lists add up across layers instead of a later layer replacing them.
+const ADDITIVE_KEYS: &[&str] = &[
+    "deny",
+    "allow",
+    "wrappers",
+    "quotas",
+    "unprotected_paths",
+    "protected_paths",
+    "protected_branches",
+    "artifact_dirs",
+    "suppressible_rules",
+    "rewrite_rules",
+];
+
+/// Environment variable naming the user layer's file, in place of `safe-bash-user.*` under
+/// the config directory.
+pub const CONFIG_VAR: &str = "SAFE_BASH_CONFIG";
+
+/// Load and compile the config layers in `dirs`, merged in `LAYERS` order, with the `paths`
+/// overlays of the directory trees `cwd` is in applied on top.
+pub fn load(dirs: &Dirs, cwd: Option<&Path>) -> CompiledConfig {
+    // The org file is replaced by the hourly update, which only fetches JSON
+    let mut paths: Vec<PathBuf> = LAYERS
+        .iter()
+        .map(|(layer, stem)| {
+            if *layer == "org" {
+                find_file(&dirs.cache, stem, &FORMATS[..1])
+            } else {
+                find_file(&dirs.config, stem, FORMATS)
+            }
+        })
+        .collect();
+    if let Some(user) = std::env::var_os(CONFIG_VAR).filter(|v| !v.is_empty()) {
+        *paths.last_mut().expect("LAYERS is not empty") = PathBuf::from(user);
+    }
+    let mut compiled = load_layers(&paths, &dirs.cache, true, cwd);
+    compiled.allow_file = paths[paths.len() - 1].display().to_string();
+    compiled
+}
+
+/// Load and compile patterns from the given path as the only layer (with what it extends).
 /// Returns an empty config if the file doesn't exist or has errors (non-fatal).
 pub fn load_config(path: &Path) -> CompiledConfig {
+    load_layers(
+        &[path.to_path_buf()],
+        path.parent().unwrap_or(Path::new(".")),
+        false,
+        None,
+    )
+}
+
+/// One layer's settings, or None if the file is missing or unreadable.
+fn read_layer(path: &Path) -> Option<serde_json::Map<String, serde_json::Value>> {
     if !path.exists() {
-        return CompiledConfig::default();
+        return None;
     }
-
     let contents = match fs::read_to_string(path) {
         Ok(s) => s,
         Err(e) => {
-            eprintln!("safe-bash-hook: warn: could not read {}: {}", path.display(), e);
-            return CompiledConfig::default();
+            crate::warnings::warn(
+                "config-read",
+                &format!("could not read {}: {}", path.display(), e),
+            );
+            return None;
         }
     };
-
-    let config: PatternsConfig = match serde_json::from_str(&contents) {
-        Ok(c) => c,
+    match parse(path, &contents) {
+        Ok(map) => Some(map),
         Err(e) => {
-            eprintln!(
-                "safe-bash-hook: warn: malformed JSON in {}: {} — using hardcoded patterns only",
-                path.display(),
-                e
+            let format = format_of(path);
+            let kind = format!("config-{}", format.to_lowercase());
+            crate::warnings::warn(
+                &kind,
+                &format!(
+                    "malformed {} in {}: {} — skipping this file",
+                    format,
+                    path.display(),
+                    e
+                ),
+            );
+            None
+        }
+    }
+}
+
+/// How many files deep `extends` and `include` are followed.
+const MAX_COMPOSE_DEPTH: usize = 8;
+
+/// A config file's layer together with the files it names in `extends` and `include`, each a
+/// path (relative to the file) or an https URL: every base comes before the file, with its own
+/// bases before it, and every include after it. A file already read is not read again.
+fn read_composed(source: String, path: &Path, cache_dir: &Path, depth: usize, read: &mut Composed) {
+    // The same file may be named by different relative paths
+    let key = fs::canonicalize(path).map_or_else(|_| source.clone(), |p| p.display().to_string());
+    if read.seen.contains(&key) {
+        return;
+    }
+    read.seen.push(key);
+    read.files.push((path.to_path_buf(), FileStamp::of(path)));
+    let Some(mut layer) = read_layer(path) else {
+        return;
+    };
+    let mut named = |key: &str| -> Vec<String> {
+        match layer.remove(key) {
+            None => Vec::new(),
+            Some(serde_json::Value::String(one)) => vec![one],
+            Some(serde_json::Value::Array(list)) if list.iter().all(|v| v.is_string()) => list
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect(),
+            Some(other) => {
+                crate::warnings::warn(
+                    "config-extends",
+                    &format!(
+                        "{}: ignoring {} {}: expected a list of paths or URLs",
+                        source, key, other
+                    ),
+                );
+                Vec::new()
+            }
+        }
+    };
+    let (bases, includes) = (named("extends"), named("include"));
+    let compose = |reference: &str, read: &mut Composed| {
+        if depth >= MAX_COMPOSE_DEPTH {
+            crate::warnings::warn(
+                "config-extends",
+                &format!(
+                    "{}: not following {:?}: more than {} files deep",
+                    source, reference, MAX_COMPOSE_DEPTH
+                ),
+            );
+            return;
+        }
+        let found = if reference.starts_with("https://") {
+            read.urls.push(reference.to_string());
+            cached_remote(reference, cache_dir).map(|cached| (reference.to_string(), cached))
+        } else if reference.contains("://") || source.starts_with("https://") {
+            // A fetched file could otherwise read local files by relative path
+            crate::warnings::warn(
+                "config-extends",
+                &format!(
+                    "{}: not following {:?}: only local files may name local files, and URLs must \
+                     be https",
+                    source, reference
+                ),
+            );
+            None
+        } else {
+            let local = path.parent().unwrap_or(Path::new(".")).join(reference);
+            let name = fs::canonicalize(&local).unwrap_or_else(|_| local.clone());
+            Some((name.display().to_string(), local))
+        };
+        if let Some((name, file)) = found {
+            read_composed(name, &file, cache_dir, depth + 1, read);
+        }
+    };
+    for base in &bases {
+        compose(base, read);
+    }
+    read.layers.push((source.clone(), layer));
+    for include in &includes {
+        compose(include, read);
+    }
+}
+
+/// The cached copy of a config file named by URL, refreshed in the background hourly like the
+/// org policy. None until the first fetch has finished.
+fn cached_remote(url: &str, cache_dir: &Path) -> Option<PathBuf> {
+    let cached = refresh_remote(url, cache_dir);
+    if !cached.is_file() {
+        crate::warnings::warn(
+            "config-extends",
+            &format!(
+                "{} is being fetched; its rules apply once it has arrived",
+                url
+            ),
+        );
+        return None;
+    }
+    Some(cached)
+}
+
+/// Where the copy of a config file named by URL is kept, after starting a fetch of it in the
+/// background when the last one was over an hour ago.
+fn refresh_remote(url: &str, cache_dir: &Path) -> PathBuf {
+    let cached = cache_dir.join(format!(
+        "safe-bash-extends-{:016x}.json",
+        crate::inventory::fnv1a(url.as_bytes())
+    ));
+    let stamp = cached.with_extension("last_update");
+    if crate::autoupdate::update_needed(&stamp) {
+        crate::autoupdate::touch_timestamp(&stamp);
+        if let Err(e) = crate::autoupdate::spawn_fetch(url, &cached) {
+            crate::warnings::warn("update-spawn", &e);
+        }
+    }
+    cached
+}
+
+/// The layers read from a set of config files with the files they compose, and what was read
+/// to get them. Kept in the cache directory between calls (see `read_sources`).
+#[derive(Serialize, Deserialize, Default)]
+struct Composed {
+    /// The hook version that read them: another may read the same files differently.
+    version: String,
+    /// The fingerprint of the machine that read them (see host.rs): a cache copied from
+    /// another machine is read again rather than trusted.
+    #[serde(default)]
+    host: String,
+    /// Every file read or looked for, and how it was then (None when it was missing).
+    files: Vec<(PathBuf, Option<FileStamp>)>,
+    /// The URLs of files read from their cached copies.
+    urls: Vec<String>,
+    layers: Vec<(String, serde_json::Map<String, serde_json::Value>)>,
+    #[serde(skip)]
+    seen: Vec<String>,
+}
+
+/// A config file's modification time, size and FNV-1a hash: a file saved twice within the
+/// filesystem's timestamp granularity still changes its hash.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct FileStamp {
+    modified_ns: u128,
+    len: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<FileStamp> {
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        let bytes = fs::read(path).ok()?;
+        Some(FileStamp {
+            modified_ns: modified
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos()),
+            len: bytes.len() as u64,
+            hash: crate::inventory::fnv1a(&bytes),
+        })
+    }
+}
+
+/// The layers of the config files at `paths`, with what they extend and include, in order.
+/// Parsing and composing them is kept in the cache directory, keyed by the paths, and used
+/// again while every file read is unchanged; files that gave warnings are read every time so
+/// the warnings are not lost. Unless `cached`, the files are read and nothing is kept.
+fn read_sources(
+    paths: &[PathBuf],
+    cache_dir: &Path,
+    cached: bool,
+) -> Vec<(String, serde_json::Map<String, serde_json::Value>)> {
+    let key: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    let cache = cache_dir.join("safe-bash-cache").join(format!(
+        "config-{:016x}.json",
+        crate::inventory::fnv1a(key.join("\n").as_bytes())
+    ));
+    if cached {
+        let hit = fs::read_to_string(&cache)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Composed>(&s).ok())
+            .filter(|c| {
+                c.version == env!("CARGO_PKG_VERSION")
+                    && c.host == crate::host::fingerprint()
+                    && c.files
+                        .iter()
+                        .all(|(path, stamp)| FileStamp::of(path) == *stamp)
+            });
+        if let Some(composed) = hit {
+            for url in &composed.urls {
+                refresh_remote(url, cache_dir);
+            }
+            return composed.layers;
+        }
+    }
+    let warned = crate::warnings::reported();
+    let mut read = Composed {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        host: crate::host::fingerprint().to_string(),
+        ..Composed::default()
+    };
+    for path in paths {
+        read_composed(path.display().to_string(), path, cache_dir, 0, &mut read);
+    }
+    if cached && crate::warnings::reported() == warned {
+        let saved = cache
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&cache, serde_json::to_string(&read).unwrap_or_default()));
+        if let Err(e) = saved {
+            crate::warnings::warn(
+                "config-cache",
+                &format!("could not write config cache {}: {}", cache.display(), e),
+            );
+        }
+    }
+    read.layers
+}
+
+/// Settings a `paths` or `roles` overlay cannot set: they compose files, lock settings or pick
+/// the overlays.
+const NOT_IN_OVERLAYS: &[&str] = &["extends", "include", "paths", "roles", "identity", "locked"];
+
+/// Drop the settings an overlay cannot set, with a warning naming the overlay.
+fn strip_overlay(
+    kind: &str,
+    source: &str,
+    name: &str,
+    overlay: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    for key in NOT_IN_OVERLAYS {
+        if overlay.remove(*key).is_some() {
+            crate::warnings::warn(
+                &format!("config-{}", kind),
+                &format!(
+                    "{}: ignoring {:?} in {} {:?}: only a config file may set it",
+                    source, key, kind, name
+                ),
+            );
+        }
+    }
+}
+
+/// The overlays of the layers' `roles` tables, `{"<role>": {<settings>}}`, for `roles`: for each
+/// role in turn, its overlay in every layer that has one, in layer order.
+fn role_overlays(
+    layers: &mut [(String, serde_json::Map<String, serde_json::Value>)],
+    roles: &[String],
+) -> Vec<(String, serde_json::Map<String, serde_json::Value>)> {
+    let mut tables = Vec::new();
+    for (source, layer) in layers.iter_mut() {
+        match layer.remove("roles") {
+            None => {}
+            Some(serde_json::Value::Object(table)) => tables.push((source.clone(), table)),
+            Some(other) => crate::warnings::warn(
+                "config-roles",
+                &format!(
+                    "{}: ignoring roles {}: expected a table of role names",
+                    source, other
+                ),
+            ),
+        }
+    }
+    let mut overlays = Vec::new();
+    for role in roles {
+        let mut defined = false;
+        for (source, table) in &tables {
+            match table.get(role) {
+                None => {}
+                Some(serde_json::Value::Object(overlay)) => {
+                    let mut overlay = overlay.clone();
+                    strip_overlay("roles", source, role, &mut overlay);
+                    overlays.push((format!("{} roles[{:?}]", source, role), overlay));
+                    defined = true;
+                }
+                Some(_) => crate::warnings::warn(
+                    "config-roles",
+                    &format!(
+                        "{}: ignoring roles {:?}: expected a table of settings",
+                        source, role
+                    ),
+                ),
+            }
+        }
+        if !defined {
+            crate::warnings::warn(
+                "config-roles",
+                &format!("role {:?} has no settings in any config layer", role),
+            );
+        }
+    }
+    overlays
+}
+
+/// The overlays of a layer's `paths` table, `{"<directory glob>": {<settings>}}`, that apply in
+/// `cwd`: those whose glob (`~/` for the home directory, `*` across `/` like `case`) matches
+/// `cwd` or a directory above it, most general first. None without a cwd.
+fn path_overlays(
+    source: &str,
+    paths: Option<serde_json::Value>,
+    cwd: Option<&Path>,
+) -> Vec<(String, serde_json::Map<String, serde_json::Value>)> {
+    let table = match paths {
+        None => return Vec::new(),
+        Some(serde_json::Value::Object(table)) => table,
+        Some(other) => {
+            crate::warnings::warn(
+                "config-paths",
+                &format!(
+                    "{}: ignoring paths {}: expected a table of directory globs",
+                    source, other
+                ),
+            );
+            return Vec::new();
+        }
+    };
+    let Some(cwd) = cwd else { return Vec::new() };
+    let home = std::env::var("HOME").unwrap_or_default();
+    let mut overlays = Vec::new();
+    for (glob, overlay) in table {
+        let serde_json::Value::Object(mut overlay) = overlay else {
+            crate::warnings::warn(
+                "config-paths",
+                &format!(
+                    "{}: ignoring paths {:?}: expected a table of settings",
+                    source, glob
+                ),
+            );
+            continue;
+        };
+        let expanded = match glob.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+            _ => glob.clone(),
+        };
+        let re = match crate::matcher::glob_regex(expanded.trim_end_matches('/')) {
+            Ok(re) => re,
+            Err(e) => {
+                crate::warnings::warn(
+                    "config-paths",
+                    &format!("{}: ignoring paths {:?}: {}", source, glob, e),
+                );
+                continue;
+            }
+        };
+        if !cwd
+            .ancestors()
+            .any(|dir| re.is_match(&dir.to_string_lossy()))
+        {
+            continue;
+        }
+        strip_overlay("paths", source, &glob, &mut overlay);
+        overlays.push((
+            glob.len(),
+            (format!("{} paths[{:?}]", source, glob), overlay),
+        ));
+    }
+    // A longer glob names a narrower tree, so it wins
+    overlays.sort_by_key(|(len, _)| *len);
+    overlays.into_iter().map(|(_, overlay)| overlay).collect()
+}
+
+/// Merge layers, broadest first. A later layer adds to the rule lists (`deny`, `allow`,
+/// `wrappers`, ...), merges into the tables (`category_severity`, `policy`, ...) and replaces
+/// everything else, except the settings an earlier layer named in its `locked` list: those
+/// are ignored with a warning.
+fn merge_layers(
+    layers: Vec<(String, serde_json::Map<String, serde_json::Value>)>,
+) -> serde_json::Map<String, serde_json::Value> {
+    use serde_json::Value;
+    let mut merged = serde_json::Map::new();
+    let mut locked: Vec<String> = Vec::new();
+    for (source, mut layer) in layers {
+        let locks = layer.remove("locked");
+        for (key, value) in layer {
+            if locked.contains(&key) {
+                crate::warnings::warn(
+                    "config-locked",
+                    &format!(
+                        "{}: ignoring {:?}: locked by an earlier config layer",
+                        source, key
+                    ),
+                );
+                continue;
+            }
+            match (merged.get_mut(&key), value) {
+                (Some(Value::Array(have)), Value::Array(more))
+                    if ADDITIVE_KEYS.contains(&key.as_str()) =>
+                {
+                    have.extend(more)
+                }
+                (Some(Value::Object(have)), Value::Object(more)) => have.extend(more),
+                (_, value) => {
+                    merged.insert(key, value);
+                }
+            }
+        }
+        match locks {
+            None => {}
+            Some(Value::Array(keys)) => {
+                locked.extend(keys.iter().filter_map(|k| k.as_str()).map(String::from))
+            }
+            Some(other) => crate::warnings::warn(
+                "config-locked",
+                &format!(
+                    "{}: ignoring locked {}: expected a list of settings",
+                    source, other
+                ),
+            ),
+        }
+    }
+    merged
+}
+
+/// Environment variable that selects the profile, over the config files' `profile`.
+pub const PROFILE_VAR: &str = "SAFE_BASH_PROFILE";
+
+/// Environment variable that selects the mode, over the config files' `mode`.
+pub const MODE_VAR: &str = "SAFE_BASH_MODE";
+
+/// Load and compile the given layers. Layers that are missing or malformed are skipped; if
+/// the merged settings do not fit the schema, only the hardcoded patterns apply.
+fn load_layers(
+    paths: &[PathBuf],
+    cache_dir: &Path,
+    cached: bool,
+    cwd: Option<&Path>,
+) -> CompiledConfig {
+    let mut layers = read_sources(paths, cache_dir, cached);
+    let policies: Vec<(String, Option<serde_json::Value>)> = layers
+        .iter()
+        .map(|(source, layer)| (source.clone(), layer.get("policy").cloned()))
+        .collect();
+    // The overlays for who runs the hook apply after every file, then those for where the
+    // command runs, in layer order
+    let roles = crate::identity::roles(&layers);
+    let role_overlays = role_overlays(&mut layers, &roles);
+    let overlays: Vec<_> = layers
+        .iter_mut()
+        .flat_map(|(source, layer)| path_overlays(source, layer.remove("paths"), cwd))
+        .collect();
+    for (_, layer) in &mut layers {
+        layer.remove("identity");
+    }
+    layers.extend(role_overlays);
+    layers.extend(overlays);
+    for (source, layer) in &mut layers {
+        flatten_tool_sections(source, layer);
+        // An overlay's rules belong to the file it is in, and to that file's policy unless it has
+        // its own
+        let file = policies
+            .iter()
+            .find(|(file, _)| source == file || source.starts_with(&format!("{} ", file)));
+        let policy = layer
+            .get("policy")
+            .cloned()
+            .or_else(|| file.and_then(|(_, policy)| policy.clone()));
+        record_origin(source, policy, layer);
+    }
+    let sources: Vec<String> = layers.iter().map(|(source, _)| source.clone()).collect();
+    let allow_file = paths
+        .iter()
+        .rfind(|p| p.exists())
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    // The profile variable acts as a last layer: it wins over the files unless one locks `profile`
+    if let Ok(profile) = std::env::var(PROFILE_VAR) {
+        layers.push((
+            PROFILE_VAR.to_string(),
+            serde_json::Map::from_iter([(
+                "profile".to_string(),
+                serde_json::Value::String(profile),
+            )]),
+        ));
+    }
+    // So does the mode variable: a team can try report mode on one machine, or lock enforce for
+    // everyone
+    if let Ok(mode) = std::env::var(MODE_VAR) {
+        layers.push((
+            MODE_VAR.to_string(),
+            serde_json::Map::from_iter([("mode".to_string(), serde_json::Value::String(mode))]),
+        ));
+    }
+    if layers.is_empty() {
+        return CompiledConfig::default();
+    }
+
+    let mut config: PatternsConfig =
+        match serde_json::from_value(serde_json::Value::Object(merge_layers(layers))) {
+            Ok(c) => c,
+            Err(e) => {
+                crate::warnings::warn(
+                    "config-json",
+                    &format!(
+                        "invalid config in {}: {} — using hardcoded patterns only",
+                        sources.join(" and "),
+                        e
+                    ),
+                );
+                return CompiledConfig::default();
+            }
+        };
+
+    let mut compiled = CompiledConfig {
+        root_escalation: config.root_escalation.unwrap_or(true),
+        source: sources.join(" and "),
+        allow_file,
+        wrappers: config.wrappers.clone(),
+        rollback_hints: config.rollback_hints,
+        alert_webhook: config.alert_webhook.clone(),
+        glob_delete_threshold: config.glob_delete_threshold,
+        audit_retention: config.audit_retention.clone(),
+        remote_escalation: config.remote_escalation,
+        unprotected_paths: config.unprotected_paths.clone(),
+        protected_paths: config.protected_paths.clone(),
+        protected_branches: config.protected_branches.clone(),
+        artifact_dirs: config.artifact_dirs.clone(),
+        suggest_safe_prefix: config.suggest_safe_prefix,
+        trash_deletes: config.trash_deletes,
+        sed_backups: config.sed_backups.clone(),
+        snapshots: config.snapshots,
+        retry_loop: config.retry_loop.clone(),
+        ask_unresolved_scripts: config.ask_unresolved_scripts,
+        inspect_makefiles: config.inspect_makefiles,
+        check_descriptions: config.check_descriptions.unwrap_or(true),
+        normalization: crate::normalize::Pipeline::from_config(&config.normalization),
+        session_summary: config.session_summary,
+        ..CompiledConfig::default()
+    };
+
+    for (i, rule) in config.deny.iter_mut().enumerate() {
+        // A number moves when rules are added above it, so a comment naming it could lift another
+        // rule
+        if rule.id.is_none() && rule.suppressible {
+            crate::warnings::warn(
+                "config-rule",
+                &format!(
+                    "deny rule {:?} is suppressible but has no id, so it cannot be lifted inline; \
+                     give it an `id`",
+                    rule.reason
+                ),
+            );
+            rule.suppressible = false;
+        }
+        rule.id.get_or_insert_with(|| format!("CFG{:03}", i + 1));
+    }
+    compiled.examples = rule_examples(&mut config.deny, "deny")
+        .chain(rule_examples(&mut config.allow, "allow"))
+        .collect();
+    let (deny, tool_deny) = split_by_tool(config.deny, "deny");
+    let (allow, tool_allow) = split_by_tool(config.allow, "allow");
+    let (locked, deny): (Vec<ConfigPattern>, Vec<ConfigPattern>) =
+        deny.into_iter().partition(|p| p.locked);
+    let regex_type = match config.regex_engine.as_deref() {
+        None | Some("regex") => "regex",
+        Some("fancy") => "fancy-regex",
+        Some(other) => {
+            crate::warnings::warn(
+                "config-regex",
+                &format!("ignoring regex_engine {:?}: expected regex or fancy", other),
             );
-            return CompiledConfig::default();
+            "regex"
         }
     };
+    // Zone rules resolve relative paths from where the command runs
+    let base = cwd
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    compiled.locked_deny = compile_patterns(locked, "deny", regex_type, &base);
+    compiled.deny = compile_patterns(deny, "deny", regex_type, &base);
+    compiled.allow = compile_patterns(allow, "allow", regex_type, &base);
+    for (tool, rules) in tool_deny {
+        let (locked, deny): (Vec<ConfigPattern>, Vec<ConfigPattern>) =
+            rules.into_iter().partition(|p| p.locked);
+        let entry = compiled.tools.entry(tool).or_default();
+        entry.locked_deny = compile_patterns(locked, "deny", regex_type, &base);
+        entry.deny = compile_patterns(deny, "deny", regex_type, &base);
+    }
+    for (tool, rules) in tool_allow {
+        compiled.tools.entry(tool).or_default().allow =
+            compile_patterns(rules, "allow", regex_type, &base);
+    }
+    compiled.quotas = compile_quotas(config.quotas);
 
-    let mut compiled = CompiledConfig::default();
+    match config.source_pinning.as_str() {
+        "warn" => {}
+        "deny" => compiled.refuse_changed_source = true,
+        other => crate::warnings::warn(
+            "config-pinning",
+            &format!("ignoring source_pinning {:?}: expected warn or deny", other),
+        ),
+    }
 
-    for entry in config.deny {
-        match Regex::new(&entry.pattern) {
-            Ok(re) => compiled.deny.push(CompiledPattern { re, reason: entry.reason }),
-            Err(e) => eprintln!(
-                "safe-bash-hook: warn: invalid deny regex {:?}: {}",
-                entry.pattern, e
+    match config.mode.as_deref() {
+        None | Some("enforce") => {}
+        Some("report") => compiled.report_only = true,
+        Some(other) => crate::warnings::warn(
+            "config-mode",
+            &format!("ignoring mode {:?}: expected enforce or report", other),
+        ),
+    }
+
+    if let Some(name) = &config.profile {
+        match Profile::from_name(name) {
+            Some(profile) => compiled.severities = Severities::for_profile(profile),
+            None => crate::warnings::warn(
+                "config-profile",
+                &format!(
+                    "ignoring profile {:?}: expected strict, standard or permissive",
+                    name
+                ),
             ),
         }
     }
 
-    for entry in config.allow {
-        match Regex::new(&entry.pattern) {
-            Ok(re) => compiled.allow.push(CompiledPattern { re, reason: entry.reason }),
-            Err(e) => eprintln!(
-                "safe-bash-hook: warn: invalid allow regex {:?}: {}",
-                entry.pattern, e
+    for (name, level) in &config.category_severity {
+        let (Some(category), Some(severity)) =
+            (Category::from_name(name), Severity::from_name(level))
+        else {
+            crate::warnings::warn(
+                "config-severity",
+                &format!("ignoring category_severity {:?}: {:?}", name, level),
+            );
+            continue;
+        };
+        if let Err(e) = compiled.severities.set(category, severity) {
+            crate::warnings::warn("config-severity", &format!("category_severity: {}", e));
+        }
+    }
+    let builtin: Vec<crate::patterns::DenyPattern> = crate::patterns::hardcoded_deny_patterns()
+        .into_iter()
+        .chain(crate::patterns::root_deny_patterns())
+        .collect();
+    if !config.rule_severity.is_empty() {
+        for (key, level) in &config.rule_severity {
+            // Keyed by ID, or by reason for every rule giving it
+            let rules: Vec<&crate::patterns::DenyPattern> = builtin
+                .iter()
+                .filter(|p| p.id == key || p.reason == key)
+                .collect();
+            let Some(severity) = Severity::from_name(level).filter(|_| !rules.is_empty()) else {
+                crate::warnings::warn(
+                    "config-severity",
+                    &format!("ignoring rule_severity {:?}: {:?}", key, level),
+                );
+                continue;
+            };
+            for rule in rules {
+                if let Err(e) = compiled
+                    .severities
+                    .set_rule(rule.category, rule.id, severity)
+                {
+                    crate::warnings::warn("config-severity", &format!("rule_severity: {}", e));
+                }
+            }
+        }
+    }
+
+    for id in &config.suppressible_rules {
+        match builtin.iter().find(|p| p.id == id) {
+            None => crate::warnings::warn(
+                "config-suppressible",
+                &format!("suppressible_rules: no built-in rule {:?}", id),
             ),
+            Some(p) if p.category.floor() == Severity::Deny => crate::warnings::warn(
+                "config-suppressible",
+                &format!(
+                    "suppressible_rules: {} is {}, which always blocks",
+                    id,
+                    p.category.as_str()
+                ),
+            ),
+            Some(p) => compiled.suppressible_rules.push(p.id.to_string()),
+        }
+    }
+
+    for id in &config.rewrite_rules {
+        if crate::rewrite::REWRITABLE.contains(&id.as_str()) {
+            compiled.rewrite_rules.push(id.clone());
+        } else {
+            let known = crate::rewrite::REWRITABLE.join(", ");
+            crate::warnings::warn(
+                "config-rewrite",
+                &format!(
+                    "rewrite_rules: {:?} has no rewrite (rewritable: {})",
+                    id, known
+                ),
+            );
         }
     }
 
+    for path in &compiled.unprotected_paths {
+        if !crate::protected::PROTECTED_PATHS
+            .iter()
+            .any(|p| p.trim_end_matches('/') == path.trim_end_matches('/'))
+        {
+            crate::warnings::warn(
+                "config-unprotected",
+                &format!("unprotected_paths: {:?} is not a protected path", path),
+            );
+        }
+    }
+    compiled.artifact_dirs.retain(|name| {
+        let usable = !name.contains('/') && !matches!(name.as_str(), "" | "." | ".." | "*");
+        if !usable {
+            crate::warnings::warn(
+                "config-artifacts",
+                &format!("artifact_dirs: {:?} is not a directory name", name),
+            );
+        }
+        usable
+    });
+
     compiled
 }
 
-/// Check a command against the compiled config patterns.
-/// Returns Ok(()) if allowed, Err(reason) if denied.
-/// allow overrides deny, but neither overrides the hardcoded patterns (handled by caller).
-pub fn check_config(cmd: &str, config: &CompiledConfig) -> Result<(), String> {
-    // If an allow pattern matches the full command, this config layer passes unconditionally.
-    for p in &config.allow {
-        if p.re.is_match(cmd) {
-            return Ok(());
+/// Take the examples out of deny or allow entries that give any.
+fn rule_examples<'a>(
+    entries: &'a mut [ConfigPattern],
+    kind: &'a str,
+) -> impl Iterator<Item = RuleExamples> + 'a {
+    entries
+        .iter_mut()
+        .filter(|e| !e.should_block.is_empty() || !e.should_allow.is_empty())
+        .map(move |e| RuleExamples {
+            rule: match &e.id {
+                Some(id) => format!("{} rule {} {:?}", kind, id, e.reason),
+                None => format!("{} rule {:?}", kind, e.reason),
+            },
+            tools: e.tools.clone(),
+            should_block: std::mem::take(&mut e.should_block),
+            should_allow: std::mem::take(&mut e.should_allow),
+        })
+}
+
+/// Record on each of a layer's `deny` and `allow` rules the file it is written in and that
+/// file's `policy`, for the block messages of the merged config.
+fn record_origin(
+    source: &str,
+    policy: Option<serde_json::Value>,
+    layer: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    for kind in ["deny", "allow"] {
+        let Some(serde_json::Value::Array(rules)) = layer.get_mut(kind) else {
+            continue;
+        };
+        for rule in rules.iter_mut().filter_map(|rule| rule.as_object_mut()) {
+            rule.insert("_layer".to_string(), serde_json::Value::from(source));
+            match &policy {
+                Some(policy) => rule.insert("_policy".to_string(), policy.clone()),
+                None => rule.remove("_policy"),
+            };
         }
     }
+}
 
-    // Check config deny patterns against the full command.
-    for p in &config.deny {
-        if p.re.is_match(cmd) {
-            return Err(p.reason.clone());
+/// Turn a layer's `tools` sections, `{"Write": {"deny": [...], "allow": [...]}}`, into deny and
+/// allow rules scoped to that tool, so they add up across layers like the other rules.
+fn flatten_tool_sections(source: &str, layer: &mut serde_json::Map<String, serde_json::Value>) {
+    use serde_json::Value;
+    let sections = match layer.remove("tools") {
+        None => return,
+        Some(Value::Object(sections)) => sections,
+        Some(other) => {
+            crate::warnings::warn(
+                "config-tools",
+                &format!(
+                    "{}: ignoring tools {}: expected a table of tool names",
+                    source, other
+                ),
+            );
+            return;
+        }
+    };
+    for (tool, section) in sections {
+        let Value::Object(mut section) = section else {
+            crate::warnings::warn(
+                "config-tools",
+                &format!(
+                    "{}: ignoring tools {:?}: expected deny and allow lists",
+                    source, tool
+                ),
+            );
+            continue;
+        };
+        for kind in ["deny", "allow"] {
+            let rules = match section.remove(kind) {
+                None => continue,
+                Some(Value::Array(rules)) => rules,
+                Some(other) => {
+                    crate::warnings::warn(
+                        "config-tools",
+                        &format!(
+                            "{}: ignoring tools {:?} {} {}: expected a list of rules",
+                            source, tool, kind, other
+                        ),
+                    );
+                    continue;
+                }
+            };
+            let scoped = rules.into_iter().map(|mut rule| {
+                if let Value::Object(fields) = &mut rule {
+                    fields.insert("tools".to_string(), Value::from(vec![tool.clone()]));
+                }
+                rule
+            });
+            match layer
+                .entry(kind)
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(list) => list.extend(scoped),
+                _ => crate::warnings::warn(
+                    "config-tools",
+                    &format!(
+                        "{}: ignoring tools {:?} {}: {} is not a list",
+                        source, tool, kind, kind
+                    ),
+                ),
+            }
+        }
+        for key in section.keys() {
+            crate::warnings::warn(
+                "config-tools",
+                &format!(
+                    "{}: ignoring {:?} in tools {:?}: expected deny and allow lists",
+                    source, key, tool
+                ),
+            );
         }
     }
+}
 
-    // Also check each split segment (catches compound commands like "echo ok && forbidden")
-    let segments = crate::patterns::split_command(cmd);
-    for segment in &segments {
-        // Check allow first for this segment
-        let mut segment_allowed = false;
-        for p in &config.allow {
-            if p.re.is_match(segment) {
-                segment_allowed = true;
-                break;
+/// Split deny or allow entries into the Bash rules and each other tool's share of the rest. A
+/// rule scoped to another tool is matched against a path or URL, not a command line, so
+/// `command` rules and `unless` exceptions are left to Bash; one with neither a pattern nor a
+/// command matches every call of the tool, as in `{"mcp__github__delete_repo": {"deny":
+/// [{"reason": "..."}]}}`.
+fn split_by_tool(
+    entries: Vec<ConfigPattern>,
+    kind: &str,
+) -> (Vec<ConfigPattern>, Vec<(String, Vec<ConfigPattern>)>) {
+    let mut bash = Vec::new();
+    let mut others: Vec<(String, Vec<ConfigPattern>)> = Vec::new();
+    for mut entry in entries {
+        for tool in entry.tools.iter().filter(|t| *t != "Bash") {
+            if entry.command.is_some() {
+                crate::warnings::warn(
+                    "config-tools",
+                    &format!(
+                        "ignoring {} rule {:?} for {}: command rules only check Bash",
+                        kind, entry.reason, tool
+                    ),
+                );
+                continue;
+            }
+            let mut scoped = entry.clone();
+            if !scoped.unless.is_empty() {
+                crate::warnings::warn(
+                    "config-tools",
+                    &format!(
+                        "{} rule {:?}: unless only applies to Bash, not {}",
+                        kind, entry.reason, tool
+                    ),
+                );
+                scoped.unless.clear();
+            }
+            if !scoped.zones.is_empty() {
+                crate::warnings::warn(
+                    "config-tools",
+                    &format!(
+                        "{} rule {:?}: zones only apply to Bash, not {}",
+                        kind, entry.reason, tool
+                    ),
+                );
+                scoped.zones.clear();
+            }
+            if scoped.pattern.is_none() && scoped.value.is_none() {
+                scoped.pattern = Some(String::new());
+                scoped.kind = Some("contains".to_string());
+            }
+            match others.iter_mut().find(|(name, _)| name == tool) {
+                Some((_, rules)) => rules.push(scoped),
+                None => others.push((tool.clone(), vec![scoped])),
             }
         }
-        if segment_allowed {
-            continue;
+        if entry.tools.is_empty() || entry.tools.iter().any(|t| t == "Bash") {
+            if entry.field.take().is_some() {
+                crate::warnings::warn(
+                    "config-tools",
+                    &format!(
+                        "{} rule {:?}: field only applies to other tools' calls, not Bash",
+                        kind, entry.reason
+                    ),
+                );
+            }
+            bash.push(entry);
+        }
+    }
+    (bash, others)
+}
+
+/// Compile deny or allow entries, skipping invalid regexes with a warning. Rules of the
+/// default type are compiled as `regex_type`, "regex" or "fancy-regex"; `zones` resolve
+/// relative paths from `cwd`.
+fn compile_patterns(
+    entries: Vec<ConfigPattern>,
+    kind: &str,
+    regex_type: &str,
+    cwd: &Path,
+) -> Vec<CompiledPattern> {
+    let mut compiled = Vec::new();
+    for entry in entries {
+        let severity = match entry
+            .severity
+            .as_deref()
+            .map(|name| (name, Severity::from_name(name)))
+        {
+            None => Severity::Deny,
+            Some((_, Some(severity))) if severity > Severity::Off => severity,
+            Some((name, _)) => {
+                crate::warnings::warn(
+                    "config-severity",
+                    &format!(
+                        "{} rule {:?}: unknown severity {:?}, using deny",
+                        kind, entry.reason, name
+                    ),
+                );
+                Severity::Deny
+            }
+        };
+        let matcher = match (entry.pattern, entry.command) {
+            (None, None) if entry.kind.as_deref() == Some("sha256") => {
+                match entry.value.map(|v| v.to_ascii_lowercase()) {
+                    Some(digest)
+                        if digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()) =>
+                    {
+                        Matcher::Sha256(digest)
+                    }
+                    _ => {
+                        crate::warnings::warn(
+                            "config-rule",
+                            &format!(
+                                "ignoring {} rule {:?}: a sha256 rule needs the 64 hex digits of \
+                                 the command's SHA-256 in value",
+                                kind, entry.reason
+                            ),
+                        );
+                        continue;
+                    }
+                }
+            }
+            (Some(pattern), None) => {
+                let compiled = match entry
+                    .kind
+                    .as_deref()
+                    .map(|t| if t == "regex" { regex_type } else { t })
+                    .unwrap_or(regex_type)
+                {
+                    "regex" => crate::matcher::regex(&pattern).map(Matcher::Regex),
+                    // Fails closed: a deny rule that gives up on a command matches it
+                    "fancy-regex" => crate::matcher::fancy_regex(&pattern)
+                        .map(|re| Matcher::fancy(re, kind == "deny")),
+                    "glob" => crate::matcher::glob_regex(&pattern).map(|re| Matcher::Glob {
+                        glob: pattern.clone(),
+                        re,
+                    }),
+                    "contains" => Ok(Matcher::Contains(pattern.clone())),
+                    "exact" => Ok(Matcher::Exact(pattern.clone())),
+                    "sha256" => {
+                        crate::warnings::warn(
+                            "config-rule",
+                            &format!(
+                                "ignoring {} rule {:?}: a sha256 rule takes the hash in value, \
+                                 not pattern",
+                                kind, entry.reason
+                            ),
+                        );
+                        continue;
+                    }
+                    other => {
+                        crate::warnings::warn(
+                            "config-rule",
+                            &format!(
+                                "ignoring {} rule {:?}: unknown type {:?}",
+                                kind, entry.reason, other
+                            ),
+                        );
+                        continue;
+                    }
+                };
+                match compiled {
+                    Ok(matcher) => matcher,
+                    Err(e) => {
+                        crate::warnings::warn(
+                            "config-regex",
+                            &format!("invalid {} regex {:?}: {}", kind, pattern, e),
+                        );
+                        continue;
+                    }
+                }
+            }
+            (None, Some(command)) => Matcher::Command(CommandRule {
+                command,
+                subcommand: entry.subcommand,
+                flags_any: entry.flags_any,
+            }),
+            // A zone rule on its own matches any command touching its zones
+            (None, None) if !entry.zones.is_empty() => Matcher::Contains(String::new()),
+            _ => {
+                crate::warnings::warn(
+                    "config-rule",
+                    &format!(
+                        "ignoring {} rule {:?}: give either a pattern, a command or zones",
+                        kind, entry.reason
+                    ),
+                );
+                continue;
+            }
+        };
+        let unless = entry
+            .unless
+            .into_iter()
+            .filter_map(|u| compile_unless(u, &entry.reason))
+            .collect();
+        if entry.locked && entry.suppressible {
+            crate::warnings::warn(
+                "config-rule",
+                &format!(
+                    "{} rule {:?} is locked, so it cannot be suppressible",
+                    kind, entry.reason
+                ),
+            );
+        }
+        let suppressible = entry.suppressible && !entry.locked;
+        let field = match entry.field.as_deref().map(crate::tools::JsonPath::parse) {
+            None => None,
+            Some(Ok(path)) => Some(path),
+            Some(Err(e)) => {
+                crate::warnings::warn(
+                    "config-rule",
+                    &format!("ignoring {} rule {:?}: field {}", kind, entry.reason, e),
+                );
+                continue;
+            }
+        };
+        // A schedule that cannot be read leaves the rule in force at all hours
+        let schedule = match (entry.schedule, kind) {
+            (None, _) => None,
+            (Some(_), "allow") => {
+                crate::warnings::warn(
+                    "config-rule",
+                    &format!(
+                        "allow rule {:?}: schedule only applies to deny rules",
+                        entry.reason
+                    ),
+                );
+                None
+            }
+            (Some(config), _) => crate::schedule::Schedule::parse(&config)
+                .map_err(|e| {
+                    crate::warnings::warn(
+                        "config-rule",
+                        &format!(
+                            "deny rule {:?}: ignoring its schedule, so it applies at all hours: {}",
+                            entry.reason, e
+                        ),
+                    )
+                })
+                .ok(),
+        };
+        let zones = match compile_zones(&entry.zones, entry.access.as_deref(), kind == "allow", cwd)
+        {
+            Ok(zones) => zones,
+            Err(e) => {
+                crate::warnings::warn(
+                    "config-rule",
+                    &format!("ignoring {} rule {:?}: {}", kind, entry.reason, e),
+                );
+                continue;
+            }
+        };
+        let origin = Arc::new(Origin {
+            file: entry.layer,
+            policy: entry.policy,
+        });
+        let (id, reason, suggestion) =
+            (entry.id.unwrap_or_default(), entry.reason, entry.suggestion);
+        compiled.push(CompiledPattern {
+            id,
+            matcher,
+            reason,
+            suggestion,
+            severity,
+            unless,
+            suppressible,
+            field,
+            schedule,
+            zones,
+            origin,
+        });
+    }
+    compiled
+}
+
+/// A rule's `zones` and `access`; None when it gives no zones.
+fn compile_zones(
+    names: &[String],
+    access: Option<&str>,
+    every: bool,
+    cwd: &Path,
+) -> Result<Option<crate::zones::ZoneRule>, String> {
+    if names.is_empty() {
+        if let Some(access) = access {
+            return Err(format!("access {:?} without zones", access));
         }
-        for p in &config.deny {
-            if p.re.is_match(segment) {
-                return Err(p.reason.clone());
+        return Ok(None);
+    }
+    let zones = names
+        .iter()
+        .map(|n| {
+            crate::zones::Zone::from_name(n).ok_or_else(|| {
+                format!(
+                    "unknown zone {:?}: expected system, home-config, temp, project or \
+                     outside-project",
+                    n
+                )
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let access = access
+        .map(|a| {
+            crate::zones::Access::from_name(a)
+                .ok_or_else(|| format!("unknown access {:?}: expected read, write or delete", a))
+        })
+        .transpose()?;
+    Ok(Some(crate::zones::ZoneRule {
+        zones,
+        access,
+        cwd: cwd.to_path_buf(),
+        every,
+    }))
+}
+
+/// Compile one `unless` exception of the rule `reason`. An invalid or empty one is skipped with a
+/// warning, which leaves the rule stricter rather than looser.
+fn compile_unless(entry: UnlessConfig, reason: &str) -> Option<Unless> {
+    let compile = |pattern: Option<String>| match pattern
+        .map(|p| crate::matcher::regex(&p).map_err(|e| (p, e)))
+        .transpose()
+    {
+        Ok(re) => Some(re),
+        Err((p, e)) => {
+            crate::warnings::warn(
+                "config-regex",
+                &format!("invalid unless regex {:?} in rule {:?}: {}", p, reason, e),
+            );
+            None
+        }
+    };
+    let unless = Unless {
+        pattern: compile(entry.pattern)?,
+        args_match: compile(entry.args_match)?,
+    };
+    if unless.pattern.is_none() && unless.args_match.is_none() {
+        crate::warnings::warn(
+            "config-rule",
+            &format!(
+                "ignoring an unless in rule {:?}: give a pattern or args_match",
+                reason
+            ),
+        );
+        return None;
+    }
+    Some(unless)
+}
+
+/// Compile `quotas` entries, skipping invalid ones with a warning.
+fn compile_quotas(entries: Vec<QuotaConfig>) -> Vec<CompiledQuota> {
+    let mut compiled = Vec::new();
+    for entry in entries {
+        let deny = match entry.exceed.as_str() {
+            "ask" => false,
+            "deny" => true,
+            other => {
+                crate::warnings::warn(
+                    "config-quota",
+                    &format!(
+                        "ignoring quota {:?}: exceed must be ask or deny, not {:?}",
+                        entry.name, other
+                    ),
+                );
+                continue;
             }
+        };
+        let Some(window) = crate::quota::Window::from_name(&entry.per) else {
+            crate::warnings::warn(
+                "config-quota",
+                &format!(
+                    "ignoring quota {:?}: unknown window {:?}",
+                    entry.name, entry.per
+                ),
+            );
+            continue;
+        };
+        match crate::matcher::regex(&entry.pattern) {
+            Ok(re) => compiled.push(CompiledQuota {
+                re,
+                name: entry.name,
+                max: entry.max,
+                window,
+                deny,
+            }),
+            Err(e) => crate::warnings::warn(
+                "config-regex",
+                &format!("invalid quota regex {:?}: {}", entry.pattern, e),
+            ),
+        }
+    }
+    compiled
+}
+
+/// Config files a repository can carry at its workspace root, by stem, looked for in this
+/// order (each in `FORMATS` order); the first one found is used.
+pub const PROJECT_FILES: &[&str] = &[".claude/safe-bash-patterns", ".safe-bash"];
+
+/// Settings a project config file may set. The others (severities, root escalation,
+/// unprotected paths, the policy owner, ...) would let a cloned repository loosen the user's
+/// own protection, so only the user config sets them. That includes wrappers: naming `rm` one
+/// would peel it off `rm -rf /` before the root rules see it.
+const PROJECT_KEYS: &[&str] = &[
+    "version",
+    "deny",
+    "allow",
+    "quotas",
+    "inspect_makefiles",
+    "ask_unresolved_scripts",
+];
+
+/// A repository's own config file. Its deny and allow rules join the project policy; its
+/// quotas and opt-in checks are merged into the user config.
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    pub deny: Vec<CompiledPattern>,
+    pub allow: Vec<CompiledPattern>,
+    quotas: Vec<CompiledQuota>,
+    inspect_makefiles: bool,
+    ask_unresolved_scripts: bool,
+}
+
+/// Load the first of `PROJECT_FILES` under the workspace root `root`. None when there is none
+/// or it cannot be read; keys a project may not set are ignored with a warning. Zone rules
+/// resolve relative paths from `cwd`.
+pub fn load_project(root: &Path, cwd: &Path) -> Option<ProjectConfig> {
+    let path = PROJECT_FILES
+        .iter()
+        .map(|stem| find_file(root, stem, FORMATS))
+        .find(|p| p.is_file())?;
+    let parsed = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            parse(&path, &text).map_err(|e| format!("malformed {}: {}", format_of(&path), e))
+        });
+    let mut value = match parsed {
+        Ok(map) => map,
+        Err(e) => {
+            crate::warnings::warn(
+                "config-project",
+                &format!("could not load {}: {}", path.display(), e),
+            );
+            return None;
+        }
+    };
+    value.retain(|key, _| {
+        let allowed = PROJECT_KEYS.contains(&key.as_str());
+        if !allowed {
+            crate::warnings::warn(
+                "config-project",
+                &format!(
+                    "{}: ignoring {:?}: only the user config may set it",
+                    path.display(),
+                    key
+                ),
+            );
         }
+        allowed
+    });
+    let config: PatternsConfig = match serde_json::from_value(serde_json::Value::Object(value)) {
+        Ok(c) => c,
+        Err(e) => {
+            crate::warnings::warn(
+                "config-project",
+                &format!("could not load {}: {}", path.display(), e),
+            );
+            return None;
+        }
+    };
+    // Only the user's config checks other tools
+    let bash_only = |rules: Vec<ConfigPattern>| -> Vec<ConfigPattern> {
+        rules
+            .into_iter()
+            .filter(|r| {
+                let bash = r.tools.is_empty() || r.tools.iter().any(|t| t == "Bash");
+                if !bash {
+                    crate::warnings::warn(
+                        "config-project",
+                        &format!(
+                            "{}: ignoring rule {:?}: a project config only checks Bash",
+                            path.display(),
+                            r.reason
+                        ),
+                    );
+                }
+                bash
+            })
+            .collect()
+    };
+    Some(ProjectConfig {
+        deny: compile_patterns(bash_only(config.deny), "deny", "regex", cwd),
+        allow: compile_patterns(bash_only(config.allow), "allow", "regex", cwd),
+        quotas: compile_quotas(config.quotas),
+        inspect_makefiles: config.inspect_makefiles,
+        ask_unresolved_scripts: config.ask_unresolved_scripts,
+        path,
+    })
+}
+
+impl CompiledConfig {
+    /// Add a project config's quotas to this one and turn on the checks it opts in to. A
+    /// project can add checks here but not turn the user's off.
+    pub fn merge_project(&mut self, project: &mut ProjectConfig) {
+        self.quotas.append(&mut project.quotas);
+        self.inspect_makefiles |= project.inspect_makefiles;
+        self.ask_unresolved_scripts |= project.ask_unresolved_scripts;
     }
+}
 
-    Ok(())
+/// Check a command against the compiled config patterns.
+/// Returns Ok(()) if allowed, Err with the most severe matching rule otherwise.
+/// allow overrides deny, but neither overrides the hardcoded patterns (handled by caller).
+pub fn check_config(cmd: &str, config: &CompiledConfig) -> Result<(), RuleMatch> {
+    check_patterns(cmd, &config.allow, &config.deny, &config.wrappers)
+}
+
+/// Check a command against the locked config deny rules, which no allow pattern lifts.
+pub fn check_locked(cmd: &str, config: &CompiledConfig) -> Result<(), RuleMatch> {
+    check_patterns(cmd, &[], &config.locked_deny, &config.wrappers)
+}
+
+/// Check a command against one layer's allow and deny patterns: the full command first, then
+/// each split segment (catches compound commands like "echo ok && forbidden"). A deny rule with
+/// `unless` exceptions is only checked on the segments. The most severe matching deny rule
+/// wins; on a tie the first one found is kept.
+pub fn check_patterns(
+    cmd: &str,
+    allow: &[CompiledPattern],
+    deny: &[CompiledPattern],
+    wrappers: &[String],
+) -> Result<(), RuleMatch> {
+    // If an allow pattern matches the full command, this layer passes unconditionally.
+    if allow.iter().any(|p| p.is_match(cmd, wrappers)) {
+        return Ok(());
+    }
+    let segments = crate::patterns::split_command(cmd, wrappers);
+    let mut worst: Option<&CompiledPattern> = None;
+    // Check allow first for each segment
+    let allowed = |s: &str| allow.iter().any(|p| p.is_match(s, wrappers));
+    let texts =
+        std::iter::once(cmd).chain(segments.iter().map(|s| s.as_str()).filter(|s| !allowed(s)));
+    for (i, text) in texts.enumerate() {
+        for p in deny
+            .iter()
+            .filter(|p| p.is_match(text, wrappers) && p.in_force())
+        {
+            // A rule with exceptions is weighed one simple command at a time, on the segments
+            if !p.unless.is_empty()
+                && (i == 0 || p.unless.iter().any(|u| u.holds(text, &p.matcher, wrappers)))
+            {
+                continue;
+            }
+            if p.severity == Severity::Deny {
+                return Err(p.found());
+            }
+            if worst.is_none_or(|w| p.severity > w.severity) {
+                worst = Some(p);
+            }
+        }
+    }
+    match worst {
+        Some(p) => Err(p.found()),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -157,7 +2123,8 @@ mod tests {
 
     #[test]
     fn valid_deny_pattern_loaded() {
-        let json = r#"{"version":1,"deny":[{"pattern":"\\bfoo\\b","reason":"test deny"}],"allow":[]}"#;
+        let json =
+            r#"{"version":1,"deny":[{"pattern":"\\bfoo\\b","reason":"test deny"}],"allow":[]}"#;
         let f = write_config(json);
         let config = load_config(f.path());
         assert_eq!(config.deny.len(), 1);
@@ -166,13 +2133,45 @@ mod tests {
 
     #[test]
     fn valid_allow_pattern_loaded() {
-        let json = r#"{"version":1,"deny":[],"allow":[{"pattern":"^git log\\b","reason":"safe read-only"}]}"#;
+        let json = r#"{"version":1,"deny":[],"allow":[{"pattern":"^git log\\b",
+            "reason":"safe read-only"}]}"#;
         let f = write_config(json);
         let config = load_config(f.path());
         assert!(config.deny.is_empty());
         assert_eq!(config.allow.len(), 1);
     }
 
+    #[test]
+    fn rule_examples_collected_with_rule_names() {
+        let json = r#"{"deny":[{"pattern":"^make deploy\\b","reason":"CI deploys",
+            "should_block":["make deploy"],"should_allow":["make test"]},
+            {"pattern":"^terraform apply","reason":"no applies","locked":true,
+            "should_block":["terraform apply"]},{"pattern":"x","reason":"no examples"}],
+            "allow":[{"pattern":"^make deploy --dry-run","reason":"dry runs",
+            "should_allow":["make deploy --dry-run"]}]}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        let rules: Vec<&str> = config.examples.iter().map(|e| e.rule.as_str()).collect();
+        assert_eq!(
+            rules,
+            [
+                "deny rule CFG001 \"CI deploys\"",
+                "deny rule CFG002 \"no applies\"",
+                "allow rule \"dry runs\""
+            ]
+        );
+        assert_eq!(
+            (
+                config.examples[0].should_block.as_slice(),
+                config.examples[0].should_allow.as_slice()
+            ),
+            (
+                &["make deploy".to_string()][..],
+                &["make test".to_string()][..]
+            )
+        );
+    }
+
     #[test]
     fn empty_arrays_ok() {
         let json = r#"{"version":1,"deny":[],"allow":[]}"#;
@@ -182,9 +2181,18 @@ mod tests {
         assert!(config.allow.is_empty());
     }
 
+    #[test]
+    fn root_escalation_defaults_on() {
+        let f = write_config(r#"{"deny":[],"allow":[]}"#);
+        assert!(load_config(f.path()).root_escalation);
+        let f = write_config(r#"{"root_escalation":false}"#);
+        assert!(!load_config(f.path()).root_escalation);
+    }
+
     #[test]
     fn config_deny_blocks_command() {
-        let json = r#"{"deny":[{"pattern":"\\bforbidden\\b","reason":"forbidden command"}],"allow":[]}"#;
+        let json =
+            r#"{"deny":[{"pattern":"\\bforbidden\\b","reason":"forbidden command"}],"allow":[]}"#;
         let f = write_config(json);
         let config = load_config(f.path());
         assert!(check_config("run forbidden now", &config).is_err());
@@ -205,9 +2213,41 @@ mod tests {
         assert!(check_config("run foo", &config).is_err());
     }
 
+    #[test]
+    fn fancy_regex_rules_opt_in_to_lookaround() {
+        let rule = r#"{"pattern":"\\brm\\s+-rf\\s+(?!node_modules\\b)",
+            "reason":"rm -rf outside node_modules"}"#;
+        let f = write_config(&format!(r#"{{"deny":[{}]}}"#, rule));
+        assert!(
+            load_config(f.path()).deny.is_empty(),
+            "the default engine has no lookahead"
+        );
+        for json in [
+            format!(
+                r#"{{"deny":[{}]}}"#,
+                rule.replacen('{', r#"{"type":"fancy-regex","#, 1)
+            ),
+            format!(r#"{{"regex_engine":"fancy","deny":[{}]}}"#, rule),
+        ] {
+            let f = write_config(&json);
+            let config = load_config(f.path());
+            assert_eq!(
+                config
+                    .deny
+                    .iter()
+                    .map(|p| p.matcher.kind())
+                    .collect::<Vec<_>>(),
+                ["fancy-regex"]
+            );
+            assert!(check_config("cd app && rm -rf src", &config).is_err());
+            assert!(check_config("rm -rf node_modules", &config).is_ok());
+        }
+    }
+
     #[test]
     fn invalid_regex_in_deny_skipped() {
-        let json = r#"{"deny":[{"pattern":"[invalid","reason":"bad pattern"},{"pattern":"\\bsafe\\b","reason":"good"}],"allow":[]}"#;
+        let json = r#"{"deny":[{"pattern":"[invalid","reason":"bad pattern"},
+            {"pattern":"\\bsafe\\b","reason":"good"}],"allow":[]}"#;
         let f = write_config(json);
         let config = load_config(f.path());
         // The valid pattern should still be loaded
@@ -216,7 +2256,8 @@ mod tests {
 
     #[test]
     fn config_deny_catches_compound_command() {
-        let json = r#"{"deny":[{"pattern":"^forbidden\\b","reason":"deny forbidden at start"}],"allow":[]}"#;
+        let json = r#"{"deny":[{"pattern":"^forbidden\\b","reason":"deny forbidden at start"}],
+            "allow":[]}"#;
         let f = write_config(json);
         let config = load_config(f.path());
         // "echo ok && forbidden thing" — full command does NOT start with "forbidden"
@@ -224,6 +2265,116 @@ mod tests {
         assert!(check_config("echo ok && forbidden thing", &config).is_err());
     }
 
+    #[test]
+    fn config_deny_catches_command_substitution() {
+        let json =
+            r#"{"deny":[{"pattern":"^terraform\\s+destroy\\b","reason":"no destroy"}],"allow":[]}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        assert!(check_config("echo $(terraform destroy -auto-approve)", &config).is_err());
+    }
+
+    #[test]
+    fn config_deny_catches_shell_heredoc_but_not_data_heredoc() {
+        let json =
+            r#"{"deny":[{"pattern":"^terraform\\s+destroy\\b","reason":"no destroy"}],"allow":[]}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        assert!(check_config("bash <<'EOF'\nterraform destroy\nEOF", &config).is_err());
+        assert!(check_config("cat > notes.md <<'EOF'\nterraform destroy\nEOF", &config).is_ok());
+    }
+
+    #[test]
+    fn category_severity_overrides_load() {
+        let json = r#"{"category_severity":{"sensitive-read":"ask","in-place-edit":"warn",
+            "file-destructive":"warn","bogus":"ask"}}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        assert_eq!(
+            config.severities.get(Category::SensitiveRead),
+            Severity::Ask
+        );
+        assert_eq!(config.severities.get(Category::InPlaceEdit), Severity::Warn);
+        // Clamped to the floor
+        assert_eq!(
+            config.severities.get(Category::FileDestructive),
+            Severity::Ask
+        );
+        assert_eq!(config.severities.get(Category::System), Severity::Deny);
+    }
+
+    #[test]
+    fn layer_note_cites_policy_and_contact() {
+        let json = r#"{"policy":{"name":"Acme org policy",
+            "contact":"https://wiki.acme.test/exceptions"},"deny":[{"pattern":"^terraform\\b",
+            "reason":"CI only"}]}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        let note = config.layer_note(&check_config("terraform apply", &config).unwrap_err());
+        assert_eq!(
+            note,
+            "rule from Acme org policy — request an exception via \
+             https://wiki.acme.test/exceptions"
+        );
+    }
+
+    #[test]
+    fn layer_note_without_policy_points_at_file() {
+        let f = write_config(r#"{"deny":[{"pattern":"^terraform\\b","reason":"CI only"}]}"#);
+        let config = load_config(f.path());
+        let note = config.layer_note(&check_config("terraform apply", &config).unwrap_err());
+        assert!(note.contains(&f.path().display().to_string()), "{}", note);
+        assert!(note.contains("add an allow pattern"), "{}", note);
+    }
+
+    #[test]
+    fn anchored_config_deny_sees_through_wrappers() {
+        let json = r#"{"wrappers":["with-lock"],"deny":[{"pattern":"^terraform\\s+destroy\\b",
+            "reason":"no destroy"}]}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        assert!(check_config("timeout 600 terraform destroy", &config).is_err());
+        assert!(check_config("with-lock terraform destroy", &config).is_err());
+    }
+
+    #[test]
+    fn config_deny_catches_ssh_remote_command() {
+        let json = r#"{"remote_escalation":true,"deny":[{"pattern":"^terraform\\s+destroy\\b",
+            "reason":"no destroy"}]}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        assert!(config.remote_escalation);
+        assert!(check_config(
+            "ssh -i ~/.ssh/ops bastion 'cd infra && terraform destroy'",
+            &config
+        )
+        .is_err());
+        assert!(check_config("ssh bastion terraform plan", &config).is_ok());
+    }
+
+    #[test]
+    fn alert_webhook_defaults_to_deny_decisions() {
+        let f = write_config(r#"{"alert_webhook":{"url":"https://alerts.example.test/hook"}}"#);
+        let alert = load_config(f.path()).alert_webhook.unwrap();
+        assert_eq!(alert.url, "https://alerts.example.test/hook");
+        assert_eq!(alert.decisions, ["deny"]);
+        let f = write_config(r#"{"deny":[]}"#);
+        assert!(load_config(f.path()).alert_webhook.is_none());
+    }
+
+    #[test]
+    fn audit_retention_defaults_per_field() {
+        let f = write_config(r#"{"audit_retention":{"max_age_days":7}}"#);
+        let retention = load_config(f.path()).audit_retention;
+        assert_eq!(
+            retention,
+            AuditRetention {
+                max_age_days: 7,
+                max_bytes: 10 * 1024 * 1024
+            }
+        );
+    }
+
     #[test]
     fn config_allow_works_per_segment() {
         let json = r#"{
@@ -237,4 +2388,496 @@ mod tests {
         // "git log" should be allowed even with compound
         assert!(check_config("git log --oneline", &config).is_ok());
     }
+
+    #[test]
+    fn project_config_found_and_limited_to_project_keys() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(load_project(dir.path(), dir.path()).is_none());
+        fs::write(
+            dir.path().join(".safe-bash.json"),
+            r#"{"deny":[{"pattern":"^make\\s+release\\b","reason":"CI only"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(load_project(dir.path(), dir.path()).unwrap().deny.len(), 1);
+        fs::create_dir(dir.path().join(".claude")).unwrap();
+        let json = r#"{"wrappers":["rm"],"inspect_makefiles":true,
+            "category_severity":{"file-destructive":"ask"},"root_escalation":false}"#;
+        fs::write(dir.path().join(".claude/safe-bash-patterns.json"), json).unwrap();
+        let mut project = load_project(dir.path(), dir.path()).unwrap();
+        assert!(project.path.ends_with(".claude/safe-bash-patterns.json"));
+        assert!(project.deny.is_empty());
+        let mut config = CompiledConfig {
+            wrappers: vec!["with-lock".to_string()],
+            ..CompiledConfig::default()
+        };
+        config.merge_project(&mut project);
+        assert_eq!(config.wrappers, ["with-lock"]);
+        assert!(config.inspect_makefiles && config.root_escalation);
+        assert_eq!(
+            config.severities.get(Category::FileDestructive),
+            Severity::Deny
+        );
+    }
+
+    #[test]
+    fn path_overlays_apply_where_the_command_runs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let org = r#"{"locked":["root_escalation"],"paths":{"/work/prod-*":{"profile":"strict",
+            "deny":[{"pattern":"^terraform apply","reason":"prod applies go through CI"}]}}}"#;
+        fs::write(dir.path().join("safe-bash-patterns.json"), org).unwrap();
+        let user = r#"{"category_severity":{"sensitive-read":"ask"},"paths":{
+            "~/scratch":{"profile":"permissive","root_escalation":false},
+            "~/scratch/keep/":{"category_severity":{"sensitive-read":"deny"},
+            "extends":["other.json"]}}}"#;
+        fs::write(dir.path().join("safe-bash-user.json"), user).unwrap();
+        let home = PathBuf::from(std::env::var("HOME").unwrap());
+
+        let config = load(
+            &Dirs::single(dir.path()),
+            Some(Path::new("/work/prod-infra/modules")),
+        );
+        assert_eq!(config.severities.profile(), Profile::Strict);
+        assert!(check_config("terraform apply", &config).is_err());
+        assert!(
+            config
+                .source
+                .ends_with("safe-bash-patterns.json paths[\"/work/prod-*\"]"),
+            "{}",
+            config.source
+        );
+
+        let config = load(&Dirs::single(dir.path()), Some(&home.join("scratch/tmp")));
+        assert_eq!(config.severities.profile(), Profile::Permissive);
+        assert!(config.root_escalation, "locked by the org layer");
+        assert!(check_config("terraform apply", &config).is_ok());
+        // The narrower tree's overlay comes last
+        let config = load(&Dirs::single(dir.path()), Some(&home.join("scratch/keep")));
+        assert_eq!(
+            (
+                config.severities.profile(),
+                config.severities.get(Category::SensitiveRead)
+            ),
+            (Profile::Permissive, Severity::Deny)
+        );
+
+        for cwd in [
+            None,
+            Some(Path::new("/work/staging")),
+            Some(home.join("scratchpad").as_path()),
+        ] {
+            let config = load(&Dirs::single(dir.path()), cwd);
+            assert_eq!(
+                (
+                    config.severities.profile(),
+                    config.severities.get(Category::SensitiveRead)
+                ),
+                (Profile::Standard, Severity::Ask)
+            );
+        }
+    }
+
+    #[test]
+    fn role_overlays_follow_the_roles_order() {
+        let layer =
+            |source: &str, json: &str| (source.to_string(), serde_json::from_str(json).unwrap());
+        let mut layers = vec![
+            layer(
+                "org",
+                r#"{"roles":{"ops":{"profile":"permissive","locked":["profile"]},
+                    "lead":{"wrappers":["with-lock"]}}}"#,
+            ),
+            layer(
+                "user",
+                r#"{"roles":{"ops":{"wrappers":["retry"]}},"profile":"standard"}"#,
+            ),
+        ];
+        let overlays = role_overlays(
+            &mut layers,
+            &["lead".to_string(), "ops".to_string(), "intern".to_string()],
+        );
+        let sources: Vec<&str> = overlays.iter().map(|(source, _)| source.as_str()).collect();
+        assert_eq!(
+            sources,
+            [
+                r#"org roles["lead"]"#,
+                r#"org roles["ops"]"#,
+                r#"user roles["ops"]"#
+            ]
+        );
+        assert!(
+            !overlays[1].1.contains_key("locked"),
+            "an overlay cannot lock"
+        );
+        assert!(layers.iter().all(|(_, layer)| !layer.contains_key("roles")));
+        layers.extend(overlays);
+        let merged = merge_layers(layers);
+        assert_eq!(
+            (merged["profile"].as_str(), &merged["wrappers"]),
+            (
+                Some("permissive"),
+                &serde_json::json!(["with-lock", "retry"])
+            )
+        );
+    }
+
+    #[test]
+    fn layers_merge_in_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let org = r#"{"policy":{"name":"Org policy"},"category_severity":{"sensitive-read":"ask"},
+            "deny":[{"pattern":"^terraform\\s+destroy\\b","reason":"CI only"}],
+            "wrappers":["with-lock"]}"#;
+        fs::write(dir.path().join("safe-bash-patterns.json"), org).unwrap();
+        fs::write(
+            dir.path().join("safe-bash-team.json"),
+            r#"{"category_severity":{"in-place-edit":"warn"},
+            "wrappers":["retry"],"remote_escalation":true}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("safe-bash-user.json"),
+            r#"{"remote_escalation":false,
+            "allow":[{"pattern":"^terraform destroy -target","reason":"scratch"}]}"#,
+        )
+        .unwrap();
+        let config = load(&Dirs::single(dir.path()), None);
+        assert_eq!(config.wrappers, ["with-lock", "retry"]);
+        assert_eq!(
+            config.severities.get(Category::SensitiveRead),
+            Severity::Ask
+        );
+        assert_eq!(config.severities.get(Category::InPlaceEdit), Severity::Warn);
+        assert!(!config.remote_escalation);
+        assert!(check_config("terraform destroy", &config).is_err());
+        assert!(check_config("terraform destroy -target=x", &config).is_ok());
+        assert!(
+            config.source.contains("safe-bash-patterns.json and ")
+                && config.source.contains("safe-bash-team.json and "),
+            "{}",
+            config.source
+        );
+        let user = dir.path().join("safe-bash-user.json");
+        let found = check_config("terraform destroy", &config).unwrap_err();
+        assert_eq!(
+            config.layer_note(&found),
+            format!(
+                "rule from Org policy — add an allow pattern in {} to override",
+                user.display()
+            )
+        );
+    }
+
+    #[test]
+    fn each_rule_cites_its_own_layer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let org = r#"{"policy":{"name":"Org policy","contact":"https://wiki.org.test/exceptions"},
+            "deny":[{"pattern":"^terraform\\s+destroy\\b","reason":"CI only"}]}"#;
+        fs::write(dir.path().join("safe-bash-patterns.json"), org).unwrap();
+        fs::write(
+            dir.path().join("safe-bash-team.json"),
+            r#"{"deny":[{"pattern":"^kubectl\\s+delete\\b","reason":"team rule"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("safe-bash-user.json"),
+            r#"{"deny":[{"pattern":"^docker\\s+system\\s+prune\\b","reason":"my cache"}]}"#,
+        )
+        .unwrap();
+        let config = load(&Dirs::single(dir.path()), None);
+        let note = |cmd| config.layer_note(&check_config(cmd, &config).unwrap_err());
+        assert_eq!(
+            note("terraform destroy"),
+            "rule from Org policy — request an exception via https://wiki.org.test/exceptions"
+        );
+        let user = dir.path().join("safe-bash-user.json");
+        let team = dir.path().join("safe-bash-team.json");
+        assert_eq!(
+            note("kubectl delete ns app"),
+            format!(
+                "rule from config {} — add an allow pattern in {} to override",
+                team.display(),
+                user.display()
+            )
+        );
+        assert_eq!(
+            note("docker system prune"),
+            format!(
+                "rule from config {} — add an allow pattern in {} to override",
+                user.display(),
+                user.display()
+            )
+        );
+    }
+
+    #[test]
+    fn extends_and_include_compose_in_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let rule = |reason: &str| format!(r#"{{"pattern":"^{0}\\b","reason":"{0}"}}"#, reason);
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        // Bases come first, their own bases before them; a file already read is skipped
+        fs::write(
+            dir.path().join("shared/base.json"),
+            format!(
+                r#"{{"extends":["common.json"],"deny":[{}],"wrappers":["with-lock"]}}"#,
+                rule("base")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("shared/common.json"),
+            format!(
+                r#"{{"extends":"../safe-bash-user.json","deny":[{}],"remote_escalation":true}}"#,
+                rule("common")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("extra.toml"),
+            "remote_escalation = false\n[[deny]]\npattern = '^extra\\b'\nreason = \"extra\"\n",
+        )
+        .unwrap();
+        // A URL is read from its cached copy, refreshed in the background
+        let url = "https://policy.example.test/org.json";
+        let cached = dir.path().join(format!(
+            "safe-bash-extends-{:016x}.json",
+            crate::inventory::fnv1a(url.as_bytes())
+        ));
+        fs::write(
+            &cached,
+            format!(
+                r#"{{"deny":[{}],"extends":["../etc/passwd"]}}"#,
+                rule("remote")
+            ),
+        )
+        .unwrap();
+        fs::write(cached.with_extension("last_update"), "0").unwrap();
+        let user = format!(
+            r#"{{"extends":["shared/base.json","{}","http://insecure.example.test/p.json"],
+                "include":["extra.toml"],"deny":[{}]}}"#,
+            url,
+            rule("user")
+        );
+        fs::write(dir.path().join("safe-bash-user.json"), user).unwrap();
+        let config = load(&Dirs::single(dir.path()), None);
+        let reasons: Vec<&str> = config.deny.iter().map(|p| p.reason.as_str()).collect();
+        assert_eq!(reasons, ["common", "base", "remote", "user", "extra"]);
+        assert_eq!(config.wrappers, ["with-lock"]);
+        assert!(!config.remote_escalation);
+        assert!(
+            config.source.contains("shared/common.json and "),
+            "{}",
+            config.source
+        );
+        assert!(config.source.contains(url), "{}", config.source);
+        assert!(
+            config.allow_file.ends_with("safe-bash-user.json"),
+            "{}",
+            config.allow_file
+        );
+    }
+
+    #[test]
+    fn read_layers_are_cached_until_a_file_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dirs = Dirs::single(dir.path());
+        let reasons = |config: &CompiledConfig| {
+            config
+                .deny
+                .iter()
+                .map(|p| p.reason.clone())
+                .collect::<Vec<_>>()
+        };
+        fs::write(
+            dir.path().join("base.json"),
+            r#"{"deny":[{"pattern":"^aaa\\b","reason":"aaa"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("safe-bash-user.json"),
+            r#"{"extends":"base.json","paths":{"/srv/*":{"wrappers":["w"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(reasons(&load(&dirs, None)), ["aaa"]);
+        let cache = fs::read_dir(dir.path().join("safe-bash-cache"))
+            .unwrap()
+            .count();
+        assert_eq!(cache, 1);
+
+        // The cached layers still take their overlays from the cwd of each call
+        assert_eq!(load(&dirs, Some(Path::new("/srv/app"))).wrappers, ["w"]);
+        assert!(load(&dirs, None).wrappers.is_empty());
+        // A cache written on another machine is not used
+        let path = fs::read_dir(dir.path().join("safe-bash-cache"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mut composed: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        composed["host"] = "0123456789abcdef".into();
+        composed["layers"][0][1]["deny"][0]["reason"] = "copied".into();
+        fs::write(&path, composed.to_string()).unwrap();
+        assert_eq!(reasons(&load(&dirs, None)), ["aaa"]);
+        // A change of the same size to a file it extends, or a new team layer, is read
+        fs::write(
+            dir.path().join("base.json"),
+            r#"{"deny":[{"pattern":"^bbb\\b","reason":"bbb"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(reasons(&load(&dirs, None)), ["bbb"]);
+        fs::write(
+            dir.path().join("safe-bash-team.json"),
+            r#"{"deny":[{"pattern":"^ccc\\b","reason":"ccc"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(reasons(&load(&dirs, None)), ["ccc", "bbb"]);
+        // A file that warns is read again on every call
+        fs::write(dir.path().join("safe-bash-team.json"), "{").unwrap();
+        let (_, warnings) = crate::warnings::capture(|| load(&dirs, None));
+        assert_eq!(warnings.len(), 1);
+        let (_, warnings) = crate::warnings::capture(|| load(&dirs, None));
+        assert_eq!(warnings.len(), 1, "not cached");
+    }
+
+    #[test]
+    fn locked_settings_and_rules_hold() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let org = r#"{"locked":["root_escalation"],"deny":[{"pattern":"^kubectl\\s+delete\\b",
+            "reason":"Cluster changes go through CI","locked":true}]}"#;
+        fs::write(dir.path().join("safe-bash-patterns.json"), org).unwrap();
+        fs::write(
+            dir.path().join("safe-bash-user.json"),
+            r#"{"root_escalation":false,"allow":[{"pattern":"^kubectl\\b","reason":"mine"}]}"#,
+        )
+        .unwrap();
+        let config = load(&Dirs::single(dir.path()), None);
+        assert!(config.root_escalation);
+        assert_eq!(
+            check_locked("kubectl delete ns app", &config).map_err(|m| m.reason),
+            Err("Cluster changes go through CI".to_string())
+        );
+        assert!(check_config("kubectl delete ns app", &config).is_ok());
+        let note = config.locked_note(&check_locked("kubectl delete ns app", &config).unwrap_err());
+        assert!(
+            note.starts_with("locked rule from config ")
+                && note.contains("safe-bash-patterns.json"),
+            "{}",
+            note
+        );
+    }
+
+    #[test]
+    fn toml_and_yaml_layers_share_the_schema() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let team = "wrappers = [\"with-lock\"]\n\n[[deny]]\npattern = \
+                    '^terraform\\s+destroy\\b'\nreason = \"CI \
+                    only\"\n\n[category_severity]\nsensitive-read = \"ask\"\n";
+        fs::write(dir.path().join("safe-bash-team.toml"), team).unwrap();
+        let user =
+            "allow:\n  - pattern: '^terraform\\s+destroy\\s+-target=module\\.scratch\\b'\n    \
+             reason: scratch\nremote_escalation: true\n";
+        fs::write(dir.path().join("safe-bash-user.yml"), user).unwrap();
+        // The org layer is the auto-updated file, always JSON
+        fs::write(
+            dir.path().join("safe-bash-patterns.toml"),
+            "remote_escalation = false\n",
+        )
+        .unwrap();
+        let config = load(&Dirs::single(dir.path()), None);
+        assert_eq!(config.wrappers, ["with-lock"]);
+        assert_eq!(
+            config.severities.get(Category::SensitiveRead),
+            Severity::Ask
+        );
+        assert!(config.remote_escalation);
+        assert_eq!(
+            check_config("terraform destroy", &config).map_err(|m| m.reason),
+            Err("CI only".to_string())
+        );
+        assert!(check_config("terraform destroy -target=module.scratch", &config).is_ok());
+        assert!(
+            config.allow_file.ends_with("safe-bash-user.yml"),
+            "{}",
+            config.allow_file
+        );
+
+        fs::write(
+            dir.path().join(".safe-bash.yaml"),
+            "deny:\n  - pattern: '^make\\s+release\\b'\n    reason: CI only\n",
+        )
+        .unwrap();
+        assert_eq!(load_project(dir.path(), dir.path()).unwrap().deny.len(), 1);
+    }
+
+    #[test]
+    fn rule_severities_for_config_and_hardcoded_rules() {
+        let json = r#"{"deny":[
+            {"pattern":"^terraform\\s+plan\\b","reason":"plan is logged","severity":"audit"},
+            {"pattern":"\\bterraform\\b","reason":"terraform is watched","severity":"warn"},
+            {"id":"TF001","pattern":"^terraform\\s+apply\\b","reason":"apply needs a look",
+            "severity":"ask"},
+            {"pattern":"^terraform\\s+destroy\\b","reason":"typo","severity":"sometimes"}],
+            "rule_severity":{"GIT002":"audit","Destructive: rm -rf":"warn",
+            "No such rule":"warn"}}"#;
+        let f = write_config(json);
+        let config = load_config(f.path());
+        let found = |cmd| check_config(cmd, &config).unwrap_err();
+        let summary = |m: RuleMatch| (m.id, m.reason, m.suggestion, m.severity);
+        assert_eq!(
+            summary(found("terraform plan")),
+            (
+                "CFG002".to_string(),
+                "terraform is watched".to_string(),
+                None,
+                Severity::Warn
+            )
+        );
+        assert_eq!(
+            summary(found("terraform fmt && terraform apply")),
+            (
+                "TF001".to_string(),
+                "apply needs a look".to_string(),
+                None,
+                Severity::Ask
+            )
+        );
+        // An unknown severity falls back to deny
+        assert_eq!(found("terraform destroy").severity, Severity::Deny);
+        assert_eq!(
+            config.severities.rule(Category::GitDestructive, "GIT002"),
+            Severity::Audit
+        );
+        assert_eq!(
+            config.severities.rule(Category::GitDestructive, "GIT001"),
+            Severity::Deny
+        );
+        assert_eq!(
+            config.severities.rule(Category::FileDestructive, "FS001"),
+            Severity::Ask
+        );
+    }
+
+    #[test]
+    fn profile_sets_defaults_under_category_overrides() {
+        let f = write_config(r#"{"profile":"strict","category_severity":{"network":"ask"}}"#);
+        let config = load_config(f.path());
+        assert_eq!(config.severities.profile(), Profile::Strict);
+        assert_eq!(config.severities.get(Category::Network), Severity::Ask);
+        assert_eq!(
+            config.severities.get(Category::PackageManager),
+            Severity::Deny
+        );
+        let f = write_config(r#"{"profile":"lenient"}"#);
+        assert_eq!(
+            load_config(f.path()).severities.profile(),
+            Profile::Standard
+        );
+    }
+
+    #[test]
+    fn report_mode_from_config() {
+        assert!(load_config(write_config(r#"{"mode":"report"}"#).path()).report_only);
+        assert!(!load_config(write_config(r#"{"mode":"enforce"}"#).path()).report_only);
+        assert!(!load_config(write_config("{}").path()).report_only);
+    }
 }
@@ -0,0 +1,180 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+use crate::shell::{Connector, Item, Node, SimpleCommand};
+
+/// Wrappers that keep a command running after the shell that started it exits.
+const DETACHERS: &[&str] = &["nohup", "setsid"];
+
+/// A command that keeps running in the background, out of reach once the hook has allowed it.
+#[derive(Debug)]
+pub struct Detached<'a> {
+    /// How it was detached: `nohup`, `&`, `setsid + & disown`.
+    pub how: String,
+    pub command: &'a SimpleCommand,
+}
+
+/// The simple commands in `ast` that are backgrounded with `&` (the whole `a && b &` list, as
+/// the shell does), run under `nohup`/`setsid`, or both.
+pub fn detached<'a>(ast: &'a Node, wrappers: &[String]) -> Vec<Detached<'a>> {
+    let mut out = Vec::new();
+    walk(ast, None, wrappers, &mut out);
+    out
+}
+
+fn walk<'a>(
+    node: &'a Node,
+    background: Option<&str>,
+    wrappers: &[String],
+    out: &mut Vec<Detached<'a>>,
+) {
+    match node {
+        Node::Simple(command) => {
+            let args = command.args();
+            let mut how: Vec<&str> = args[..crate::patterns::unwrap_index(&args, wrappers)]
+                .iter()
+                .map(|a| a.rsplit('/').next().unwrap_or(a))
+                .filter(|a| DETACHERS.contains(a))
+                .collect();
+            how.extend(background);
+            if !how.is_empty() && !args.is_empty() {
+                out.push(Detached {
+                    how: how.join(" + "),
+                    command,
+                });
+            }
+        }
+        Node::List(items) | Node::Subshell(items) | Node::Group(items) => {
+            // `&` ends an and-or list and backgrounds all of it
+            let mut start = 0;
+            for (i, item) in items.iter().enumerate() {
+                match item.connector {
+                    Some(Connector::Background) => {
+                        let how = if items.get(i + 1).is_some_and(is_disown) {
+                            "& disown"
+                        } else {
+                            "&"
+                        };
+                        for item in &items[start..=i] {
+                            walk(&item.node, Some(how), wrappers, out);
+                        }
+                        start = i + 1;
+                    }
+                    Some(Connector::Seq) | None => {
+                        for item in &items[start..=i] {
+                            walk(&item.node, background, wrappers, out);
+                        }
+                        start = i + 1;
+                    }
+                    Some(Connector::And | Connector::Or | Connector::Pipe) => {}
+                }
+            }
+        }
+        // A definition runs nothing by itself
+        Node::Function { .. } => {}
+    }
+}
+
+fn is_disown(item: &Item) -> bool {
+    matches!(&item.node, Node::Simple(c) if c.args().first() == Some(&"disown"))
+}
+
+/// Check detached commands against the hardcoded patterns one level stricter (audit → warn →
+/// ask → deny): once backgrounded, the work outlives any later attempt to stop it. The reason names
+/// how the command was detached.
+pub fn check(
+    cmd: &str,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    let ast = crate::shell::parse(cmd);
+    let mut result = CheckResult::Allow;
+    for detached in detached(&ast, wrappers) {
+        let label = |hit: Hit| Hit {
+            reason: format!("Detached ({}): {}", detached.how, hit.reason),
+            ..hit
+        };
+        let found = match crate::patterns::check_command(
+            &detached.command.text,
+            patterns,
+            severities,
+            wrappers,
+        ) {
+            CheckResult::Allow => CheckResult::Allow,
+            CheckResult::Audit(hit) => CheckResult::Warn(label(hit)),
+            CheckResult::Warn(hit) => CheckResult::Ask(label(hit)),
+            CheckResult::Ask(hit) | CheckResult::Deny(hit) => CheckResult::Deny(label(hit)),
+        };
+        result = result.or_worse(found);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+    use crate::severity::{Category, Severity};
+
+    fn how(cmd: &str) -> Vec<(String, String)> {
+        let ast = crate::shell::parse(cmd);
+        detached(&ast, &[])
+            .into_iter()
+            .map(|d| (d.how, d.command.text.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_backgrounded_and_nohup_commands() {
+        let pair = |h: &str, c: &str| (h.to_string(), c.to_string());
+        assert_eq!(
+            how("nohup rm -rf /srv &"),
+            [pair("nohup + &", "nohup rm -rf /srv")]
+        );
+        assert_eq!(
+            how("setsid -f make deploy"),
+            [pair("setsid", "setsid -f make deploy")]
+        );
+        assert_eq!(
+            how("sleep 5 && git push --force & disown"),
+            [
+                pair("& disown", "sleep 5"),
+                pair("& disown", "git push --force")
+            ]
+        );
+        assert_eq!(
+            how("(cd build && make) &"),
+            [pair("&", "cd build"), pair("&", "make")]
+        );
+        assert!(how("make && ls; echo done").is_empty());
+        assert_eq!(how("npm run dev & npm test").len(), 1);
+    }
+
+    #[test]
+    fn detached_matches_raised_one_level() {
+        let mut sev = Severities::default();
+        sev.set(Category::InPlaceEdit, Severity::Warn).unwrap();
+        sev.set(Category::GitDestructive, Severity::Ask).unwrap();
+        let patterns = hardcoded_deny_patterns();
+        let CheckResult::Ask(hit) = check("nohup sed -i s/a/b/ app.conf &", &patterns, &sev, &[])
+        else {
+            panic!()
+        };
+        assert_eq!(
+            hit.reason,
+            "Detached (nohup + &): Destructive: sed -i (in-place edit)"
+        );
+        assert!(matches!(
+            check("git reset --hard origin/main &", &patterns, &sev, &[]),
+            CheckResult::Deny(_)
+        ));
+        assert!(matches!(
+            check("git reset --hard origin/main", &patterns, &sev, &[]),
+            CheckResult::Allow
+        ));
+        assert_eq!(
+            check("nohup npm run dev > dev.log 2>&1 &", &patterns, &sev, &[]),
+            CheckResult::Allow
+        );
+    }
+}
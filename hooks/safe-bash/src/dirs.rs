@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that puts every file the hook reads and writes in one directory,
+/// in place of `~/.claude/hooks` and the XDG directories.
+pub const HOOKS_DIR_VAR: &str = "SAFE_BASH_HOOKS_DIR";
+
+/// Where the hook's files live: the config layers users edit, the state it writes as it runs
+/// (update timestamp, audit and debug logs, counters, the trusted policy source) and what it
+/// can fetch or rebuild again (the org patterns, remote `extends` files, inventories).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dirs {
+    pub config: PathBuf,
+    pub state: PathBuf,
+    pub cache: PathBuf,
+}
+
+impl Dirs {
+    /// Every kind of file in `dir`.
+    pub fn single(dir: &Path) -> Dirs {
+        Dirs {
+            config: dir.to_path_buf(),
+            state: dir.to_path_buf(),
+            cache: dir.to_path_buf(),
+        }
+    }
+
+    /// The directories for this environment: `SAFE_BASH_HOOKS_DIR` if set, otherwise the
+    /// `safe-bash` directory under `XDG_CONFIG_HOME`, `XDG_STATE_HOME` and `XDG_CACHE_HOME`.
+    pub fn locate() -> Dirs {
+        let var = |name: &str| {
+            std::env::var_os(name)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        if let Some(dir) = var(HOOKS_DIR_VAR) {
+            return Dirs::single(&dir);
+        }
+        let home = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()));
+        resolve(&home, var)
+    }
+
+    /// Create the state and cache directories when they are missing, so the first write to
+    /// either succeeds. The config directory is left to the user.
+    pub fn create(&self) {
+        for dir in [&self.state, &self.cache] {
+            if !dir.is_dir() {
+                let _ = fs::create_dir_all(dir);
+            }
+        }
+    }
+}
+
+/// The XDG directory of each kind (a relative `XDG_*` value is ignored, as the spec asks). An
+/// install from before they were used keeps `~/.claude/hooks` for a kind until its XDG
+/// directory is created.
+fn resolve(home: &Path, var: impl Fn(&str) -> Option<PathBuf>) -> Dirs {
+    let legacy = home.join(".claude").join("hooks");
+    let xdg = |name: &str, default: &str| {
+        let dir = var(name)
+            .filter(|d| d.is_absolute())
+            .unwrap_or_else(|| home.join(default))
+            .join("safe-bash");
+        if dir.is_dir() || !legacy.is_dir() {
+            dir
+        } else {
+            legacy.clone()
+        }
+    };
+    Dirs {
+        config: xdg("XDG_CONFIG_HOME", ".config"),
+        state: xdg("XDG_STATE_HOME", ".local/state"),
+        cache: xdg("XDG_CACHE_HOME", ".cache"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xdg_dirs_unless_an_older_install_uses_the_hooks_dir() {
+        let home = tempfile::TempDir::new().unwrap();
+        let home = home.path();
+        let none = |_: &str| None;
+        assert_eq!(
+            resolve(home, none),
+            Dirs {
+                config: home.join(".config/safe-bash"),
+                state: home.join(".local/state/safe-bash"),
+                cache: home.join(".cache/safe-bash")
+            }
+        );
+        let state = home.join("state");
+        let vars = |name: &str| match name {
+            "XDG_STATE_HOME" => Some(state.clone()),
+            "XDG_CACHE_HOME" => Some(PathBuf::from("relative")),
+            _ => None,
+        };
+        assert_eq!(
+            (resolve(home, vars).state, resolve(home, vars).cache),
+            (state.join("safe-bash"), home.join(".cache/safe-bash"))
+        );
+
+        // With ~/.claude/hooks in place, only the kinds whose XDG directory exists move
+        let legacy = home.join(".claude/hooks");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::create_dir_all(home.join(".cache/safe-bash")).unwrap();
+        assert_eq!(
+            resolve(home, none),
+            Dirs {
+                config: legacy.clone(),
+                state: legacy.clone(),
+                cache: home.join(".cache/safe-bash")
+            }
+        );
+    }
+}
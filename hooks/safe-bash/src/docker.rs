@@ -0,0 +1,455 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::{Category, Severities};
+use std::path::{Path, PathBuf};
+
+/// Container CLIs that share docker's `run`/`exec` syntax.
+const CLIS: &[&str] = &["docker", "podman", "nerdctl"];
+
+/// Options of docker itself and of `run`/`exec` that take no argument. Every other option
+/// takes one, as a separate word unless written `--opt=value` or `-pVALUE`.
+const LONG_FLAGS: &[&str] = &[
+    "--detach",
+    "--interactive",
+    "--tty",
+    "--rm",
+    "--privileged",
+    "--init",
+    "--publish-all",
+    "--read-only",
+    "--no-healthcheck",
+    "--oom-kill-disable",
+    "--quiet",
+    "--disable-content-trust",
+    "--debug",
+    "--tls",
+    "--tlsverify",
+    "--help",
+];
+const SHORT_FLAGS: &str = "ditPqD";
+
+/// Host paths that give a container control over the host when bind-mounted.
+const DANGEROUS_MOUNTS: &[&str] = &[
+    "/",
+    "/etc",
+    "/root",
+    "/boot",
+    "/usr",
+    "/var/run/docker.sock",
+    "/run/docker.sock",
+    "/proc",
+    "/sys",
+    "/dev",
+];
+
+/// A host path mounted into the container.
+#[derive(Debug, PartialEq)]
+struct Mount {
+    host: PathBuf,
+    target: String,
+}
+
+/// The command a `docker run`/`docker exec` (or `podman`, `nerdctl`) invocation runs in
+/// its container.
+#[derive(Debug, PartialEq)]
+pub struct ContainerCommand<'a> {
+    /// The image for `run`, the container for `exec`.
+    pub name: &'a str,
+    pub command: Vec<&'a str>,
+    /// The `run` options (empty for `exec`, whose mounts were set when the container started).
+    options: Vec<&'a str>,
+}
+
+/// Index of the first word at or after `i` that is not an option or an option's argument.
+fn skip_options(args: &[&str], mut i: usize) -> usize {
+    while let Some(arg) = args.get(i) {
+        i += 1;
+        if *arg == "--" {
+            break;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            if !long.contains('=') && !LONG_FLAGS.contains(arg) {
+                i += 1;
+            }
+        } else if let Some(flags) = arg.strip_prefix('-').filter(|f| !f.is_empty()) {
+            if flags
+                .find(|c| !SHORT_FLAGS.contains(c))
+                .is_some_and(|pos| pos + 1 == flags.len())
+            {
+                i += 1;
+            }
+        } else {
+            return i - 1;
+        }
+    }
+    i.min(args.len())
+}
+
+/// The container command of `docker [global options] run|exec [options] NAME COMMAND...`
+/// (also `docker container run|exec`). None for other subcommands or without a command.
+pub fn parse<'a>(args: &[&'a str]) -> Option<ContainerCommand<'a>> {
+    if !CLIS.contains(&args.first()?.rsplit('/').next()?) {
+        return None;
+    }
+    let mut i = skip_options(args, 1);
+    if args.get(i) == Some(&"container") {
+        i += 1;
+    }
+    let subcommand = *args.get(i)?;
+    if subcommand != "run" && subcommand != "exec" {
+        return None;
+    }
+    let start = i + 1;
+    let name_at = skip_options(args, start);
+    let name = *args.get(name_at)?;
+    let command = args[name_at + 1..].to_vec();
+    if command.is_empty() {
+        return None;
+    }
+    let options = if subcommand == "run" {
+        args[start..name_at].to_vec()
+    } else {
+        Vec::new()
+    };
+    Some(ContainerCommand {
+        name,
+        command,
+        options,
+    })
+}
+
+/// Host bind mounts among `docker run` options: `-v SRC:DST[:opts]`, `--volume=SRC:DST` and
+/// `--mount type=bind,source=SRC,target=DST`. Named volumes are not host paths and are skipped.
+fn mounts(options: &[&str], cwd: &Path) -> Vec<Mount> {
+    let mut found = Vec::new();
+    let mut iter = options.iter();
+    while let Some(opt) = iter.next() {
+        let (host, target) = if let Some(spec) = option_value(opt, &["-v", "--volume"], &mut iter) {
+            let mut parts = spec.splitn(3, ':');
+            (
+                parts.next().unwrap_or_default().to_string(),
+                parts.next().unwrap_or_default().to_string(),
+            )
+        } else if let Some(spec) = option_value(opt, &["--mount"], &mut iter) {
+            let field = |keys: &[&str]| {
+                spec.split(',')
+                    .filter_map(|kv| kv.split_once('='))
+                    .find(|(k, _)| keys.contains(k))
+                    .map(|(_, v)| v.to_string())
+                    .unwrap_or_default()
+            };
+            if !matches!(field(&["type"]).as_str(), "" | "bind") {
+                continue;
+            }
+            (
+                field(&["source", "src"]),
+                field(&["target", "destination", "dst"]),
+            )
+        } else {
+            continue;
+        };
+        if let (Some(host), true) = (host_path(&host, cwd), target.starts_with('/')) {
+            found.push(Mount { host, target });
+        }
+    }
+    found
+}
+
+/// The value of `opt` if it is one of `names`: `-v X`, `-vX`, `--volume X` or `--volume=X`.
+fn option_value<'a>(
+    opt: &'a str,
+    names: &[&str],
+    rest: &mut std::slice::Iter<&'a str>,
+) -> Option<&'a str> {
+    for name in names {
+        if opt == *name {
+            return rest.next().copied();
+        }
+        let attached = if name.starts_with("--") {
+            opt.strip_prefix(name).and_then(|v| v.strip_prefix('='))
+        } else {
+            opt.strip_prefix(name)
+        };
+        if let Some(value) = attached.filter(|v| !v.is_empty()) {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// The host path a mount source names, resolving `.`, `~`, `$HOME`, `$PWD` and `$(pwd)`.
+/// None for named volumes and other variables.
+fn host_path(source: &str, cwd: &Path) -> Option<PathBuf> {
+    let cwd_text = cwd.to_string_lossy();
+    let mut source = source.to_string();
+    for pwd in ["${PWD}", "$PWD", "$(pwd)", "`pwd`"] {
+        if let Some(rest) = source.strip_prefix(pwd) {
+            source = format!("{}{}", cwd_text, rest);
+        }
+    }
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let is_path = source.starts_with(['/', '.', '~'])
+        || source == "$HOME"
+        || source.starts_with("$HOME/")
+        || source == "${HOME}";
+    is_path.then(|| crate::perms::resolve(&source, cwd, home.as_deref()))
+}
+
+/// A command's words with paths under a mount target rewritten to the host path they reach,
+/// keeping the program and its options and dropping other arguments (`rm -rf /host/etc` with
+/// `-v /:/host` → `rm -rf /etc`). None when no word reaches the host.
+fn on_host(words: &[&str], mounts: &[Mount]) -> Option<String> {
+    let program = crate::patterns::unwrap_index(words, &[]);
+    let mut reached = false;
+    let mapped: Vec<String> = words
+        .iter()
+        .enumerate()
+        .filter_map(|(i, w)| {
+            if i <= program || w.starts_with('-') {
+                return Some(crate::shell::quote(w));
+            }
+            let mount = mounts.iter().find(|m| under(w, &m.target))?;
+            reached = true;
+            let rest = w[mount.target.trim_end_matches('/').len()..].trim_start_matches('/');
+            let host = if rest.is_empty() {
+                mount.host.clone()
+            } else {
+                mount.host.join(rest)
+            };
+            Some(crate::shell::quote(&host.to_string_lossy()))
+        })
+        .collect();
+    reached.then(|| mapped.join(" "))
+}
+
+/// The first mount a command's words (or the words of a script argument) reach.
+fn reached_mount<'m>(command: &[&str], mounts: &'m [Mount]) -> Option<&'m Mount> {
+    mounts.iter().find(|m| {
+        command
+            .iter()
+            .flat_map(|w| w.split_whitespace())
+            .any(|t| under(t, &m.target))
+    })
+}
+
+/// `path` is `target` or below it.
+fn under(path: &str, target: &str) -> bool {
+    let target = target.trim_end_matches('/');
+    path == target
+        || path.starts_with(&format!("{}/", target))
+        || (target.is_empty() && path.starts_with('/'))
+}
+
+/// Check the commands run inside containers: the hardcoded patterns apply as they would
+/// locally, with the reason naming the container and the host path a bind mount exposes;
+/// an `rm -r` that reaches `/`, home or a system directory of the host through a bind mount
+/// blocks like the local one; mounting the host root, a system directory or the docker
+/// socket is a container escape.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some(container) = parse(&args[crate::patterns::unwrap_index(&args, wrappers)..])
+            else {
+                continue;
+            };
+            let mounts = mounts(&container.options, cwd);
+            for mount in &mounts {
+                if DANGEROUS_MOUNTS.iter().any(|d| mount.host == Path::new(d)) {
+                    let reason = format!(
+                        "Container escape: docker run mounts host {} at {}",
+                        mount.host.display(),
+                        mount.target
+                    );
+                    result = result.or_worse(CheckResult::weigh(
+                        Hit {
+                            id: "CTR101",
+                            reason,
+                            category: Category::ContainerEscape,
+                        },
+                        severities,
+                    ));
+                }
+            }
+
+            let via = reached_mount(&container.command, &mounts)
+                .map(|m| format!(" (bind mount {} → host {})", m.target, m.host.display()))
+                .unwrap_or_default();
+            let label = |hit: Hit| Hit {
+                reason: format!("In container {}{}: {}", container.name, via, hit.reason),
+                ..hit
+            };
+            let inner: Vec<String> = container
+                .command
+                .iter()
+                .map(|w| crate::shell::quote(w))
+                .collect();
+            let found =
+                crate::patterns::check_command(&inner.join(" "), patterns, severities, wrappers)
+                    .relabel(label);
+            result = result.or_worse(found);
+
+            if let Some(hit) = reach_host(&inner.join(" "), &mounts, cwd, wrappers, 0) {
+                result = CheckResult::Deny(label(hit)).or_worse(result);
+            }
+        }
+    }
+    result
+}
+
+/// An `rm -r` anywhere in a container command — its pipelines, lists and `sh -c` scripts —
+/// that wipes `/`, home or a system directory of the host through a bind mount.
+fn reach_host(
+    script: &str,
+    mounts: &[Mount],
+    cwd: &Path,
+    wrappers: &[String],
+    depth: usize,
+) -> Option<Hit> {
+    if mounts.is_empty() || depth > 4 {
+        return None;
+    }
+    for segment in crate::patterns::split_command(script, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let words = c.args();
+            let hit = on_host(&words, mounts)
+                .and_then(|host_cmd| crate::wildcard::check(&host_cmd, cwd, wrappers))
+                .or_else(|| {
+                    reach_host(
+                        crate::patterns::shell_script(&words, wrappers)?,
+                        mounts,
+                        cwd,
+                        wrappers,
+                        depth + 1,
+                    )
+                });
+            if hit.is_some() {
+                return hit;
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+
+    fn container(cmd: &str) -> Option<(String, String, Vec<Mount>)> {
+        let ast = crate::shell::parse(cmd);
+        let c = ast.simple_commands().into_iter().next()?;
+        let args = c.args();
+        parse(&args).map(|c| {
+            (
+                c.name.to_string(),
+                c.command.join(" "),
+                mounts(&c.options, Path::new("/work/app")),
+            )
+        })
+    }
+
+    fn check_in(cmd: &str) -> CheckResult {
+        check(
+            cmd,
+            Path::new("/work/app"),
+            &hardcoded_deny_patterns(),
+            &Severities::default(),
+            &[],
+        )
+    }
+
+    #[test]
+    fn parses_container_command_and_mounts() {
+        let (name, command, mounts) =
+            container("docker run --rm -it -v $PWD:/w -e A=1 --name x alpine rm -rf /w").unwrap();
+        assert_eq!((name.as_str(), command.as_str()), ("alpine", "rm -rf /w"));
+        assert_eq!(
+            mounts,
+            [Mount {
+                host: PathBuf::from("/work/app"),
+                target: "/w".to_string()
+            }]
+        );
+
+        let (name, command, _) = container("docker exec -u root app sh -c 'rm -rf /data'").unwrap();
+        assert_eq!(
+            (name.as_str(), command.as_str()),
+            ("app", "sh -c rm -rf /data")
+        );
+
+        let (_, _, mounts) = container(
+            "podman container run --mount type=bind,src=./data,dst=/d -v cache:/c img ls",
+        )
+        .unwrap();
+        assert_eq!(
+            mounts,
+            [Mount {
+                host: PathBuf::from("/work/app/data"),
+                target: "/d".to_string()
+            }]
+        );
+
+        assert_eq!(container("docker run -d nginx"), None);
+        assert_eq!(container("docker ps -a"), None);
+        assert_eq!(
+            container("docker -H tcp://h:2375 exec db psql").map(|c| c.0),
+            Some("db".to_string())
+        );
+    }
+
+    #[test]
+    fn inner_command_checked_with_container_and_mount_in_reason() {
+        let CheckResult::Deny(hit) = check_in("docker exec app sh -c 'rm -rf /data'") else {
+            panic!()
+        };
+        assert_eq!(
+            hit.reason,
+            "In container app: Shell injection: rm inside shell -c"
+        );
+        let CheckResult::Deny(hit) = check_in("docker run -v $PWD:/w alpine rm -rf /w/src") else {
+            panic!()
+        };
+        assert_eq!(
+            hit.reason,
+            "In container alpine (bind mount /w → host /work/app): Destructive: rm -rf"
+        );
+        assert_eq!(
+            check_in("docker run --rm -v $PWD:/w -w /w rust:1 cargo test"),
+            CheckResult::Allow
+        );
+    }
+
+    #[test]
+    fn host_root_reached_through_mount_is_catastrophic() {
+        let CheckResult::Deny(hit) =
+            check_in("docker run -v /:/host alpine sh -c 'rm -rf /host/*'")
+        else {
+            panic!()
+        };
+        assert_eq!(hit.category, Category::RootWildcard, "{}", hit.reason);
+        assert!(
+            hit.reason
+                .contains("deletes everything under the filesystem root"),
+            "{}",
+            hit.reason
+        );
+        let CheckResult::Deny(hit) =
+            check_in("docker run -v /var/run/docker.sock:/var/run/docker.sock docker:cli ps")
+        else {
+            panic!()
+        };
+        assert_eq!(
+            hit.reason,
+            "Container escape: docker run mounts host /var/run/docker.sock at /var/run/docker.sock"
+        );
+    }
+}
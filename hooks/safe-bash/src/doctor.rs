@@ -0,0 +1,204 @@
+use crate::config::CompiledConfig;
+use std::time::{Duration, Instant};
+
+/// A long compound command touching most checks: git, cargo, find/xargs, docker, npm, rm.
+pub const BENCH_COMMAND: &str = "cd build && git status --short && git stash list | head -n 5 && \
+                                 cargo build --release 2>&1 | tail -n 20 && find . -name '*.o' \
+                                 -newer Makefile -print0 | xargs -0 -r ls -la && docker compose \
+                                 ps && npm run lint -- --fix && sed -n '1,40p' src/main.rs | grep \
+                                 -c TODO && rm -rf ./target/tmp && echo \"done: $(date +%s)\"";
+
+/// Samples per stage; the first is reported on its own, the median stands for the warm case.
+const RUNS: usize = 15;
+
+/// A first run this many times slower than the warm median points at a cold cache.
+const COLD_FACTOR: u32 = 5;
+
+/// A single config regex slower than this per match makes every hook call slow.
+const SLOW_PATTERN: Duration = Duration::from_millis(1);
+
+/// One timed stage of an evaluation.
+#[derive(Debug)]
+pub struct Stage {
+    pub name: &'static str,
+    /// What the stage may take on every hook call without users noticing.
+    pub budget: Duration,
+    pub first: Duration,
+    pub median: Duration,
+}
+
+impl Stage {
+    /// Time `f` over several runs and keep its last result.
+    pub fn time<T>(name: &'static str, budget: Duration, mut f: impl FnMut() -> T) -> (Self, T) {
+        let mut samples = Vec::with_capacity(RUNS);
+        let mut last = None;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            last = Some(f());
+            samples.push(start.elapsed());
+        }
+        let first = samples[0];
+        samples.sort();
+        let stage = Stage {
+            name,
+            budget,
+            first,
+            median: samples[RUNS / 2],
+        };
+        (stage, last.expect("RUNS > 0"))
+    }
+
+    /// What is wrong with the stage's timings, if anything.
+    pub fn problem(&self) -> Option<String> {
+        if self.median > self.budget {
+            return Some(format!(
+                "over budget ({} > {})",
+                ms(self.median),
+                ms(self.budget)
+            ));
+        }
+        if self.first > self.budget && self.first > self.median * COLD_FACTOR {
+            return Some(format!(
+                "first run {} against {} warm: cold disk cache or a slow filesystem under the \
+                 hooks directory",
+                ms(self.first),
+                ms(self.median)
+            ));
+        }
+        None
+    }
+}
+
+/// Config deny/allow regexes slower than `SLOW_PATTERN` per match against `cmd`, slowest first.
+pub fn slow_patterns(config: &CompiledConfig, cmd: &str) -> Vec<(String, Duration)> {
+    let mut slow: Vec<(String, Duration)> = config
+        .deny
+        .iter()
+        .chain(&config.allow)
+        .map(|p| {
+            let start = Instant::now();
+            for _ in 0..RUNS {
+                std::hint::black_box(p.matcher.is_match(cmd, &config.wrappers));
+            }
+            (p.matcher.to_string(), start.elapsed() / RUNS as u32)
+        })
+        .filter(|(_, per_match)| *per_match > SLOW_PATTERN)
+        .collect();
+    slow.sort_by_key(|(_, per_match)| std::cmp::Reverse(*per_match));
+    slow
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.2}ms", d.as_secs_f64() * 1000.0)
+}
+
+/// The `doctor` report, and whether anything was flagged.
+pub fn report(
+    config: &CompiledConfig,
+    stages: &[Stage],
+    slow: &[(String, Duration)],
+) -> (String, bool) {
+    let source = if config.source.is_empty() {
+        "none (hardcoded patterns only)"
+    } else {
+        &config.source
+    };
+    let normalization: Vec<&str> = config
+        .normalization
+        .stages()
+        .iter()
+        .map(|s| s.as_str())
+        .collect();
+    let mut out = format!(
+        "config: {}\n  profile: {}; {} deny, {} allow patterns; normalization: {}\n",
+        source,
+        config.severities.profile().as_str(),
+        config.deny.len(),
+        config.allow.len(),
+        normalization.join(", ")
+    );
+    out.push_str(&format!("timings ({} runs each):\n", RUNS));
+    let mut flagged = false;
+    for stage in stages {
+        let status = match stage.problem() {
+            Some(problem) => {
+                flagged = true;
+                format!("SLOW: {}", problem)
+            }
+            None => "ok".to_string(),
+        };
+        out.push_str(&format!(
+            "  {:<16} {:>9} median, {:>9} first, budget {:>8}  {}\n",
+            stage.name,
+            ms(stage.median),
+            ms(stage.first),
+            ms(stage.budget),
+            status
+        ));
+    }
+    for (pattern, per_match) in slow {
+        flagged = true;
+        out.push_str(&format!(
+            "SLOW: config pattern {:?} takes {} per match — anchor or simplify it\n",
+            pattern,
+            ms(*per_match)
+        ));
+    }
+    (out, flagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(median_ms: u64, first_ms: u64) -> Stage {
+        let ms = Duration::from_millis;
+        Stage {
+            name: "evaluation",
+            budget: ms(10),
+            first: ms(first_ms),
+            median: ms(median_ms),
+        }
+    }
+
+    #[test]
+    fn flags_over_budget_and_cold_first_run() {
+        assert_eq!(stage(2, 3).problem(), None);
+        assert_eq!(
+            stage(12, 12).problem().as_deref(),
+            Some("over budget (12.00ms > 10.00ms)")
+        );
+        assert!(stage(2, 40)
+            .problem()
+            .unwrap()
+            .starts_with("first run 40.00ms against 2.00ms warm: cold"));
+        // Slow first run within budget is not worth reporting
+        assert_eq!(stage(1, 8).problem(), None);
+    }
+
+    #[test]
+    fn report_lists_stages_and_slow_patterns() {
+        let (stage, n) = Stage::time("count", Duration::from_secs(1), || 7);
+        assert_eq!(n, 7);
+        let slow = vec![("(a+)+$".to_string(), Duration::from_millis(3))];
+        let (out, flagged) = report(&CompiledConfig::default(), &[stage], &slow);
+        assert!(flagged);
+        assert!(
+            out.starts_with(
+                "config: none (hardcoded patterns only)\n  profile: standard; 0 deny, 0 allow \
+                 patterns; normalization: unicode, continuations, whitespace\ntimings (15 runs \
+                 each):\n  count "
+            ),
+            "{}",
+            out
+        );
+        assert!(out.contains("budget 1000.00ms  ok\n"), "{}", out);
+        assert!(
+            out.ends_with(
+                "SLOW: config pattern \"(a+)+$\" takes 3.00ms per match — anchor or simplify it\n"
+            ),
+            "{}",
+            out
+        );
+    }
+}
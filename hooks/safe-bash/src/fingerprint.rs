@@ -0,0 +1,148 @@
+use crate::inventory::fnv1a;
+
+/// Programs whose first operand selects a subcommand that belongs in the shape: `git push`,
+/// `npm run`, `docker exec`.
+const SUBCOMMAND_TOOLS: &[&str] = &[
+    "apt",
+    "apt-get",
+    "aws",
+    "az",
+    "brew",
+    "cargo",
+    "conda",
+    "docker",
+    "gcloud",
+    "gh",
+    "git",
+    "go",
+    "helm",
+    "kubectl",
+    "nerdctl",
+    "npm",
+    "oc",
+    "pip",
+    "pip3",
+    "pnpm",
+    "podman",
+    "rustup",
+    "systemctl",
+    "terraform",
+    "yarn",
+];
+
+/// Programs with long options behind a single dash (`find -name`), which are not split into
+/// letters like `-rf` is.
+const SINGLE_DASH_LONG: &[&str] = &["find", "java", "gcc", "clang"];
+
+/// A command reduced to what it does rather than what it does it to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    /// Program, subcommand and sorted flag set of each simple command: `rm -f -r`,
+    /// `git push --force ; cargo test`. Commands that differ only in their paths, messages or
+    /// quoting share a shape.
+    pub shape: String,
+    /// Hash of the operands, so identical commands can be told from merely similar ones.
+    pub literals: u64,
+}
+
+impl Fingerprint {
+    /// Stable key for an exact command (shape and operands): wrappers, quoting and spacing
+    /// do not change it.
+    pub fn id(&self) -> String {
+        format!(
+            "{:016x}",
+            fnv1a(format!("{}\0{:016x}", self.shape, self.literals).as_bytes())
+        )
+    }
+}
+
+/// Fingerprint every simple command in `cmd`, with wrappers (`sudo`, `timeout 5`, `extra`)
+/// peeled off.
+pub fn of(cmd: &str, extra: &[String]) -> Fingerprint {
+    let ast = crate::shell::parse(cmd);
+    let mut shapes = Vec::new();
+    let mut literals = Vec::new();
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, extra)..];
+        let Some(first) = args.first() else { continue };
+        let program = first.rsplit('/').next().unwrap_or(first);
+        let mut words = vec![program.to_string()];
+        let mut flags = Vec::new();
+        let mut options_done = false;
+        for arg in &args[1..] {
+            if !options_done && arg.starts_with('-') && arg.len() > 1 {
+                options_done = *arg == "--";
+                flags.extend(split_flag(arg, SINGLE_DASH_LONG.contains(&program)));
+            } else if words.len() == 1 && SUBCOMMAND_TOOLS.contains(&program) && is_subcommand(arg)
+            {
+                words.push(arg.to_string());
+            } else {
+                literals.push(*arg);
+            }
+        }
+        flags.sort();
+        flags.dedup();
+        words.extend(flags);
+        shapes.push(words.join(" "));
+    }
+    Fingerprint {
+        shape: shapes.join(" ; "),
+        literals: fnv1a(literals.join("\0").as_bytes()),
+    }
+}
+
+/// `-rf` → `-f`, `-r`; `-n5` → `-n`; `--force=yes` → `--force`.
+fn split_flag(arg: &str, single_dash_long: bool) -> Vec<String> {
+    if arg == "--" {
+        return Vec::new();
+    }
+    if arg.starts_with("--") || single_dash_long {
+        return vec![arg.split('=').next().unwrap_or(arg).to_string()];
+    }
+    arg[1..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .map(|c| format!("-{}", c))
+        .collect()
+}
+
+fn is_subcommand(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_lowercase())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(cmd: &str) -> String {
+        of(cmd, &[]).shape
+    }
+
+    #[test]
+    fn shape_keeps_program_subcommand_and_flag_set() {
+        assert_eq!(shape("rm -rf ./a"), "rm -f -r");
+        assert_eq!(shape("sudo /bin/rm -f -r ./b"), "rm -f -r");
+        assert_eq!(
+            shape("git push --force=true origin main"),
+            "git push --force"
+        );
+        assert_eq!(shape("git commit -m 'fix: parser'"), "git commit -m");
+        assert_eq!(shape("find . -name '*.o' -delete"), "find -delete -name");
+        assert_eq!(
+            shape("cargo build && timeout 60 cargo test -- --nocapture"),
+            "cargo build ; cargo test"
+        );
+        assert_eq!(shape("head -n5 log.txt | sort"), "head -n ; sort");
+    }
+
+    #[test]
+    fn literals_tell_exact_duplicates_apart() {
+        assert_eq!(of("rm -rf ./a", &[]).id(), of("rm  -fr './a'", &[]).id());
+        assert_ne!(of("rm -rf ./a", &[]).id(), of("rm -rf ./b", &[]).id());
+        assert_eq!(of("rm -rf ./a", &[]).shape, of("rm -rf ./b", &[]).shape);
+    }
+}
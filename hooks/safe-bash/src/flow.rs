@@ -0,0 +1,274 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// How deep a script that writes and runs another script is followed.
+const MAX_DEPTH: usize = 4;
+
+/// Output redirections that write a file.
+const WRITES: &[&str] = &[">", ">>", ">|", "&>", "&>>"];
+
+/// The path a command string refers to, made comparable: `run.sh`, `./run.sh` and
+/// `sub/../run.sh` are the same file. Lexical only; `cd` between the steps is not followed.
+fn key(path: &str, cwd: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for part in cwd.join(path).components() {
+        match part {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// What `echo`/`printf` prints, as far as it can be known without running it.
+fn printed(args: &[&str]) -> Option<String> {
+    let (program, rest) = args.split_first()?;
+    match program.rsplit('/').next()? {
+        "echo" => {
+            let flags: Vec<&str> = rest
+                .iter()
+                .take_while(|a| matches!(**a, "-n" | "-e" | "-E" | "-ne" | "-en"))
+                .copied()
+                .collect();
+            let newline = if flags.iter().any(|f| f.contains('n')) {
+                ""
+            } else {
+                "\n"
+            };
+            Some(rest[flags.len()..].join(" ") + newline)
+        }
+        "printf" => rest.first().map(|format| format.replace("\\n", "\n")),
+        _ => None,
+    }
+}
+
+/// What a command writes to stdin-fed files: a heredoc or herestring body, or what the
+/// previous pipeline stage printed.
+fn input(c: &crate::shell::SimpleCommand, piped: Option<&String>) -> Option<String> {
+    let fed = c
+        .redirects
+        .iter()
+        .find_map(|r| match (&r.heredoc, r.op.as_str()) {
+            (Some(body), _) => Some(body.clone()),
+            (None, "<<<") => Some(format!("{}\n", r.target.value)),
+            _ => None,
+        });
+    fed.or_else(|| piped.filter(|_| c.piped).cloned())
+}
+
+/// The script a command runs from a file: `./run.sh`, `sh run.sh`, `bash -e run.sh`,
+/// `source run.sh`, `. run.sh`. A bare `run.sh` is looked up on PATH, not here.
+fn executed<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let (program, rest) = args.split_first()?;
+    let name = program.rsplit('/').next()?;
+    if crate::patterns::SHELLS.contains(&name) {
+        // `sh -c '…'` runs its argument, not a file
+        let mut operands = rest
+            .iter()
+            .skip_while(|a| a.starts_with('-') && !a.contains('c'));
+        return operands.next().filter(|a| !a.starts_with('-')).copied();
+    }
+    if matches!(name, "source" | ".") {
+        return rest.first().copied();
+    }
+    program.contains('/').then_some(*program)
+}
+
+/// Whether a script's shebang names something other than a shell; its lines are not shell.
+fn foreign_shebang(content: &str) -> bool {
+    let Some(line) = content.lines().next().and_then(|l| l.strip_prefix("#!")) else {
+        return false;
+    };
+    let mut words = line.split_whitespace();
+    let mut program = words
+        .next()
+        .and_then(|p| p.rsplit('/').next())
+        .unwrap_or("");
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-')).unwrap_or("");
+    }
+    !crate::patterns::SHELLS.contains(&program)
+}
+
+/// Check scripts that a command string writes and then runs: in
+/// `cat > run.sh <<EOF … EOF && chmod +x run.sh && ./run.sh` the body of the heredoc is what
+/// runs, so it is checked against the hardcoded patterns and the root-wildcard rule. Writes by
+/// heredoc, herestring, `echo`/`printf` redirects and `tee` are followed; the reason names the
+/// script.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    check_in(cmd, cwd, patterns, severities, wrappers, 0)
+}
+
+fn check_in(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+    depth: usize,
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    if depth >= MAX_DEPTH {
+        return result;
+    }
+    let ast = crate::shell::parse(cmd);
+    let mut written: HashMap<PathBuf, String> = HashMap::new();
+    let mut printed_by_previous: Option<String> = None;
+    for c in ast.simple_commands() {
+        let all = c.args();
+        let args = &all[crate::patterns::unwrap_index(&all, wrappers)..];
+        let output = printed(args);
+        let content = output
+            .clone()
+            .or_else(|| input(c, printed_by_previous.as_ref()));
+        for r in c
+            .redirects
+            .iter()
+            .filter(|r| WRITES.contains(&r.op.as_str()))
+        {
+            let path = key(&r.target.value, cwd);
+            match &content {
+                Some(text) if r.op.ends_with(">>") => {
+                    written.entry(path).or_default().push_str(text)
+                }
+                Some(text) => {
+                    written.insert(path, text.clone());
+                }
+                // Written from somewhere this check cannot see; forget what was there
+                None => {
+                    written.remove(&path);
+                }
+            }
+        }
+        if args
+            .first()
+            .is_some_and(|p| p.rsplit('/').next() == Some("tee"))
+        {
+            let append = args.iter().any(|a| matches!(*a, "-a" | "--append"));
+            for file in args[1..].iter().filter(|a| !a.starts_with('-')) {
+                let path = key(file, cwd);
+                match (&content, append) {
+                    (Some(text), true) => written.entry(path).or_default().push_str(text),
+                    (Some(text), false) => {
+                        written.insert(path, text.clone());
+                    }
+                    (None, _) => {
+                        written.remove(&path);
+                    }
+                }
+            }
+        }
+        printed_by_previous = output;
+
+        let Some(script) = executed(args) else {
+            continue;
+        };
+        let Some(body) = written
+            .get(&key(script, cwd))
+            .filter(|b| !foreign_shebang(b))
+        else {
+            continue;
+        };
+        let label = |hit: Hit| Hit {
+            reason: format!(
+                "Script {} written earlier in this command runs: {}",
+                script, hit.reason
+            ),
+            ..hit
+        };
+        if let Some(hit) = crate::wildcard::check(body, cwd, wrappers) {
+            return CheckResult::Deny(label(hit));
+        }
+        let direct = crate::patterns::check_command(body, patterns, severities, wrappers);
+        let nested = check_in(body, cwd, patterns, severities, wrappers, depth + 1);
+        let found = direct.or_worse(nested).relabel(label);
+        result = result.or_worse(found);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+
+    fn check_with(cmd: &str) -> CheckResult {
+        check(
+            cmd,
+            Path::new("/work"),
+            &hardcoded_deny_patterns(),
+            &Severities::default(),
+            &[],
+        )
+    }
+
+    #[test]
+    fn written_scripts_checked_when_run() {
+        let heredoc =
+            "cat > run.sh <<'EOF'\necho cleaning\nrm -rf src\nEOF\nchmod +x run.sh && ./run.sh";
+        let CheckResult::Deny(hit) = check_with(heredoc) else {
+            panic!()
+        };
+        assert!(
+            hit.reason.starts_with(
+                "Script ./run.sh written earlier in this command runs: Destructive: rm -rf"
+            ),
+            "{}",
+            hit.reason
+        );
+        assert!(matches!(
+            check_with("echo 'git push --force' > p.sh; bash p.sh"),
+            CheckResult::Deny(_)
+        ));
+        assert!(matches!(
+            check_with("printf 'ls\\nrm -rf /\\n' > x && sh ./x"),
+            CheckResult::Deny(_)
+        ));
+        assert!(matches!(
+            check_with("echo 'rm -rf build' | tee -a ./t.sh >/dev/null && source t.sh"),
+            CheckResult::Deny(_)
+        ));
+        assert!(matches!(
+            check_with("echo ls > a.sh; echo 'rm -rf src' >> a.sh; . ./a.sh"),
+            CheckResult::Deny(_)
+        ));
+    }
+
+    #[test]
+    fn unrelated_or_harmless_flows_allowed() {
+        assert_eq!(
+            check_with("echo 'rm -rf src' > notes.txt && cat notes.txt"),
+            CheckResult::Allow
+        );
+        assert_eq!(
+            check_with("cat > run.sh <<EOF\ncargo test\nEOF\nsh run.sh"),
+            CheckResult::Allow
+        );
+        assert_eq!(
+            check_with(
+                "echo 'rm -rf src' > run.sh; curl -fsS https://x.test/ok.sh > run.sh; sh run.sh"
+            ),
+            CheckResult::Allow
+        );
+        assert_eq!(
+            check_with("cat > t.py <<EOF\n#!/usr/bin/env python3\nimport os\nEOF\n./t.py"),
+            CheckResult::Allow
+        );
+        // A bare name is looked up on PATH, not in the working directory
+        assert_eq!(
+            check_with("echo 'rm -rf src' > deploy; deploy"),
+            CheckResult::Allow
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How deep an alias of an alias is followed.
+const MAX_DEPTH: usize = 4;
+
+/// git's own commands. git never lets an alias shadow one, so they are not looked up.
+const BUILTINS: &[&str] = &[
+    "add",
+    "am",
+    "annotate",
+    "apply",
+    "archive",
+    "bisect",
+    "blame",
+    "branch",
+    "bundle",
+    "cat-file",
+    "check-ignore",
+    "checkout",
+    "cherry",
+    "cherry-pick",
+    "clean",
+    "clone",
+    "commit",
+    "config",
+    "describe",
+    "diff",
+    "difftool",
+    "fetch",
+    "for-each-ref",
+    "format-patch",
+    "fsck",
+    "gc",
+    "grep",
+    "help",
+    "init",
+    "log",
+    "ls-files",
+    "ls-remote",
+    "ls-tree",
+    "merge",
+    "merge-base",
+    "mergetool",
+    "mv",
+    "notes",
+    "pull",
+    "push",
+    "range-diff",
+    "rebase",
+    "reflog",
+    "remote",
+    "repack",
+    "replace",
+    "reset",
+    "restore",
+    "rev-list",
+    "rev-parse",
+    "revert",
+    "rm",
+    "shortlog",
+    "show",
+    "show-branch",
+    "show-ref",
+    "sparse-checkout",
+    "stash",
+    "status",
+    "submodule",
+    "switch",
+    "symbolic-ref",
+    "tag",
+    "update-index",
+    "update-ref",
+    "var",
+    "version",
+    "whatchanged",
+    "worktree",
+];
+
+/// git options that take a separate argument.
+pub const OPTIONS_WITH_ARG: &[&str] = &[
+    "-C",
+    "-c",
+    "--git-dir",
+    "--work-tree",
+    "--namespace",
+    "--exec-path",
+    "--config-env",
+];
+
+/// An alias invocation: `git nuke origin` → name `nuke`, operands `origin`.
+#[derive(Debug, PartialEq)]
+struct AliasRun<'a> {
+    /// Where git would read its config (`-C` applied).
+    dir: PathBuf,
+    name: &'a str,
+    /// Aliases defined inline with `-c alias.<name>=<value>`.
+    inline: HashMap<String, String>,
+    operands: &'a [&'a str],
+}
+
+fn alias_run<'a>(args: &'a [&'a str], cwd: &Path) -> Option<AliasRun<'a>> {
+    if args.first()?.rsplit('/').next()? != "git" {
+        return None;
+    }
+    let mut dir = cwd.to_path_buf();
+    let mut inline = HashMap::new();
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        if OPTIONS_WITH_ARG.contains(arg) {
+            let value = args.get(i + 1)?;
+            match *arg {
+                "-C" => dir = dir.join(value),
+                "-c" => {
+                    if let Some((key, body)) = value.split_once('=') {
+                        if let Some(name) = key.strip_prefix("alias.") {
+                            inline.insert(name.to_string(), body.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 2;
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    let name = *args.get(i)?;
+    if BUILTINS.contains(&name) {
+        return None;
+    }
+    Some(AliasRun {
+        dir,
+        name,
+        inline,
+        operands: &args[i + 1..],
+    })
+}
+
+/// The aliases git sees in `dir`: system, global and repository config, as
+/// `git config --get-regexp` lists them.
+fn aliases(dir: &Path) -> HashMap<String, String> {
+    let Ok(output) = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["config", "--null", "--get-regexp", "^alias\\."])
+        .output()
+    else {
+        return HashMap::new();
+    };
+    parse_config_list(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `alias.<name>\n<value>\0` records from `git config --null --get-regexp`. Later definitions win.
+fn parse_config_list(text: &str) -> HashMap<String, String> {
+    text.split('\0')
+        .filter_map(|record| {
+            let (key, value) = record.split_once('\n')?;
+            Some((key.strip_prefix("alias.")?.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The shell command an alias runs with its operands: `push --force` → `git push --force …`,
+/// `!rm -rf build` → `rm -rf build …`.
+fn expansion(body: &str, operands: &[&str]) -> String {
+    let rest: Vec<String> = operands
+        .iter()
+        .map(|o| crate::autoupdate::shell_quote(o))
+        .collect();
+    let command = match body.strip_prefix('!') {
+        Some(shell) => shell.to_string(),
+        None => format!("git {}", body),
+    };
+    std::iter::once(command)
+        .chain(rest)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Check what `git <alias>` invocations expand to against the hardcoded patterns and the
+/// root-wildcard rule, following aliases of aliases. The reason names the alias and its
+/// expansion.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    check_in(cmd, cwd, None, patterns, severities, wrappers, 0)
+}
+
+fn check_in(
+    cmd: &str,
+    cwd: &Path,
+    known: Option<&HashMap<String, String>>,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+    depth: usize,
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    if depth >= MAX_DEPTH {
+        return result;
+    }
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some(run) = alias_run(&args[crate::patterns::unwrap_index(&args, wrappers)..], cwd)
+            else {
+                continue;
+            };
+            let mut defined = known.cloned().unwrap_or_else(|| aliases(&run.dir));
+            defined.extend(run.inline.clone());
+            let Some(body) = defined.get(run.name) else {
+                continue;
+            };
+            let expanded = expansion(body, run.operands);
+            let label = |hit: Hit| Hit {
+                reason: format!(
+                    "git alias {:?} runs `{}`: {}",
+                    run.name, expanded, hit.reason
+                ),
+                ..hit
+            };
+            if let Some(hit) = crate::wildcard::check(&expanded, &run.dir, wrappers) {
+                return CheckResult::Deny(label(hit));
+            }
+            let direct = crate::patterns::check_command(&expanded, patterns, severities, wrappers);
+            let nested = check_in(
+                &expanded,
+                &run.dir,
+                Some(&defined),
+                patterns,
+                severities,
+                wrappers,
+                depth + 1,
+            );
+            let found = direct.or_worse(nested).relabel(label);
+            result = result.or_worse(found);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+
+    fn defined(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn check_with(cmd: &str, aliases: &HashMap<String, String>) -> CheckResult {
+        check_in(
+            cmd,
+            Path::new("/tmp"),
+            Some(aliases),
+            &hardcoded_deny_patterns(),
+            &Severities::default(),
+            &[],
+            0,
+        )
+    }
+
+    #[test]
+    fn parses_config_list() {
+        let list = parse_config_list("alias.nuke\npush --force\0alias.cl\n!git clean -fdx\nsudo\0");
+        assert_eq!(
+            list,
+            defined(&[("nuke", "push --force"), ("cl", "!git clean -fdx\nsudo")])
+        );
+    }
+
+    #[test]
+    fn aliases_expanded_and_checked() {
+        let aliases = defined(&[
+            ("nuke", "push --force"),
+            ("undo", "reset --hard HEAD~3"),
+            ("wipe", "!rm -rf"),
+            ("again", "nuke"),
+            ("st", "status -sb"),
+        ]);
+        let CheckResult::Deny(hit) = check_with("git nuke origin main", &aliases) else {
+            panic!()
+        };
+        assert!(
+            hit.reason
+                .starts_with("git alias \"nuke\" runs `git push --force 'origin' 'main'`: "),
+            "{}",
+            hit.reason
+        );
+        assert!(matches!(
+            check_with("git -C sub undo", &aliases),
+            CheckResult::Ask(_)
+        ));
+        let CheckResult::Deny(hit) = check_with("git again", &aliases) else {
+            panic!()
+        };
+        assert!(
+            hit.reason
+                .starts_with("git alias \"again\" runs `git nuke`: git alias \"nuke\""),
+            "{}",
+            hit.reason
+        );
+        assert!(matches!(
+            check_with("git wipe ./build", &aliases),
+            CheckResult::Deny(_)
+        ));
+        assert_eq!(check_with("git st", &aliases), CheckResult::Allow);
+        // Builtins are never aliases; unknown names are left to git
+        assert_eq!(
+            check_with(
+                "git push origin main",
+                &defined(&[("push", "push --force")])
+            ),
+            CheckResult::Allow
+        );
+        assert_eq!(check_with("git frobnicate", &aliases), CheckResult::Allow);
+        let inline = check_with("git -c alias.x='reset --hard' x", &HashMap::new());
+        assert!(matches!(inline, CheckResult::Ask(_)), "{:?}", inline);
+    }
+}
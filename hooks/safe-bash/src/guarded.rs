@@ -0,0 +1,132 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+use std::path::{Path, PathBuf};
+
+/// Flag any path a command reads, writes or deletes (see `zones::path_args`) that reaches a
+/// `protected_paths` entry, comparing both the path as resolved and its real path with symlinks
+/// followed. So `cat ~/.s?h/id_rsa`, `cd ~/.ssh && cat id_rsa` and a link into `~/.aws` are all
+/// caught.
+pub fn check(cmd: &str, cwd: &Path, wrappers: &[String], protected: &[String]) -> Option<Hit> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let root = crate::blast::workspace_root(cwd).unwrap_or_else(|| cwd.to_path_buf());
+    // Each entry's absolute forms: `~` expanded, a relative one joined to the workspace root,
+    // and its real path when symlinks lead elsewhere. Globs match any characters, `/` included.
+    let entries: Vec<(&str, Vec<String>)> = protected
+        .iter()
+        .map(|text| (text.as_str(), entry_forms(text, &root, home.as_deref())))
+        .collect();
+    crate::zones::path_args(cmd, cwd, wrappers)
+        .into_iter()
+        .find_map(|found| {
+            let real = crate::zones::real_path(&found.path);
+            let entry = [&found.path, &real].into_iter().find_map(|path| {
+                entries.iter().find(|(_, forms)| {
+                    path.ancestors().any(|a| {
+                        forms
+                            .iter()
+                            .any(|f| crate::blast::glob_match(f, &a.to_string_lossy()))
+                    })
+                })
+            })?;
+            let reason = format!(
+                "Protected path: {} {} reaches {} (protected_paths)",
+                found.what, found.arg, entry.0
+            );
+            Some(Hit {
+                id: "PATH101",
+                reason,
+                category: Category::ProtectedPath,
+            })
+        })
+}
+
+/// The absolute forms of a `protected_paths` entry.
+fn entry_forms(entry: &str, root: &Path, home: Option<&Path>) -> Vec<String> {
+    let lexical = crate::perms::resolve(entry.trim_end_matches('/'), root, home);
+    let mut forms = vec![lexical.to_string_lossy().to_string()];
+    if !entry.contains(['*', '?', '[']) {
+        let real = crate::zones::real_path(&lexical)
+            .to_string_lossy()
+            .to_string();
+        if real != forms[0] {
+            forms.push(real);
+        }
+    }
+    forms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_paths_before_matching() {
+        let home = tempfile::TempDir::new().unwrap();
+        let repo = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(home.path().join(".ssh")).unwrap();
+        std::fs::write(home.path().join(".ssh/id_rsa"), "").unwrap();
+        std::fs::create_dir_all(repo.path().join(".git")).unwrap();
+        std::fs::create_dir_all(repo.path().join("src")).unwrap();
+        std::os::unix::fs::symlink(home.path().join(".ssh"), repo.path().join("keys")).unwrap();
+        let ssh = home.path().join(".ssh").to_string_lossy().to_string();
+        let protected = [ssh.clone(), "/etc".to_string(), ".git".to_string()];
+        let check =
+            |cmd: &str| check(cmd, &repo.path().join("src"), &[], &protected).map(|h| h.reason);
+
+        let reason = |what: &str, arg: &str, entry: &str| {
+            Some(format!(
+                "Protected path: {} {} reaches {} (protected_paths)",
+                what, arg, entry
+            ))
+        };
+        let id_rsa = format!("{}/id_rsa", ssh);
+        assert_eq!(
+            check(&format!("cat {}", id_rsa)),
+            reason("cat", &id_rsa, &ssh)
+        );
+        let glob = format!("{}/.s?h/id_rsa", home.path().display());
+        assert_eq!(check(&format!("cat {}", glob)), reason("cat", &glob, &ssh));
+        assert_eq!(
+            check(&format!("cd {} && cat id_rsa", ssh)),
+            reason("cat", "id_rsa", &ssh)
+        );
+        assert_eq!(
+            check("cat ../keys/id_rsa"),
+            reason("cat", "../keys/id_rsa", &ssh),
+            "through a symlink"
+        );
+        assert_eq!(
+            check("cp x ../../../../../../../../etc/hosts"),
+            reason("cp", "../../../../../../../../etc/hosts", "/etc")
+        );
+        assert_eq!(
+            check("echo x >> /etc/hosts"),
+            reason("redirect >>", "/etc/hosts", "/etc")
+        );
+        assert_eq!(
+            check("rm -rf ../.git"),
+            reason("rm", "../.git", ".git"),
+            "relative to the repository root"
+        );
+        assert_eq!(
+            check("dd if=/etc/shadow of=out"),
+            reason("dd", "if=/etc/shadow", "/etc")
+        );
+        assert_eq!(
+            check("sudo tar czf /tmp/k.tgz ../keys/"),
+            reason("tar", "../keys/", &ssh)
+        );
+
+        for cmd in [
+            "cat src/main.rs",
+            "cat ../.gitignore",
+            "echo /etc/hosts",
+            "ls /etcetera",
+            "grep -r ssh . 2>&1",
+            "cd /tmp && cat id_rsa",
+            "git status",
+        ] {
+            assert_eq!(check(cmd), None, "{}", cmd);
+        }
+    }
+}
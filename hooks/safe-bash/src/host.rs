@@ -0,0 +1,86 @@
+use std::fs;
+use std::sync::OnceLock;
+
+/// This machine's fingerprint: the first 16 hex digits of the SHA-256 of its hostname and
+/// machine id. State that relaxes a decision (the trusted policy source, the config cache) is
+/// stored with it and ignored on any other machine, so a state directory synced by a dotfile
+/// manager or restored from a backup carries no exceptions with it. Read from files and system
+/// calls, never a subprocess, since every hook call checks it; computed once per process.
+pub fn fingerprint() -> &'static str {
+    static FINGERPRINT: OnceLock<String> = OnceLock::new();
+    FINGERPRINT.get_or_init(|| {
+        let id = format!("{}\n{}", hostname(), machine_id());
+        crate::matcher::sha256_hex(&id)[..16].to_string()
+    })
+}
+
+fn first_line(path: &str) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let line = text.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is writable for its whole length, which is what gethostname is given.
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return String::new();
+    }
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).trim().to_string()
+}
+
+/// systemd's (or D-Bus's) machine id, or the IOPlatformUUID on macOS.
+fn machine_id() -> String {
+    first_line("/etc/machine-id")
+        .or_else(|| first_line("/var/lib/dbus/machine-id"))
+        .or_else(platform_uuid)
+        .unwrap_or_default()
+}
+
+/// The IOPlatformUUID as `ioreg` prints it: upper-case hex in 8-4-4-4-12 groups.
+#[cfg(target_os = "macos")]
+fn platform_uuid() -> Option<String> {
+    let mut id = [0u8; 16];
+    let wait = libc::timespec {
+        tv_sec: 1,
+        tv_nsec: 0,
+    };
+    // SAFETY: gethostuuid writes exactly 16 bytes, the size of `id`.
+    if unsafe { libc::gethostuuid(id.as_mut_ptr(), &wait) } != 0 {
+        return None;
+    }
+    let hex: String = id.iter().map(|b| format!("{:02X}", b)).collect();
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_uuid() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_is_stable_hex() {
+        assert_eq!(fingerprint().len(), 16);
+        assert!(
+            fingerprint().chars().all(|c| c.is_ascii_hexdigit()),
+            "{}",
+            fingerprint()
+        );
+        assert_eq!(
+            fingerprint(),
+            &crate::matcher::sha256_hex(&format!("{}\n{}", hostname(), machine_id()))[..16]
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Environment variable naming the roles whose `roles` overlays apply, e.g. "release-manager"
+/// (several separated by commas). Ignored when the policy sets an `identity`.
+pub const ROLE_VAR: &str = "SAFE_BASH_ROLE";
+
+/// The `identity` setting: roles come only from a file signed by the policy owner.
+#[derive(Deserialize, Debug)]
+struct IdentityConfig {
+    /// JSON with the user's `roles`, e.g. `{"user": "ana", "roles": ["release-manager"]}`,
+    /// next to a detached signature in `<file>.sig`.
+    file: String,
+    /// The PEM public key the signature is checked with.
+    public_key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct IdentityFile {
+    #[serde(default)]
+    roles: Vec<String>,
+    /// The fingerprints of the machines the roles apply on (`safe-bash-hook host-id`); every
+    /// machine when empty.
+    #[serde(default)]
+    hosts: Vec<String>,
+}
+
+/// The roles of whoever runs the hook. With an `identity` in the config layers (the value the
+/// locks leave in force), they are read from its file once the signature checks out;
+/// otherwise from `SAFE_BASH_ROLE`.
+pub fn roles(layers: &[(String, Map<String, Value>)]) -> Vec<String> {
+    let Some((source, identity)) = setting(layers, "identity") else {
+        let var = std::env::var(ROLE_VAR).unwrap_or_default();
+        return var
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(String::from)
+            .collect();
+    };
+    if std::env::var_os(ROLE_VAR).is_some() {
+        crate::warnings::warn(
+            "config-identity",
+            &format!(
+                "ignoring {}: {} takes roles from a signed identity file",
+                ROLE_VAR, source
+            ),
+        );
+    }
+    let identity: IdentityConfig = match serde_json::from_value(identity.clone()) {
+        Ok(identity) => identity,
+        Err(e) => {
+            crate::warnings::warn(
+                "config-identity",
+                &format!("{}: ignoring identity: {}", source, e),
+            );
+            return Vec::new();
+        }
+    };
+    let (file, key) = (expand(&identity.file), expand(&identity.public_key));
+    let signature = PathBuf::from(format!("{}.sig", file.display()));
+    // The bytes checked are the bytes read, so the file cannot change in between
+    let data = match std::fs::read(&file) {
+        Ok(data) => data,
+        Err(e) => {
+            crate::warnings::warn(
+                "config-identity",
+                &format!(
+                    "no roles: could not read identity file {}: {}",
+                    file.display(),
+                    e
+                ),
+            );
+            return Vec::new();
+        }
+    };
+    if let Err(e) = verify(&data, &signature, &key) {
+        crate::warnings::warn(
+            "config-identity",
+            &format!("no roles: identity file {} {}", file.display(), e),
+        );
+        return Vec::new();
+    }
+    match serde_json::from_slice::<IdentityFile>(&data) {
+        Ok(parsed)
+            if !parsed.hosts.is_empty()
+                && !parsed.hosts.iter().any(|h| h == crate::host::fingerprint()) =>
+        {
+            crate::warnings::warn(
+                "config-identity",
+                &format!(
+                    "no roles: identity file {} is not for this machine ({})",
+                    file.display(),
+                    crate::host::fingerprint()
+                ),
+            );
+            Vec::new()
+        }
+        Ok(parsed) => parsed.roles,
+        Err(e) => {
+            crate::warnings::warn(
+                "config-identity",
+                &format!("no roles: invalid identity file {}: {}", file.display(), e),
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// The value of `key` that merging `layers` leaves in force, and the layer it comes from: the
+/// last one to set it, up to a layer that locks it.
+fn setting<'a>(
+    layers: &'a [(String, Map<String, Value>)],
+    key: &str,
+) -> Option<(&'a str, &'a Value)> {
+    let mut found = None;
+    for (source, layer) in layers {
+        if let Some(value) = layer.get(key) {
+            found = Some((source.as_str(), value));
+        }
+        let locks = layer.get("locked").and_then(Value::as_array);
+        if locks.is_some_and(|keys| keys.iter().any(|k| k.as_str() == Some(key))) {
+            break;
+        }
+    }
+    found
+}
+
+fn expand(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Check the detached SHA-256 signature of `data` with `openssl dgst -verify`.
+fn verify(data: &[u8], signature: &Path, key: &Path) -> Result<(), String> {
+    for (what, path) in [("signature", signature), ("public key", key)] {
+        if !path.is_file() {
+            return Err(format!(
+                "is not usable: {} {} is missing",
+                what,
+                path.display()
+            ));
+        }
+    }
+    let output = Command::new("openssl")
+        .args(["dgst", "-sha256", "-verify"])
+        .arg(key)
+        .arg("-signature")
+        .arg(signature)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(data)?;
+            child.wait_with_output()
+        })
+        .map_err(|e| format!("could not be checked: openssl: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "does not match its signature {} for {}",
+            signature.display(),
+            key.display()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(json: &str) -> (String, Map<String, Value>) {
+        ("test".to_string(), serde_json::from_str(json).unwrap())
+    }
+
+    fn openssl(dir: &Path, args: &[&str]) -> bool {
+        Command::new("openssl")
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    #[test]
+    fn locked_setting_stays() {
+        let layers = [
+            layer(r#"{"identity": 1, "locked": ["identity"]}"#),
+            layer(r#"{"identity": 2}"#),
+        ];
+        assert_eq!(
+            setting(&layers, "identity").map(|(_, v)| v.clone()),
+            Some(Value::from(1))
+        );
+        let layers = [layer(r#"{"identity": 1}"#), layer(r#"{"identity": 2}"#)];
+        assert_eq!(
+            setting(&layers, "identity").map(|(_, v)| v.clone()),
+            Some(Value::from(2))
+        );
+        assert!(setting(&layers, "roles").is_none());
+    }
+
+    #[test]
+    fn roles_only_from_a_verified_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        if !openssl(dir.path(), &["version"]) {
+            return; // openssl not installed
+        }
+        assert!(openssl(
+            dir.path(),
+            &[
+                "ecparam",
+                "-name",
+                "prime256v1",
+                "-genkey",
+                "-noout",
+                "-out",
+                "key.pem"
+            ]
+        ));
+        assert!(openssl(
+            dir.path(),
+            &["ec", "-in", "key.pem", "-pubout", "-out", "pub.pem"]
+        ));
+        let file = dir.path().join("identity.json");
+        std::fs::write(&file, r#"{"user": "ana", "roles": ["release-manager"]}"#).unwrap();
+        assert!(openssl(
+            dir.path(),
+            &[
+                "dgst",
+                "-sha256",
+                "-sign",
+                "key.pem",
+                "-out",
+                "identity.json.sig",
+                "identity.json"
+            ]
+        ));
+        let identity = format!(
+            r#"{{"identity": {{"file": "{}", "public_key": "{}"}}}}"#,
+            file.display(),
+            dir.path().join("pub.pem").display()
+        );
+        assert_eq!(roles(&[layer(&identity)]), ["release-manager"]);
+
+        // An edit after signing voids the roles
+        std::fs::write(
+            &file,
+            r#"{"user": "ana", "roles": ["release-manager", "admin"]}"#,
+        )
+        .unwrap();
+        assert!(roles(&[layer(&identity)]).is_empty());
+
+        // A file bound to other machines grants nothing here
+        let sign = |hosts: &str| {
+            std::fs::write(
+                &file,
+                format!(r#"{{"roles": ["release-manager"], "hosts": [{}]}}"#, hosts),
+            )
+            .unwrap();
+            assert!(openssl(
+                dir.path(),
+                &[
+                    "dgst",
+                    "-sha256",
+                    "-sign",
+                    "key.pem",
+                    "-out",
+                    "identity.json.sig",
+                    "identity.json"
+                ]
+            ));
+        };
+        sign("\"0123456789abcdef\"");
+        assert!(roles(&[layer(&identity)]).is_empty());
+        sign(&format!(
+            "\"0123456789abcdef\", \"{}\"",
+            crate::host::fingerprint()
+        ));
+        assert_eq!(roles(&[layer(&identity)]), ["release-manager"]);
+    }
+}
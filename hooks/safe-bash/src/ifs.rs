@@ -0,0 +1,179 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+use crate::shell::{SimpleCommand, Word};
+
+/// Builtins that can assign IFS as an argument: `export IFS=,`.
+const DECLARERS: &[&str] = &["export", "declare", "typeset", "local", "readonly"];
+
+/// Flag word-splitting tricks that hide a command from pattern matching: IFS reassigned and
+/// then an expansion run as a command (`IFS=,;$(echo rm,-rf,/)`, `IFS=,; c=rm,-rf,/; $c`,
+/// `IFS=,; eval $c`), or `$IFS`/`${IFS}` standing in for spaces (`rm${IFS}-rf${IFS}/`). Neither
+/// has a use in an agent's command, so these always block.
+pub fn check(cmd: &str, wrappers: &[String]) -> Option<Hit> {
+    let mut ifs = None;
+    scan(cmd, wrappers, &mut ifs)
+}
+
+/// Walk the commands in source order, substitution bodies included, remembering the last IFS
+/// assignment seen.
+fn scan(cmd: &str, wrappers: &[String], ifs: &mut Option<String>) -> Option<Hit> {
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        if let Some(word) = command
+            .assignments
+            .iter()
+            .chain(&command.argv)
+            .find(|w| uses_ifs(w))
+        {
+            return Some(hit(format!(
+                "Obfuscation: $IFS used as a word separator ({})",
+                word.raw
+            )));
+        }
+        for body in command.substitutions() {
+            if let Some(hit) = scan(body, wrappers, ifs) {
+                return Some(hit);
+            }
+        }
+        let args = command.args();
+        let start = crate::patterns::unwrap_index(&args, wrappers);
+        if let (Some(value), Some(program)) = (ifs.as_deref(), command.argv.get(start)) {
+            if let Some(expanded) = executed_expansion(command, start, program) {
+                return Some(hit(format!(
+                    "Obfuscation: IFS set to {:?} before running {} as a command",
+                    value, expanded
+                )));
+            }
+        }
+        if let Some(value) = ifs_assignment(command) {
+            *ifs = Some(value);
+        }
+    }
+    None
+}
+
+fn hit(reason: String) -> Hit {
+    Hit {
+        id: "OBF101",
+        reason,
+        category: Category::Obfuscation,
+    }
+}
+
+/// The value of a standalone `IFS=…` or `export IFS=…`. A prefix assignment (`IFS= read -r l`)
+/// only applies to its own command, after that command's words are split.
+fn ifs_assignment(command: &SimpleCommand) -> Option<String> {
+    let args = command.args();
+    let words: &[Word] = match args.first() {
+        None => &command.assignments,
+        Some(first) if DECLARERS.contains(first) => &command.argv[1..],
+        Some(_) => return None,
+    };
+    words
+        .iter()
+        .rev()
+        .find_map(|w| w.value.strip_prefix("IFS=").map(str::to_string))
+}
+
+/// The expansion a command runs: an unquoted `$var`/`$(…)` program word, or one passed to `eval`.
+fn executed_expansion<'a>(
+    command: &'a SimpleCommand,
+    start: usize,
+    program: &'a Word,
+) -> Option<&'a str> {
+    if has_expansion(&unquoted(&program.raw)) {
+        return Some(&program.raw);
+    }
+    if program.value == "eval" {
+        return command.argv[start + 1..]
+            .iter()
+            .find(|w| has_expansion(&unquoted(&w.raw)))
+            .map(|w| w.raw.as_str());
+    }
+    None
+}
+
+fn has_expansion(text: &str) -> bool {
+    text.contains('$') || text.contains('`')
+}
+
+/// `$IFS` or `${IFS…}` outside quotes, where it splits the word.
+fn uses_ifs(word: &Word) -> bool {
+    let text = unquoted(&word.raw);
+    text.match_indices("$IFS")
+        .chain(text.match_indices("${IFS"))
+        .any(|(i, m)| {
+            let next = text[i + m.len()..].chars().next();
+            m.starts_with("${") || !next.is_some_and(|c| c.is_alphanumeric() || c == '_')
+        })
+}
+
+/// The raw word with quoted parts removed: `"$x"y'$z'` → `y`.
+fn unquoted(raw: &str) -> String {
+    let mut out = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' => {
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(cmd: &str) -> Option<String> {
+        check(cmd, &[]).map(|h| h.reason)
+    }
+
+    #[test]
+    fn ifs_reassigned_before_expanded_command() {
+        assert_eq!(
+            reason("IFS=,;$(echo rm,-rf,/)").as_deref(),
+            Some(r#"Obfuscation: IFS set to "," before running $(echo rm,-rf,/) as a command"#)
+        );
+        assert!(reason("IFS=,; c=rm,-rf,/; $c").is_some());
+        assert!(reason("export IFS=:; c=git:push:--force; eval $c").is_some());
+        assert!(reason("IFS=_; timeout 5 `echo rm_-rf_build`").is_some());
+        // Expansion before the assignment, quoted program words, and prefix assignments are fine
+        assert!(reason("$EDITOR notes.md; IFS=,").is_none());
+        assert!(reason(r#"IFS=,; "$cmd" build"#).is_none());
+        assert!(reason("while IFS= read -r line; do echo \"$line\"; done < list.txt").is_none());
+        assert!(reason("IFS=$'\\n'; for f in $(ls); do wc -l \"$f\"; done").is_none());
+    }
+
+    #[test]
+    fn ifs_as_word_separator() {
+        assert_eq!(
+            reason("rm${IFS}-rf${IFS}/").as_deref(),
+            Some("Obfuscation: $IFS used as a word separator (rm${IFS}-rf${IFS}/)")
+        );
+        assert!(reason("cat$IFS/etc/passwd").is_some());
+        assert!(reason("echo $(cat${IFS%?}/etc/shadow)").is_some());
+        assert!(reason(r#"printf '%q' "$IFS""#).is_none());
+        assert!(reason("echo $IFSX").is_none());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::shell::{Item, Node, SimpleCommand};
+use std::collections::HashMap;
+
+/// Builtins whose `name=value` arguments set shell variables.
+const DECLARATION_BUILTINS: &[&str] = &["export", "declare", "local", "readonly", "typeset"];
+
+/// How deep function calls are expanded; recursive functions stop here.
+const MAX_CALL_DEPTH: usize = 4;
+
+/// Variables, aliases and functions defined so far in the command string.
+#[derive(Default, Clone)]
+struct Scope {
+    vars: HashMap<String, String>,
+    aliases: HashMap<String, String>,
+    functions: HashMap<String, Vec<Item>>,
+}
+
+/// Substitute variables assigned earlier in the same command string, so `cmd=rm; $cmd -rf /`
+/// is also checked as `rm -rf /`. Aliases and shell functions defined earlier are expanded
+/// at their call sites the same way: `alias nuke='rm -rf'; nuke /` and
+/// `f(){ rm -rf "$1"; }; f /srv` are both checked as `rm -rf /…`. Definitions are tracked
+/// in source order across `;`, `&&`, `|` etc.; single-quoted text is left alone. Returns
+/// None when nothing was substituted.
+pub fn resolve(cmd: &str) -> Option<String> {
+    let ast = crate::shell::parse(cmd);
+    let mut commands: Vec<(bool, String)> = Vec::new();
+    let mut changed = false;
+    walk(&ast, &mut Scope::default(), 0, &mut commands, &mut changed);
+    if !changed {
+        return None;
+    }
+    let mut out = String::new();
+    for (piped, text) in commands {
+        if !out.is_empty() {
+            out.push_str(if piped { " | " } else { "; " });
+        }
+        out.push_str(&text);
+    }
+    Some(out)
+}
+
+/// Append the simple commands under `node` to `out` as `(piped, text)`, expanded in `scope`.
+fn walk(
+    node: &Node,
+    scope: &mut Scope,
+    depth: usize,
+    out: &mut Vec<(bool, String)>,
+    changed: &mut bool,
+) {
+    match node {
+        Node::Simple(command) => simple(command, scope, depth, out, changed),
+        Node::List(items) | Node::Subshell(items) | Node::Group(items) => {
+            for item in items {
+                walk(&item.node, scope, depth, out, changed);
+            }
+        }
+        Node::Function { name, body } => {
+            // The body is checked where it is defined too, with its parameters unknown
+            for item in body {
+                walk(&item.node, scope, depth, out, changed);
+            }
+            scope.functions.insert(name.clone(), body.clone());
+        }
+    }
+}
+
+fn simple(
+    command: &SimpleCommand,
+    scope: &mut Scope,
+    depth: usize,
+    out: &mut Vec<(bool, String)>,
+    changed: &mut bool,
+) {
+    let text = expand(&command.text, &scope.vars);
+    let program = command.args().first().copied().unwrap_or_default();
+
+    // `nuke /` after `alias nuke='rm -rf'`: the alias value takes the program's place
+    if let (Some(value), Some((prefix, rest)), true) = (
+        scope.aliases.get(program).cloned(),
+        split_at_program(&command.text, command),
+        depth < MAX_CALL_DEPTH,
+    ) {
+        let text = expand(&format!("{}{}{}", prefix, value, rest), &scope.vars);
+        // As in bash, an alias is not expanded again inside its own expansion (`alias ls='ls -la'`)
+        scope.aliases.remove(program);
+        let first = out.len();
+        walk(&crate::shell::parse(&text), scope, depth + 1, out, changed);
+        scope.aliases.insert(program.to_string(), value);
+        if let Some(call) = out.get_mut(first) {
+            call.0 = command.piped;
+        }
+        *changed = true;
+        return;
+    }
+
+    // `f /srv` after `f(){ rm -rf "$1"; }`: the body, with `$1`, `$@` etc. set to the arguments
+    if let Some(body) = scope
+        .functions
+        .get(program)
+        .filter(|_| depth < MAX_CALL_DEPTH)
+    {
+        let reparsed = crate::shell::parse(&text);
+        let args: Vec<String> = reparsed
+            .simple_commands()
+            .first()
+            .map(|c| c.args().iter().skip(1).map(|a| a.to_string()).collect())
+            .unwrap_or_default();
+        let mut inner = scope.clone();
+        inner.vars.retain(|name, _| is_identifier(name));
+        for (i, arg) in args.iter().enumerate() {
+            inner.vars.insert((i + 1).to_string(), arg.clone());
+        }
+        inner.vars.insert("@".to_string(), args.join(" "));
+        inner.vars.insert("*".to_string(), args.join(" "));
+        inner.vars.insert("#".to_string(), args.len().to_string());
+        let first = out.len();
+        for item in body.clone().iter() {
+            walk(&item.node, &mut inner, depth + 1, out, changed);
+        }
+        if let Some(call) = out.get_mut(first) {
+            call.0 = command.piped;
+        }
+        *changed = true;
+        return;
+    }
+
+    *changed |= text != command.text;
+    out.push((command.piped, text));
+
+    // Prefix assignments (`X=1 cmd`) only reach the command's environment
+    let declared: Vec<&str> = match command.args().first() {
+        None => command
+            .assignments
+            .iter()
+            .map(|w| w.value.as_str())
+            .collect(),
+        Some(p) if DECLARATION_BUILTINS.contains(p) => command.args()[1..].to_vec(),
+        Some(&"alias") => {
+            for definition in &command.args()[1..] {
+                if let Some((name, value)) = definition.split_once('=') {
+                    scope
+                        .aliases
+                        .insert(name.to_string(), expand_unquoted(value, &scope.vars));
+                }
+            }
+            return;
+        }
+        Some(_) => return,
+    };
+    for assignment in declared {
+        if let Some((name, value)) = assignment.split_once('=') {
+            if is_identifier(name) {
+                let value = expand_unquoted(value, &scope.vars);
+                scope.vars.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+/// `text` split around the command's program word: prefix assignments before, arguments and
+/// redirects after.
+fn split_at_program<'t>(text: &'t str, command: &SimpleCommand) -> Option<(&'t str, &'t str)> {
+    let mut cursor = 0;
+    for word in &command.assignments {
+        cursor += text[cursor..].find(&word.raw)? + word.raw.len();
+    }
+    let program = &command.argv.first()?.raw;
+    let at = cursor + text[cursor..].find(program.as_str())?;
+    Some((&text[..at], &text[at + program.len()..]))
+}
+
+/// Flag commands whose program is still an expansion after `resolve` — a variable assigned
+/// outside this command string, or a command substitution — when it is run with `-rf`-style
+/// flags. Returns the ask reason.
+pub fn check_unresolved(cmd: &str, wrappers: &[String]) -> Option<String> {
+    let resolved = resolve(cmd);
+    let ast = crate::shell::parse(resolved.as_deref().unwrap_or(cmd));
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        let Some(program) = args.first() else {
+            continue;
+        };
+        if !(program.starts_with('$') || program.starts_with('`')) {
+            continue;
+        }
+        if let Some(flag) = args[1..].iter().find(|a| is_destructive_flag(a)) {
+            return Some(format!(
+                "Indirect execution: {} {} runs a command chosen at run time — the hook cannot \
+                 see what it is",
+                program, flag
+            ));
+        }
+    }
+    None
+}
+
+fn is_destructive_flag(arg: &str) -> bool {
+    if let Some(long) = arg.strip_prefix("--") {
+        return matches!(long, "force" | "recursive" | "no-preserve-root");
+    }
+    arg.strip_prefix('-')
+        .is_some_and(|short| short.contains(['r', 'R']) && short.contains('f'))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace `$name` and `${name}` for known variables outside single quotes.
+fn expand(text: &str, vars: &HashMap<String, String>) -> String {
+    expand_with(text, vars, true)
+}
+
+/// Same, for a value whose quotes were already removed.
+fn expand_unquoted(text: &str, vars: &HashMap<String, String>) -> String {
+    expand_with(text, vars, false)
+}
+
+fn expand_with(text: &str, vars: &HashMap<String, String>, honor_quotes: bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let (mut single, mut double) = (false, false);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if honor_quotes && !single => {
+                out.push(c);
+                if let Some(next) = chars.get(i + 1) {
+                    out.push(*next);
+                }
+                i += 2;
+                continue;
+            }
+            '\'' if honor_quotes && !double => single = !single,
+            '"' if honor_quotes && !single => double = !double,
+            '$' if !single => {
+                if let Some((name, len)) = variable_at(&chars[i + 1..]) {
+                    if let Some(value) = vars.get(&name) {
+                        out.push_str(value);
+                        i += 1 + len;
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// The variable name right after a `$` (`name`, a positional or special parameter such as
+/// `1` or `@`, or `{name}`) and how many chars it spans. `${name:?msg}` and `${name:-default}`
+/// count as `name`: with the variable known, both are its value.
+fn variable_at(rest: &[char]) -> Option<(String, usize)> {
+    if rest.first() == Some(&'{') {
+        let end = rest.iter().position(|c| *c == '}')?;
+        let inner: String = rest[1..end].iter().collect();
+        let name_len = inner
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(inner.len());
+        let (name, modifier) = inner.split_at(name_len);
+        let modifier_ok = modifier.is_empty()
+            || [":?", "?", ":-", "-", ":=", "="]
+                .iter()
+                .any(|m| modifier.starts_with(m));
+        return (modifier_ok && (is_identifier(name) || is_positional(name)))
+            .then(|| (name.to_string(), end + 1));
+    }
+    match rest.first() {
+        Some(c) if c.is_ascii_digit() || matches!(c, '@' | '*' | '#') => {
+            return Some((c.to_string(), 1))
+        }
+        _ => {}
+    }
+    let len = rest
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+        .count();
+    let name: String = rest[..len].iter().collect();
+    is_identifier(&name).then_some((name, len))
+}
+
+fn is_positional(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_assigned_command() {
+        assert_eq!(
+            resolve("cmd=rm; $cmd -rf /").as_deref(),
+            Some("cmd=rm; rm -rf /")
+        );
+        assert_eq!(
+            resolve("x=\"rm -rf\" && ${x} build").as_deref(),
+            Some("x=\"rm -rf\"; rm -rf build")
+        );
+        assert_eq!(
+            resolve("export S=bash\ncurl -s x | $S").as_deref(),
+            Some("export S=bash; curl -s x | bash")
+        );
+    }
+
+    #[test]
+    fn tracks_assignments_in_order() {
+        assert_eq!(
+            resolve("a=git; b=\"$a push\"; $b --force").as_deref(),
+            Some("a=git; b=\"git push\"; git push --force")
+        );
+        // Used before it is assigned: nothing to substitute
+        assert_eq!(resolve("$c -rf x; c=rm"), None);
+        // A prefix assignment only reaches the command's environment
+        assert_eq!(resolve("c=rm env; $c -rf x"), None);
+    }
+
+    #[test]
+    fn leaves_single_quotes_and_unknown_names() {
+        assert_eq!(resolve("c=rm; echo '$c -rf /'"), None);
+        assert_eq!(resolve("c=rm; echo $cc $HOME"), None);
+    }
+
+    #[test]
+    fn expands_aliases_at_call_sites() {
+        assert_eq!(
+            resolve("alias nuke='rm -rf'; nuke /").as_deref(),
+            Some("alias nuke='rm -rf'; rm -rf /")
+        );
+        assert_eq!(
+            resolve("alias g=git p='g push'\np --force origin").as_deref(),
+            Some("alias g=git p='g push'; git push --force origin")
+        );
+        // `alias ls='ls -la'` does not expand itself again
+        assert_eq!(
+            resolve("alias ls='ls -la'; ls /tmp").as_deref(),
+            Some("alias ls='ls -la'; ls -la /tmp")
+        );
+        assert_eq!(resolve("nuke /; alias nuke='rm -rf'"), None);
+    }
+
+    #[test]
+    fn expands_function_calls_with_arguments() {
+        assert_eq!(
+            resolve("f(){ rm -rf \"$1\"; }; f /srv").as_deref(),
+            Some("rm -rf \"$1\"; rm -rf \"/srv\"")
+        );
+        assert_eq!(
+            resolve("function wipe { git push --force \"$@\"; }; wipe origin main").as_deref(),
+            Some("git push --force \"$@\"; git push --force \"origin main\"")
+        );
+        assert_eq!(
+            resolve("d=/srv; f() { rm -rf \"${1:?}\"; }; f $d").as_deref(),
+            Some("d=/srv; rm -rf \"${1:?}\"; rm -rf \"/srv\"")
+        );
+        // Recursion stops at the call depth limit
+        assert!(resolve("f() { f; }; f").is_some());
+    }
+
+    #[test]
+    fn asks_for_unresolved_program_with_destructive_flags() {
+        assert!(check_unresolved("$CMD -rf /tmp/x", &[]).is_some());
+        assert!(check_unresolved("$(which rm) -fr build", &[]).is_some());
+        assert!(check_unresolved("sudo ${TOOL} --force", &[]).is_some());
+        assert!(check_unresolved("$EDITOR notes.txt", &[]).is_none());
+        assert!(check_unresolved("cmd=ls; $cmd -rf", &[]).is_none());
+    }
+}
@@ -0,0 +1,313 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Hook payload formats understood by the binary.
+/// Each adapter converts its framework's payload into the Claude Code shape so the
+/// rest of the pipeline (patterns, config) only ever sees one structure.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    /// Detect the format from the payload's keys.
+    Auto,
+    /// Claude Code PreToolUse: {"tool_name": "Bash", "tool_input": {"command": "..."}}
+    #[default]
+    Claude,
+    /// Minimal envelope for custom harnesses: {"command": "..."} or {"cmd": [...argv]}
+    Generic,
+    /// Open Interpreter code blocks: {"language": "shell", "code": "..."}
+    OpenInterpreter,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "claude" | "claude-code" => Some(Self::Claude),
+            "generic" => Some(Self::Generic),
+            "open-interpreter" | "interpreter" => Some(Self::OpenInterpreter),
+            _ => None,
+        }
+    }
+}
+
+/// The normalized hook input. Mirrors the structure sent by Claude Code's PreToolUse hook.
+#[derive(Deserialize, Debug)]
+pub struct HookInput {
+    #[serde(default)]
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: Value,
+    /// Working directory of the agent session, when the framework provides it.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Agent session the call belongs to, when the framework provides it.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// The payload format the input came in.
+    #[serde(skip)]
+    pub format: Format,
+}
+
+impl HookInput {
+    /// Whether the caller reads Claude Code's hook JSON on stdout, where asks and updated input
+    /// are answered. Other harnesses only see the exit code and stderr.
+    pub fn reads_hook_json(&self) -> bool {
+        self.format == Format::Claude
+    }
+}
+
+/// Languages that Open Interpreter runs through a shell.
+const SHELL_LANGUAGES: &[&str] = &["shell", "bash", "sh", "zsh"];
+
+/// Languages whose code blocks are checked as the one-liner that would run them: a `python`
+/// block as `python3 -c CODE`.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python3 -c"),
+    ("javascript", "node -e"),
+    ("js", "node -e"),
+    ("node", "node -e"),
+    ("ruby", "ruby -e"),
+    ("perl", "perl -e"),
+];
+
+/// Parse a raw stdin payload using the given format.
+/// Some wrappers send several JSON objects back to back or leave trailing garbage after the
+/// envelope: values are read one at a time and the first that is a hook envelope is used,
+/// with a warning when anything else came with it.
+/// Returns None if no value parses and matches the format (caller allows).
+pub fn parse_input(raw: &str, format: Format) -> Option<HookInput> {
+    let mut values = serde_json::Deserializer::from_str(raw).into_iter::<Value>();
+    let mut skipped = 0;
+    let input = loop {
+        match values.next()? {
+            Ok(value) => match envelope(value, format) {
+                Some(input) => break input,
+                None => skipped += 1,
+            },
+            Err(_) => return None,
+        }
+    };
+    let rest = raw[values.byte_offset()..].trim();
+    if skipped > 0 || !rest.is_empty() {
+        crate::warnings::warn(
+            "input-extra",
+            &format!(
+                "stdin held more than one JSON value ({} before the hook envelope, {} bytes after \
+                 it); checked the envelope",
+                skipped,
+                rest.len()
+            ),
+        );
+    }
+    Some(input)
+}
+
+/// The hook input a single JSON value describes, if it matches the format.
+fn envelope(value: Value, format: Format) -> Option<HookInput> {
+    let format = match format {
+        Format::Auto => detect_format(&value)?,
+        f => f,
+    };
+    let input = match format {
+        Format::Claude => serde_json::from_value(value).ok(),
+        Format::Generic => from_generic(&value),
+        Format::OpenInterpreter => from_open_interpreter(&value),
+        Format::Auto => None,
+    };
+    input.map(|input| HookInput { format, ..input })
+}
+
+/// Guess the payload format from its top-level keys.
+fn detect_format(value: &Value) -> Option<Format> {
+    let obj = value.as_object()?;
+    if obj.contains_key("tool_name") {
+        Some(Format::Claude)
+    } else if obj.contains_key("language") && obj.contains_key("code") {
+        Some(Format::OpenInterpreter)
+    } else if obj.contains_key("command") || obj.contains_key("cmd") {
+        Some(Format::Generic)
+    } else {
+        None
+    }
+}
+
+/// {"command": "git status"}, {"cmd": "git status"} or {"command": ["git", "status"]}
+fn from_generic(value: &Value) -> Option<HookInput> {
+    let raw = value.get("command").or_else(|| value.get("cmd"))?;
+    let command = match raw {
+        Value::String(s) => s.clone(),
+        Value::Array(argv) => argv
+            .iter()
+            .map(|a| a.as_str().map(crate::shell::quote))
+            .collect::<Option<Vec<_>>>()?
+            .join(" "),
+        _ => return None,
+    };
+    Some(bash_input(command, value))
+}
+
+/// {"language": "shell", "code": "rm -rf /"}; a block in an interpreted language is checked as a
+/// one-liner, and other languages are passed through to the config rules for that tool name.
+fn from_open_interpreter(value: &Value) -> Option<HookInput> {
+    let language = value.get("language")?.as_str()?.to_lowercase();
+    let code = value.get("code")?.as_str()?.to_string();
+    if let Some((_, run)) = INTERPRETERS.iter().find(|(name, _)| *name == language) {
+        return Some(bash_input(
+            format!("{} {}", run, crate::shell::quote(&code)),
+            value,
+        ));
+    }
+    if !SHELL_LANGUAGES.contains(&language.as_str()) {
+        return Some(HookInput {
+            tool_name: language,
+            tool_input: json!({ "code": code }),
+            cwd: cwd_of(value),
+            session_id: session_of(value),
+            format: Format::OpenInterpreter,
+        });
+    }
+    Some(bash_input(code, value))
+}
+
+fn bash_input(command: String, value: &Value) -> HookInput {
+    HookInput {
+        tool_name: "Bash".to_string(),
+        tool_input: json!({ "command": command }),
+        cwd: cwd_of(value),
+        session_id: session_of(value),
+        // The adapter's own format is filled in by `envelope`
+        format: Format::Auto,
+    }
+}
+
+fn cwd_of(value: &Value) -> Option<String> {
+    value
+        .get("cwd")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn session_of(value: &Value) -> Option<String> {
+    value
+        .get("session_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_of(input: &HookInput) -> &str {
+        input
+            .tool_input
+            .get("command")
+            .and_then(|v| v.as_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn claude_payload_detected() {
+        let input = parse_input(
+            r#"{"tool_name":"Bash","tool_input":{"command":"ls"}}"#,
+            Format::Auto,
+        )
+        .unwrap();
+        assert_eq!(input.tool_name, "Bash");
+        assert_eq!(command_of(&input), "ls");
+        assert_eq!(input.session_id, None);
+        let input = parse_input(
+            r#"{"tool_name":"Bash","tool_input":{"command":"ls"},"session_id":"abc"}"#,
+            Format::Auto,
+        )
+        .unwrap();
+        assert_eq!(input.session_id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn generic_string_command() {
+        let input = parse_input(r#"{"command":"rm -rf /"}"#, Format::Auto).unwrap();
+        assert_eq!(input.tool_name, "Bash");
+        assert_eq!(command_of(&input), "rm -rf /");
+        assert!(!input.reads_hook_json());
+        assert!(parse_input(
+            r#"{"tool_name":"Bash","tool_input":{"command":"ls"}}"#,
+            Format::Auto
+        )
+        .unwrap()
+        .reads_hook_json());
+    }
+
+    #[test]
+    fn generic_argv_command_is_joined() {
+        let input = parse_input(r#"{"cmd":["git","commit","-m","a b"]}"#, Format::Generic).unwrap();
+        assert_eq!(command_of(&input), "git commit -m 'a b'");
+    }
+
+    #[test]
+    fn open_interpreter_shell_block() {
+        let input = parse_input(r#"{"language":"bash","code":"sudo ls"}"#, Format::Auto).unwrap();
+        assert_eq!(input.tool_name, "Bash");
+        assert_eq!(command_of(&input), "sudo ls");
+    }
+
+    #[test]
+    fn open_interpreter_code_runs_as_a_one_liner() {
+        let input =
+            parse_input(r#"{"language":"python","code":"print(1)"}"#, Format::Auto).unwrap();
+        assert_eq!(
+            (input.tool_name.as_str(), command_of(&input)),
+            ("Bash", "python3 -c 'print(1)'")
+        );
+        let input = parse_input(r#"{"language":"JavaScript","code":"1"}"#, Format::Auto).unwrap();
+        assert_eq!(command_of(&input), "node -e 1");
+        let input = parse_input(r#"{"language":"html","code":"<p>"}"#, Format::Auto).unwrap();
+        assert_eq!(input.tool_name, "html");
+    }
+
+    #[test]
+    fn unknown_shape_returns_none() {
+        assert!(parse_input(r#"{"foo":"bar"}"#, Format::Auto).is_none());
+        assert!(parse_input("not json", Format::Auto).is_none());
+    }
+
+    #[test]
+    fn forced_format_mismatch_returns_none() {
+        assert!(parse_input(r#"{"tool_name":"Bash"}"#, Format::Generic).is_none());
+    }
+
+    #[test]
+    fn first_envelope_of_concatenated_payloads() {
+        let raw = r#"{"tool_name":"Bash","tool_input":{"command":"rm -rf /"}}{"tool_name":"Bash",
+            "tool_input":{"command":"ls"}}"#;
+        assert_eq!(
+            command_of(&parse_input(raw, Format::Auto).unwrap()),
+            "rm -rf /"
+        );
+        let raw =
+            "{\"tool_name\":\"Bash\",\"tool_input\":{\"command\":\"git status\"}}\n\u{0}garbage{{";
+        assert_eq!(
+            command_of(&parse_input(raw, Format::Auto).unwrap()),
+            "git status"
+        );
+        let raw = r#"{"event":"start"} {"command":"sudo ls"}"#;
+        assert_eq!(
+            command_of(&parse_input(raw, Format::Auto).unwrap()),
+            "sudo ls"
+        );
+        assert!(parse_input(
+            r#"{"event":"start"} garbage {"command":"ls"}"#,
+            Format::Auto
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(Format::from_name("generic"), Some(Format::Generic));
+        assert_eq!(
+            Format::from_name("open-interpreter"),
+            Some(Format::OpenInterpreter)
+        );
+        assert_eq!(Format::from_name("bogus"), None);
+    }
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long a cached inventory is trusted before the workspace is walked again.
+const TTL_SECS: u64 = 30;
+
+/// Stop walking after this many entries. A truncated inventory is not used for counts.
+const MAX_ENTRIES: usize = 50_000;
+
+/// A snapshot of a workspace's files, so path checks don't re-walk the tree and re-run git
+/// on every hook call.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Inventory {
+    pub root: PathBuf,
+    pub built_at: u64,
+    /// Every file and directory under the root, relative and '/'-separated (`.git` excluded).
+    pub entries: Vec<String>,
+    /// True when the walk stopped at MAX_ENTRIES.
+    pub truncated: bool,
+    /// Files tracked by git, plus every directory that contains one.
+    pub tracked: HashSet<String>,
+}
+
+/// Cache file for a workspace root, under the cache dir.
+pub fn cache_path(cache_dir: &Path, root: &Path) -> PathBuf {
+    cache_dir.join("safe-bash-cache").join(format!(
+        "inventory-{:016x}.json",
+        fnv1a(root.to_string_lossy().as_bytes())
+    ))
+}
+
+/// The cached inventory for `root` if it is fresh, otherwise a rebuilt one (which is saved).
+pub fn load(cache_dir: &Path, root: &Path) -> Inventory {
+    let path = cache_path(cache_dir, root);
+    if let Some(cached) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Inventory>(&s).ok())
+    {
+        if cached.root == root && now_secs().saturating_sub(cached.built_at) < TTL_SECS {
+            return cached;
+        }
+    }
+
+    let inventory = build(root);
+    let saved = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, serde_json::to_string(&inventory).unwrap_or_default()));
+    if let Err(e) = saved {
+        crate::warnings::warn(
+            "inventory-write",
+            &format!("could not write inventory cache {}: {}", path.display(), e),
+        );
+    }
+    inventory
+}
+
+/// Walk the workspace and ask git for the tracked set.
+pub fn build(root: &Path) -> Inventory {
+    let mut inventory = Inventory {
+        root: root.to_path_buf(),
+        built_at: now_secs(),
+        ..Inventory::default()
+    };
+
+    let mut stack = vec![(root.to_path_buf(), String::new())];
+    'walk: while let Some((dir, prefix)) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if prefix.is_empty() && name == ".git" {
+                continue;
+            }
+            if inventory.entries.len() >= MAX_ENTRIES {
+                inventory.truncated = true;
+                break 'walk;
+            }
+            let rel = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                stack.push((entry.path(), rel.clone()));
+            }
+            inventory.entries.push(rel);
+        }
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z"])
+        .output();
+    if let Some(output) = output.ok().filter(|o| o.status.success()) {
+        for file in output.stdout.split(|b| *b == 0).filter(|f| !f.is_empty()) {
+            let file = String::from_utf8_lossy(file).to_string();
+            let mut dir = file.as_str();
+            while let Some((parent, _)) = dir.rsplit_once('/') {
+                inventory.tracked.insert(parent.to_string());
+                dir = parent;
+            }
+            inventory.tracked.insert(file);
+        }
+    }
+    inventory
+}
+
+impl Inventory {
+    /// Entries matching a root-relative glob, one glob per path component.
+    /// Like the shell, `*` doesn't match a leading dot unless the pattern component has one.
+    pub fn glob_matches(&self, pattern: &str) -> Vec<&str> {
+        let parts: Vec<&str> = pattern
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                let names: Vec<&str> = entry.split('/').collect();
+                names.len() == parts.len()
+                    && parts.iter().zip(&names).all(|(p, n)| {
+                        (!n.starts_with('.') || p.starts_with('.'))
+                            && crate::blast::glob_match(p, n)
+                    })
+            })
+            .map(|s| s.as_str())
+            .collect()
+    }
+
+    pub fn is_tracked(&self, rel: &str) -> bool {
+        self.tracked.contains(rel)
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// FNV-1a, for cache file names that stay stable across builds.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workspace() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        for f in [
+            "src/a.rs",
+            "src/b.rs",
+            "src/bin/c.rs",
+            "src/.hidden",
+            "README.md",
+        ] {
+            fs::write(dir.path().join(f), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn build_lists_entries_relative_to_root() {
+        let dir = workspace();
+        let inv = build(dir.path());
+        let mut entries = inv.entries.clone();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                "README.md",
+                "src",
+                "src/.hidden",
+                "src/a.rs",
+                "src/b.rs",
+                "src/bin",
+                "src/bin/c.rs"
+            ]
+        );
+        assert!(!inv.truncated);
+    }
+
+    #[test]
+    fn glob_matches_per_component_and_skips_dotfiles() {
+        let dir = workspace();
+        let inv = build(dir.path());
+        let mut m = inv.glob_matches("src/*");
+        m.sort();
+        assert_eq!(m, vec!["src/a.rs", "src/b.rs", "src/bin"]);
+        assert_eq!(inv.glob_matches("./src/*/*.rs"), vec!["src/bin/c.rs"]);
+        assert_eq!(inv.glob_matches("src/.*"), vec!["src/.hidden"]);
+    }
+
+    #[test]
+    fn tracked_set_includes_parent_dirs() {
+        let dir = workspace();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            return; // git not installed
+        }
+        assert!(git(&["add", "src/bin/c.rs"]));
+        let inv = build(dir.path());
+        assert!(inv.is_tracked("src/bin/c.rs"));
+        assert!(inv.is_tracked("src/bin"));
+        assert!(inv.is_tracked("src"));
+        assert!(!inv.is_tracked("src/a.rs"));
+    }
+
+    #[test]
+    fn load_reuses_fresh_cache() {
+        let dir = workspace();
+        let hooks = TempDir::new().unwrap();
+        let first = load(hooks.path(), dir.path());
+        assert!(cache_path(hooks.path(), dir.path()).exists());
+        fs::write(dir.path().join("new.txt"), "").unwrap();
+        let second = load(hooks.path(), dir.path());
+        assert_eq!(first.entries.len(), second.entries.len());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::config::{CompiledPattern, PatternsConfig};
+use crate::matcher::Matcher;
+use serde_json::Value;
+use std::path::Path;
+
+/// Problems with a config file (`safe-bash-hook lint <file>`): keys the schema does not know,
+/// values that do not fit it (which would leave only the hardcoded patterns), everything the
+/// hook would warn about while loading it, and rules that repeat or can never match. Empty
+/// when the file is fine.
+pub fn lint(path: &Path) -> Vec<String> {
+    let map = match crate::config::read_file(path) {
+        Ok(map) => map,
+        Err(e) => return vec![e],
+    };
+    let mut problems = Vec::new();
+    unknown_keys(
+        &Value::Object(map.clone()),
+        &crate::schema::schema(),
+        "",
+        &mut problems,
+    );
+    unnamed(&map, &mut problems);
+    if let Err(e) = serde_json::from_value::<PatternsConfig>(Value::Object(map)) {
+        problems.push(format!(
+            "invalid config: {} — the hook would use the hardcoded patterns only",
+            e
+        ));
+        return problems;
+    }
+    let (config, warnings) = crate::warnings::capture(|| crate::config::load_config(path));
+    problems.extend(warnings);
+    let deny: Vec<&CompiledPattern> = config.locked_deny.iter().chain(&config.deny).collect();
+    repeated(&deny, "deny", &mut problems);
+    repeated(
+        &config.allow.iter().collect::<Vec<_>>(),
+        "allow",
+        &mut problems,
+    );
+    unreachable(&config.deny, &config.allow, &mut problems);
+    problems
+}
+
+/// Report keys the schema does not list, with where they are: `deny[2]: unknown key "patern"`.
+fn unknown_keys(value: &Value, schema: &Value, at: &str, problems: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            let known = schema.get("properties").and_then(Value::as_object);
+            for (key, child) in map {
+                let child_at = if at.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", at, key)
+                };
+                match known.and_then(|k| k.get(key)) {
+                    Some(child_schema) => unknown_keys(child, child_schema, &child_at, problems),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        let place = if at.is_empty() {
+                            String::new()
+                        } else {
+                            format!("{}: ", at)
+                        };
+                        problems.push(format!("{}unknown key {:?}", place, key));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    unknown_keys(item, item_schema, &format!("{}[{}]", at, i), problems);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Report deny rules with their own `severity` but no `id`: audit entries and warnings cite
+/// them as CFG001, CFG002, …, numbers that move when rules are added above them.
+fn unnamed(map: &serde_json::Map<String, Value>, problems: &mut Vec<String>) {
+    let rules = map
+        .get("deny")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten();
+    for (i, rule) in rules
+        .enumerate()
+        .filter(|(_, r)| r.get("severity").is_some() && r.get("id").is_none())
+    {
+        let reason = rule
+            .get("reason")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        problems.push(format!(
+            "deny[{}]: rule {:?} sets a severity but has no id; give it an `id` so its entries \
+             keep citing it",
+            i, reason
+        ));
+    }
+}
+
+/// Report explicit IDs given to two rules, and rules that match exactly what an earlier one does.
+fn repeated(rules: &[&CompiledPattern], kind: &str, problems: &mut Vec<String>) {
+    for (i, rule) in rules.iter().enumerate() {
+        let earlier = &rules[..i];
+        if kind == "deny" && earlier.iter().any(|e| e.id == rule.id) {
+            problems.push(format!("deny rule ID {} is used more than once", rule.id));
+        }
+        if let Some(first) = earlier
+            .iter()
+            .find(|e| same_match(e, rule) && e.unless.is_empty() && rule.unless.is_empty())
+        {
+            problems.push(format!(
+                "{} rule {:?} repeats {:?} ({} `{}`)",
+                kind,
+                rule.reason,
+                first.reason,
+                rule.matcher.kind(),
+                rule.matcher
+            ));
+        }
+    }
+}
+
+/// Report deny rules no command can reach: every allow rule lifts a config deny, so an allow
+/// that matches anything at all, or exactly what the deny rule matches, leaves it nothing.
+/// Locked rules are checked before the allow rules and are always reachable.
+fn unreachable(deny: &[CompiledPattern], allow: &[CompiledPattern], problems: &mut Vec<String>) {
+    if let Some(everything) = allow.iter().find(|a| matches_anything(a)) {
+        if !deny.is_empty() {
+            problems.push(format!(
+                "allow rule {:?} matches every command, so none of the {} unlocked deny rules can \
+                 match",
+                everything.reason,
+                deny.len()
+            ));
+        }
+        return;
+    }
+    for rule in deny {
+        let lifted = allow.iter().find(|a| {
+            same_match(a, rule)
+                || matches!(&rule.matcher, Matcher::Exact(whole) if a.is_match(whole, &[]))
+        });
+        if let Some(a) = lifted {
+            problems.push(format!(
+                "deny rule {} ({:?}) can never match: allow rule {:?} lets every command it \
+                 matches through",
+                rule.id, rule.reason, a.reason
+            ));
+        }
+    }
+}
+
+/// Zone rules are told apart by the paths commands touch, which lint does not see.
+fn same_match(a: &CompiledPattern, b: &CompiledPattern) -> bool {
+    a.zones.is_none()
+        && b.zones.is_none()
+        && a.matcher.kind() == b.matcher.kind()
+        && a.matcher.to_string() == b.matcher.to_string()
+}
+
+/// Whether a rule matches the empty command and unrelated ones alike, like `.*` or `""`.
+fn matches_anything(rule: &CompiledPattern) -> bool {
+    rule.zones.is_none()
+        && ["", "ls", "git status"]
+            .iter()
+            .all(|cmd| rule.matcher.is_match(cmd, &[]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn lint_json(json: &str) -> Vec<String> {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("safe-bash-user.json");
+        fs::write(&path, json).unwrap();
+        lint(&path)
+    }
+
+    #[test]
+    fn clean_config_has_no_problems() {
+        let json = r#"{"$schema":"x","deny":[{"pattern":"^terraform\\s+apply\\b","reason":"CI only",
+            "unless":[{"args_match":"^-target="}]}],
+            "allow":[{"type":"exact","pattern":"terraform plan","reason":"read only"}],
+            "category_severity":{"sensitive-read":"ask"}}"#;
+        assert_eq!(lint_json(json), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_typos_bad_values_and_dead_rules() {
+        let json = r#"{"deny_rules":[],"deny":[
+            {"patern":"x","reason":"typo"},
+            {"pattern":"(","reason":"broken"},
+            {"id":"DEPLOY","pattern":"^deploy\\b","reason":"no deploys"},
+            {"id":"DEPLOY","pattern":"^deploy\\b","reason":"again"},
+            {"type":"exact","pattern":"make release","reason":"CI releases"}],
+            "allow":[{"pattern":"^make\\b","reason":"make is fine"}],
+            "retry_loop":{"max_repeat":5},"category_severity":{"sensitive-reads":"ask"}}"#;
+        let problems = lint_json(json);
+        let expected = [
+            "unknown key \"deny_rules\"",
+            "deny[0]: unknown key \"patern\"",
+            "retry_loop: unknown key \"max_repeat\"",
+            "ignoring deny rule \"typo\": give either a pattern, a command or zones",
+            "invalid deny regex \"(\"",
+            "ignoring category_severity \"sensitive-reads\": \"ask\"",
+            "deny rule ID DEPLOY is used more than once",
+            "deny rule \"again\" repeats \"no deploys\" (regex `^deploy\\b`)",
+            "deny rule CFG005 (\"CI releases\") can never match: allow rule \"make is fine\" lets \
+             every command it matches through",
+        ];
+        for e in expected {
+            assert!(
+                problems.iter().any(|p| p.starts_with(e)),
+                "missing {:?} in {:#?}",
+                e,
+                problems
+            );
+        }
+        assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+    }
+
+    #[test]
+    fn reports_rules_cited_by_a_number_that_moves() {
+        let json = r#"{"deny":[{"pattern":"^deploy\\b","reason":"no deploys","suppressible":true},
+            {"pattern":"^terraform\\b","reason":"watched","severity":"warn"},
+            {"id":"TF","pattern":"^tofu\\b","reason":"named","severity":"warn",
+            "suppressible":true}]}"#;
+        assert_eq!(
+            lint_json(json),
+            [
+                "deny[1]: rule \"watched\" sets a severity but has no id; give it an `id` so its \
+                 entries keep citing it",
+                "deny rule \"no deploys\" is suppressible but has no id, so it cannot be lifted \
+                 inline; give it an `id`",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_values_that_empty_the_config() {
+        let problems = lint_json(r#"{"deny":[{"pattern":"x"}]}"#);
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("invalid config: missing field `reason`"),
+            "{:?}",
+            problems
+        );
+        assert!(lint_json("{")
+            .first()
+            .is_some_and(|p| p.starts_with("malformed JSON")));
+        let problems = lint_json(
+            r#"{"deny":[{"pattern":"^rm\\b","reason":"r"}],"allow":[{"pattern":".*",
+                "reason":"all"}]}"#,
+        );
+        assert_eq!(
+            problems,
+            [
+                "allow rule \"all\" matches every command, so none of the 1 unlocked deny rules \
+                 can match"
+            ]
+        );
+        // Zone rules without a pattern differ by the paths they look at
+        let zoned = r#"{"deny":[{"zones":["system"],"access":"write","reason":"w"},
+            {"zones":["home-config"],"reason":"h"}],"allow":[{"zones":["temp"],"reason":"t"}]}"#;
+        assert!(lint_json(zoned).is_empty(), "{:?}", lint_json(zoned));
+    }
+}
@@ -1,27 +1,1348 @@
+// The config schema is one large json! literal
+#![recursion_limit = "256"]
+
+mod archive;
+mod artifacts;
+mod audit;
 mod autoupdate;
+mod blast;
+mod branches;
+mod cargo;
 mod config;
+mod decode;
+mod detach;
+mod dirs;
+mod docker;
+mod doctor;
+mod editor;
+mod examples;
+mod fingerprint;
+mod flow;
+mod gitalias;
+mod guarded;
+mod host;
+mod identity;
+mod ifs;
+mod indirection;
+mod input;
+mod intent;
+mod inventory;
+mod lint;
+mod make;
+mod matcher;
+mod normalize;
+mod oneliner;
+mod output;
 mod patterns;
+mod perms;
+mod privilege;
+mod project;
+mod protected;
+mod quota;
+mod remote;
+mod retry;
+mod rewrite;
+mod rollback;
+mod schedule;
+mod schema;
+mod scope;
+mod scripts;
+mod sedbackup;
+mod session;
+mod severity;
+mod shell;
+mod snapshot;
+mod suppress;
+mod tofu;
+mod tools;
+mod trash;
+mod unguarded;
+mod unicode;
+mod venv;
+mod warnings;
+mod webhook;
+mod wildcard;
+mod zones;
 
-use serde::Deserialize;
-use serde_json::Value;
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The directory commands run in when the hook input has no `cwd`: the project Claude Code
+/// was started in (`CLAUDE_PROJECT_DIR`, set for hooks), else the hook's own.
+fn default_cwd() -> PathBuf {
+    match std::env::var_os("CLAUDE_PROJECT_DIR").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+    }
+}
+
+/// Parse `--format <name>` / `--format=<name>` from the command line.
+/// Unknown formats warn and fall back to auto-detection.
+fn input_format(args: &[String]) -> input::Format {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let name = if arg == "--format" {
+            iter.next().map(|s| s.as_str())
+        } else {
+            arg.strip_prefix("--format=")
+        };
+        if let Some(name) = name {
+            return input::Format::from_name(name).unwrap_or_else(|| {
+                warnings::warn(
+                    "format",
+                    &format!("unknown --format {:?}, auto-detecting", name),
+                );
+                input::Format::Auto
+            });
+        }
+    }
+    input::Format::Auto
+}
+
+/// Everything known about the current invocation that a decision needs.
+struct Invocation<'a> {
+    /// Decisions to alert on (`alert_webhook` in the config).
+    alert: Option<&'a config::AlertWebhook>,
+    command: &'a str,
+    cwd: Option<&'a Path>,
+    dirs: &'a dirs::Dirs,
+    privilege: privilege::Privilege,
+    /// Workspace root (or cwd) whose audit log the decision goes to.
+    project: &'a Path,
+    retention: &'a config::AuditRetention,
+    /// Record an undo command with ask decisions (`rollback_hints` in the config).
+    rollback_hints: bool,
+    wrappers: &'a [String],
+    /// The branches block and ask context calls protected (`protected_branches` in the config).
+    protected_branches: &'a [String],
+    /// Whether the caller reads the hook's JSON answers; other harnesses only see the exit code.
+    hook_json: bool,
+}
+
+/// Append the decision to the audit log and, when it is one the alert webhook wants, to the
+/// alert queue. Returns the audit id.
+fn record(
+    decision: &str,
+    reason: &str,
+    rollback: Option<&str>,
+    rewritten: Option<&str>,
+    backups: &[String],
+    inv: &Invocation,
+) -> String {
+    let privilege = inv.privilege.as_str();
+    let fingerprint = fingerprint::of(inv.command, inv.wrappers).shape;
+    let entry = audit::Entry {
+        decision,
+        reason,
+        command: inv.command,
+        fingerprint: &fingerprint,
+        privilege,
+        project: inv.project,
+        rollback,
+        rewritten,
+        backups,
+    };
+    let id = audit::record(&inv.dirs.state, &entry, inv.retention);
+    if inv
+        .alert
+        .is_some_and(|a| a.decisions.iter().any(|d| d == decision))
+    {
+        let event = serde_json::json!({
+            "id": id,
+            "decision": decision,
+            "reason": reason,
+            "command": inv.command,
+            "fingerprint": fingerprint,
+            "privilege": privilege,
+            "project": inv.project.to_string_lossy(),
+        });
+        webhook::enqueue(&inv.dirs.state, &event);
+        webhook::maybe_flush(&inv.dirs.state);
+    }
+    id
+}
+
+/// Print the block message (with blast-radius context when available, the policy layer the
+/// rule came from and, for compound commands, the decision per segment) on stderr and as the
+/// hook's JSON deny decision on stdout, audit it, and exit 2.
+fn block(reason: &str, layer: &str, segments: Option<&str>, inv: &Invocation) -> ! {
+    let mut message = match blast::describe(
+        inv.command,
+        inv.cwd,
+        &inv.dirs.cache,
+        inv.protected_branches,
+    ) {
+        Some(context) => format!(
+            "Blocked: {} (context: {})\nPolicy: {}",
+            reason, context, layer
+        ),
+        None => format!("Blocked: {}\nPolicy: {}", reason, layer),
+    };
+    if let Some(segments) = segments {
+        message.push('\n');
+        message.push_str(segments);
+    }
+    eprintln!("{}", message);
+    println!("{}", output::deny_json(&message));
+    record("deny", reason, None, None, &[], inv);
+    std::process::exit(2);
+}
+
+/// Ask the user to confirm via the hook's JSON output, audit it, and exit 0; block instead for
+/// a harness that reads no JSON. With rollback
+/// hints on, the undo command is captured now (before the command can run), stored in the
+/// audit entry, and quoted in the reason with the entry id. Compound commands list the
+/// decision per segment, as blocks do.
+fn ask(reason: &str, layer: &str, segments: Option<&str>, inv: &Invocation) -> ! {
+    // A harness that cannot show a confirmation would run the command on exit 0
+    if !inv.hook_json {
+        block(
+            &format!(
+                "{} (needs confirmation, which this harness cannot ask for)",
+                reason
+            ),
+            layer,
+            segments,
+            inv,
+        );
+    }
+    let reason = match blast::describe(
+        inv.command,
+        inv.cwd,
+        &inv.dirs.cache,
+        inv.protected_branches,
+    ) {
+        Some(context) => format!("{} (context: {})", reason, context),
+        None => reason.to_string(),
+    };
+    let rollback = if inv.rollback_hints {
+        let cwd = inv
+            .cwd
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok());
+        cwd.and_then(|cwd| rollback::hint(inv.command, &cwd, inv.wrappers))
+    } else {
+        None
+    };
+    let id = record("ask", &reason, rollback.as_deref(), None, &[], inv);
+    let mut message = format!("{}\nPolicy: {}", reason, layer);
+    if let Some(segments) = segments {
+        message.push('\n');
+        message.push_str(segments);
+    }
+    if let Some(rollback) = &rollback {
+        message.push_str(&format!(
+            "\nRollback: {} (safe-bash-hook rollback-info {})",
+            rollback, id
+        ));
+    }
+    println!("{}", output::ask_json(&message));
+    std::process::exit(0);
+}
+
+/// The segments of a blocked compound command before its first blocked segment
+/// (`cargo build && rm -rf target/old && deploy` → `cargo build`), if they pass on their own.
+fn safe_prefix<'c>(command: &'c str, checker: &Checker) -> Option<&'c str> {
+    let segments = shell::pipelines(command);
+    let blocked = segments
+        .iter()
+        .position(|r| matches!(decide(&command[r.clone()], checker), Decision::Deny(..)))?;
+    let prefix = &command[..segments.get(blocked.checked_sub(1)?)?.end];
+    (!matches!(decide(prefix, checker), Decision::Deny(..))).then_some(prefix)
+}
+
+/// Block the command, but offer to run its safe prefix instead (`suggest_safe_prefix` in the
+/// config): the decision is an ask whose updated tool input is the prefix, so approving runs
+/// only the part before the blocked segment. The original command is audited as denied.
+fn suggest(
+    reason: &str,
+    layer: &str,
+    prefix: &str,
+    tool_input: &serde_json::Value,
+    inv: &Invocation,
+) -> ! {
+    record("deny", reason, None, None, &[], inv);
+    let mut input = tool_input.clone();
+    input["command"] = serde_json::Value::from(prefix);
+    let message = format!(
+        "Blocked: {}\nPolicy: {}\nSuggested: approve to run only `{}`, the part before the \
+         blocked segment",
+        reason, layer, prefix
+    );
+    println!("{}", output::rewrite_json(&message, &input));
+    std::process::exit(0);
+}
+
+/// The safer command an opted-in rule (`rewrite_rules` in the config) turns a blocked or asked
+/// command into, with the rule's reason. The rewrite must not be blocked itself once the rules
+/// it answers are set aside, and `rsync --delete` is only previewed on its first pass in a
+/// session (none without a session), so the real run that follows is decided as usual.
+fn rewritten(
+    command: &str,
+    checker: &Checker,
+    session: Option<&str>,
+    state_dir: &Path,
+) -> Option<(String, String)> {
+    let config = checker.config;
+    let hit = match patterns::check_command(
+        command,
+        checker.hardcoded,
+        &config.severities,
+        &config.wrappers,
+    ) {
+        patterns::CheckResult::Ask(hit) | patterns::CheckResult::Deny(hit) => hit,
+        _ => return None,
+    };
+    if !config.rewrite_rules.iter().any(|id| id == hit.id) {
+        return None;
+    }
+    let safer = rewrite::apply(hit.id, command, &config.wrappers)?;
+    let covered = rewrite::covered(hit.id);
+    let others: Vec<_> = checker
+        .hardcoded
+        .iter()
+        .filter(|p| !covered.contains(&p.id))
+        .cloned()
+        .collect();
+    let relaxed = Checker {
+        hardcoded: &others,
+        config,
+        project: checker.project,
+        cwd: checker.cwd,
+    };
+    if matches!(decide(&safer, &relaxed), Decision::Deny(..)) {
+        return None;
+    }
+    if hit.id == "FS018"
+        && !session.is_some_and(|s| {
+            rewrite::first_pass(
+                state_dir,
+                s,
+                &fingerprint::of(command, &config.wrappers).id(),
+            )
+        })
+    {
+        return None;
+    }
+    Some((hit.cited(), safer))
+}
+
+/// Offer the safer command in place of the original: the decision is an ask whose updated tool
+/// input is the rewrite, so approving runs only that. Both commands are audited.
+fn rewrite(reason: &str, safer: &str, tool_input: &serde_json::Value, inv: &Invocation) -> ! {
+    record("ask", reason, None, Some(safer), &[], inv);
+    let mut input = tool_input.clone();
+    input["command"] = serde_json::Value::from(safer);
+    let message = format!("Rewritten: {}\nApprove to run `{}` instead", reason, safer);
+    println!("{}", output::rewrite_json(&message, &input));
+    std::process::exit(0);
+}
+
+/// The `safe-bash-hook trash` call that moves what an `rm` of workspace files would delete into
+/// the session's trash (`trash_deletes` in the config), if the command passes every check but
+/// the rm rules once it no longer deletes anything for good. The call is approved in the state
+/// directory, so `trash` runs it and nothing else; unless `approve` is off, for a call that is
+/// only reported.
+fn trashed(
+    command: &str,
+    checker: &Checker,
+    project: &Path,
+    session: Option<&str>,
+    state_dir: &Path,
+    approve: bool,
+) -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    let (session, id) = (session.unwrap_or("no-session"), trash::new_id());
+    let (call, approval) = trash::rewrite(
+        command,
+        checker.cwd,
+        project,
+        &exe.to_string_lossy(),
+        session,
+        &id,
+    )?;
+    let others: Vec<_> = checker
+        .hardcoded
+        .iter()
+        .filter(|p| !trash::COVERED.contains(&p.id))
+        .cloned()
+        .collect();
+    let relaxed = Checker {
+        hardcoded: &others,
+        config: checker.config,
+        project: checker.project,
+        cwd: checker.cwd,
+    };
+    if !matches!(decide(command, &relaxed), Decision::Allow) {
+        return None;
+    }
+    if !approve {
+        return Some(call);
+    }
+    trash::approve(state_dir, session, &id, &approval)
+        .ok()
+        .map(|_| call)
+}
+
+/// Run the trash call in place of the `rm`, and audit both. The user's permission rules still
+/// decide whether it prompts.
+fn trash_instead(call: &str, tool_input: &serde_json::Value, inv: &Invocation) -> ! {
+    let reason = "rm moved to the trash (trash_deletes)";
+    record("trash", reason, None, Some(call), &[], inv);
+    let mut input = tool_input.clone();
+    input["command"] = serde_json::Value::from(call);
+    eprintln!(
+        "{}; `safe-bash-hook restore` lists what can be put back",
+        reason
+    );
+    println!("{}", output::update_json(&input));
+    std::process::exit(0);
+}
+
+/// The command with its in-place seds keeping a backup of each file (`sed_backups` in the
+/// config), if that passes every check but the in-place edit rule.
+fn backed_up(command: &str, checker: &Checker) -> Option<sedbackup::Backed> {
+    let backed = sedbackup::rewrite(
+        command,
+        checker.cwd,
+        &checker.config.wrappers,
+        sedbackup::Flavor::host(),
+    )?;
+    let others: Vec<_> = checker
+        .hardcoded
+        .iter()
+        .filter(|p| !sedbackup::COVERED.contains(&p.id))
+        .cloned()
+        .collect();
+    let relaxed = Checker {
+        hardcoded: &others,
+        config: checker.config,
+        project: checker.project,
+        cwd: checker.cwd,
+    };
+    matches!(decide(&backed.command, &relaxed), Decision::Allow).then_some(backed)
+}
+
+/// Run the backed-up command in place of the original, and audit it with the backup paths. The
+/// user's permission rules still decide whether it prompts.
+fn with_backups(backed: &sedbackup::Backed, tool_input: &serde_json::Value, inv: &Invocation) -> ! {
+    let reason = "sed -i keeps a backup (sed_backups)";
+    record(
+        "backup",
+        reason,
+        None,
+        Some(&backed.command),
+        &backed.backups,
+        inv,
+    );
+    let mut input = tool_input.clone();
+    input["command"] = serde_json::Value::from(backed.command.as_str());
+    eprintln!("{}: {}", reason, backed.backups.join(", "));
+    println!("{}", output::update_json(&input));
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook trash --session <id> --id <batch> [-r] [-f] -- <path>…`: what a
+/// `trash_deletes` rewrite runs instead of `rm`. Moves the paths, all inside the workspace of the
+/// working directory, into the session's trash and says how to get them back; errors read like
+/// rm's. Runs only a call the hook approved.
+fn trash_files(args: &[String]) -> ! {
+    let Some(mut request) = trash::parse_request(args) else {
+        eprintln!("usage: safe-bash-hook trash --session <id> --id <batch> [-r] [-f] -- <path>...");
+        std::process::exit(1);
+    };
+    let dirs = dirs::Dirs::locate();
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    request.root = blast::workspace_root(&cwd).unwrap_or_else(|| cwd.clone());
+    if !trash::take_approval(&dirs.state, &request, &cwd, trash::now_secs()) {
+        eprintln!(
+            "safe-bash-hook: batch {} was not approved by the hook for this directory; nothing \
+             moved",
+            request.id
+        );
+        std::process::exit(1);
+    }
+    let (moved, errors) = trash::move_to_trash(&dirs.state, &request, &cwd);
+    for error in &errors {
+        eprintln!("rm: {}", error);
+    }
+    if !moved.is_empty() {
+        println!(
+            "Moved {} path(s) to the trash instead of deleting them; undo with: safe-bash-hook \
+             restore {}",
+            moved.len(),
+            request.id
+        );
+    }
+    std::process::exit(if errors.is_empty() { 0 } else { 1 });
+}
+
+/// `safe-bash-hook restore [<batch-id>]`: list what `trash_deletes` moved to the trash and the
+/// snapshots `snapshots` took, or put a batch back where it was.
+fn restore_trash(id: Option<&String>) -> ! {
+    let dirs = dirs::Dirs::locate();
+    let Some(id) = id else {
+        let batches = trash::batches(&dirs.state);
+        for (_, batch) in &batches {
+            let paths: Vec<String> = batch
+                .paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            println!(
+                "{}  {}  session {}  {}",
+                batch.id,
+                batch.kind.as_str(),
+                batch.session,
+                paths.join(" ")
+            );
+        }
+        if batches.is_empty() {
+            println!("Nothing in the trash");
+        }
+        std::process::exit(0);
+    };
+    match trash::restore(&dirs.state, id) {
+        Some((restored, errors)) => {
+            for path in &restored {
+                println!("Restored {}", path.display());
+            }
+            for error in &errors {
+                eprintln!("safe-bash-hook: {}", error);
+            }
+            std::process::exit(if errors.is_empty() { 0 } else { 1 });
+        }
+        None => {
+            eprintln!(
+                "safe-bash-hook: no trashed batch {} in {}",
+                id,
+                trash::trash_dir(&dirs.state).display()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `safe-bash-hook rollback-info <audit-id>`: print the undo command recorded for an ask.
+fn rollback_info(id: Option<&String>) -> ! {
+    let Some(id) = id else {
+        eprintln!("usage: safe-bash-hook rollback-info <audit-id>");
+        std::process::exit(1);
+    };
+    let dirs = dirs::Dirs::locate();
+    match audit::lookup(&dirs.state, id) {
+        Some(entry) => match entry["rollback"].as_str() {
+            Some(hint) => {
+                println!(
+                    "Command:  {}\nRollback: {}",
+                    entry["command"].as_str().unwrap_or(""),
+                    hint
+                );
+                std::process::exit(0);
+            }
+            None => {
+                eprintln!("safe-bash-hook: no rollback recorded for {}", id);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            eprintln!(
+                "safe-bash-hook: no audit entry {} in {}",
+                id,
+                audit::audit_dir(&dirs.state).display()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `safe-bash-hook purge [<project-dir>]`: apply the audit retention policy to every log now,
+/// or delete the audit log of the project containing `<project-dir>`.
+fn purge(project: Option<&String>) -> ! {
+    let dirs = dirs::Dirs::locate();
+    match project {
+        Some(dir) => {
+            let dir = Path::new(dir)
+                .canonicalize()
+                .unwrap_or_else(|_| PathBuf::from(dir));
+            let root = blast::workspace_root(&dir).unwrap_or(dir);
+            match audit::purge_project(&dirs.state, &root) {
+                Some(n) => println!("Purged {} audit entries for {}", n, root.display()),
+                None => println!("No audit log for {}", root.display()),
+            }
+        }
+        None => {
+            let retention = config::load(&dirs, None).audit_retention;
+            let pruned = audit::purge_all(&dirs.state, &retention);
+            for (log, removed) in &pruned {
+                println!("{}: removed {} entries", log.display(), removed);
+            }
+            if pruned.is_empty() {
+                println!(
+                    "Nothing to purge (max_age_days {}, max_bytes {})",
+                    retention.max_age_days, retention.max_bytes
+                );
+            }
+        }
+    }
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook trust-source`: accept the remote policy source the last update saw.
+fn trust_source() -> ! {
+    match tofu::trust(&dirs::Dirs::locate().state) {
+        Some(source) => {
+            println!("Trusted policy source: {} (key {})", source.url, source.key);
+            std::process::exit(0);
+        }
+        None => {
+            eprintln!("safe-bash-hook: no policy source observed yet");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `safe-bash-hook host-id`: print this machine's fingerprint, for the `hosts` of an identity
+/// file.
+fn host_id() -> ! {
+    println!("{}", host::fingerprint());
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook stats [<project-dir>]`: audited decisions per command shape, across every
+/// project or for the project containing `<project-dir>`.
+fn stats(project: Option<&String>) -> ! {
+    let dirs = dirs::Dirs::locate();
+    let log = project.map(|dir| {
+        let dir = Path::new(dir)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(dir));
+        audit::project_log_path(&dirs.state, &blast::workspace_root(&dir).unwrap_or(dir))
+    });
+    let rows = audit::stats(&dirs.state, log.as_deref());
+    for (shape, decisions) in &rows {
+        let total: usize = decisions.values().sum();
+        let detail: Vec<String> = decisions
+            .iter()
+            .map(|(d, n)| format!("{} {}", d, n))
+            .collect();
+        println!("{:>6}  {:<24}  {}", total, detail.join(", "), shape);
+    }
+    if rows.is_empty() {
+        println!("No audited decisions");
+    }
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook doctor`: time config load, pattern compilation and a long compound command
+/// on this machine against the per-call budgets. Exits 1 when anything is flagged.
+fn doctor() -> ! {
+    let dirs = dirs::Dirs::locate();
+    let cwd = default_cwd();
+    let (load, config) = doctor::Stage::time("config load", Duration::from_millis(10), || {
+        config::load(&dirs, Some(&cwd))
+    });
+    let (compile, hardcoded) = doctor::Stage::time(
+        "pattern compile",
+        Duration::from_millis(100),
+        patterns::hardcoded_deny_patterns,
+    );
+    let checker = Checker {
+        hardcoded: &hardcoded,
+        config: &config,
+        project: None,
+        cwd: &cwd,
+    };
+    let (evaluation, _) = doctor::Stage::time("evaluation", Duration::from_millis(25), || {
+        decide(doctor::BENCH_COMMAND, &checker)
+    });
+    let slow = doctor::slow_patterns(&config, doctor::BENCH_COMMAND);
+    let (report, flagged) = doctor::report(&config, &[load, compile, evaluation], &slow);
+    print!("{}", report);
+    std::process::exit(if flagged { 1 } else { 0 });
+}
+
+/// `safe-bash-hook schema`: print the JSON Schema of a config file.
+fn schema() -> ! {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema::schema()).unwrap_or_default()
+    );
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook hash <command>`: print a `"type": "sha256"` allow entry for one exact command.
+fn hash(command: Option<&String>) -> ! {
+    let Some(command) = command else {
+        eprintln!("usage: safe-bash-hook hash '<command>'");
+        std::process::exit(1);
+    };
+    let entry = serde_json::json!({
+        "type": "sha256",
+        "value": matcher::sha256_hex(command.trim()),
+        "reason": "",
+    });
+    println!("{}", entry);
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook lint <file>`: check a config file without installing it. Prints each
+/// problem and exits 1 when there are any.
+fn lint(path: Option<&String>) -> ! {
+    let Some(path) = path else {
+        eprintln!("usage: safe-bash-hook lint <config-file>");
+        std::process::exit(2);
+    };
+    let problems = lint::lint(Path::new(path));
+    for problem in &problems {
+        println!("{}: {}", path, problem);
+    }
+    if problems.is_empty() {
+        println!("{}: OK", path);
+    }
+    std::process::exit(if problems.is_empty() { 0 } else { 1 });
+}
+
+/// `safe-bash-hook test-config [<file>]`: check the `should_block` and `should_allow` examples
+/// of the config rules against the hardcoded patterns and the installed config layers, or
+/// the given file alone. Exits 1 when any example fails.
+fn test_config(path: Option<&String>) -> ! {
+    let dirs = dirs::Dirs::locate();
+    let cwd = default_cwd();
+    let mut config = match path {
+        Some(path) => config::load_config(Path::new(path)),
+        None => config::load(&dirs, Some(&cwd)),
+    };
+    config.ignore_schedules();
+    let hardcoded = patterns::hardcoded_deny_patterns();
+    let checker = Checker {
+        hardcoded: &hardcoded,
+        config: &config,
+        project: None,
+        cwd: &cwd,
+    };
+    let (ran, failures) = examples::check(&config.examples, |tool, example| {
+        let decision = match tool {
+            "Bash" => decide(example, &checker),
+            // An example of another tool's rule is the path or URL a call touches, or its input
+            _ => match tools::check(tool, &tools::example_input(tool, example), &config) {
+                Err((found, layer)) if found.severity == severity::Severity::Deny => {
+                    Decision::Deny(found.cited(), layer, false)
+                }
+                Err((found, layer)) if found.severity == severity::Severity::Ask => {
+                    Decision::Ask(found.cited(), layer)
+                }
+                _ => Decision::Allow,
+            },
+        };
+        match decision {
+            Decision::Allow | Decision::Audit(_) | Decision::Warn(_) => None,
+            Decision::Ask(reason, _) => Some(format!("asks ({})", reason)),
+            Decision::Deny(reason, ..) => Some(reason),
+        }
+    });
+    for failure in &failures {
+        println!("FAIL {}", failure);
+    }
+    println!(
+        "{} passed, {} failed ({})",
+        ran - failures.len(),
+        failures.len(),
+        if config.source.is_empty() {
+            "no config"
+        } else {
+            &config.source
+        }
+    );
+    std::process::exit(if failures.is_empty() { 0 } else { 1 });
+}
+
+/// `safe-bash-hook session-end`: the SessionEnd hook. Writes the session's tally to its
+/// project's audit log and, with `session_summary` on, shows the user a one-line summary.
+fn session_end() -> ! {
+    let mut raw = String::new();
+    let _ = io::stdin().read_to_string(&mut raw);
+    let payload: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+    let dirs = dirs::Dirs::locate();
+    let Some((session, tally)) = payload["session_id"]
+        .as_str()
+        .and_then(|s| session::take(&dirs.state, s).map(|t| (s, t)))
+    else {
+        std::process::exit(0);
+    };
+    let cwd = payload["cwd"]
+        .as_str()
+        .map(PathBuf::from)
+        .unwrap_or_else(default_cwd);
+    let config = config::load(&dirs, Some(&cwd));
+    let project = blast::workspace_root(&cwd).unwrap_or(cwd);
+    let summary = session::summary(&tally);
+    audit::record_summary(
+        &dirs.state,
+        &project,
+        session,
+        &tally,
+        &summary,
+        &config.audit_retention,
+    );
+    if config.session_summary {
+        println!("{}", serde_json::json!({ "systemMessage": summary }));
+    }
+    std::process::exit(0);
+}
+
+/// `safe-bash-hook flush-alerts`: deliver queued alert events (spawned detached by the hook).
+fn flush_alerts() -> ! {
+    let dirs = dirs::Dirs::locate();
+    if let Some(alert) = config::load(&dirs, None).alert_webhook {
+        webhook::flush(&dirs.state, &mut |body| webhook::post(&alert.url, body));
+    }
+    std::process::exit(0);
+}
+
+/// Let the command through but print the reason on stderr and audit it.
+fn warn(reason: &str, inv: &Invocation) {
+    eprintln!("Warning: {}", reason);
+    record("warn", reason, None, None, &[], inv);
+}
+
+/// Let the command through silently; only the audit log records the reason.
+fn audit_only(reason: &str, inv: &Invocation) {
+    record("audit", reason, None, None, &[], inv);
+}
+
+/// In report-only mode (`mode: "report"` or `SAFE_BASH_MODE=report`), print what would have
+/// been decided as a warning, audit it as `would-deny` or `would-ask`, and let the command
+/// through to the usual permission flow.
+fn report(would: &str, reason: &str, layer: &str, inv: &Invocation) -> ! {
+    let verb = if would == "deny" { "block" } else { "ask" };
+    eprintln!(
+        "Warning: report only, would {}: {}\nPolicy: {}",
+        verb, reason, layer
+    );
+    record(&format!("would-{}", would), reason, None, None, &[], inv);
+    std::process::exit(0);
+}
+
+/// In report-only mode, say what a `trash_deletes` or `sed_backups` rewrite would have run
+/// instead, audit it as `would-trash` or `would-backup`, and let the command through unchanged.
+fn report_rewrite(
+    would: &str,
+    reason: &str,
+    rewritten: &str,
+    backups: &[String],
+    inv: &Invocation,
+) -> ! {
+    eprintln!(
+        "Warning: report only, would run `{}` instead: {}",
+        rewritten, reason
+    );
+    record(
+        &format!("would-{}", would),
+        reason,
+        None,
+        Some(rewritten),
+        backups,
+        inv,
+    );
+    std::process::exit(0);
+}
+
+/// Decide a call of a tool other than Bash, MCP tools included, by the config rules for that
+/// tool, on the path or URL it touches or its input. Calls of tools without rules go through
+/// untouched.
+fn tool_call(hook_input: &input::HookInput, dirs: &dirs::Dirs) -> ! {
+    let tool = hook_input.tool_name.as_str();
+    let cwd = hook_input
+        .cwd
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(default_cwd);
+    let mut compiled_config = config::load(dirs, Some(&cwd));
+    if tools::rules_for(tool, &compiled_config).is_empty() {
+        std::process::exit(0);
+    }
+    // As root, allow rules lift nothing here either
+    if privilege::detect().is_root() && compiled_config.root_escalation {
+        compiled_config
+            .tools
+            .values_mut()
+            .for_each(|rules| rules.allow.clear());
+    }
+    let Err((found, layer)) = tools::check(tool, &hook_input.tool_input, &compiled_config) else {
+        std::process::exit(0);
+    };
+    let subjects = tools::subjects(tool, &hook_input.tool_input);
+    let command = format!("{} {}", tool, subjects.first().map_or("", |s| s.as_str()));
+    let project = blast::workspace_root(&cwd).unwrap_or_else(|| cwd.clone());
+    let inv = Invocation {
+        alert: compiled_config.alert_webhook.as_ref(),
+        command: &command,
+        cwd: hook_input.cwd.as_deref().map(Path::new),
+        dirs,
+        privilege: privilege::detect(),
+        project: &project,
+        retention: &compiled_config.audit_retention,
+        rollback_hints: false,
+        wrappers: &[],
+        protected_branches: &compiled_config.protected_branches,
+        hook_json: hook_input.reads_hook_json(),
+    };
+    let layer = instead(layer, found.suggestion.as_deref());
+    if compiled_config.report_only && found.severity >= severity::Severity::Ask {
+        report(
+            if found.severity == severity::Severity::Deny {
+                "deny"
+            } else {
+                "ask"
+            },
+            &found.cited(),
+            &layer,
+            &inv,
+        );
+    }
+    match found.severity {
+        severity::Severity::Deny => block(&found.cited(), &layer, None, &inv),
+        severity::Severity::Ask => ask(&found.cited(), &layer, None, &inv),
+        severity::Severity::Warn => warn(&found.cited(), &inv),
+        _ => audit_only(&found.cited(), &inv),
+    }
+    std::process::exit(0);
+}
+
+/// What the checks decided for a command: the reason and the policy layer it came from.
+enum Decision {
+    Allow,
+    Audit(String),
+    Warn(String),
+    Ask(String, String),
+    /// Blocked; firm when nothing the user may configure turns it into an ask: a hardcoded rule
+    /// whose category cannot go below deny, or a locked rule.
+    Deny(String, String, bool),
+}
+
+/// The loaded rules and the directory commands run in.
+struct Checker<'a> {
+    hardcoded: &'a [patterns::DenyPattern],
+    config: &'a config::CompiledConfig,
+    /// Rules from the `safe-bash` block of the workspace's CLAUDE.md.
+    project: Option<&'a project::ProjectPolicy>,
+    cwd: &'a Path,
+}
+
+/// Run every check on a command, most severe first.
+fn decide(command: &str, checker: &Checker) -> Decision {
+    // Match both the command as sent and a normalized form (by default invisible characters
+    // stripped, NFKC and homoglyphs folded, continuations joined, blanks collapsed) so
+    // `r\u{200b}m`, `гm`, and flags split across lines or padded with tabs still match
+    let normalized = checker
+        .config
+        .normalization
+        .apply(command, &checker.config.wrappers);
+    let mut forms: Vec<&str> = if normalized == command {
+        vec![command]
+    } else {
+        vec![command, &normalized]
+    };
+
+    // Also match with variables assigned earlier in the command substituted, so
+    // `cmd=rm; $cmd -rf /` is seen as `rm -rf /`
+    let resolved = indirection::resolve(&normalized);
+    if let Some(resolved) = &resolved {
+        forms.push(resolved);
+    }
+
+    // 1. Check hardcoded patterns first (cannot be allowed, only lowered to ask/warn per category).
+    // Archive extraction is parsed rather than matched, so it can peek inside the archive.
+    // With protected_branches set, force pushes and remote branch deletions are judged by the
+    // branches they reach: a protected one always blocks, and pushes that reach none are let off
+    // the force-push rules
+    let mut hardcoded_result = if checker.config.protected_branches.is_empty() {
+        patterns::check_forms(
+            &forms,
+            checker.hardcoded,
+            &checker.config.severities,
+            &checker.config.wrappers,
+        )
+    } else {
+        branches::check_forms(
+            &forms,
+            checker.hardcoded,
+            &checker.config.severities,
+            &checker.config.wrappers,
+            checker.cwd,
+            &checker.config.protected_branches,
+        )
+    };
+    if let Some(hit) = archive::check(&normalized, checker.cwd, &checker.config.wrappers) {
+        hardcoded_result = hardcoded_result.or_worse(patterns::CheckResult::weigh(
+            hit,
+            &checker.config.severities,
+        ));
+    }
+    let generic_rm = match &hardcoded_result {
+        patterns::CheckResult::Allow => true,
+        patterns::CheckResult::Audit(hit)
+        | patterns::CheckResult::Warn(hit)
+        | patterns::CheckResult::Ask(hit)
+        | patterns::CheckResult::Deny(hit) => hit.category == severity::Category::FileDestructive,
+    };
+    if generic_rm {
+        // With glob_delete_threshold set, `rm -r build/* tmp/*` is decided by how much its globs
+        // expand to instead of the generic rm rules
+        if let Some(threshold) = checker.config.glob_delete_threshold {
+            if let Some(result) = scope::check(
+                &normalized,
+                checker.cwd,
+                &checker.config.wrappers,
+                threshold,
+                &checker.config.severities,
+            ) {
+                hardcoded_result = result;
+            }
+        }
+        // `rm -rf .venv` throws away an environment, not project files: env-destruction decides it
+        if let Some(hit) = venv::check(&normalized, &checker.config.wrappers) {
+            hardcoded_result = patterns::CheckResult::weigh(hit, &checker.config.severities);
+        }
+        // `rm -rf node_modules dist` inside the project only throws away what a build recreates
+        if artifacts::check(
+            &normalized,
+            checker.cwd,
+            &checker.config.wrappers,
+            &checker.config.artifact_dirs,
+        ) {
+            hardcoded_result = patterns::CheckResult::Allow;
+        }
+    }
+    // A full `cargo clean` of a huge target directory costs a long rebuild
+    if let Some(hit) = cargo::check_clean(&normalized, checker.cwd, &checker.config.wrappers) {
+        hardcoded_result = hardcoded_result.or_worse(patterns::CheckResult::weigh(
+            hit,
+            &checker.config.severities,
+        ));
+    }
+    // Commands sent over ssh/mosh name their host; remote_escalation raises them one level
+    let remote = remote::check(
+        &normalized,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+        checker.config.remote_escalation,
+    );
+    hardcoded_result = remote.or_worse(hardcoded_result);
+    // Commands run in containers name the container and the host paths their bind mounts reach
+    let container = docker::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = container.or_worse(hardcoded_result);
+    // `nohup rm -rf build &` keeps running after the fact, so a detached match is raised one level
+    let detached = detach::check(
+        &normalized,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = detached.or_worse(hardcoded_result);
+
+    // python -c, node -e, perl -e, ruby -e and awk programs are checked through the shell commands
+    // their calls amount to
+    let oneliner = oneliner::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = oneliner.or_worse(hardcoded_result);
+    // `echo cm0gLXJmIC8= | base64 -d | sh`: the decoded payload is checked, even with pipe-to-shell
+    // lowered
+    let decoded = decode::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = decoded.or_worse(hardcoded_result);
+    // `vim -c '!cmd'` and friends run cmd through the shell
+    let escape = editor::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = escape.or_worse(hardcoded_result);
+    // `npm run clean` runs whatever package.json says it does
+    let script = scripts::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = script.or_worse(hardcoded_result);
+    // `git nuke` whatever its alias expands to
+    let alias = gitalias::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = alias.or_worse(hardcoded_result);
+    // and `make distclean` whatever its recipe says, when the config opts in
+    if checker.config.inspect_makefiles {
+        let recipe = make::check(
+            &normalized,
+            checker.cwd,
+            checker.hardcoded,
+            &checker.config.severities,
+            &checker.config.wrappers,
+        );
+        hardcoded_result = recipe.or_worse(hardcoded_result);
+    }
+    // `cat > run.sh <<EOF … EOF && ./run.sh` runs what the heredoc wrote
+    let flow = flow::check(
+        &normalized,
+        checker.cwd,
+        checker.hardcoded,
+        &checker.config.severities,
+        &checker.config.wrappers,
+    );
+    hardcoded_result = flow.or_worse(hardcoded_result);
+    // Editor, language-server and git state (.idea/, .vscode/settings.json, .git/index) is
+    // maintained by the tools that own it; the specific reason wins over rm/sed -i ones
+    if let Some(hit) = protected::check(
+        &normalized,
+        checker.cwd,
+        &checker.config.wrappers,
+        &checker.config.unprotected_paths,
+    ) {
+        hardcoded_result = patterns::CheckResult::weigh(hit, &checker.config.severities)
+            .or_worse(hardcoded_result);
+    }
+    // Paths the config protects are compared after resolving ~, cd, globs and symlinks, which
+    // `cat ~/.s?h/id_rsa` or `cd ~/.ssh && cat id_rsa` would otherwise slip past
+    if !checker.config.protected_paths.is_empty() {
+        if let Some(hit) = guarded::check(
+            resolved.as_deref().unwrap_or(&normalized),
+            checker.cwd,
+            &checker.config.wrappers,
+            &checker.config.protected_paths,
+        ) {
+            hardcoded_result = patterns::CheckResult::weigh(hit, &checker.config.severities)
+                .or_worse(hardcoded_result);
+        }
+    }
+    // `rm -rf "$DIR"/` with an unguarded variable gets its own category, so it still blocks when
+    // file-destructive is lowered to ask, and its specific reason wins over the generic rm one
+    if let Some(hit) = unguarded::check(
+        resolved.as_deref().unwrap_or(&normalized),
+        &checker.config.wrappers,
+    ) {
+        hardcoded_result = patterns::CheckResult::weigh(hit, &checker.config.severities)
+            .or_worse(hardcoded_result);
+    }
+    // Wiping `/`, home or a system directory always blocks, with a reason naming the target
+    if let Some(hit) = wildcard::check(
+        resolved.as_deref().unwrap_or(&normalized),
+        checker.cwd,
+        &checker.config.wrappers,
+    ) {
+        hardcoded_result = patterns::CheckResult::Deny(hit);
+    }
+    // `IFS=,;$(echo rm,-rf,/)` and `rm${IFS}-rf${IFS}/` hide their words from every pattern
+    if let Some(hit) = ifs::check(&normalized, &checker.config.wrappers) {
+        hardcoded_result = patterns::CheckResult::Deny(hit);
+    }
+    if let patterns::CheckResult::Deny(hit) = &hardcoded_result {
+        let firm = hit.category.floor() == severity::Severity::Deny;
+        return Decision::Deny(
+            hit.cited(),
+            instead(
+                hit.layer_note(&checker.config.allow_file),
+                patterns::suggestion(hit, checker.hardcoded),
+            ),
+            firm,
+        );
+    }
+
+    // 2. Check locked config deny patterns (no allow lifts them)
+    // 3. Check config allow patterns (override config deny), then config deny patterns.
+    // A rule with a softer severity than deny is held until the hardcoded asks and warns of
+    // the same level have had their say.
+    let mut softer: Option<(config::RuleMatch, String)> = None;
+    let mut hold = |found: config::RuleMatch, note: String| {
+        if softer
+            .as_ref()
+            .is_none_or(|(held, _)| found.severity > held.severity)
+        {
+            softer = Some((found, note));
+        }
+    };
+    for form in &forms {
+        if let Err(found) = config::check_locked(form, checker.config) {
+            let note = instead(
+                checker.config.locked_note(&found),
+                found.suggestion.as_deref(),
+            );
+            if found.severity == severity::Severity::Deny {
+                return Decision::Deny(found.cited(), note, true);
+            }
+            hold(found, note);
+        }
+        if let Err(found) = config::check_config(form, checker.config) {
+            let note = instead(
+                checker.config.layer_note(&found),
+                found.suggestion.as_deref(),
+            );
+            if found.severity == severity::Severity::Deny {
+                return Decision::Deny(found.cited(), note, false);
+            }
+            hold(found, note);
+        }
+    }
+    // Project rules add to the config ones; a project allow only lifts the project's own denies
+    if let Some(project) = checker.project {
+        for form in &forms {
+            if let Err(found) = project.check(form, &checker.config.wrappers) {
+                let note = instead(project.layer_note(), found.suggestion.as_deref());
+                if found.severity == severity::Severity::Deny {
+                    return Decision::Deny(found.cited(), note, false);
+                }
+                hold(found, note);
+            }
+        }
+    }
+
+    // 4. Hardcoded matches and config rules lowered to ask, then heuristics that need a human
+    //    decision
+    if let patterns::CheckResult::Ask(hit) = &hardcoded_result {
+        return Decision::Ask(
+            hit.cited(),
+            instead(
+                hit.layer_note(&checker.config.allow_file),
+                patterns::suggestion(hit, checker.hardcoded),
+            ),
+        );
+    }
+    if let Some((found, note)) =
+        softer.take_if(|(found, _)| found.severity == severity::Severity::Ask)
+    {
+        return Decision::Ask(found.cited(), note);
+    }
+    if let Some(reason) = perms::check_mass_change(&normalized, checker.cwd) {
+        return Decision::Ask(
+            reason,
+            "built-in heuristic — approve if the change is intended".to_string(),
+        );
+    }
+    if let Some(reason) = indirection::check_unresolved(&normalized, &checker.config.wrappers) {
+        return Decision::Ask(
+            reason,
+            "built-in heuristic — approve if the command is intended".to_string(),
+        );
+    }
+    if checker.config.ask_unresolved_scripts {
+        if let Some(reason) =
+            scripts::unresolved(&normalized, checker.cwd, &checker.config.wrappers)
+        {
+            return Decision::Ask(
+                reason,
+                format!(
+                    "ask_unresolved_scripts in {} — approve if the script is intended",
+                    checker.config.source
+                ),
+            );
+        }
+    }
 
-/// The top-level JSON structure sent by Claude Code's PreToolUse hook.
-#[derive(Deserialize, Debug)]
-struct HookInput {
-    #[serde(default)]
-    tool_name: String,
-    #[serde(default)]
-    tool_input: Value,
+    // 5. Matches lowered to warn still run, and ones lowered to audit run silently
+    if let patterns::CheckResult::Warn(hit) = &hardcoded_result {
+        return Decision::Warn(hit.cited());
+    }
+    if let Some((found, _)) =
+        softer.take_if(|(found, _)| found.severity == severity::Severity::Warn)
+    {
+        return Decision::Warn(found.cited());
+    }
+    if let patterns::CheckResult::Audit(hit) = &hardcoded_result {
+        return Decision::Audit(hit.cited());
+    }
+    if let Some((found, _)) = softer {
+        return Decision::Audit(found.cited());
+    }
+
+    Decision::Allow
+}
+
+/// A decision's policy note, followed by the rule's safer alternative when it suggests one:
+/// `Policy:` and `Instead:` lines in the block or ask message.
+fn instead(note: String, suggestion: Option<&str>) -> String {
+    match suggestion {
+        Some(suggestion) => format!("{}\nInstead: {}", note, suggestion),
+        None => note,
+    }
 }
 
-fn hooks_dir() -> PathBuf {
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(home).join(".claude").join("hooks")
+/// For a compound command that is blocked or asked, the decision for each of its pipelines on
+/// its own, so the ones that were fine can be resubmitted without the flagged ones. None for a
+/// single pipeline, or when no segment is flagged on its own.
+fn segment_report(command: &str, checker: &Checker) -> Option<String> {
+    let segments = shell::pipelines(command);
+    if segments.len() < 2 {
+        return None;
+    }
+    let mut report =
+        String::from("Segments (resubmit the allowed ones on their own if they are still needed):");
+    let mut flagged = false;
+    for segment in segments.into_iter().map(|r| &command[r]) {
+        let line = match decide(segment, checker) {
+            Decision::Allow | Decision::Audit(_) => format!("allow  {}", segment),
+            Decision::Warn(reason) => format!("warn   {} — {}", segment, reason),
+            Decision::Ask(reason, _) => format!("ask    {} — {}", segment, reason),
+            Decision::Deny(reason, ..) => format!("deny   {} — {}", segment, reason),
+        };
+        flagged |= line.starts_with("ask") || line.starts_with("deny");
+        report.push_str("\n  ");
+        report.push_str(&line);
+    }
+    // Flagged only as a whole (`cmd=rm; $cmd -rf /`): no segment can be resubmitted on its own
+    flagged.then_some(report)
 }
 
 fn main() {
+    // Repeated warnings print once per interval; all of them go to the debug log
+    let dirs = dirs::Dirs::locate();
+    dirs.create();
+    warnings::init(&dirs.state);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|a| a.as_str()) {
+        Some("rollback-info") => rollback_info(args.get(1)),
+        Some("flush-alerts") => flush_alerts(),
+        Some("purge") => purge(args.get(1)),
+        Some("stats") => stats(args.get(1)),
+        Some("trust-source") => trust_source(),
+        Some("host-id") => host_id(),
+        Some("doctor") => doctor(),
+        Some("schema") => schema(),
+        Some("hash") => hash(args.get(1)),
+        Some("lint") => lint(args.get(1)),
+        Some("test-config") => test_config(args.get(1)),
+        Some("session-end") => session_end(),
+        Some("trash") => trash_files(&args[1..]),
+        Some("restore") => restore_trash(args.get(1)),
+        _ => {}
+    }
+    let format = input_format(&args);
+
     // Read all stdin
     let mut input = String::new();
     if io::stdin().read_to_string(&mut input).is_err() {
@@ -29,46 +1350,339 @@ fn main() {
         std::process::exit(0);
     }
 
-    // Parse JSON — if malformed, allow (don't block Claude)
-    let hook_input: HookInput = match serde_json::from_str(&input) {
-        Ok(v) => v,
-        Err(_) => std::process::exit(0),
+    // Parse JSON via the selected adapter — if malformed, allow (don't block Claude)
+    let hook_input = match input::parse_input(&input, format) {
+        Some(v) => v,
+        None => std::process::exit(0),
     };
 
-    // Only act on Bash tool calls
+    // Other tools' calls are checked against the config rules scoped to them
     if hook_input.tool_name != "Bash" {
-        std::process::exit(0);
+        tool_call(&hook_input, &dirs);
     }
 
     // Extract tool_input.command — if missing, allow
-    let command = match hook_input.tool_input.get("command").and_then(|v| v.as_str()) {
+    let command = match hook_input
+        .tool_input
+        .get("command")
+        .and_then(|v| v.as_str())
+    {
         Some(cmd) => cmd.to_string(),
         None => std::process::exit(0),
     };
 
-    let hooks_dir = hooks_dir();
+    let privilege = privilege::detect();
+    let cwd = hook_input
+        .cwd
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(default_cwd);
 
+    // Load the optional config layers (org, team, user), with the overlays for where the command
+    // runs
+    let mut compiled_config = config::load(&dirs, Some(&cwd));
+
+    // A remote policy source that moved or changed keys since first use is reported on every call
+    if let Some(warning) = tofu::check(&dirs.state, compiled_config.refuse_changed_source) {
+        eprintln!("safe-bash-hook: WARNING: {}", warning);
+    }
     // Trigger hourly background update of remote patterns (non-blocking)
-    autoupdate::maybe_update(&hooks_dir);
+    autoupdate::maybe_update(&dirs, compiled_config.refuse_changed_source);
 
-    // Load optional config patterns
-    let config_path = autoupdate::patterns_path(&hooks_dir);
-    let compiled_config = config::load_config(&config_path);
+    // Retry alerts queued while the webhook was unreachable (non-blocking, honors backoff)
+    if compiled_config.alert_webhook.is_some() {
+        webhook::maybe_flush(&dirs.state);
+    }
 
-    // Load hardcoded deny patterns
-    let hardcoded = patterns::hardcoded_deny_patterns();
+    // Load hardcoded deny patterns; running as root adds the root-only set
+    // and disables config allow rules and raises the severities (unless root_escalation is false)
+    let mut hardcoded = patterns::hardcoded_deny_patterns();
+    if privilege.is_root() && compiled_config.root_escalation {
+        hardcoded.extend(patterns::root_deny_patterns());
+        compiled_config.allow.clear();
+        // A strict profile or override stays strict; a permissive one no longer loosens anything
+        compiled_config.severities = compiled_config.severities.escalated();
+        compiled_config.glob_delete_threshold = None;
+    }
 
-    // 1. Check hardcoded patterns first (cannot be overridden)
-    if let patterns::CheckResult::Deny(reason) = patterns::check_command(&command, &hardcoded) {
-        eprintln!("Blocked: {}", reason);
-        std::process::exit(2);
+    // Audit entries go to a log per workspace root, whose CLAUDE.md may add project rules
+    let project = blast::workspace_root(&cwd).unwrap_or_else(|| cwd.clone());
+    // A repository's own config file adds rules to the project's and checks to the user's
+    let mut project_config = config::load_project(&project, &cwd);
+    if let Some(found) = project_config.as_mut() {
+        compiled_config.merge_project(found);
     }
+    let mut project_policy = project::load(&project, project_config);
+    if let Some(policy) = project_policy
+        .as_mut()
+        .filter(|_| privilege.is_root() && compiled_config.root_escalation)
+    {
+        policy.allow.clear();
+    }
+    // `# safe-bash: allow GIT002` or `SAFE_BASH_ALLOW=GIT002 git …` lifts a suppressible rule for
+    // this one command; as root with root_escalation nothing is lifted, like the severity
+    // overrides
+    let requested = suppress::requested(&command);
+    let suppression = if privilege.is_root() && compiled_config.root_escalation {
+        suppress::Suppression {
+            lifted: Vec::new(),
+            refused: requested,
+        }
+    } else {
+        suppress::apply(
+            &requested,
+            &mut compiled_config,
+            project_policy.as_mut(),
+            &hardcoded,
+        )
+    };
 
-    // 2. Check config allow patterns (override config deny)
-    // 3. Check config deny patterns
-    if let Err(reason) = config::check_config(&command, &compiled_config) {
-        eprintln!("Blocked: {}", reason);
-        std::process::exit(2);
+    let inv = Invocation {
+        alert: compiled_config.alert_webhook.as_ref(),
+        command: &command,
+        cwd: hook_input.cwd.as_deref().map(Path::new),
+        dirs: &dirs,
+        privilege,
+        project: &project,
+        retention: &compiled_config.audit_retention,
+        rollback_hints: compiled_config.rollback_hints,
+        wrappers: &compiled_config.wrappers,
+        protected_branches: &compiled_config.protected_branches,
+        hook_json: hook_input.reads_hook_json(),
+    };
+
+    let checker = Checker {
+        hardcoded: &hardcoded,
+        config: &compiled_config,
+        project: project_policy.as_ref(),
+        cwd: &cwd,
+    };
+    let mut decision = decide(&command, &checker);
+    // An inline suppression always shows, as a warning if nothing else does, and is logged
+    if let Some(note) = suppression.note() {
+        decision = match decision {
+            Decision::Allow | Decision::Audit(_) => Decision::Warn(note),
+            Decision::Warn(reason) => Decision::Warn(format!("{}; {}", reason, note)),
+            Decision::Ask(reason, layer) => Decision::Ask(format!("{}; {}", reason, note), layer),
+            Decision::Deny(reason, layer, firm) => {
+                Decision::Deny(format!("{}; {}", reason, note), layer, firm)
+            }
+        };
+    }
+    // A description promising a read-only look on a command that changes things is an anomaly:
+    // what would run asks, what would ask blocks
+    let description = hook_input
+        .tool_input
+        .get("description")
+        .and_then(|v| v.as_str());
+    if let Some(mismatch) = description
+        .filter(|_| compiled_config.check_descriptions)
+        .and_then(|d| intent::check(d, &command, &compiled_config.wrappers))
+    {
+        let layer = "built-in heuristic (check_descriptions) — approve only if the description is \
+                     merely incomplete"
+            .to_string();
+        decision = match decision {
+            Decision::Allow | Decision::Audit(_) | Decision::Warn(_) => {
+                Decision::Ask(mismatch, layer)
+            }
+            Decision::Ask(reason, _) => {
+                Decision::Deny(format!("{}; {}", mismatch, reason), layer, false)
+            }
+            deny => deny,
+        };
+    }
+    // Commands about to run count against their quotas; one that is used up asks or blocks
+    if matches!(
+        decision,
+        Decision::Allow | Decision::Audit(_) | Decision::Warn(_)
+    ) {
+        let session = hook_input.session_id.as_deref();
+        if let Some(over) = quota::take(
+            &dirs.state,
+            &compiled_config.quotas,
+            &command,
+            session,
+            &compiled_config.wrappers,
+        ) {
+            let layer = format!(
+                "quota from {} — wait for the window to pass, or raise its max there",
+                compiled_config.source
+            );
+            decision = if over.deny {
+                Decision::Deny(over.reason, layer, false)
+            } else {
+                Decision::Ask(over.reason, layer)
+            };
+        }
+    }
+    // Every decision counts toward the session summary written when the session ends
+    if let Some(session) = hook_input.session_id.as_deref() {
+        let (name, reason) = match &decision {
+            Decision::Allow => ("allow", ""),
+            Decision::Audit(reason) => ("audit", reason.as_str()),
+            Decision::Warn(reason) => ("warn", reason.as_str()),
+            Decision::Ask(reason, _) => ("ask", reason.as_str()),
+            Decision::Deny(reason, ..) => ("deny", reason.as_str()),
+        };
+        // Nothing is blocked or asked about in report-only mode; what would have been counts as a
+        // warning
+        let name = if compiled_config.report_only && matches!(name, "ask" | "deny") {
+            "warn"
+        } else {
+            name
+        };
+        session::record(&dirs.state, session, name, reason);
+    }
+    // The rewrites below hand the caller updated input, which only Claude Code's hook JSON carries:
+    // for other harnesses the command is decided as it is
+    let rewrites = hook_input.reads_hook_json();
+    // With trash_deletes on, deleting files inside the workspace moves them to the session's trash
+    // instead (in report-only mode the rewrite is only reported, and the command runs
+    // unchanged)
+    let report_only = compiled_config.report_only;
+    if compiled_config.trash_deletes && rewrites {
+        if let Some(call) = trashed(
+            &command,
+            &checker,
+            &project,
+            hook_input.session_id.as_deref(),
+            &dirs.state,
+            !report_only,
+        ) {
+            if report_only {
+                report_rewrite(
+                    "trash",
+                    "rm moved to the trash (trash_deletes)",
+                    &call,
+                    &[],
+                    &inv,
+                );
+            }
+            trash_instead(&call, &hook_input.tool_input, &inv);
+        }
+    }
+    // With sed_backups on, in-place seds run with a backup of each file instead of asking
+    if compiled_config.sed_backups.enabled && rewrites {
+        if let Some(backed) = backed_up(&command, &checker) {
+            if report_only {
+                report_rewrite(
+                    "backup",
+                    "sed -i keeps a backup (sed_backups)",
+                    &backed.command,
+                    &backed.backups,
+                    &inv,
+                );
+            }
+            if let Some(days) = compiled_config.sed_backups.max_age_days {
+                sedbackup::track(&dirs.state, &backed.backups, days);
+            }
+            with_backups(&backed, &hook_input.tool_input, &inv);
+        }
+    }
+    // In report-only mode a block or ask that no trash or backup rewrite would take care of is only
+    // reported
+    if report_only {
+        match &decision {
+            Decision::Deny(reason, layer, _) => report("deny", reason, layer, &inv),
+            Decision::Ask(reason, layer) => report("ask", reason, layer, &inv),
+            _ => {}
+        }
+    }
+    // Opted-in rules offer a safer form of the command instead of blocking or asking about it as is
+    if matches!(decision, Decision::Deny(..) | Decision::Ask(..))
+        && !compiled_config.rewrite_rules.is_empty()
+        && rewrites
+    {
+        if let Some((reason, safer)) = rewritten(
+            &command,
+            &checker,
+            hook_input.session_id.as_deref(),
+            &dirs.state,
+        ) {
+            rewrite(&reason, &safer, &hook_input.tool_input, &inv);
+        }
+    }
+    // With snapshots on, what a command let through may destroy is saved first; a snapshot of an
+    // allowed command is audited so its id is on record
+    if compiled_config.snapshots
+        && matches!(
+            decision,
+            Decision::Allow | Decision::Audit(_) | Decision::Warn(_)
+        )
+    {
+        let session = hook_input.session_id.as_deref().unwrap_or("no-session");
+        if let Some(taken) = snapshot::take(
+            &dirs.state,
+            &command,
+            &cwd,
+            &project,
+            &compiled_config.wrappers,
+            session,
+        ) {
+            let note = match taken {
+                Ok(id) => format!(
+                    "snapshot {} taken (undo with: safe-bash-hook restore {})",
+                    id, id
+                ),
+                Err(why) => why,
+            };
+            decision = match decision {
+                Decision::Warn(reason) => Decision::Warn(format!("{}; {}", reason, note)),
+                Decision::Audit(reason) => Decision::Audit(format!("{}; {}", reason, note)),
+                _ => Decision::Audit(note),
+            };
+        }
+    }
+    match decision {
+        Decision::Deny(reason, layer, firm) => {
+            // The same blocked command coming back again and again: say so plainly, or hand it to
+            // the user, unless the block is firm or root escalation keeps every block
+            let id = fingerprint::of(&command, &compiled_config.wrappers).id();
+            let blocks = hook_input
+                .session_id
+                .as_deref()
+                .map_or(0, |s| retry::record_block(&dirs.state, s, &id));
+            if blocks > compiled_config.retry_loop.max_repeats {
+                let segments = segment_report(&command, &checker);
+                let escalated = privilege.is_root() && compiled_config.root_escalation;
+                if compiled_config.retry_loop.escalate && !firm && !escalated {
+                    ask(
+                        &retry::escalation(&reason, blocks),
+                        &layer,
+                        segments.as_deref(),
+                        &inv,
+                    );
+                }
+                block(
+                    &retry::advice(&reason, blocks),
+                    &layer,
+                    segments.as_deref(),
+                    &inv,
+                );
+            }
+            if let Some(prefix) = (compiled_config.suggest_safe_prefix && rewrites)
+                .then(|| safe_prefix(&command, &checker))
+                .flatten()
+            {
+                suggest(&reason, &layer, prefix, &hook_input.tool_input, &inv);
+            }
+            block(
+                &reason,
+                &layer,
+                segment_report(&command, &checker).as_deref(),
+                &inv,
+            )
+        }
+        Decision::Ask(reason, layer) => ask(
+            &reason,
+            &layer,
+            segment_report(&command, &checker).as_deref(),
+            &inv,
+        ),
+        Decision::Warn(reason) => warn(&reason, &inv),
+        Decision::Audit(reason) => audit_only(&reason, &inv),
+        Decision::Allow => {}
     }
 
     // All checks passed — allow
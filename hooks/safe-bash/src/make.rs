@@ -0,0 +1,428 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The makefile names make reads by default, in its order.
+const MAKEFILES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// How deep prerequisites are followed.
+const MAX_DEPTH: usize = 8;
+
+/// make options that take a separate argument.
+const OPTIONS_WITH_ARG: &[&str] = &[
+    "-C",
+    "--directory",
+    "-f",
+    "--file",
+    "--makefile",
+    "-I",
+    "--include-dir",
+    "-j",
+    "--jobs",
+    "-l",
+    "--load-average",
+    "-o",
+    "--old-file",
+    "-W",
+    "--what-if",
+];
+
+/// The rules of a makefile: recipe lines and prerequisites per target, after variable expansion.
+#[derive(Debug, Default)]
+pub struct Makefile {
+    rules: HashMap<String, Rule>,
+    /// The first target that does not start with `.`.
+    default_goal: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Rule {
+    prerequisites: Vec<String>,
+    recipe: Vec<String>,
+}
+
+impl Makefile {
+    /// Parse the parts of make syntax recipes depend on: `VAR = value` (also `:=`, `::=`, `?=`,
+    /// `+=`), `target: prerequisites` and tab-indented recipe lines. Conditionals, includes
+    /// and pattern rules are not evaluated.
+    pub fn parse(text: &str) -> Self {
+        let mut vars: HashMap<String, String> = HashMap::new();
+        let mut raw_rules: Vec<(Vec<String>, String, Vec<String>)> = Vec::new();
+        let joined = text.replace("\\\n\t", " ").replace("\\\n", " ");
+        for line in joined.lines() {
+            if let Some(recipe) = line.strip_prefix('\t') {
+                if let Some((_, _, lines)) = raw_rules.last_mut() {
+                    lines.push(recipe.to_string());
+                }
+                continue;
+            }
+            let line = line.split('#').next().unwrap_or("").trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some((name, op, value)) = assignment(line) {
+                let value = value.trim().to_string();
+                match op {
+                    "?=" => {
+                        vars.entry(name).or_insert(value);
+                    }
+                    "+=" => {
+                        let entry = vars.entry(name).or_default();
+                        *entry = format!("{} {}", entry, value).trim().to_string();
+                    }
+                    _ => {
+                        vars.insert(name, value);
+                    }
+                }
+            } else if let Some((targets, prereqs)) = line.split_once(':') {
+                let (prereqs, inline) = match prereqs.split_once(';') {
+                    Some((p, r)) => (p, vec![r.trim().to_string()]),
+                    None => (prereqs, Vec::new()),
+                };
+                let targets = targets.split_whitespace().map(str::to_string).collect();
+                raw_rules.push((targets, prereqs.trim_start_matches(':').to_string(), inline));
+            }
+        }
+
+        let mut makefile = Makefile::default();
+        for (targets, prereqs, recipe) in raw_rules {
+            let prereqs: Vec<String> = expand(&prereqs, &vars, "")
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            for target in targets {
+                let target = expand(&target, &vars, "");
+                if makefile.default_goal.is_none()
+                    && !target.starts_with('.')
+                    && !target.contains('%')
+                {
+                    makefile.default_goal = Some(target.clone());
+                }
+                let lines: Vec<String> = recipe
+                    .iter()
+                    .map(|r| expand(strip_prefixes(r), &vars, &target))
+                    .collect();
+                let rule = makefile.rules.entry(target).or_default();
+                rule.prerequisites.extend(prereqs.iter().cloned());
+                if !lines.is_empty() {
+                    rule.recipe = lines;
+                }
+            }
+        }
+        makefile
+    }
+
+    /// The recipe lines `make <goals>` runs, prerequisites first, each with the target it
+    /// belongs to.
+    pub fn recipes(&self, goals: &[String]) -> Vec<(String, String)> {
+        let mut out = Vec::new();
+        let mut seen = Vec::new();
+        let goals = if goals.is_empty() {
+            self.default_goal.iter().cloned().collect()
+        } else {
+            goals.to_vec()
+        };
+        for goal in &goals {
+            self.collect(goal, 0, &mut seen, &mut out);
+        }
+        out
+    }
+
+    fn collect(
+        &self,
+        target: &str,
+        depth: usize,
+        seen: &mut Vec<String>,
+        out: &mut Vec<(String, String)>,
+    ) {
+        if depth >= MAX_DEPTH || seen.iter().any(|s| s == target) {
+            return;
+        }
+        seen.push(target.to_string());
+        let Some(rule) = self.rules.get(target) else {
+            return;
+        };
+        for prereq in &rule.prerequisites {
+            self.collect(prereq, depth + 1, seen, out);
+        }
+        out.extend(
+            rule.recipe
+                .iter()
+                .map(|line| (target.to_string(), line.clone())),
+        );
+    }
+}
+
+/// `NAME = value` and friends; None for rules (`a: b`) and other lines.
+fn assignment(line: &str) -> Option<(String, &'static str, &str)> {
+    let line = line
+        .strip_prefix("export ")
+        .or_else(|| line.strip_prefix("override "))
+        .unwrap_or(line);
+    let eq = line.find('=')?;
+    let op = ["::=", ":=", "?=", "+=", "!="]
+        .into_iter()
+        .find(|op| line[..eq + 1].ends_with(op))
+        .unwrap_or("=");
+    let name = line[..eq + 1 - op.len()].trim();
+    if name.is_empty() || name.contains([':', ' ', '\t']) {
+        return None;
+    }
+    Some((name.to_string(), op, &line[eq + 1..]))
+}
+
+/// Recipe lines may start with `@` (silent), `-` (ignore errors) and `+` (always run).
+fn strip_prefixes(line: &str) -> &str {
+    line.trim_start()
+        .trim_start_matches(['@', '-', '+'])
+        .trim_start()
+}
+
+/// Expand `$(VAR)`/`${VAR}` from the makefile's variables, `$@` to the target and `$$` to `$`.
+/// A variable the makefile does not define comes from the environment, so it is left as the
+/// shell variable of the same name (`$(HOME)` → `$HOME`).
+fn expand(text: &str, vars: &HashMap<String, String>, target: &str) -> String {
+    expand_depth(text, vars, target, 0)
+}
+
+fn expand_depth(text: &str, vars: &HashMap<String, String>, target: &str, depth: usize) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (close, body_start) = match after.chars().next() {
+            Some('(') => (')', 1),
+            Some('{') => ('}', 1),
+            Some('$') => {
+                out.push('$');
+                rest = &after[1..];
+                continue;
+            }
+            Some('@') => {
+                out.push_str(target);
+                rest = &after[1..];
+                continue;
+            }
+            _ => {
+                out.push('$');
+                rest = after;
+                continue;
+            }
+        };
+        let Some(end) = after.find(close) else {
+            out.push_str(&rest[i..]);
+            return out;
+        };
+        let name = &after[body_start..end];
+        match vars.get(name) {
+            Some(value) if depth < MAX_DEPTH => {
+                out.push_str(&expand_depth(value, vars, target, depth + 1))
+            }
+            Some(value) => out.push_str(value),
+            None if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                out.push_str(&format!("${}", name))
+            }
+            None => out.push_str(&rest[i..i + 1 + end + 1]),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `make [options] [VAR=value…] [goals…]`: the directory it runs in, the makefile it reads
+/// (None for the default names) and its goals.
+fn invocation(args: &[&str], cwd: &Path) -> Option<(PathBuf, Option<PathBuf>, Vec<String>)> {
+    let program = args.first()?.rsplit('/').next()?;
+    if !matches!(program, "make" | "gmake") {
+        return None;
+    }
+    let mut dir = cwd.to_path_buf();
+    let mut file = None;
+    let mut goals = Vec::new();
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let value = |flag: &str| {
+            arg.strip_prefix(flag)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.trim_start_matches('='))
+        };
+        if OPTIONS_WITH_ARG.contains(arg) {
+            let Some(value) = iter.next() else { break };
+            match *arg {
+                "-C" | "--directory" => dir = dir.join(value),
+                "-f" | "--file" | "--makefile" => file = Some(dir.join(value)),
+                _ => {}
+            }
+        } else if let Some(value) = value("-C").or_else(|| value("--directory=")) {
+            dir = dir.join(value);
+        } else if let Some(value) = value("-f")
+            .or_else(|| value("--file="))
+            .or_else(|| value("--makefile="))
+        {
+            file = Some(dir.join(value));
+        } else if !arg.starts_with('-') && !arg.contains('=') {
+            goals.push(arg.to_string());
+        }
+    }
+    Some((dir, file, goals))
+}
+
+fn load(dir: &Path, file: Option<&Path>) -> Option<Makefile> {
+    let path = match file {
+        Some(f) => f.to_path_buf(),
+        None => MAKEFILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|p| p.is_file())?,
+    };
+    Some(Makefile::parse(&std::fs::read_to_string(path).ok()?))
+}
+
+/// Check the recipe lines `make <target>` would run (`inspect_makefiles` in the config)
+/// against the hardcoded patterns and the root-wildcard rule. The reason names the target
+/// and the recipe line.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some((dir, file, goals)) =
+                invocation(&args[crate::patterns::unwrap_index(&args, wrappers)..], cwd)
+            else {
+                continue;
+            };
+            let Some(makefile) = load(&dir, file.as_deref()) else {
+                continue;
+            };
+            for (target, line) in makefile.recipes(&goals) {
+                let label = |hit: Hit| Hit {
+                    reason: format!("make target {:?} runs `{}`: {}", target, line, hit.reason),
+                    ..hit
+                };
+                if let Some(hit) = crate::wildcard::check(&line, &dir, wrappers) {
+                    return CheckResult::Deny(label(hit));
+                }
+                let found = crate::patterns::check_command(&line, patterns, severities, wrappers)
+                    .relabel(label);
+                result = result.or_worse(found);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+    use crate::severity::Category;
+
+    const MAKEFILE: &str = "\
+PREFIX ?= /usr/local
+BUILD := build
+CLEAN_DIRS = $(BUILD) dist
+
+.PHONY: all clean distclean install
+
+all: $(BUILD)/app
+
+$(BUILD)/app: src/main.c
+\t@mkdir -p $(BUILD)
+\tcc -o $@ $<
+
+clean:
+\t-rm -rf $(CLEAN_DIRS)
+
+distclean: clean
+\trm -rf $(HOME)/.cache/app \\
+\t  $$TMPDIR/app
+
+install: all ; cp $(BUILD)/app $(PREFIX)/bin
+";
+
+    fn lines(goals: &[&str]) -> Vec<String> {
+        let goals: Vec<String> = goals.iter().map(|g| g.to_string()).collect();
+        Makefile::parse(MAKEFILE)
+            .recipes(&goals)
+            .into_iter()
+            .map(|(t, l)| format!("{}: {}", t, l))
+            .collect()
+    }
+
+    #[test]
+    fn recipes_expand_variables_and_follow_prerequisites() {
+        assert_eq!(
+            lines(&[]),
+            ["build/app: mkdir -p build", "build/app: cc -o build/app $<"]
+        );
+        assert_eq!(lines(&["clean"]), ["clean: rm -rf build dist"]);
+        assert_eq!(
+            lines(&["distclean"]),
+            [
+                "clean: rm -rf build dist",
+                "distclean: rm -rf $HOME/.cache/app    $TMPDIR/app"
+            ]
+        );
+        assert_eq!(
+            lines(&["install"]).last().unwrap(),
+            "install: cp build/app /usr/local/bin"
+        );
+        assert!(lines(&["nope"]).is_empty());
+    }
+
+    #[test]
+    fn make_recipes_checked_with_target_in_reason() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("Makefile"),
+            "build:\n\tcargo build\nnuke:\n\trm -rf $(HOME)\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(
+            dir.path().join("sub/ci.mk"),
+            "push:\n\tgit push --force origin main\n",
+        )
+        .unwrap();
+        let check_in = |cmd: &str| {
+            check(
+                cmd,
+                dir.path(),
+                &hardcoded_deny_patterns(),
+                &Severities::default(),
+                &[],
+            )
+        };
+        let CheckResult::Deny(hit) = check_in("make nuke") else {
+            panic!()
+        };
+        assert_eq!(hit.category, Category::RootWildcard);
+        assert!(
+            hit.reason
+                .starts_with("make target \"nuke\" runs `rm -rf $HOME`: "),
+            "{}",
+            hit.reason
+        );
+        let CheckResult::Deny(hit) = check_in("make -C sub -f ci.mk push") else {
+            panic!()
+        };
+        assert!(
+            hit.reason
+                .starts_with("make target \"push\" runs `git push --force origin main`: "),
+            "{}",
+            hit.reason
+        );
+        assert_eq!(check_in("make -j4 build"), CheckResult::Allow);
+        assert_eq!(check_in("make"), CheckResult::Allow);
+    }
+}
@@ -0,0 +1,497 @@
+use regex::{Regex, RegexBuilder};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Longest config pattern accepted, in bytes.
+pub const MAX_PATTERN_LEN: usize = 4096;
+/// Compiled-size limit of a config regex. The linear-time engine's match time is bounded by
+/// its size and the command's length, so this bounds every match; `\w{1000}`-style patterns
+/// that would blow past it are rejected.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+const NEST_LIMIT: u32 = 64;
+/// Backtracking steps a fancy regex may take on one command before giving up.
+const BACKTRACK_LIMIT: usize = 100_000;
+/// Wall-clock budget of one fancy regex match, enforced by a watchdog.
+pub const MATCH_BUDGET: Duration = Duration::from_millis(50);
+
+/// Compile a config regex within the size and complexity limits, so a pathological pattern
+/// from a config layer or the remote policy is rejected instead of slowing every call.
+pub fn regex(pattern: &str) -> Result<Regex, String> {
+    check_len(pattern)?;
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .nest_limit(NEST_LIMIT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Compile a config regex for the backtracking engine within the same limits, with
+/// `BACKTRACK_LIMIT` on each match.
+pub fn fancy_regex(pattern: &str) -> Result<fancy_regex::Regex, String> {
+    check_len(pattern)?;
+    fancy_regex::RegexBuilder::new(pattern)
+        .delegate_size_limit(REGEX_SIZE_LIMIT)
+        .delegate_dfa_size_limit(REGEX_SIZE_LIMIT)
+        .backtrack_limit(BACKTRACK_LIMIT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn check_len(pattern: &str) -> Result<(), String> {
+    if pattern.len() > MAX_PATTERN_LEN {
+        return Err(format!(
+            "pattern is {} bytes, over the limit of {}",
+            pattern.len(),
+            MAX_PATTERN_LEN
+        ));
+    }
+    Ok(())
+}
+
+/// The lowercase hex SHA-256 of `text`, as `printf %s "$cmd" | sha256sum` prints it.
+pub fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// How a config rule matches a command.
+pub enum Matcher {
+    /// A regex over the command text (`"pattern"`).
+    Regex(Regex),
+    /// A regex with lookaround and backreferences (`"type": "fancy-regex"`, or every regex rule
+    /// with `"regex_engine": "fancy"`). It can backtrack; a command that hits the backtracking
+    /// limit or `MATCH_BUDGET` counts as `on_error`: a match for deny rules, none for allow
+    /// rules. After it runs out of time once, it is not run again for the rest of the call.
+    Fancy {
+        re: fancy_regex::Regex,
+        on_error: bool,
+        timed_out: AtomicBool,
+    },
+    /// A shell-style wildcard over the whole command text (`"type": "glob"`), kept for display.
+    Glob { glob: String, re: Regex },
+    /// Text that appears anywhere in the command (`"type": "contains"`).
+    Contains(String),
+    /// The whole command, word for word (`"type": "exact"`).
+    Exact(String),
+    /// A rule on the parsed words of each simple command (`"command"`).
+    Command(CommandRule),
+    /// The whole command by the lowercase hex SHA-256 of its text (`"type": "sha256"`), so an
+    /// admin can approve one exact command without a pattern that might match more.
+    Sha256(String),
+}
+
+/// A rule on a command's parsed words rather than its text, so flag order, quoting and
+/// wrappers do not matter: `{"command": "git", "subcommand": "push", "flags_any": ["--force",
+/// "-f"]}` matches `git push origin main --force`, `sudo git push -fq` and `git -C app push
+/// "--force"`.
+pub struct CommandRule {
+    pub command: String,
+    /// The first word after the command and its options.
+    pub subcommand: Option<String>,
+    /// At least one of these flags must be given; empty matches any flags.
+    pub flags_any: Vec<String>,
+}
+
+impl CommandRule {
+    /// Whether the words of one simple command (wrappers already peeled off) match.
+    fn matches(&self, args: &[&str]) -> bool {
+        let Some((program, rest)) = args.split_first() else {
+            return false;
+        };
+        if program.rsplit('/').next() != Some(self.command.as_str()) {
+            return false;
+        }
+        let rest = match &self.subcommand {
+            None => rest,
+            Some(sub) => match subcommand_index(&self.command, rest) {
+                Some(i) if rest[i] == sub => &rest[i + 1..],
+                _ => return false,
+            },
+        };
+        if self.flags_any.is_empty() {
+            return true;
+        }
+        let mut flags = rest
+            .iter()
+            .take_while(|a| **a != "--")
+            .filter(|a| a.starts_with('-'));
+        flags.any(|arg| self.flags_any.iter().any(|flag| given(flag, arg)))
+    }
+}
+
+/// Where the subcommand is among a command's arguments: the first word that is not an option or
+/// the value of one. Only git's options with a separate value are known.
+fn subcommand_index(command: &str, args: &[&str]) -> Option<usize> {
+    let with_value: &[&str] = if command == "git" {
+        crate::gitalias::OPTIONS_WITH_ARG
+    } else {
+        &[]
+    };
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        if with_value.contains(arg) {
+            i += 2;
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Whether `arg` gives `flag`: the same word, a long flag with `=value`, or a short flag in a
+/// cluster (`-f` in `-fq`).
+fn given(flag: &str, arg: &str) -> bool {
+    if arg == flag {
+        return true;
+    }
+    if flag.starts_with("--") {
+        return arg
+            .strip_prefix(flag)
+            .is_some_and(|tail| tail.starts_with('='));
+    }
+    match (
+        flag.strip_prefix('-').filter(|f| f.chars().count() == 1),
+        arg.strip_prefix('-'),
+    ) {
+        (Some(letter), Some(cluster)) if !cluster.starts_with('-') => cluster.contains(letter),
+        _ => false,
+    }
+}
+
+/// A regex matching what `glob` matches the way `case` and `[[ == ]]` do: the whole text, `*`
+/// for any run of characters (`/` and spaces included), `?` for one, `[…]`/`[!…]` for one of a
+/// set, and `\` to take the next character literally.
+pub fn glob_regex(glob: &str) -> Result<Regex, String> {
+    let mut out = String::from("^");
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            '\\' => out.push_str(&regex::escape(
+                &chars.next().map(String::from).unwrap_or_default(),
+            )),
+            '[' => {
+                let rest: String = chars.clone().collect();
+                let Some(end) = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| *c == ']')
+                    .map(|(i, _)| i)
+                else {
+                    out.push_str(r"\[");
+                    continue;
+                };
+                let set = &rest[..end];
+                let set = set
+                    .strip_prefix('!')
+                    .map(|s| format!("^{}", s))
+                    .unwrap_or_else(|| set.to_string());
+                out.push('[');
+                out.push_str(&set.replace('\\', r"\\").replace('[', r"\["));
+                out.push(']');
+                for _ in 0..=rest[..end].chars().count() {
+                    chars.next();
+                }
+            }
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out.push('$');
+    regex(&out)
+}
+
+/// An exception to a deny rule (`"unless"`): the rule does not apply to a simple command for
+/// which every condition given holds.
+pub struct Unless {
+    /// The simple command's text matches.
+    pub pattern: Option<Regex>,
+    /// There is at least one operand — an argument after the command (and the rule's
+    /// subcommand) that is not a flag — and every one matches, e.g.
+    /// `^(node_modules|target|dist)/`.
+    pub args_match: Option<Regex>,
+}
+
+impl Unless {
+    /// Whether the exception holds for `text`, one simple command of a command string.
+    pub fn holds(&self, text: &str, rule: &Matcher, wrappers: &[String]) -> bool {
+        if self.pattern.as_ref().is_some_and(|re| !re.is_match(text)) {
+            return false;
+        }
+        let Some(args_match) = &self.args_match else {
+            return true;
+        };
+        let ast = crate::shell::parse(text.trim_start_matches("| "));
+        let Some(c) = ast.simple_commands().into_iter().next() else {
+            return false;
+        };
+        let all = c.args();
+        let Some((_, args)) = all[crate::patterns::unwrap_index(&all, wrappers)..].split_first()
+        else {
+            return false;
+        };
+        let mut subcommand = match rule {
+            Matcher::Command(CommandRule { subcommand, .. }) => subcommand.as_deref(),
+            _ => None,
+        };
+        let mut after_dashes = false;
+        let mut operands = Vec::new();
+        for arg in args {
+            if !after_dashes && *arg == "--" {
+                after_dashes = true;
+            } else if after_dashes || !arg.starts_with('-') {
+                // The subcommand is a word of the command, not something it acts on
+                if subcommand.take_if(|sub| sub == arg).is_none() {
+                    operands.push(*arg);
+                }
+            }
+        }
+        !operands.is_empty() && operands.iter().all(|o| args_match.is_match(o))
+    }
+}
+
+impl Matcher {
+    /// The rule's `type` ("command" for command rules).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Matcher::Regex(_) => "regex",
+            Matcher::Fancy { .. } => "fancy-regex",
+            Matcher::Glob { .. } => "glob",
+            Matcher::Contains(_) => "contains",
+            Matcher::Exact(_) => "exact",
+            Matcher::Command(_) => "command",
+            Matcher::Sha256(_) => "sha256",
+        }
+    }
+
+    /// Whether the rule matches `text`, a whole command or one segment of it. Command rules
+    /// look at every simple command in it, behind wrappers (built in, or `wrappers` from config).
+    pub fn is_match(&self, text: &str, wrappers: &[String]) -> bool {
+        match self {
+            Matcher::Regex(re) | Matcher::Glob { re, .. } => re.is_match(text),
+            Matcher::Fancy {
+                on_error,
+                timed_out,
+                ..
+            } if timed_out.load(Ordering::Relaxed) => *on_error,
+            Matcher::Fancy {
+                re,
+                on_error,
+                timed_out,
+            } => watched(re, text, timed_out).unwrap_or_else(|e| {
+                crate::warnings::warn(
+                    "config-regex",
+                    &format!("fancy regex {:?} gave up on a command: {}", re.as_str(), e),
+                );
+                *on_error
+            }),
+            Matcher::Contains(needle) => text.contains(needle.as_str()),
+            Matcher::Exact(whole) => text.trim() == whole,
+            Matcher::Sha256(digest) => sha256_hex(text.trim()) == *digest,
+            Matcher::Command(rule) => crate::shell::parse(text.trim_start_matches("| "))
+                .simple_commands()
+                .iter()
+                .any(|c| {
+                    let args = c.args();
+                    rule.matches(&args[crate::patterns::unwrap_index(&args, wrappers)..])
+                }),
+        }
+    }
+}
+
+/// Match on a watchdog thread, waiting at most `MATCH_BUDGET`. A match that runs over is left
+/// to finish on its own (the hook exits right after deciding) and `timed_out` is set.
+fn watched(re: &fancy_regex::Regex, text: &str, timed_out: &AtomicBool) -> Result<bool, String> {
+    let (tx, rx) = mpsc::channel();
+    let (re, text) = (re.clone(), text.to_string());
+    std::thread::spawn(move || tx.send(re.is_match(&text).map_err(|e| e.to_string())));
+    rx.recv_timeout(MATCH_BUDGET).unwrap_or_else(|_| {
+        timed_out.store(true, Ordering::Relaxed);
+        Err(format!("no answer within {} ms", MATCH_BUDGET.as_millis()))
+    })
+}
+
+impl Matcher {
+    /// A fancy regex rule; see `Matcher::Fancy` for `on_error`.
+    pub fn fancy(re: fancy_regex::Regex, on_error: bool) -> Matcher {
+        Matcher::Fancy {
+            re,
+            on_error,
+            timed_out: AtomicBool::new(false),
+        }
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Matcher::Regex(re) => write!(f, "{}", re.as_str()),
+            Matcher::Fancy { re, .. } => write!(f, "{}", re.as_str()),
+            Matcher::Glob { glob, .. }
+            | Matcher::Contains(glob)
+            | Matcher::Exact(glob)
+            | Matcher::Sha256(glob) => write!(f, "{}", glob),
+            Matcher::Command(rule) => {
+                write!(f, "{}", rule.command)?;
+                if let Some(sub) = &rule.subcommand {
+                    write!(f, " {}", sub)?;
+                }
+                if !rule.flags_any.is_empty() {
+                    write!(f, " [{}]", rule.flags_any.join("|"))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn force_push() -> Matcher {
+        Matcher::Command(CommandRule {
+            command: "git".to_string(),
+            subcommand: Some("push".to_string()),
+            flags_any: vec!["--force".to_string(), "-f".to_string()],
+        })
+    }
+
+    #[test]
+    fn command_rules_match_parsed_words() {
+        let rule = force_push();
+        for cmd in [
+            "git push --force",
+            "git push origin main -f",
+            "sudo git push -fq",
+            "git -C app push \"--force\"",
+            "git push --force=true",
+            "cd x && /usr/bin/git push -f",
+        ] {
+            assert!(rule.is_match(cmd, &[]), "{}", cmd);
+        }
+        for cmd in [
+            "git push",
+            "git push --force-with-lease",
+            "git log --force",
+            "git push -- -f",
+            "echo git push --force",
+            "gitk push -f",
+        ] {
+            assert!(!rule.is_match(cmd, &[]), "{}", cmd);
+        }
+        assert!(rule.is_match("with-lock git push -f", &["with-lock".to_string()]));
+        assert_eq!(rule.to_string(), "git push [--force|-f]");
+    }
+
+    #[test]
+    fn globs_match_the_whole_text_like_case() {
+        let glob = |g: &str| glob_regex(g).unwrap();
+        assert!(glob("rm -rf /Users/*").is_match("rm -rf /Users/bob/Documents"));
+        assert!(!glob("rm -rf /Users/*").is_match("echo rm -rf /Users/bob"));
+        assert!(glob("cat */.env").is_match("cat services/api/.env"));
+        assert!(!glob("cat */.env").is_match("cat .env.example"));
+        assert!(glob("kubectl --context=prod-? *").is_match("kubectl --context=prod-1 delete ns"));
+        assert!(glob("git push * [!o]*").is_match("git push -f upstream"));
+        assert!(!glob("git push * [!o]*").is_match("git push -f origin"));
+        // Regex metacharacters are literal, and so are escaped wildcards
+        assert!(glob("make deploy.(prod)").is_match("make deploy.(prod)"));
+        assert!(!glob("make deploy.(prod)").is_match("make deployX(prod)"));
+        assert!(glob(r"echo \*").is_match("echo *") && !glob(r"echo \*").is_match("echo hi"));
+        assert!(glob("ls [").is_match("ls ["));
+    }
+
+    #[test]
+    fn unless_holds_when_every_operand_matches() {
+        let rule = Matcher::Regex(Regex::new(r"\brm\s+-\w*r").unwrap());
+        let unless = Unless {
+            pattern: None,
+            args_match: Some(Regex::new(r"^(node_modules|target|dist)/?$").unwrap()),
+        };
+        assert!(unless.holds("rm -rf node_modules dist/", &rule, &[]));
+        assert!(unless.holds("sudo rm -r -- target", &rule, &[]));
+        assert!(!unless.holds("rm -rf node_modules src", &rule, &[]));
+        assert!(!unless.holds("rm -rf", &rule, &[]));
+        let rule = Matcher::Command(CommandRule {
+            command: "git".to_string(),
+            subcommand: Some("branch".to_string()),
+            flags_any: vec!["-D".to_string()],
+        });
+        let unless = Unless {
+            pattern: None,
+            args_match: Some(Regex::new("^scratch/").unwrap()),
+        };
+        assert!(unless.holds("git branch -D scratch/a scratch/b", &rule, &[]));
+        assert!(!unless.holds("git branch -D main", &rule, &[]));
+    }
+
+    #[test]
+    fn fancy_regexes_fail_closed_for_deny_rules() {
+        let re = |limit| {
+            fancy_regex::RegexBuilder::new(r"^rm -rf (?!node_modules\b)")
+                .backtrack_limit(limit)
+                .build()
+                .unwrap()
+        };
+        let deny = Matcher::fancy(re(1_000_000), true);
+        assert!(deny.is_match("rm -rf src", &[]));
+        assert!(!deny.is_match("rm -rf node_modules", &[]));
+        assert_eq!(
+            (deny.kind(), deny.to_string().as_str()),
+            ("fancy-regex", r"^rm -rf (?!node_modules\b)")
+        );
+        // A command that runs into the backtracking limit matches a deny rule and no allow rule
+        assert!(Matcher::fancy(re(1), true).is_match("rm -rf node_modules", &[]));
+        assert!(!Matcher::fancy(re(1), false).is_match("rm -rf node_modules", &[]));
+    }
+
+    #[test]
+    fn pathological_patterns_are_rejected_or_cut_off() {
+        assert!(regex(&"a".repeat(MAX_PATTERN_LEN + 1))
+            .unwrap_err()
+            .contains("over the limit"));
+        assert!(regex(r"\w{1000}{1000}").is_err());
+        assert!(fancy_regex(r"(\w{1000}){1000}(?=x)").is_err());
+        let catastrophic = r"^(a|aa)+(?!b)$";
+        let slow = format!("{}b", "a".repeat(28));
+        // The backtracking limit stops it first…
+        let deny = Matcher::fancy(fancy_regex(catastrophic).unwrap(), true);
+        assert!(deny.is_match(&slow, &[]));
+        // …and the watchdog when a match runs over its time anyway; the rule is not run again
+        let unlimited = fancy_regex::RegexBuilder::new(catastrophic)
+            .backtrack_limit(usize::MAX)
+            .build()
+            .unwrap();
+        let deny = Matcher::fancy(unlimited, true);
+        let start = std::time::Instant::now();
+        assert!(deny.is_match(&slow, &[]));
+        assert!(deny.is_match("b", &[]));
+        assert!(start.elapsed() < MATCH_BUDGET * 4, "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn literal_types_ignore_metacharacters() {
+        let contains = Matcher::Contains("db.prod(1)".to_string());
+        assert!(contains.is_match("psql -h db.prod(1) -c 'select 1'", &[]));
+        assert!(!contains.is_match("psql -h dbXprod1", &[]));
+        let exact = Matcher::Exact("npm run deploy:staging".to_string());
+        assert!(exact.is_match("npm run deploy:staging", &[]));
+        assert!(!exact.is_match("npm run deploy:staging --force", &[]));
+        assert!(!exact.is_match("npm run deploy:staging-eu", &[]));
+        let hashed = Matcher::Sha256(sha256_hex("./deploy.sh --env prod --tag v1.4"));
+        assert!(hashed.is_match("  ./deploy.sh --env prod --tag v1.4\n", &[]));
+        assert!(!hashed.is_match("./deploy.sh --env prod --tag v1.4 && rm -rf /", &[]));
+        assert_eq!(
+            sha256_hex("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
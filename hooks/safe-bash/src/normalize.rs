@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+/// One step of turning a command into the normalized form every check also sees. Stages run
+/// in the order listed here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    /// Invisible characters stripped, NFKC and homoglyphs folded (`гm` → `rm`).
+    Unicode,
+    /// Backslash-newline continuations joined.
+    Continuations,
+    /// `# …` comments dropped.
+    Comments,
+    /// Runs of blanks collapsed to one space, blanks around line breaks dropped. Line breaks
+    /// are kept because they separate commands.
+    Whitespace,
+    /// Each simple command rewritten with its quotes and escapes resolved (`r'm'` → `rm`).
+    Quotes,
+    /// Benign wrappers peeled off each simple command (`timeout 5 rm` → `rm`).
+    Wrappers,
+}
+
+pub const ALL_STAGES: &[Stage] = &[
+    Stage::Unicode,
+    Stage::Continuations,
+    Stage::Comments,
+    Stage::Whitespace,
+    Stage::Quotes,
+    Stage::Wrappers,
+];
+
+impl Stage {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_STAGES.iter().copied().find(|s| s.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unicode => "unicode",
+            Self::Continuations => "continuations",
+            Self::Comments => "comments",
+            Self::Whitespace => "whitespace",
+            Self::Quotes => "quotes",
+            Self::Wrappers => "wrappers",
+        }
+    }
+
+    /// Quote resolution and wrapper stripping already happen per segment during matching;
+    /// as whole-command stages they are opt-in.
+    fn enabled_by_default(&self) -> bool {
+        !matches!(self, Self::Comments | Self::Quotes | Self::Wrappers)
+    }
+
+    pub fn apply(&self, cmd: &str, wrappers: &[String]) -> String {
+        match self {
+            Self::Unicode => crate::unicode::fold(cmd),
+            Self::Continuations => crate::shell::join_continuations(cmd),
+            Self::Comments => strip_comments(cmd),
+            Self::Whitespace => cmd
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Quotes => rewrite_commands(cmd, |c| Some(c.canonical())),
+            Self::Wrappers => rewrite_commands(cmd, |c| {
+                let args = c.args();
+                let start = crate::patterns::unwrap_index(&args, wrappers);
+                if start == 0 || start >= args.len() {
+                    return None;
+                }
+                let words = c.argv[start..].iter().map(|w| w.raw.clone());
+                let redirects = c
+                    .redirects
+                    .iter()
+                    .filter(|r| r.heredoc.is_none())
+                    .map(|r| format!("{}{}", r.op, r.target.raw));
+                Some(words.chain(redirects).collect::<Vec<_>>().join(" "))
+            }),
+        }
+    }
+}
+
+/// The enabled stages, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            stages: ALL_STAGES
+                .iter()
+                .copied()
+                .filter(Stage::enabled_by_default)
+                .collect(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// The default stages with `normalization` from the config turning stages on or off by
+    /// name. Unknown names are ignored with a warning.
+    pub fn from_config(overrides: &HashMap<String, bool>) -> Self {
+        for name in overrides.keys().filter(|n| Stage::from_name(n).is_none()) {
+            crate::warnings::warn(
+                "config-normalization",
+                &format!("ignoring normalization stage {:?}", name),
+            );
+        }
+        let enabled = |stage: &Stage| {
+            overrides
+                .get(stage.as_str())
+                .copied()
+                .unwrap_or(stage.enabled_by_default())
+        };
+        Self {
+            stages: ALL_STAGES.iter().copied().filter(enabled).collect(),
+        }
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    pub fn apply(&self, cmd: &str, wrappers: &[String]) -> String {
+        self.stages
+            .iter()
+            .fold(cmd.to_string(), |text, stage| stage.apply(&text, wrappers))
+    }
+}
+
+/// Drop `#` comments: a `#` that starts a word outside quotes, up to the end of its line.
+fn strip_comments(src: &str) -> String {
+    split_comments(src).0
+}
+
+/// The text of each `#` comment in `src`, without the `#`.
+pub fn comments(src: &str) -> Vec<String> {
+    split_comments(src).1
+}
+
+/// `src` without its comments, and the comments.
+fn split_comments(src: &str) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(src.len());
+    let mut comments = Vec::new();
+    let mut chars = src.chars();
+    let (mut single, mut double) = (false, false);
+    let mut prev: Option<char> = None;
+    while let Some(c) = chars.next() {
+        if single {
+            single = c != '\'';
+        } else if c == '\\' {
+            out.push(c);
+            if let Some(next) = chars.next() {
+                out.push(next);
+                prev = Some(next);
+            }
+            continue;
+        } else if c == '\'' && !double {
+            single = true;
+        } else if c == '"' {
+            double = !double;
+        } else if c == '#'
+            && !double
+            && prev.is_none_or(|p| p.is_whitespace() || ";&|(".contains(p))
+        {
+            let mut comment = String::new();
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    out.push(c);
+                    break;
+                }
+                comment.push(c);
+            }
+            comments.push(comment);
+            prev = Some('\n');
+            continue;
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    (out, comments)
+}
+
+/// Replace the text of each simple command with what `rewrite` makes of it.
+fn rewrite_commands(
+    cmd: &str,
+    rewrite: impl Fn(&crate::shell::SimpleCommand) -> Option<String>,
+) -> String {
+    let ast = crate::shell::parse(cmd);
+    let mut out = String::with_capacity(cmd.len());
+    let mut rest = cmd;
+    for command in ast.simple_commands() {
+        let Some(at) = rest
+            .find(&command.text)
+            .filter(|_| !command.text.is_empty())
+        else {
+            continue;
+        };
+        let Some(new) = rewrite(command).filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        out.push_str(&rest[..at]);
+        out.push_str(&new);
+        rest = &rest[at + command.text.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(stage: Stage, cmd: &str) -> String {
+        stage.apply(cmd, &[])
+    }
+
+    #[test]
+    fn each_stage_on_its_own() {
+        assert_eq!(stage(Stage::Unicode, "г\u{200b}m -rf /"), "rm -rf /");
+        assert_eq!(stage(Stage::Continuations, "rm \\\n-rf /"), "rm -rf /");
+        assert_eq!(
+            stage(Stage::Comments, "ls # tidy up\nrm -rf build # later"),
+            "ls \nrm -rf build "
+        );
+        assert_eq!(
+            stage(Stage::Comments, "echo '#x' \"a # b\" c#d"),
+            "echo '#x' \"a # b\" c#d"
+        );
+        assert_eq!(
+            stage(Stage::Whitespace, "git  push\t\t--force \n ls"),
+            "git push --force\nls"
+        );
+        assert_eq!(
+            stage(Stage::Quotes, "cd x && r'm' -rf \"/\""),
+            "cd x && rm -rf /"
+        );
+        assert_eq!(
+            stage(Stage::Wrappers, "timeout 5 sudo rm -rf /srv > log; ls"),
+            "rm -rf /srv >log; ls"
+        );
+        assert_eq!(
+            Stage::Wrappers.apply("with-lock rm -rf x", &["with-lock".to_string()]),
+            "rm -rf x"
+        );
+    }
+
+    #[test]
+    fn default_pipeline_collapses_blanks_and_keeps_lines() {
+        let normalize = |cmd: &str| Pipeline::default().apply(cmd, &[]);
+        assert_eq!(normalize("rm \\\n   -r\t\t-f   /"), "rm -r -f /");
+        assert_eq!(normalize("  cd /tmp  \n\tls   -la "), "cd /tmp\nls -la");
+    }
+
+    #[test]
+    fn config_toggles_stages_in_fixed_order() {
+        assert_eq!(
+            Pipeline::default().stages(),
+            [Stage::Unicode, Stage::Continuations, Stage::Whitespace]
+        );
+        let overrides = HashMap::from([
+            ("wrappers".to_string(), true),
+            ("unicode".to_string(), false),
+            ("comments".to_string(), true),
+        ]);
+        let pipeline = Pipeline::from_config(&overrides);
+        assert_eq!(
+            pipeline.stages(),
+            [
+                Stage::Continuations,
+                Stage::Comments,
+                Stage::Whitespace,
+                Stage::Wrappers
+            ]
+        );
+        assert_eq!(
+            pipeline.apply("nice  -n 5 rm \\\n -rf /  # ok", &[]),
+            "rm -rf /"
+        );
+    }
+}
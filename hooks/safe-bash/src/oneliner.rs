@@ -0,0 +1,588 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::{Category, Severities, Severity};
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Lang {
+    Python,
+    Node,
+    Perl,
+    Ruby,
+    Awk,
+}
+
+/// What a call in the program does, as the shell command with the same effect.
+#[derive(Clone, Copy)]
+enum Effect {
+    /// Runs a shell command (`os.system`, `execSync`, `system`, backticks).
+    Shell,
+    /// Deletes a tree (`shutil.rmtree`, `FileUtils.rm_rf`): `rm -r PATH`.
+    DeleteTree,
+    /// Deletes a file (`os.remove`, `unlinkSync`): `rm PATH`. Node's `fs.rm*` with
+    /// `recursive: true` is a tree delete.
+    DeleteFile,
+}
+
+/// Calls each language's heuristics look for. Each regex ends where the arguments start.
+fn calls(lang: Lang) -> &'static [(Regex, Effect)] {
+    static PYTHON: OnceLock<Vec<(Regex, Effect)>> = OnceLock::new();
+    static NODE: OnceLock<Vec<(Regex, Effect)>> = OnceLock::new();
+    static PERL: OnceLock<Vec<(Regex, Effect)>> = OnceLock::new();
+    static RUBY: OnceLock<Vec<(Regex, Effect)>> = OnceLock::new();
+    static AWK: OnceLock<Vec<(Regex, Effect)>> = OnceLock::new();
+    let build = |table: &[(&str, Effect)]| -> Vec<(Regex, Effect)> {
+        table
+            .iter()
+            .map(|(re, e)| (Regex::new(re).expect("invalid one-liner call pattern"), *e))
+            .collect()
+    };
+    match lang {
+        Lang::Python => PYTHON.get_or_init(|| {
+            build(&[
+                (r"\bos\.(system|popen)\s*", Effect::Shell),
+                (
+                    concat!(
+                        r"\bsubprocess\.",
+                        r"(run|call|check_call|check_output|Popen|getoutput|getstatusoutput)\s*",
+                    ),
+                    Effect::Shell,
+                ),
+                (r"\bshutil\.rmtree\s*", Effect::DeleteTree),
+                (r"\bos\.(remove|unlink)\s*", Effect::DeleteFile),
+            ])
+        }),
+        Lang::Node => NODE.get_or_init(|| {
+            build(&[
+                (
+                    r"\b(execSync|exec|spawnSync|spawn|execFileSync|execFile)\s*",
+                    Effect::Shell,
+                ),
+                (
+                    r"\.(rmSync|rmdirSync|rm|rmdir|unlinkSync|unlink)\s*",
+                    Effect::DeleteFile,
+                ),
+            ])
+        }),
+        Lang::Perl => PERL.get_or_init(|| {
+            build(&[
+                (r"\b(system|exec)\b\s*", Effect::Shell),
+                (r"\b(rmtree|remove_tree)\b\s*", Effect::DeleteTree),
+                (r"\bunlink\b\s*", Effect::DeleteFile),
+            ])
+        }),
+        Lang::Ruby => RUBY.get_or_init(|| {
+            build(&[
+                (r"\b(system|exec|spawn)\b\s*", Effect::Shell),
+                (r"\b(IO\.popen|Open3\.\w+)\s*", Effect::Shell),
+                (
+                    concat!(
+                        r"\bFileUtils\.",
+                        r"(rm_rf|rm_r|remove_dir|remove_entry_secure|remove_entry|rmtree)\b\s*",
+                    ),
+                    Effect::DeleteTree,
+                ),
+                (
+                    r"\b(FileUtils\.(rm_f|rm|remove)|File\.(delete|unlink))\b\s*",
+                    Effect::DeleteFile,
+                ),
+            ])
+        }),
+        Lang::Awk => AWK.get_or_init(|| build(&[(r"\bsystem\s*", Effect::Shell)])),
+    }
+}
+
+/// A call found in a one-liner and the shell command it amounts to.
+#[derive(Debug, PartialEq)]
+struct Call {
+    /// The call as written up to its arguments (`shutil.rmtree`, `` ` ``).
+    name: String,
+    /// None when the command or path is computed at run time.
+    command: Option<String>,
+}
+
+/// The interpreter and program of `python3 -c CODE`, `node -e CODE`, `perl -le CODE`,
+/// `ruby -e CODE` (also `node --eval`/`-p`, `bun -e`, `deno eval`) or `awk [-F SEP] [-v VAR=VAL]
+/// CODE`, if `args` is one.
+fn program<'a>(args: &[&'a str]) -> Option<(Lang, String, &'a str)> {
+    let name = args.first()?.rsplit('/').next()?;
+    let lang = match name {
+        n if n.starts_with("python") => Lang::Python,
+        "node" | "nodejs" | "bun" | "deno" => Lang::Node,
+        "perl" => Lang::Perl,
+        "ruby" => Lang::Ruby,
+        "awk" | "gawk" | "mawk" | "nawk" => {
+            return awk_program(&args[1..]).map(|code| (Lang::Awk, name.to_string(), code))
+        }
+        _ => return None,
+    };
+    for (i, arg) in args.iter().enumerate().skip(1) {
+        let is_code_flag = match lang {
+            Lang::Python => arg.starts_with('-') && !arg.starts_with("--") && arg.ends_with('c'),
+            Lang::Node => {
+                matches!(*arg, "-e" | "--eval" | "-p" | "--print")
+                    || (name == "deno" && *arg == "eval")
+            }
+            Lang::Perl => {
+                arg.starts_with('-') && !arg.starts_with("--") && arg.ends_with(['e', 'E'])
+            }
+            Lang::Ruby | Lang::Awk => {
+                arg.starts_with('-') && !arg.starts_with("--") && arg.ends_with('e')
+            }
+        };
+        if is_code_flag {
+            return Some((lang, format!("{} {}", name, arg), args.get(i + 1)?));
+        }
+        if let Some(code) = arg.strip_prefix("--eval=").filter(|_| lang == Lang::Node) {
+            return Some((lang, format!("{} --eval", name), code));
+        }
+    }
+    None
+}
+
+/// The program text of an awk invocation: the first operand after the options. A program
+/// read from a file (`-f prog.awk`) is not inspected.
+fn awk_program<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-f" | "--file" => return None,
+            "-F" | "-v" | "--field-separator" | "--assign" => {
+                args.next();
+            }
+            "--" => return args.next().copied(),
+            a if a.starts_with("-f") || a.starts_with("--file=") => return None,
+            a if a.starts_with('-') => {}
+            a => return Some(a),
+        }
+    }
+    None
+}
+
+/// Find the dangerous calls in a one-liner's program and translate them to shell commands.
+fn find_calls(lang: Lang, code: &str) -> Vec<Call> {
+    let mut found = Vec::new();
+    // Node's exec family is only a shell when child_process is in play (`/x/.exec(s)` is a regex)
+    let node_shell = lang != Lang::Node || code.contains("child_process");
+    for (re, effect) in calls(lang) {
+        for m in re.find_iter(code) {
+            if matches!(effect, Effect::Shell) && !node_shell {
+                continue;
+            }
+            let name = m.as_str().trim_end().trim_start_matches('.').to_string();
+            let (words, literal) = literal_args(&code[m.end()..]);
+            let call = &code[m.start()..];
+            let call = &call[..call.find([';', '\n']).unwrap_or(call.len())];
+            let tree = match effect {
+                Effect::DeleteTree => true,
+                Effect::DeleteFile => {
+                    lang == Lang::Node && call.contains("recursive") && call.contains("true")
+                }
+                Effect::Shell => false,
+            };
+            let command = match effect {
+                Effect::Shell if literal && words.len() == 1 => Some(words[0].clone()),
+                Effect::Shell if literal && !words.is_empty() => Some(join(&words)),
+                Effect::Shell => None,
+                // A path computed at run time: a tree delete is still a recursive delete
+                _ if !literal || words.is_empty() => tree.then(|| "rm -r '<path>'".to_string()),
+                _ => Some(format!(
+                    "rm {}{}",
+                    if tree { "-r " } else { "" },
+                    join(&words)
+                )),
+            };
+            if command.is_some() || matches!(effect, Effect::Shell) {
+                found.push(Call { name, command });
+            }
+        }
+    }
+    // Command substitution built into the language: `...`, qx{...}, %x(...)
+    if matches!(lang, Lang::Perl | Lang::Ruby) {
+        static SUBST: OnceLock<Regex> = OnceLock::new();
+        let re = SUBST.get_or_init(|| {
+            Regex::new(concat!(
+                r"`([^`]*)`",
+                r"|\bqx\s*\{([^}]*)\}|\bqx\s*\(([^)]*)\)",
+                r"|%x\s*\(([^)]*)\)|%x\s*\{([^}]*)\}",
+            ))
+            .unwrap()
+        });
+        for caps in re.captures_iter(code) {
+            let body = caps
+                .iter()
+                .skip(1)
+                .flatten()
+                .next()
+                .map_or("", |m| m.as_str());
+            let name = ["`", "qx", "%x"]
+                .into_iter()
+                .find(|n| caps[0].starts_with(n))
+                .unwrap_or("`");
+            found.push(Call {
+                name: name.to_string(),
+                command: Some(body.to_string()),
+            });
+        }
+    }
+    // awk output redirection: `print | "cmd"` and `"cmd" | getline` run cmd; `print > "file"`
+    // truncates file. A computed file name is left alone, like a shell redirect to `$f`.
+    if lang == Lang::Awk {
+        static IO: OnceLock<Regex> = OnceLock::new();
+        let re = IO.get_or_init(|| {
+            Regex::new(concat!(
+                r#"\|&?\s*"((?:[^"\\]|\\.)*)""#,
+                r#"|"((?:[^"\\]|\\.)*)"\s*\|&?\s*getline"#,
+                r#"|\bprintf?\b[^;}|>]*?(>>?)\s*"((?:[^"\\]|\\.)*)""#,
+            ))
+            .unwrap()
+        });
+        for caps in re.captures_iter(code) {
+            let unescape = |s: &str| s.replace("\\\"", "\"").replace("\\\\", "\\");
+            let call = if let Some(target) = caps.get(1) {
+                Call {
+                    name: "print |".to_string(),
+                    command: Some(format!("print | {}", unescape(target.as_str()))),
+                }
+            } else if let Some(command) = caps.get(2) {
+                Call {
+                    name: "getline".to_string(),
+                    command: Some(unescape(command.as_str())),
+                }
+            } else {
+                let file = crate::shell::quote(&unescape(&caps[4]));
+                Call {
+                    name: format!("print {}", &caps[3]),
+                    command: Some(format!("print {} {}", &caps[3], file)),
+                }
+            };
+            found.push(call);
+        }
+    }
+    found
+}
+
+fn join(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|w| crate::shell::quote(w))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The leading string literals of a call's arguments — `('rm -rf /')`, `(["rm", "-rf", x])`,
+/// `"rm", "-rf", "/"` — and whether they were all literal up to the first non-string argument.
+/// Interpolated strings (f-strings, `${}` templates, `#{}`) count as computed.
+fn literal_args(rest: &str) -> (Vec<String>, bool) {
+    let mut chars = rest
+        .trim_start()
+        .strip_prefix('(')
+        .unwrap_or(rest.trim_start())
+        .chars()
+        .peekable();
+    let mut words = Vec::new();
+    let mut depth = 0;
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut interpolated = false;
+        while let Some(prefix) = chars.peek().copied().filter(|c| "rRbBuUfF".contains(*c)) {
+            interpolated |= prefix == 'f' || prefix == 'F';
+            chars.next();
+        }
+        match chars.peek().copied() {
+            Some('[') => {
+                chars.next();
+                depth += 1;
+                continue;
+            }
+            Some(quote @ ('\'' | '"' | '`')) => {
+                chars.next();
+                let mut word = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => word.extend(chars.next()),
+                        c if c == quote => break,
+                        c => word.push(c),
+                    }
+                }
+                let computed = (interpolated && word.contains('{'))
+                    || word.contains("${")
+                    || word.contains("#{");
+                if computed {
+                    return (words, false);
+                }
+                words.push(word);
+            }
+            _ => return (words.clone(), !words.is_empty()),
+        }
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        while depth > 0 && chars.peek() == Some(&']') {
+            chars.next();
+            depth -= 1;
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+        }
+        // `"rm -rf " + d`, `"rm -rf " $1`, `"rm " . $d`: the command is built at run time
+        let rest: String = chars.clone().collect();
+        let keyword = ["or ", "and ", "if ", "unless "]
+            .iter()
+            .any(|k| rest.starts_with(k));
+        if !keyword
+            && rest.starts_with(|c: char| "+.$%~'\"".contains(c) || c.is_alphanumeric() || c == '_')
+        {
+            return (words, false);
+        }
+        if chars.peek() == Some(&',') {
+            chars.next();
+            if depth == 0 && !chars.clone().any(|c| !c.is_whitespace()) {
+                break;
+            }
+            continue;
+        }
+        break;
+    }
+    (words, true)
+}
+
+/// The shell commands a one-liner's calls amount to, checked as segments of their own so
+/// config rules see them too (`python3 -c "import shutil; shutil.rmtree('/srv')"` → `rm -r /srv`).
+pub fn commands(args: &[&str]) -> Vec<String> {
+    let Some((lang, _, code)) = program(args) else {
+        return Vec::new();
+    };
+    find_calls(lang, code)
+        .into_iter()
+        .filter_map(|c| c.command)
+        .collect()
+}
+
+/// Check interpreter one-liners: each shell command a call runs or amounts to is checked
+/// against the hardcoded patterns and the root-wildcard rule, with the reason naming the
+/// interpreter and the call. A shell command computed at run time cannot be checked and asks.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some((lang, label, code)) =
+                program(&args[crate::patterns::unwrap_index(&args, wrappers)..])
+            else {
+                continue;
+            };
+            for call in find_calls(lang, code) {
+                let Some(command) = call.command else {
+                    let reason = format!(
+                        "Shell injection: {} one-liner runs a shell command built at run time \
+                         ({}) — the hook cannot see what it is",
+                        label, call.name
+                    );
+                    let hit = Hit {
+                        id: "SH102",
+                        reason,
+                        category: Category::ShellInjection,
+                    };
+                    let found = match severities.get(Category::ShellInjection) {
+                        Severity::Off => CheckResult::Allow,
+                        Severity::Audit => CheckResult::Audit(hit),
+                        Severity::Warn => CheckResult::Warn(hit),
+                        _ => CheckResult::Ask(hit),
+                    };
+                    result = result.or_worse(found);
+                    continue;
+                };
+                let label = |hit: Hit| Hit {
+                    reason: format!(
+                        "{} one-liner runs `{}` ({}): {}",
+                        label, command, call.name, hit.reason
+                    ),
+                    ..hit
+                };
+                if let Some(hit) = crate::wildcard::check(&command, cwd, wrappers) {
+                    return CheckResult::Deny(label(hit));
+                }
+                let found =
+                    crate::patterns::check_command(&command, patterns, severities, wrappers)
+                        .relabel(label);
+                result = result.or_worse(found);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+
+    fn translated(cmd: &str) -> Vec<String> {
+        let ast = crate::shell::parse(cmd);
+        let c = ast.simple_commands().into_iter().next().unwrap();
+        commands(&c.args())
+    }
+
+    fn check_in(cmd: &str) -> CheckResult {
+        check(
+            cmd,
+            Path::new("/work/app"),
+            &hardcoded_deny_patterns(),
+            &Severities::default(),
+            &[],
+        )
+    }
+
+    #[test]
+    fn calls_translate_to_shell_commands() {
+        assert_eq!(
+            translated(r#"python3 -c "import shutil; shutil.rmtree('/srv')""#),
+            ["rm -r /srv"]
+        );
+        assert_eq!(
+            translated(r#"python -c "import subprocess; subprocess.run(['rm', '-rf', 'build'])""#),
+            ["rm -rf build"]
+        );
+        assert_eq!(
+            translated(r#"node -e "require('child_process').execSync('git push --force')""#),
+            ["git push --force"]
+        );
+        assert_eq!(
+            translated(r#"node -e "require('fs').rmSync('dist', {recursive: true})""#),
+            ["rm -r dist"]
+        );
+        assert_eq!(
+            translated(r#"node -e "require('fs').unlinkSync('a.txt')""#),
+            ["rm a.txt"]
+        );
+        assert_eq!(
+            translated(r#"perl -le 'system("rm", "-rf", "/tmp/x"); print `id`'"#),
+            ["rm -rf /tmp/x", "id"]
+        );
+        assert_eq!(
+            translated(r#"ruby -rfileutils -e 'FileUtils.rm_rf("vendor")'"#),
+            ["rm -r vendor"]
+        );
+        assert!(translated(r#"node -e "console.log(/x/.exec('x'))""#).is_empty());
+        assert!(translated(r#"python3 -c "print(1)""#).is_empty());
+    }
+
+    #[test]
+    fn dangerous_calls_block_with_interpreter_in_reason() {
+        let CheckResult::Deny(hit) =
+            check_in(r#"python3 -c "import shutil; shutil.rmtree('/srv')""#)
+        else {
+            panic!()
+        };
+        assert_eq!(
+            hit.reason,
+            "python3 -c one-liner runs `rm -r /srv` (shutil.rmtree): Catastrophic: rm -r /srv \
+             deletes the system directory /srv"
+        );
+        let CheckResult::Deny(hit) =
+            check_in(r#"node -e "require('child_process').execSync('rm -rf /')""#)
+        else {
+            panic!()
+        };
+        assert_eq!(hit.category, Category::RootWildcard);
+        assert!(matches!(
+            check_in(r#"ruby -e 'system("git reset --hard")'"#),
+            CheckResult::Ask(_)
+        ));
+        assert_eq!(
+            check_in(r#"python3 -c "import os; os.remove('out.txt')""#),
+            CheckResult::Allow
+        );
+    }
+
+    #[test]
+    fn computed_shell_command_asks() {
+        let result = check_in(r#"python3 -c "import os, sys; os.system(sys.argv[1])" x"#);
+        let CheckResult::Ask(hit) = result else {
+            panic!("{:?}", result)
+        };
+        assert!(
+            hit.reason.contains("built at run time (os.system)"),
+            "{}",
+            hit.reason
+        );
+        assert!(matches!(
+            check_in(r#"python3 -c "import os; os.system(f'rm -rf {d}')""#),
+            CheckResult::Ask(_)
+        ));
+        assert!(matches!(
+            check_in(r#"python3 -c "import os; os.system('rm -rf ' + d)""#),
+            CheckResult::Ask(_)
+        ));
+    }
+
+    #[test]
+    fn awk_system_pipes_and_output_files() {
+        assert_eq!(
+            translated(r#"awk 'BEGIN{system("rm -rf /")}'"#),
+            ["rm -rf /"]
+        );
+        assert_eq!(
+            translated(
+                r#"gawk -F: -v OFS=, '{print $1 | "sort -u"; "date" | getline d}' /etc/passwd"#
+            ),
+            ["print | sort -u", "date"]
+        );
+        assert_eq!(
+            translated(r#"awk '{print $0 > "/etc/passwd"}' users"#),
+            ["print > /etc/passwd"]
+        );
+        assert_eq!(
+            translated(r#"awk '{print > ($1 ".txt")}' data"#),
+            Vec::<String>::new()
+        );
+        assert!(translated("awk -f prog.awk data").is_empty());
+
+        let CheckResult::Deny(hit) = check_in(r#"awk 'BEGIN{system("rm -rf /")}'"#) else {
+            panic!()
+        };
+        assert!(
+            hit.reason
+                .starts_with("awk one-liner runs `rm -rf /` (system):"),
+            "{}",
+            hit.reason
+        );
+        let CheckResult::Deny(hit) = check_in(r#"awk '{print "rm -rf " $1 | "sh"}' dirs"#) else {
+            panic!()
+        };
+        assert!(
+            hit.reason.contains("(print |): Shell injection"),
+            "{}",
+            hit.reason
+        );
+        let mut patterns = hardcoded_deny_patterns();
+        patterns.extend(crate::patterns::root_deny_patterns());
+        let result = check(
+            r#"awk '{print > "/etc/passwd"}' x"#,
+            Path::new("/"),
+            &patterns,
+            &Severities::default(),
+            &[],
+        );
+        assert!(matches!(result, CheckResult::Deny(_)), "{:?}", result);
+        assert!(matches!(
+            check_in(r#"awk '{system("mkdir -p " $1)}' dirs"#),
+            CheckResult::Ask(_)
+        ));
+        assert_eq!(
+            check_in(r#"awk -F, '{print $2 > "col2.txt"}' data.csv"#),
+            CheckResult::Allow
+        );
+    }
+}
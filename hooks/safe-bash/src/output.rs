@@ -0,0 +1,92 @@
+use serde_json::json;
+
+/// Build the PreToolUse hook JSON that asks the user to confirm the tool call.
+/// Claude Code shows `reason` in the permission prompt.
+pub fn ask_json(reason: &str) -> String {
+    json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "permissionDecision": "ask",
+            "permissionDecisionReason": reason,
+        }
+    })
+    .to_string()
+}
+
+/// Build the PreToolUse hook JSON that refuses the tool call, printed along with exit code 2
+/// so harnesses that read either get the decision. `reason` goes back to the model.
+pub fn deny_json(reason: &str) -> String {
+    json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "permissionDecision": "deny",
+            "permissionDecisionReason": reason,
+        }
+    })
+    .to_string()
+}
+
+/// Like `ask_json`, but the tool call runs with `input` instead of the original tool input
+/// once the user approves.
+pub fn rewrite_json(reason: &str, input: &serde_json::Value) -> String {
+    json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "permissionDecision": "ask",
+            "permissionDecisionReason": reason,
+            "updatedInput": input,
+        }
+    })
+    .to_string()
+}
+
+/// Build the PreToolUse hook JSON that runs the tool call with `input` instead of the original
+/// tool input, making no permission decision: the user's own permission rules still decide
+/// whether it runs without a prompt.
+pub fn update_json(input: &serde_json::Value) -> String {
+    json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "updatedInput": input,
+        }
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ask_json_shape() {
+        let v: serde_json::Value = serde_json::from_str(&ask_json("confirm this")).unwrap();
+        assert_eq!(v["hookSpecificOutput"]["hookEventName"], "PreToolUse");
+        assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+        assert_eq!(
+            v["hookSpecificOutput"]["permissionDecisionReason"],
+            "confirm this"
+        );
+    }
+
+    #[test]
+    fn deny_json_shape() {
+        let v: serde_json::Value = serde_json::from_str(&deny_json("Blocked: rm -rf /")).unwrap();
+        assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "deny");
+        assert_eq!(
+            v["hookSpecificOutput"]["permissionDecisionReason"],
+            "Blocked: rm -rf /"
+        );
+    }
+
+    #[test]
+    fn rewrite_json_carries_updated_input() {
+        let v: serde_json::Value =
+            serde_json::from_str(&rewrite_json("run less", &json!({"command": "ls"}))).unwrap();
+        assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+        assert_eq!(v["hookSpecificOutput"]["updatedInput"]["command"], "ls");
+        let v: serde_json::Value =
+            serde_json::from_str(&update_json(&json!({"command": "ls"}))).unwrap();
+        assert!(v["hookSpecificOutput"].get("permissionDecision").is_none());
+        assert_eq!(v["hookSpecificOutput"]["updatedInput"]["command"], "ls");
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Commands that rewrite permissions or ownership.
+const PERMISSION_COMMANDS: &[&str] = &["chmod", "chown", "chgrp"];
+
+/// Detect recursive permission/ownership changes over the repository root or the
+/// home directory. Even "reasonable" modes (`chmod -R 755 .`) rewrite .git objects,
+/// hooks and editor state wholesale, so these are surfaced for confirmation.
+/// Returns the ask reason, or None if the command is not a mass change.
+pub fn check_mass_change(cmd: &str, cwd: &Path) -> Option<String> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let repo_root = crate::blast::find_git_dir(cwd).and_then(|g| g.parent().map(Path::to_path_buf));
+
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        let words = command.args();
+        let Some(program) = words.first() else {
+            continue;
+        };
+        if !PERMISSION_COMMANDS.contains(program) {
+            continue;
+        }
+        if !words.iter().skip(1).any(|w| is_recursive_flag(w)) {
+            continue;
+        }
+
+        // First non-flag argument is the mode/owner; the rest are targets
+        let targets = words.iter().skip(1).filter(|w| !w.starts_with('-')).skip(1);
+        for target in targets {
+            let resolved = resolve(target, cwd, home.as_deref());
+            let zone = if repo_root.as_deref() == Some(resolved.as_path()) {
+                "the repository root"
+            } else if home.as_deref() == Some(resolved.as_path()) {
+                "the home directory"
+            } else if resolved == Path::new("/") {
+                "the filesystem root"
+            } else {
+                continue;
+            };
+            return Some(format!(
+                "Mass permission change: {} -R over {} ({}) rewrites git metadata and editor \
+                 state — confirm this is intended",
+                program, zone, target
+            ));
+        }
+    }
+    None
+}
+
+fn is_recursive_flag(word: &str) -> bool {
+    word == "--recursive"
+        || (word.starts_with('-') && !word.starts_with("--") && word.contains('R'))
+}
+
+/// Resolve a path argument against cwd and HOME, normalizing `.` and `..` lexically.
+pub fn resolve(arg: &str, cwd: &Path, home: Option<&Path>) -> PathBuf {
+    let expanded = if arg == "~" || arg == "$HOME" || arg == "${HOME}" {
+        home.map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(arg))
+    } else if let Some(rest) = arg
+        .strip_prefix("~/")
+        .or_else(|| arg.strip_prefix("$HOME/"))
+    {
+        home.map(|h| h.join(rest))
+            .unwrap_or_else(|| PathBuf::from(arg))
+    } else {
+        cwd.join(arg)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in expanded.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn chmod_r_over_repo_root_asks() {
+        let dir = repo();
+        assert!(check_mass_change("chmod -R 755 .", dir.path()).is_some());
+        assert!(check_mass_change("chown -R me:staff ./", dir.path()).is_some());
+    }
+
+    #[test]
+    fn chmod_r_from_subdir_targeting_root_asks() {
+        let dir = repo();
+        let reason = check_mass_change("chmod -R u+w ..", &dir.path().join("src")).unwrap();
+        assert!(reason.contains("repository root"));
+    }
+
+    #[test]
+    fn chmod_r_on_subdirectory_allowed() {
+        let dir = repo();
+        assert!(check_mass_change("chmod -R 755 src", dir.path()).is_none());
+    }
+
+    #[test]
+    fn non_recursive_chmod_allowed() {
+        let dir = repo();
+        assert!(check_mass_change("chmod 755 .", dir.path()).is_none());
+    }
+
+    #[test]
+    fn chown_r_home_asks() {
+        let dir = repo();
+        let reason = check_mass_change("sudo true; chown -R me ~", dir.path());
+        assert!(reason.unwrap().contains("home directory"));
+    }
+
+    #[test]
+    fn resolve_normalizes() {
+        let cwd = Path::new("/work/repo/src");
+        assert_eq!(resolve("..", cwd, None), PathBuf::from("/work/repo"));
+        assert_eq!(
+            resolve("./a/../b", cwd, None),
+            PathBuf::from("/work/repo/src/b")
+        );
+        assert_eq!(
+            resolve("~/x", cwd, Some(Path::new("/home/me"))),
+            PathBuf::from("/home/me/x")
+        );
+    }
+}
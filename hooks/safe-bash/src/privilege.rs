@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+/// Privilege level the hook (and therefore the Bash tool) is running with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Privilege {
+    User,
+    Root,
+    /// euid 0 inside a container — root there is often root-equivalent on mounted host paths.
+    ContainerRoot,
+}
+
+impl Privilege {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Privilege::User => "user",
+            Privilege::Root => "root",
+            Privilege::ContainerRoot => "container-root",
+        }
+    }
+
+    pub fn is_root(self) -> bool {
+        self != Privilege::User
+    }
+}
+
+/// Detect the current privilege level from the effective uid and container markers.
+pub fn detect() -> Privilege {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let euid = unsafe { libc::geteuid() };
+    classify(euid, in_container(Path::new("/")))
+}
+
+fn classify(euid: u32, container: bool) -> Privilege {
+    match (euid, container) {
+        (0, true) => Privilege::ContainerRoot,
+        (0, false) => Privilege::Root,
+        _ => Privilege::User,
+    }
+}
+
+/// Best-effort container detection: Docker/Podman marker files or a container cgroup for PID 1.
+fn in_container(root: &Path) -> bool {
+    if root.join(".dockerenv").exists() || root.join("run/.containerenv").exists() {
+        return true;
+    }
+    match fs::read_to_string(root.join("proc/1/cgroup")) {
+        Ok(cgroup) => ["docker", "kubepods", "containerd", "lxc", "podman"]
+            .iter()
+            .any(|marker| cgroup.contains(marker)),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn classify_levels() {
+        assert_eq!(classify(1000, false), Privilege::User);
+        assert_eq!(classify(1000, true), Privilege::User);
+        assert_eq!(classify(0, false), Privilege::Root);
+        assert_eq!(classify(0, true), Privilege::ContainerRoot);
+    }
+
+    #[test]
+    fn container_detected_from_dockerenv() {
+        let dir = TempDir::new().unwrap();
+        assert!(!in_container(dir.path()));
+        fs::write(dir.path().join(".dockerenv"), "").unwrap();
+        assert!(in_container(dir.path()));
+    }
+
+    #[test]
+    fn container_detected_from_cgroup() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("proc/1")).unwrap();
+        fs::write(
+            dir.path().join("proc/1/cgroup"),
+            "0::/kubepods/besteffort/pod123\n",
+        )
+        .unwrap();
+        assert!(in_container(dir.path()));
+    }
+
+    #[test]
+    fn root_levels_are_root() {
+        assert!(Privilege::Root.is_root());
+        assert!(Privilege::ContainerRoot.is_root());
+        assert!(!Privilege::User.is_root());
+    }
+}
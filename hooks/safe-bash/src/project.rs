@@ -0,0 +1,245 @@
+use crate::config::{CompiledPattern, Origin, PolicyInfo, ProjectConfig, RuleMatch};
+use crate::matcher::Matcher;
+use crate::severity::Severity;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Files under the workspace root whose fenced `safe-bash` blocks add project rules, read in
+/// this order.
+const POLICY_FILES: &[&str] = &["CLAUDE.md", ".claude/policy.md"];
+
+/// Reason for a project deny line written without one.
+const DEFAULT_REASON: &str = "Denied by project policy";
+
+/// Allow/deny rules a repository declares next to its instructions for Claude:
+///
+/// ````markdown
+/// ```safe-bash
+/// deny ^terraform\s+destroy\b -- Run terraform destroy by hand
+/// allow ^terraform\s+destroy\s+-target=module\.scratch\b
+/// ```
+/// ````
+///
+/// They add to the config file's rules: a project allow only lifts the project's own denies.
+pub struct ProjectPolicy {
+    pub deny: Vec<CompiledPattern>,
+    pub allow: Vec<CompiledPattern>,
+    /// The files the rules were read from.
+    pub source: String,
+}
+
+impl ProjectPolicy {
+    /// Where a project deny rule comes from and how to get past it.
+    pub fn layer_note(&self) -> String {
+        format!(
+            "rule from project policy in {} — edit the rule there to change it",
+            self.source
+        )
+    }
+
+    /// Err with the rule when a project deny rule matches and no project allow rule does.
+    pub fn check(&self, cmd: &str, wrappers: &[String]) -> Result<(), RuleMatch> {
+        crate::config::check_patterns(cmd, &self.allow, &self.deny, wrappers)
+    }
+}
+
+/// Read the `safe-bash` blocks of the policy files under `root`, together with the deny and
+/// allow rules of the project config file, if any. None when there are no rules.
+pub fn load(root: &Path, config: Option<ProjectConfig>) -> Option<ProjectPolicy> {
+    let mut policy = ProjectPolicy {
+        deny: Vec::new(),
+        allow: Vec::new(),
+        source: String::new(),
+    };
+    let mut sources = Vec::new();
+    for name in POLICY_FILES {
+        let path = root.join(name);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let before = policy.deny.len() + policy.allow.len();
+        for line in blocks(&text) {
+            parse_line(line, &path, &mut policy);
+        }
+        if policy.deny.len() + policy.allow.len() > before {
+            sources.push(path.display().to_string());
+        }
+    }
+    if let Some(config) = config.filter(|c| !c.deny.is_empty() || !c.allow.is_empty()) {
+        policy.deny.extend(config.deny);
+        policy.allow.extend(config.allow);
+        sources.push(config.path.display().to_string());
+    }
+    for (i, rule) in policy
+        .deny
+        .iter_mut()
+        .filter(|r| r.id.is_empty())
+        .enumerate()
+    {
+        rule.id = format!("PRJ{:03}", i + 1);
+    }
+    policy.source = sources.join(" and ");
+    (!sources.is_empty()).then_some(policy)
+}
+
+/// The lines inside ```` ```safe-bash ```` fences.
+fn blocks(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut inside = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            inside = !inside && info.trim() == "safe-bash";
+        } else if inside {
+            lines.push(trimmed);
+        }
+    }
+    lines
+}
+
+/// `allow|deny <regex> [-- reason]`; blank lines and `#` comments are skipped, anything else
+/// is ignored with a warning.
+fn parse_line(line: &str, path: &Path, policy: &mut ProjectPolicy) {
+    if line.is_empty() || line.starts_with('#') {
+        return;
+    }
+    let (verb, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let (pattern, reason) = match rest.split_once(" -- ") {
+        Some((pattern, reason)) => (pattern.trim(), reason.trim()),
+        None => (rest.trim(), ""),
+    };
+    let rules = match verb {
+        "deny" => &mut policy.deny,
+        "allow" => &mut policy.allow,
+        _ => {
+            crate::warnings::warn(
+                "project-policy",
+                &format!(
+                    "{}: ignoring {:?}: expected allow or deny",
+                    path.display(),
+                    line
+                ),
+            );
+            return;
+        }
+    };
+    if pattern.is_empty() {
+        crate::warnings::warn(
+            "project-policy",
+            &format!("{}: ignoring {:?}: no pattern", path.display(), line),
+        );
+        return;
+    }
+    let reason = if reason.is_empty() {
+        DEFAULT_REASON
+    } else {
+        reason
+    };
+    match crate::matcher::regex(pattern) {
+        Ok(re) => rules.push(CompiledPattern {
+            id: String::new(),
+            matcher: Matcher::Regex(re),
+            reason: reason.to_string(),
+            suggestion: None,
+            severity: Severity::Deny,
+            unless: Vec::new(),
+            suppressible: false,
+            field: None,
+            schedule: None,
+            zones: None,
+            origin: Arc::new(Origin {
+                file: path.display().to_string(),
+                policy: PolicyInfo::default(),
+            }),
+        }),
+        Err(e) => crate::warnings::warn(
+            "project-policy",
+            &format!("{}: invalid regex {:?}: {}", path.display(), pattern, e),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE_MD: &str = "# Notes\n\n```sh\ndeny ^ls\n```\n\n```safe-bash\n# infra is applied \
+                             by CI\ndeny ^terraform\\s+(apply|destroy)\\b -- Terraform runs in CI \
+                             only\nallow ^terraform\\s+apply\\s+-target=module\\.scratch\\b\ndeny \
+                             \\bdeploy\\.sh\\b\nmaybe ^rm\n```\n";
+
+    #[test]
+    fn reads_only_safe_bash_blocks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("CLAUDE.md"), CLAUDE_MD).unwrap();
+        let policy = load(dir.path(), None).unwrap();
+        assert_eq!((policy.deny.len(), policy.allow.len()), (2, 1));
+        assert_eq!(policy.check("ls -la", &[]), Ok(()));
+        assert_eq!(
+            policy
+                .check("cd infra && terraform apply", &[])
+                .map_err(|m| m.reason),
+            Err("Terraform runs in CI only".to_string())
+        );
+        assert_eq!(
+            policy.check("terraform apply -target=module.scratch", &[]),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check("./deploy.sh prod", &[]).map_err(|m| m.reason),
+            Err(DEFAULT_REASON.to_string())
+        );
+        assert!(
+            policy.layer_note().contains("CLAUDE.md"),
+            "{}",
+            policy.layer_note()
+        );
+    }
+
+    #[test]
+    fn policy_md_is_read_too() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(load(dir.path(), None).is_none());
+        fs::create_dir(dir.path().join(".claude")).unwrap();
+        fs::write(
+            dir.path().join(".claude/policy.md"),
+            "```safe-bash\ndeny ^make\\s+release\\b\n```\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("CLAUDE.md"), "No rules here.\n").unwrap();
+        let policy = load(dir.path(), None).unwrap();
+        assert!(policy.check("make release", &[]).is_err());
+        assert!(policy.source.ends_with("policy.md"), "{}", policy.source);
+    }
+
+    #[test]
+    fn project_config_rules_join_the_policy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("CLAUDE.md"),
+            "```safe-bash\ndeny ^make\\s+release\\b\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(".safe-bash.json"),
+            r#"{"deny":[{"pattern":"^terraform\\s+destroy\\b","reason":"CI only"}]}"#,
+        )
+        .unwrap();
+        let policy = load(
+            dir.path(),
+            crate::config::load_project(dir.path(), dir.path()),
+        )
+        .unwrap();
+        assert!(policy.check("make release", &[]).is_err());
+        assert_eq!(
+            policy.check("terraform destroy", &[]).map_err(|m| m.reason),
+            Err("CI only".to_string())
+        );
+        assert!(
+            policy.source.contains("CLAUDE.md and ") && policy.source.ends_with(".safe-bash.json"),
+            "{}",
+            policy.source
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+use std::path::{Path, PathBuf};
+
+/// Editor, language-server and git state that commands should not touch directly. Entries
+/// ending in `/` protect everything under a directory of that name; the others protect a
+/// file with that trailing path. `unprotected_paths` in the config opts entries out.
+pub const PROTECTED_PATHS: &[&str] = &[
+    ".idea/",
+    ".vscode/settings.json",
+    ".history/",
+    ".viminfo",
+    ".local/share/nvim/shada/",
+    ".emacs.d/auto-save-list/",
+    ".git/index",
+];
+
+/// Commands that delete or move their path arguments.
+const REMOVERS: &[&str] = &["rm", "unlink", "rmdir", "shred", "mv", "trash", "trash-put"];
+
+/// Flag direct deletion, truncation or rewriting of protected editor and git state:
+/// `rm -rf .idea`, `truncate -s 0 .git/index`, `sed -i … .vscode/settings.json`, or a
+/// redirect into one of them. Editors and git rewrite these files themselves; a command
+/// "fixing" them by hand leaves the developer's workspace corrupted.
+pub fn check(cmd: &str, cwd: &Path, wrappers: &[String], unprotected: &[String]) -> Option<Hit> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let protected: Vec<&str> = PROTECTED_PATHS
+        .iter()
+        .copied()
+        .filter(|p| {
+            !unprotected
+                .iter()
+                .any(|u| u.trim_end_matches('/') == p.trim_end_matches('/'))
+        })
+        .collect();
+    let hit = |what: String, target: &str, entry: &str| {
+        let reason = format!(
+            "Editor state: {} {} touches {}, which editors and git maintain themselves",
+            what, target, entry
+        );
+        Hit {
+            id: "EST101",
+            reason,
+            category: Category::EditorState,
+        }
+    };
+
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        for redirect in &command.redirects {
+            if redirect.op.contains('>') && !redirect.op.contains('&')
+                || matches!(redirect.op.as_str(), "&>" | "&>>")
+            {
+                let target = redirect.target.value.as_str();
+                if let Some(entry) = protected_entry(target, cwd, home.as_deref(), &protected) {
+                    return Some(hit(format!("redirect {}", redirect.op), target, entry));
+                }
+            }
+        }
+
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        let Some(program) = args.first().and_then(|p| p.rsplit('/').next()) else {
+            continue;
+        };
+        let operands: Vec<&str> = match program {
+            p if REMOVERS.contains(&p) => operands(&args[1..], &[]),
+            "truncate" => operands(&args[1..], &["-s", "--size", "-r", "--reference"]),
+            "cp" | "install" => operands(&args[1..], &["-t", "--target-directory"])
+                .last()
+                .copied()
+                .into_iter()
+                .collect(),
+            "tee" => operands(&args[1..], &[]),
+            "sed" | "perl"
+                if args[1..].iter().any(|a| {
+                    a.starts_with("-i")
+                        || a.starts_with("--in-place")
+                        || (a.starts_with("-p") && a.contains('i'))
+                }) =>
+            {
+                operands(&args[1..], &["-e", "--expression", "-f", "--file"])
+            }
+            _ => continue,
+        };
+        let what = match program {
+            "sed" | "perl" => format!("{} -i", program),
+            _ => program.to_string(),
+        };
+        for target in operands {
+            if let Some(entry) = protected_entry(target, cwd, home.as_deref(), &protected) {
+                return Some(hit(what, target, entry));
+            }
+        }
+    }
+    None
+}
+
+/// Non-flag arguments, skipping the value of each option in `with_value`.
+fn operands<'a>(args: &[&'a str], with_value: &[&str]) -> Vec<&'a str> {
+    let mut out = Vec::new();
+    let mut skip = false;
+    for arg in args {
+        if std::mem::take(&mut skip) {
+            continue;
+        }
+        if with_value.contains(arg) {
+            skip = true;
+        } else if !arg.starts_with('-') {
+            out.push(*arg);
+        }
+    }
+    out
+}
+
+/// The protected entry `target` falls under, if any.
+fn protected_entry<'a>(
+    target: &str,
+    cwd: &Path,
+    home: Option<&Path>,
+    protected: &[&'a str],
+) -> Option<&'a str> {
+    let path = crate::perms::resolve(target, cwd, home);
+    protected
+        .iter()
+        .copied()
+        .find(|entry| match entry.strip_suffix('/') {
+            Some(dir) => path.ancestors().any(|a| a.ends_with(dir)),
+            None => path.ends_with(entry),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_in(cmd: &str) -> Option<Hit> {
+        check(cmd, Path::new("/work/app"), &[], &[])
+    }
+
+    #[test]
+    fn direct_edits_of_editor_state_flagged() {
+        let hit = check_in("rm -rf .idea").unwrap();
+        assert_eq!(hit.category, Category::EditorState);
+        assert_eq!(
+            hit.reason,
+            "Editor state: rm .idea touches .idea/, which editors and git maintain themselves"
+        );
+        assert!(check_in("truncate -s 0 .git/index").is_some());
+        assert!(check_in("sed -i 's/4/2/' .vscode/settings.json").is_some());
+        assert!(check_in("echo '{}' > .vscode/settings.json").is_some());
+        assert!(check_in("cp /dev/null /work/app/.git/index").is_some());
+        assert!(check_in("rm .idea/workspace.xml").is_some());
+        assert!(check(":> index", Path::new("/work/app/.git"), &[], &[]).is_some());
+    }
+
+    #[test]
+    fn reads_and_other_files_allowed() {
+        assert!(check_in("cat .vscode/settings.json").is_none());
+        assert!(check_in("rm .vscode/launch.json.bak").is_none());
+        assert!(check_in("cp .vscode/settings.json /tmp/settings.json").is_none());
+        assert!(check_in("sed -n 1p .git/index").is_none());
+        assert!(check_in("git add -A").is_none());
+        assert!(check_in("rm src/index").is_none());
+    }
+
+    #[test]
+    fn unprotected_paths_opt_out() {
+        let unprotected = [".idea".to_string()];
+        assert!(check("rm -rf .idea", Path::new("/work/app"), &[], &unprotected).is_none());
+        assert!(check("rm .git/index", Path::new("/work/app"), &[], &unprotected).is_some());
+    }
+}
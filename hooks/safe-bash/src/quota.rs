@@ -0,0 +1,259 @@
+use crate::config::CompiledQuota;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Uses older than this are dropped from the state, whatever the window.
+const MAX_AGE_SECS: u64 = 24 * 3600;
+
+/// The span a quota's `max` applies to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+    /// The last this many seconds.
+    Seconds(u64),
+    /// The current agent session (needs a `session_id` in the hook input).
+    Session,
+}
+
+impl Window {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "minute" => Some(Self::Seconds(60)),
+            "hour" => Some(Self::Seconds(3600)),
+            "day" => Some(Self::Seconds(MAX_AGE_SECS)),
+            "session" => Some(Self::Session),
+            _ => None,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Seconds(60) => "in the last minute",
+            Self::Seconds(3600) => "in the last hour",
+            Self::Seconds(_) => "in the last day",
+            Self::Session => "this session",
+        }
+    }
+}
+
+/// A quota the command would go over.
+#[derive(Debug, PartialEq)]
+pub struct Exceeded {
+    pub reason: String,
+    pub deny: bool,
+}
+
+/// Timestamps of counted runs keyed by quota name, or `<name>@<session>` for session quotas.
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-quotas.state")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Count a command the hook is about to allow against the configured quotas. Each simple
+/// command matching a quota uses one run of it; if any quota would go over its `max`, nothing is
+/// counted and the first one exceeded is returned instead.
+pub fn take(
+    state_dir: &Path,
+    quotas: &[CompiledQuota],
+    cmd: &str,
+    session: Option<&str>,
+    wrappers: &[String],
+) -> Option<Exceeded> {
+    take_at(state_dir, quotas, cmd, session, wrappers, now_secs())
+}
+
+fn take_at(
+    state_dir: &Path,
+    quotas: &[CompiledQuota],
+    cmd: &str,
+    session: Option<&str>,
+    wrappers: &[String],
+    now: u64,
+) -> Option<Exceeded> {
+    if quotas.is_empty() {
+        return None;
+    }
+    // Each simple command, as written and with wrappers (`sudo`, `timeout 60`) peeled off
+    let ast = crate::shell::parse(cmd);
+    let commands: Vec<[String; 2]> = ast
+        .simple_commands()
+        .into_iter()
+        .map(|c| {
+            let args = c.args();
+            let inner: Vec<String> = args[crate::patterns::unwrap_index(&args, wrappers)..]
+                .iter()
+                .map(|a| crate::shell::quote(a))
+                .collect();
+            [c.text.clone(), inner.join(" ")]
+        })
+        .collect();
+    let mut uses: Vec<(&CompiledQuota, String, usize)> = Vec::new();
+    for quota in quotas {
+        let runs = commands
+            .iter()
+            .filter(|forms| forms.iter().any(|f| quota.re.is_match(f)))
+            .count();
+        if runs == 0 {
+            continue;
+        }
+        let key = match (quota.window, session) {
+            (Window::Session, Some(session)) => format!("{}@{}", quota.name, session),
+            // Without a session id there is nothing to count a session quota against
+            (Window::Session, None) => continue,
+            (Window::Seconds(_), _) => quota.name.clone(),
+        };
+        uses.push((quota, key, runs));
+    }
+    if uses.is_empty() {
+        return None;
+    }
+
+    let path = state_path(state_dir);
+    let mut state: HashMap<String, Vec<u64>> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    state.retain(|_, times| {
+        times.retain(|t| now.saturating_sub(*t) < MAX_AGE_SECS);
+        !times.is_empty()
+    });
+
+    for (quota, key, runs) in &uses {
+        let used = state.get(key).map_or(0, |times| match quota.window {
+            Window::Seconds(span) => times
+                .iter()
+                .filter(|t| now.saturating_sub(**t) < span)
+                .count(),
+            Window::Session => times.len(),
+        });
+        if used + runs > quota.max as usize {
+            let reason = format!(
+                "Quota: {} has run {} of {} allowed times {}",
+                quota.name,
+                used,
+                quota.max,
+                quota.window.describe()
+            );
+            return Some(Exceeded {
+                reason,
+                deny: quota.deny,
+            });
+        }
+    }
+
+    for (_, key, runs) in uses {
+        state
+            .entry(key)
+            .or_default()
+            .extend(std::iter::repeat_n(now, runs));
+    }
+    // If the state can't be saved, runs go uncounted; the command itself is unaffected
+    let _ = fs::write(&path, serde_json::to_string(&state).unwrap_or_default());
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use tempfile::TempDir;
+
+    fn quota(pattern: &str, name: &str, max: u32, window: Window, deny: bool) -> CompiledQuota {
+        CompiledQuota {
+            re: Regex::new(pattern).unwrap(),
+            name: name.to_string(),
+            max,
+            window,
+            deny,
+        }
+    }
+
+    #[test]
+    fn runs_counted_within_the_window() {
+        let dir = TempDir::new().unwrap();
+        let quotas = [quota(
+            r"^git\s+push\b",
+            "git push",
+            2,
+            Window::Seconds(3600),
+            false,
+        )];
+        let take = |cmd: &str, now: u64| take_at(dir.path(), &quotas, cmd, None, &[], now);
+        assert_eq!(take("git push origin main", 1000), None);
+        assert_eq!(take("git status", 1001), None);
+        assert_eq!(take("timeout 60 git push origin dev", 1002), None);
+        let over = take("git push origin main", 1003).unwrap();
+        assert_eq!(
+            over.reason,
+            "Quota: git push has run 2 of 2 allowed times in the last hour"
+        );
+        assert!(!over.deny);
+        // The first run falls out of the window
+        assert_eq!(take("git push origin main", 1000 + 3600), None);
+    }
+
+    #[test]
+    fn each_matching_segment_uses_a_run() {
+        let dir = TempDir::new().unwrap();
+        let quotas = [quota(
+            r"^git\s+push\b",
+            "git push",
+            2,
+            Window::Seconds(3600),
+            true,
+        )];
+        assert!(
+            take_at(
+                dir.path(),
+                &quotas,
+                "git push a && git push b && git push c",
+                None,
+                &[],
+                1000
+            )
+            .unwrap()
+            .deny
+        );
+        // Nothing was counted for the refused command
+        assert_eq!(
+            take_at(
+                dir.path(),
+                &quotas,
+                "git push a && git push b",
+                None,
+                &[],
+                1001
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn session_quotas_are_per_session() {
+        let dir = TempDir::new().unwrap();
+        let quotas = [quota(
+            r"^terraform\s+apply\b",
+            "terraform apply",
+            1,
+            Window::Session,
+            true,
+        )];
+        let take = |session: Option<&str>, now: u64| {
+            take_at(dir.path(), &quotas, "terraform apply", session, &[], now)
+        };
+        assert_eq!(take(Some("s1"), 1000), None);
+        assert_eq!(
+            take(Some("s1"), 5000).unwrap().reason,
+            "Quota: terraform apply has run 1 of 1 allowed times this session"
+        );
+        assert_eq!(take(Some("s2"), 5001), None);
+        assert_eq!(take(None, 5002), None);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+
+/// Short ssh options that take an argument (`-p 22`, `-i key`, `-o Opt=val`).
+const SSH_OPTIONS_WITH_ARG: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// mosh options that take a separate argument unless written `--opt=value`.
+const MOSH_OPTIONS_WITH_ARG: &[&str] = &[
+    "-p",
+    "--port",
+    "--ssh",
+    "--server",
+    "--client",
+    "--predict",
+    "--family",
+    "--bind-server",
+    "--experimental-remote-ip",
+];
+
+/// A command sent to another machine.
+#[derive(Debug, PartialEq)]
+pub struct RemoteCommand<'a> {
+    pub host: &'a str,
+    /// The words after the host joined by spaces, as ssh passes them to the remote shell.
+    pub command: String,
+}
+
+/// The remote command of `ssh [options] host command...` or `mosh [options] host [--] command...`,
+/// if `args` (already unwrapped) is one. Interactive sessions without a command return None.
+pub fn parse<'a>(args: &[&'a str]) -> Option<RemoteCommand<'a>> {
+    let program = args.first()?.rsplit('/').next()?;
+    let (host, rest) = match program {
+        // OpenSSH also reads options after the host (`ssh prod -t -- ls`)
+        "ssh" => {
+            let i = skip_ssh_options(args, 1);
+            let host = *args.get(i)?;
+            (host, &args[skip_ssh_options(args, i + 1)..])
+        }
+        "mosh" => {
+            let mut i = 1;
+            while let Some(arg) = args.get(i) {
+                if *arg == "--" {
+                    i += 1;
+                    break;
+                }
+                if !arg.starts_with('-') {
+                    break;
+                }
+                i += if MOSH_OPTIONS_WITH_ARG.contains(arg) {
+                    2
+                } else {
+                    1
+                };
+            }
+            let host = *args.get(i)?;
+            let rest = &args[(i + 1).min(args.len())..];
+            (host, rest.strip_prefix(&["--"][..]).unwrap_or(rest))
+        }
+        _ => return None,
+    };
+    if rest.is_empty() {
+        return None;
+    }
+    Some(RemoteCommand {
+        host,
+        command: rest.join(" "),
+    })
+}
+
+/// Index of the first word at or after `i` that is not an ssh option or its argument.
+fn skip_ssh_options(args: &[&str], mut i: usize) -> usize {
+    while let Some(arg) = args.get(i) {
+        i += 1;
+        if *arg == "--" {
+            break;
+        }
+        let Some(flags) = arg.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            return i - 1;
+        };
+        // Flags cluster (`-tt`); an option taking an argument ends the cluster and uses its
+        // remainder (`-p22`) or the next word (`-p 22`)
+        if flags
+            .find(|c| SSH_OPTIONS_WITH_ARG.contains(c))
+            .is_some_and(|pos| pos + 1 == flags.len())
+        {
+            i += 1;
+        }
+    }
+    i.min(args.len())
+}
+
+/// Check every command sent over ssh/mosh against the hardcoded patterns, naming the host in
+/// the reason. With `escalate` (`remote_escalation` in config), a match is raised one level —
+/// audit to warn, warn to ask, ask to deny — since it lands on a machine the session cannot see or
+/// undo.
+pub fn check(
+    cmd: &str,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+    escalate: bool,
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    // Segments include commands nested in substitutions, `sh -c` scripts and other remote commands
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some(remote) = parse(&args[crate::patterns::unwrap_index(&args, wrappers)..])
+            else {
+                continue;
+            };
+            let label = |hit: Hit| Hit {
+                reason: format!("Remote on {}: {}", remote.host, hit.reason),
+                ..hit
+            };
+            let found = match crate::patterns::check_command(
+                &remote.command,
+                patterns,
+                severities,
+                wrappers,
+            ) {
+                CheckResult::Allow => CheckResult::Allow,
+                CheckResult::Audit(hit) if escalate => CheckResult::Warn(label(hit)),
+                CheckResult::Audit(hit) => CheckResult::Audit(label(hit)),
+                CheckResult::Warn(hit) if escalate => CheckResult::Ask(label(hit)),
+                CheckResult::Warn(hit) => CheckResult::Warn(label(hit)),
+                CheckResult::Ask(hit) if escalate => CheckResult::Deny(label(hit)),
+                CheckResult::Ask(hit) => CheckResult::Ask(label(hit)),
+                CheckResult::Deny(hit) => CheckResult::Deny(label(hit)),
+            };
+            result = result.or_worse(found);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+    use crate::severity::{Category, Severity};
+
+    fn remote(cmd: &str) -> Option<(String, String)> {
+        let ast = crate::shell::parse(cmd);
+        let c = ast.simple_commands().into_iter().next()?;
+        parse(&c.args()).map(|r| (r.host.to_string(), r.command))
+    }
+
+    #[test]
+    fn parses_remote_command_after_options() {
+        let expect = |host: &str, command: &str| Some((host.to_string(), command.to_string()));
+        assert_eq!(
+            remote("ssh prod 'rm -rf /var/www'"),
+            expect("prod", "rm -rf /var/www")
+        );
+        assert_eq!(
+            remote("ssh -p 2222 -i ~/.ssh/k -tt deploy@prod sudo systemctl restart app"),
+            expect("deploy@prod", "sudo systemctl restart app")
+        );
+        assert_eq!(
+            remote("ssh -p2222 -o StrictHostKeyChecking=no prod -- ls"),
+            expect("prod", "ls")
+        );
+        assert_eq!(
+            remote("mosh --ssh='ssh -p 22' prod -- rm -rf /srv"),
+            expect("prod", "rm -rf /srv")
+        );
+        assert_eq!(remote("mosh -p 60001 prod htop"), expect("prod", "htop"));
+        assert_eq!(remote("ssh prod"), None);
+        assert_eq!(remote("ssh -L 8080:localhost:80 prod"), None);
+        assert_eq!(remote("sshfs prod:/ /mnt"), None);
+    }
+
+    #[test]
+    fn remote_match_names_host() {
+        let result = check(
+            "ssh prod 'rm -rf /var/www'",
+            &hardcoded_deny_patterns(),
+            &Severities::default(),
+            &[],
+            false,
+        );
+        let CheckResult::Deny(hit) = result else {
+            panic!("{:?}", result)
+        };
+        assert_eq!(hit.reason, "Remote on prod: Destructive: rm -rf");
+        let nested = check(
+            "echo $(ssh a \"ssh b 'git reset --hard'\")",
+            &hardcoded_deny_patterns(),
+            &Severities::default(),
+            &[],
+            false,
+        );
+        assert!(matches!(nested, CheckResult::Ask(_)), "{:?}", nested);
+        assert_eq!(
+            check(
+                "ssh prod uptime",
+                &hardcoded_deny_patterns(),
+                &Severities::default(),
+                &[],
+                true
+            ),
+            CheckResult::Allow
+        );
+    }
+
+    #[test]
+    fn escalation_raises_remote_matches_one_level() {
+        let mut sev = Severities::default();
+        sev.set(Category::InPlaceEdit, Severity::Warn).unwrap();
+        sev.set(Category::GitDestructive, Severity::Ask).unwrap();
+        let patterns = hardcoded_deny_patterns();
+        let cmd = "ssh prod \"sed -i s/a/b/ /etc/app.conf\"";
+        assert!(matches!(
+            check(cmd, &patterns, &sev, &[], false),
+            CheckResult::Warn(_)
+        ));
+        assert!(matches!(
+            check(cmd, &patterns, &sev, &[], true),
+            CheckResult::Ask(_)
+        ));
+        assert!(matches!(
+            check("ssh prod git reset --hard", &patterns, &sev, &[], true),
+            CheckResult::Deny(_)
+        ));
+    }
+}
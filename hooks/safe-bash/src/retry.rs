@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Counts of a session's blocked commands idle longer than this are dropped.
+const SESSION_TTL_SECS: u64 = 24 * 3600;
+
+/// How often a command has been blocked in a session, and when last.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Count {
+    blocks: u32,
+    last: u64,
+}
+
+/// Blocked-command counts keyed by `<session>:<command fingerprint id>`.
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-retries.state")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Count one more block of the command with fingerprint id `command` in `session` and return
+/// how many there have been, this one included. Requoting or respacing the command, or
+/// adding a wrapper, does not reset the count.
+pub fn record_block(state_dir: &Path, session: &str, command: &str) -> u32 {
+    record_block_at(state_dir, session, command, now_secs())
+}
+
+fn record_block_at(state_dir: &Path, session: &str, command: &str, now: u64) -> u32 {
+    let path = state_path(state_dir);
+    let mut counts: HashMap<String, Count> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    counts.retain(|_, c| now.saturating_sub(c.last) < SESSION_TTL_SECS);
+    let key = format!("{}:{}", session, command);
+    let count = counts.entry(key).or_default();
+    count.blocks += 1;
+    count.last = now;
+    let blocks = count.blocks;
+    // If the state can't be saved, every retry looks like the first; blocking is unaffected
+    let _ = fs::write(&path, serde_json::to_string(&counts).unwrap_or_default());
+    blocks
+}
+
+/// The instruction replacing the usual block message once the same command, or an equivalent
+/// form of it, keeps coming back.
+pub fn advice(reason: &str, blocks: u32) -> String {
+    format!(
+        "Stop retrying: this command (or an equivalent form) has been blocked {} times this \
+         session and will keep being blocked ({}). Do not resubmit it or reword it to get around \
+         the rule; tell the user what you were trying to do and let them run it or grant an \
+         exception.",
+        blocks, reason
+    )
+}
+
+/// The permission prompt shown instead when `retry_loop.escalate` is set.
+pub fn escalation(reason: &str, blocks: u32) -> String {
+    format!(
+        "The agent has submitted this command {} times this session despite it being blocked \
+         ({}); it runs only if you approve it",
+        blocks, reason
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn blocks_counted_per_session_and_command() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(record_block_at(dir.path(), "s1", "rm -rf /", 1000), 1);
+        assert_eq!(record_block_at(dir.path(), "s1", "rm -rf /", 1001), 2);
+        assert_eq!(record_block_at(dir.path(), "s1", "rm -rf ~", 1002), 1);
+        assert_eq!(record_block_at(dir.path(), "s2", "rm -rf /", 1003), 1);
+        assert_eq!(record_block_at(dir.path(), "s1", "rm -rf /", 1004), 3);
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let dir = TempDir::new().unwrap();
+        record_block_at(dir.path(), "s1", "rm -rf /", 1000);
+        assert_eq!(
+            record_block_at(dir.path(), "s1", "rm -rf /", 1000 + SESSION_TTL_SECS),
+            1
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::shell::{Connector, Node};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The hardcoded rules `rewrite_rules` may name: a force push becomes `--force-with-lease`,
+/// `rm -rf`/`rm -r` prompts per file with `-i`, and a first `rsync --delete` is a `--dry-run`.
+pub const REWRITABLE: &[&str] = &["GIT001", "FS001", "FS002", "FS018"];
+
+/// First passes of a session idle longer than this are forgotten.
+const SESSION_TTL_SECS: u64 = 24 * 3600;
+
+/// The rules a rewrite of `id`'s command answers, so the rewritten command is checked without
+/// them: `rm -i -r dir` still matches the `rm -r` rule, but only deletes what the user confirms.
+pub fn covered(id: &str) -> &'static [&'static str] {
+    match id {
+        "FS001" | "FS002" => &["FS001", "FS002"],
+        "GIT001" => &["GIT001"],
+        "FS018" => &["FS018"],
+        _ => &[],
+    }
+}
+
+/// The safer form of a command rule `id` matched, if it has one. Only a single simple command
+/// without substitutions is rewritten, by editing its words in place so quoting and spacing
+/// elsewhere are kept: `git push -f origin dev` → `git push --force-with-lease origin dev`,
+/// `rm -rf build` → `rm -i -r build`, `rsync -a --delete a/ b/` → `rsync --dry-run -a --delete a/
+/// b/`.
+pub fn apply(id: &str, cmd: &str, wrappers: &[String]) -> Option<String> {
+    let Node::List(items) = crate::shell::parse(cmd) else {
+        return None;
+    };
+    let [item] = items.as_slice() else {
+        return None;
+    };
+    let Node::Simple(simple) = &item.node else {
+        return None;
+    };
+    if item.connector == Some(Connector::Background) || !simple.substitutions().is_empty() {
+        return None;
+    }
+    let spans = spans(cmd, simple)?;
+    let args = simple.args();
+    let at = crate::patterns::unwrap_index(&args, wrappers);
+    let name = args.get(at)?.rsplit('/').next()?;
+    let edits = match (id, name) {
+        ("GIT001", "git") => lease(&args, &spans, at)?,
+        ("FS001" | "FS002", "rm") => interactive(cmd, &args, &spans, at)?,
+        ("FS018", "rsync") => vec![(spans[at].end..spans[at].end, " --dry-run".to_string())],
+        _ => return None,
+    };
+    let mut out = cmd.to_string();
+    for (range, text) in edits.into_iter().rev() {
+        out.replace_range(range, &text);
+    }
+    Some(out)
+}
+
+/// Byte spans of the command's words in `cmd`, found in order after its assignments.
+fn spans(cmd: &str, simple: &crate::shell::SimpleCommand) -> Option<Vec<Range<usize>>> {
+    let mut from = 0;
+    for word in &simple.assignments {
+        from += cmd[from..].find(&word.raw)? + word.raw.len();
+    }
+    let mut out = Vec::new();
+    for word in &simple.argv {
+        let start = from + cmd[from..].find(&word.raw)?;
+        from = start + word.raw.len();
+        out.push(start..from);
+    }
+    Some(out)
+}
+
+/// `--force`/`-f` after `push` become `--force-with-lease`. Flag clusters (`-fu`) and `+refspec`
+/// force pushes are left alone, since a lease cannot be had by editing one word.
+fn lease(args: &[&str], spans: &[Range<usize>], at: usize) -> Option<Vec<(Range<usize>, String)>> {
+    let push = at + 1 + args[at + 1..].iter().position(|a| *a == "push")?;
+    let mut edits = Vec::new();
+    for (arg, span) in args.iter().zip(spans).skip(push + 1) {
+        match *arg {
+            "--" => break,
+            "--force" | "-f" => edits.push((span.clone(), "--force-with-lease".to_string())),
+            a if a.starts_with('+') => return None,
+            a if a.starts_with('-') && !a.starts_with("--") && a.contains('f') => return None,
+            _ => {}
+        }
+    }
+    (!edits.is_empty()).then_some(edits)
+}
+
+/// `-i` goes right after `rm`, and every `-f`/`--force` is taken out so it cannot override it.
+fn interactive(
+    cmd: &str,
+    args: &[&str],
+    spans: &[Range<usize>],
+    at: usize,
+) -> Option<Vec<(Range<usize>, String)>> {
+    let mut edits = vec![(spans[at].end..spans[at].end, " -i".to_string())];
+    for (arg, span) in args.iter().zip(spans).skip(at + 1) {
+        if *arg == "--" {
+            break;
+        }
+        let forced = *arg == "--force"
+            || (arg.starts_with('-') && !arg.starts_with("--") && arg.contains('f'));
+        if !forced {
+            continue;
+        }
+        if &cmd[span.clone()] != *arg {
+            return None;
+        }
+        let kept: String = arg.chars().filter(|c| *c != 'f').collect();
+        if *arg == "--force" || kept == "-" {
+            // Drop the word and the blanks before it
+            let start = cmd[..span.start].trim_end().len();
+            edits.push((start..span.end, String::new()));
+        } else {
+            edits.push((span.clone(), kept));
+        }
+    }
+    Some(edits)
+}
+
+/// Commands previewed with `--dry-run` this session, keyed by `<session>:<command fingerprint id>`.
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-rewrites.state")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether this is the first time `session` sends the command with fingerprint id `command`,
+/// remembering it if so: the first pass is previewed, the next is decided as usual.
+pub fn first_pass(state_dir: &Path, session: &str, command: &str) -> bool {
+    first_pass_at(state_dir, session, command, now_secs())
+}
+
+fn first_pass_at(state_dir: &Path, session: &str, command: &str, now: u64) -> bool {
+    let path = state_path(state_dir);
+    let mut seen: HashMap<String, u64> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    seen.retain(|_, last| now.saturating_sub(*last) < SESSION_TTL_SECS);
+    let first = seen
+        .insert(format!("{}:{}", session, command), now)
+        .is_none();
+    // If the state can't be saved, every pass looks like the first and is only ever previewed
+    let _ = fs::write(&path, serde_json::to_string(&seen).unwrap_or_default());
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn rewrite(id: &str, cmd: &str) -> Option<String> {
+        apply(id, cmd, &[])
+    }
+
+    #[test]
+    fn force_push_gets_a_lease() {
+        assert_eq!(
+            rewrite("GIT001", "git push --force origin main").as_deref(),
+            Some("git push --force-with-lease origin main")
+        );
+        assert_eq!(
+            rewrite("GIT001", "git -C app push -f").as_deref(),
+            Some("git -C app push --force-with-lease")
+        );
+        assert_eq!(
+            rewrite("GIT001", "sudo  git push  -f  origin dev").as_deref(),
+            Some("sudo  git push  --force-with-lease  origin dev")
+        );
+        assert_eq!(rewrite("GIT001", "git push -fu origin main"), None);
+        assert_eq!(rewrite("GIT001", "git push -f origin +main"), None);
+        assert_eq!(rewrite("GIT001", "git fetch && git push -f"), None);
+        assert_eq!(
+            rewrite("GIT001", "git push -f origin $(git branch --show-current)"),
+            None
+        );
+    }
+
+    #[test]
+    fn rm_prompts_instead_of_forcing() {
+        assert_eq!(
+            rewrite("FS001", "rm -rf build").as_deref(),
+            Some("rm -i -r build")
+        );
+        assert_eq!(
+            rewrite("FS001", "rm -r -f 'my dir'").as_deref(),
+            Some("rm -i -r 'my dir'")
+        );
+        assert_eq!(
+            rewrite("FS001", "rm --force -R build -- -f").as_deref(),
+            Some("rm -i -R build -- -f")
+        );
+        assert_eq!(
+            rewrite("FS002", "rm -r build").as_deref(),
+            Some("rm -i -r build")
+        );
+        assert_eq!(
+            rewrite("FS001", "FOO=1 rm -rf build > /dev/null").as_deref(),
+            Some("FOO=1 rm -i -r build > /dev/null")
+        );
+        assert_eq!(rewrite("FS001", "find . -exec rm -rf {} +"), None);
+        assert_eq!(rewrite("FS001", "rm -rf build &"), None);
+    }
+
+    #[test]
+    fn rsync_delete_is_previewed() {
+        assert_eq!(
+            rewrite("FS018", "rsync -av --delete dist/ /srv/www/").as_deref(),
+            Some("rsync --dry-run -av --delete dist/ /srv/www/")
+        );
+        assert_eq!(rewrite("FS018", "rm -rf build"), None);
+        assert_eq!(rewrite("GIT002", "git reset --hard"), None);
+    }
+
+    #[test]
+    fn rewrites_pass_without_the_rules_they_answer() {
+        let patterns = crate::patterns::hardcoded_deny_patterns();
+        let severities = crate::severity::Severities::default();
+        for (id, cmd) in [
+            ("GIT001", "git push --force origin main"),
+            ("FS001", "rm -rf build"),
+            ("FS018", "rsync -a --delete a/ b/"),
+        ] {
+            let safer = rewrite(id, cmd).unwrap();
+            let others: Vec<_> = patterns
+                .iter()
+                .filter(|p| !covered(id).contains(&p.id))
+                .cloned()
+                .collect();
+            let result = crate::patterns::check_command(&safer, &others, &severities, &[]);
+            assert_eq!(result, crate::patterns::CheckResult::Allow, "{}", safer);
+        }
+        // The preview itself is not rsync --delete
+        let result = crate::patterns::check_command(
+            "rsync --dry-run -a --delete a/ b/",
+            &patterns,
+            &severities,
+            &[],
+        );
+        assert_eq!(result, crate::patterns::CheckResult::Allow);
+    }
+
+    #[test]
+    fn only_the_first_pass_per_session() {
+        let dir = TempDir::new().unwrap();
+        assert!(first_pass_at(dir.path(), "s1", "rsync", 1000));
+        assert!(!first_pass_at(dir.path(), "s1", "rsync", 1001));
+        assert!(first_pass_at(dir.path(), "s2", "rsync", 1002));
+        assert!(first_pass_at(
+            dir.path(),
+            "s1",
+            "rsync",
+            1001 + SESSION_TTL_SECS
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::Path;
+use std::process::Command;
+
+/// A command that would undo `cmd`, built from repository state captured before it runs:
+/// the current HEAD before `git reset`/`git rebase`, the remote branch tip before a force
+/// push, the branch tip before `git branch -D`. None when there is nothing to capture.
+pub fn hint(cmd: &str, cwd: &Path, wrappers: &[String]) -> Option<String> {
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().map(|p| p.rsplit('/').next()) != Some(Some("git")) {
+            continue;
+        }
+        let Some(sub) = args
+            .iter()
+            .skip(1)
+            .position(|a| !a.starts_with('-'))
+            .map(|i| i + 1)
+        else {
+            continue;
+        };
+        let rest = &args[sub + 1..];
+        let hint = match args[sub] {
+            "reset" | "rebase" => {
+                rev_parse(cwd, "HEAD").map(|sha| format!("git reset --hard {}", sha))
+            }
+            "push" if is_force_push(rest) => force_push_hint(cwd, rest),
+            "branch" if rest.iter().any(|a| *a == "-D" || *a == "--delete") => {
+                let name = rest.iter().find(|a| !a.starts_with('-'))?;
+                rev_parse(cwd, &format!("refs/heads/{}", name))
+                    .map(|sha| format!("git branch {} {}", name, sha))
+            }
+            _ => None,
+        };
+        if hint.is_some() {
+            return hint;
+        }
+    }
+    None
+}
+
+fn is_force_push(args: &[&str]) -> bool {
+    args.iter().any(|a| {
+        *a == "-f" || a.starts_with("--force") || (!a.starts_with('-') && a.starts_with('+'))
+    })
+}
+
+/// Restore the remote branch to the tip recorded in its remote-tracking ref.
+fn force_push_hint(cwd: &Path, args: &[&str]) -> Option<String> {
+    let mut positional = args.iter().filter(|a| !a.starts_with('-'));
+    let remote = positional.next().copied().unwrap_or("origin");
+    let branch = match positional.next() {
+        Some(refspec) => {
+            let refspec = refspec.trim_start_matches('+');
+            refspec
+                .rsplit(':')
+                .next()
+                .unwrap_or(refspec)
+                .trim_start_matches("refs/heads/")
+                .to_string()
+        }
+        None => rev_parse(cwd, "--abbrev-ref HEAD")?,
+    };
+    let sha = rev_parse(cwd, &format!("refs/remotes/{}/{}", remote, branch))?;
+    Some(format!(
+        "git push --force {} {}:refs/heads/{}",
+        remote, sha, branch
+    ))
+}
+
+fn rev_parse(cwd: &Path, rev: &str) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .arg("rev-parse")
+        .args(rev.split_whitespace())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let out = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!out.is_empty()).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@t",
+                "-c",
+                "commit.gpgsign=false",
+            ])
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    /// A repo with one commit on `main` and a remote-tracking ref at the same commit.
+    fn repo() -> Option<(TempDir, String)> {
+        let dir = TempDir::new().unwrap();
+        if !git(dir.path(), &["init", "-q", "-b", "main"]) {
+            return None; // git not installed
+        }
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        assert!(git(dir.path(), &["add", "a.txt"]));
+        assert!(git(dir.path(), &["commit", "-qm", "init"]));
+        assert!(git(
+            dir.path(),
+            &["update-ref", "refs/remotes/origin/main", "HEAD"]
+        ));
+        let sha = rev_parse(dir.path(), "HEAD").unwrap();
+        Some((dir, sha))
+    }
+
+    #[test]
+    fn reset_records_current_head() {
+        let Some((dir, sha)) = repo() else { return };
+        assert_eq!(
+            hint("git reset --hard HEAD~3", dir.path(), &[]),
+            Some(format!("git reset --hard {}", sha))
+        );
+    }
+
+    #[test]
+    fn force_push_records_remote_tip() {
+        let Some((dir, sha)) = repo() else { return };
+        let expected = Some(format!("git push --force origin {}:refs/heads/main", sha));
+        assert_eq!(
+            hint("git push --force origin main", dir.path(), &[]),
+            expected
+        );
+        assert_eq!(hint("git push origin +main", dir.path(), &[]), expected);
+        assert_eq!(hint("git push -f", dir.path(), &[]), expected);
+    }
+
+    #[test]
+    fn branch_delete_records_tip() {
+        let Some((dir, sha)) = repo() else { return };
+        assert!(git(dir.path(), &["branch", "feature"]));
+        assert_eq!(
+            hint("git branch -D feature", dir.path(), &[]),
+            Some(format!("git branch feature {}", sha))
+        );
+    }
+
+    #[test]
+    fn no_hint_outside_git_or_for_other_commands() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(hint("git reset --hard", dir.path(), &[]), None);
+        assert_eq!(hint("rm -rf build", dir.path(), &[]), None);
+    }
+}
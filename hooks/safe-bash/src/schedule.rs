@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A deny rule's `schedule`: the supervised hours, outside which the rule applies.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScheduleConfig {
+    /// e.g. ["mon-fri 09:00-18:00", "sat 10:00-13:00"]; days default to every day, hours to
+    /// the whole day, and a window ending before it starts runs past midnight.
+    pub windows: Vec<String>,
+    /// "local" (default), "UTC" or a fixed offset such as "+08:00".
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// One window: the days it starts on (Monday first) and its minutes of the day.
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    days: [bool; 7],
+    start: u32,
+    end: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Zone {
+    Local,
+    /// Seconds east of UTC.
+    Fixed(i64),
+}
+
+/// A compiled `schedule`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    windows: Vec<Window>,
+    zone: Zone,
+    /// How block messages name the hours: "mon-fri 09:00-18:00 +01:00".
+    text: String,
+}
+
+impl Schedule {
+    pub fn parse(config: &ScheduleConfig) -> Result<Schedule, String> {
+        let zone = match config.timezone.as_deref() {
+            None | Some("local") => Zone::Local,
+            Some(tz) => Zone::Fixed(parse_offset(tz).ok_or_else(|| {
+                format!(
+                    "unknown timezone {:?}: use local, UTC or an offset such as +08:00",
+                    tz
+                )
+            })?),
+        };
+        if config.windows.is_empty() {
+            return Err("no windows".to_string());
+        }
+        let windows = config
+            .windows
+            .iter()
+            .map(|w| {
+                parse_window(w).ok_or_else(|| {
+                    format!("bad window {:?}: expected e.g. \"mon-fri 09:00-18:00\"", w)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let mut text = config.windows.join(", ");
+        if let Some(tz) = &config.timezone {
+            text = format!("{} {}", text, tz);
+        }
+        Ok(Schedule {
+            windows,
+            zone,
+            text,
+        })
+    }
+
+    /// Whether the rule applies now, outside every window.
+    pub fn applies_now(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        self.applies_at(now)
+    }
+
+    fn applies_at(&self, secs: i64) -> bool {
+        let (day, minute) = match self.zone {
+            Zone::Local => local_time(secs),
+            Zone::Fixed(offset) => {
+                let local = secs + offset;
+                // 1970-01-01 was a Thursday
+                (
+                    (local.div_euclid(86_400) + 3).rem_euclid(7) as usize,
+                    (local.rem_euclid(86_400) / 60) as u32,
+                )
+            }
+        };
+        !self.windows.iter().any(|w| w.contains(day, minute))
+    }
+
+    pub fn describe(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Window {
+    fn contains(&self, day: usize, minute: u32) -> bool {
+        if self.start < self.end {
+            return self.days[day] && (self.start..self.end).contains(&minute);
+        }
+        // Past midnight: the evening of a listed day or the small hours after one
+        (self.days[day] && minute >= self.start) || (self.days[(day + 6) % 7] && minute < self.end)
+    }
+}
+
+/// The weekday (Monday 0) and minute of the day at `secs` in the machine's timezone.
+fn local_time(secs: i64) -> (usize, u32) {
+    let t = secs as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        return (
+            ((secs.div_euclid(86_400) + 3).rem_euclid(7)) as usize,
+            (secs.rem_euclid(86_400) / 60) as u32,
+        );
+    }
+    (
+        ((tm.tm_wday + 6) % 7) as usize,
+        (tm.tm_hour * 60 + tm.tm_min) as u32,
+    )
+}
+
+/// "UTC", "Z", "+08:00", "-0530" or "+8" as seconds east of UTC.
+fn parse_offset(tz: &str) -> Option<i64> {
+    if tz.eq_ignore_ascii_case("utc") || tz == "Z" {
+        return Some(0);
+    }
+    let (sign, rest) = match tz.as_bytes().first()? {
+        b'+' => (1, &tz[1..]),
+        b'-' => (-1, &tz[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = if digits.len() > 2 {
+        digits.split_at(digits.len() - 2)
+    } else {
+        (digits.as_str(), "0")
+    };
+    let (hours, minutes): (i64, i64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours <= 14 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_window(window: &str) -> Option<Window> {
+    let mut days = [true; 7];
+    let mut hours = (0, 24 * 60);
+    let parts: Vec<&str> = window.split_whitespace().collect();
+    let (day_part, hour_part) = match parts[..] {
+        [one] if one.contains(':') => (None, Some(one)),
+        [one] => (Some(one), None),
+        [d, h] => (Some(d), Some(h)),
+        _ => return None,
+    };
+    if let Some(spec) = day_part {
+        days = [false; 7];
+        for item in spec.split(',') {
+            let day = |name: &str| DAYS.iter().position(|d| name.eq_ignore_ascii_case(d));
+            match item.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to) = (day(from)?, day(to)?);
+                    // Ranges wrap around the week: "fri-mon"
+                    let mut d = from;
+                    loop {
+                        days[d] = true;
+                        if d == to {
+                            break;
+                        }
+                        d = (d + 1) % 7;
+                    }
+                }
+                None => days[day(item)?] = true,
+            }
+        }
+    }
+    if let Some(spec) = hour_part {
+        let minute = |hhmm: &str| {
+            let (h, m) = hhmm.split_once(':')?;
+            let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+            (m < 60 && (h < 24 || (h == 24 && m == 0))).then_some(h * 60 + m)
+        };
+        let (from, to) = spec.split_once('-')?;
+        hours = (minute(from)?, minute(to)?);
+        if hours.0 == hours.1 || hours.0 == 24 * 60 {
+            return None;
+        }
+    }
+    Some(Window {
+        days,
+        start: hours.0,
+        end: hours.1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(windows: &[&str], timezone: Option<&str>) -> Result<Schedule, String> {
+        Schedule::parse(&ScheduleConfig {
+            windows: windows.iter().map(|w| w.to_string()).collect(),
+            timezone: timezone.map(str::to_string),
+        })
+    }
+
+    /// Seconds at `hh:mm` UTC on 2024-01-01, a Monday, plus `days`.
+    fn at(days: i64, hh: i64, mm: i64) -> i64 {
+        1_704_067_200 + days * 86_400 + hh * 3600 + mm * 60
+    }
+
+    #[test]
+    fn rules_apply_outside_the_windows() {
+        let office = schedule(&["mon-fri 09:00-18:00"], Some("UTC")).unwrap();
+        assert!(!office.applies_at(at(0, 9, 0)));
+        assert!(!office.applies_at(at(4, 17, 59)));
+        assert!(office.applies_at(at(0, 18, 0)));
+        assert!(office.applies_at(at(0, 8, 59)));
+        assert!(office.applies_at(at(5, 12, 0)), "saturday");
+
+        // The offset moves the hours: 09:00 at +08:00 is 01:00 UTC
+        let manila = schedule(&["mon-fri 09:00-18:00"], Some("+08:00")).unwrap();
+        assert!(!manila.applies_at(at(0, 1, 0)));
+        assert!(manila.applies_at(at(0, 12, 0)));
+        // Sunday 20:00 UTC is Monday 04:00 in Manila
+        assert!(manila.applies_at(at(6, 20, 0)));
+
+        let night = schedule(&["fri-sun 22:00-06:00", "wed"], Some("Z")).unwrap();
+        assert!(!night.applies_at(at(4, 23, 0)), "friday night");
+        assert!(
+            !night.applies_at(at(7, 5, 59)),
+            "the monday after sunday night"
+        );
+        assert!(
+            night.applies_at(at(4, 5, 0)),
+            "friday before dawn, after thursday"
+        );
+        assert!(
+            !night.applies_at(at(2, 0, 0)) && !night.applies_at(at(2, 23, 59)),
+            "all of wednesday"
+        );
+
+        assert_eq!(
+            schedule(&["mon-fri 09:00-18:00"], Some("-05:30"))
+                .unwrap()
+                .describe(),
+            "mon-fri 09:00-18:00 -05:30"
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read() {
+        assert!(schedule(&[], None).is_err());
+        assert!(schedule(&["weekdays 09:00-18:00"], None).is_err());
+        assert!(schedule(&["mon-fri 9-18"], None).is_err());
+        assert!(schedule(&["mon 09:00-09:00"], None).is_err());
+        assert!(schedule(&["mon-fri 09:00-18:00"], Some("Europe/Berlin"))
+            .unwrap_err()
+            .contains("unknown timezone"));
+        assert_eq!(parse_offset("+0530"), Some(19_800));
+        assert_eq!(parse_offset("-8"), Some(-28_800));
+        assert_eq!(parse_offset("+25:00"), None);
+        assert!(schedule(&["00:00-24:00"], Some("local")).is_ok());
+    }
+}
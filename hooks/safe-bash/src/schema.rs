@@ -0,0 +1,268 @@
+use serde_json::{json, Value};
+
+/// The JSON Schema of a config file (`safe-bash-hook schema`), for editors and for `lint`,
+/// which reports keys it does not list. Keep it in step with `config::PatternsConfig`.
+pub fn schema() -> Value {
+    let severities = ["off", "audit", "warn", "ask", "deny", "block"];
+    let categories: Vec<&str> = crate::severity::ALL_CATEGORIES
+        .iter()
+        .map(|c| c.as_str())
+        .collect();
+    let zones: Vec<&str> = crate::zones::ALL_ZONES.iter().map(|z| z.as_str()).collect();
+    let stages: Vec<&str> = crate::normalize::ALL_STAGES
+        .iter()
+        .map(|s| s.as_str())
+        .collect();
+    let strings = json!({"type": "array", "items": {"type": "string"}});
+    let files = json!({"oneOf": [{"type": "string"}, strings]});
+    let rule = json!({
+        "type": "object",
+        "description": "A deny or allow rule: a pattern, a command on the parsed words of each \
+         simple command, or zones its paths lie in.",
+        "properties": {
+            "id": {
+                "type": "string",
+                "description": "Cited in block messages and named by # safe-bash: allow comments; \
+                 CFG001, CFG002, … when absent, numbers that move as rules are added; suppressible \
+                 rules need one."
+            },
+            "pattern": {"type": "string"},
+            "type": {
+                "enum": ["regex", "fancy-regex", "glob", "contains", "exact", "sha256"],
+                "default": "regex"
+            },
+            "value": {
+                "type": "string",
+                "pattern": "^[0-9a-fA-F]{64}$",
+                "description": "With type sha256: the SHA-256 of the exact command (safe-bash-hook \
+                 hash)."
+            },
+            "command": {
+                "type": "string",
+                "description": "The program a command rule matches, e.g. git."
+            },
+            "subcommand": {"type": "string"},
+            "flags_any": strings,
+            "unless": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {"pattern": {"type": "string"}, "args_match": {"type": "string"}},
+                    "additionalProperties": false
+                }
+            },
+            "reason": {"type": "string"},
+            "suggestion": {
+                "type": "string",
+                "description": "What to do instead, shown under the reason when the rule blocks or \
+                 asks."
+            },
+            "locked": {
+                "type": "boolean",
+                "description": "No allow pattern can lift this deny rule."
+            },
+            "severity": {"enum": ["deny", "ask", "warn", "audit"], "default": "deny"},
+            "suppressible": {"type": "boolean"},
+            "should_block": {
+                "type": "array",
+                "items": {"type": "string"},
+                "description": "Example commands the merged policy must block (test-config)."
+            },
+            "should_allow": {
+                "type": "array",
+                "items": {"type": "string"},
+                "description": "Example commands the merged policy must let run (test-config)."
+            },
+            "tools": {
+                "type": "array",
+                "items": {"type": "string"},
+                "default": ["Bash"],
+                "description": "The tools whose calls the rule checks, e.g. Write, WebFetch or \
+                 mcp__github__*."
+            },
+            "schedule": {
+                "type": "object",
+                "description": "Supervised hours of a deny rule, which applies only outside them.",
+                "properties": {
+                    "windows": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "minItems": 1,
+                        "description": "e.g. mon-fri 09:00-18:00"
+                    },
+                    "timezone": {
+                        "type": "string",
+                        "default": "local",
+                        "description": "local, UTC or an offset such as +08:00."
+                    }
+                },
+                "required": ["windows"],
+                "additionalProperties": false
+            },
+            "field": {
+                "type": "string",
+                "description": "JSON path into another tool's input whose values the rule matches, \
+                 e.g. $.files[*].path."
+            },
+            "zones": {
+                "type": "array",
+                "items": {"enum": zones},
+                "description": "Where a Bash rule's paths lie: any of them for a deny rule, every \
+                 one for an allow rule."
+            },
+            "access": {
+                "enum": ["read", "write", "delete"],
+                "description": "The paths zones looks at; all of them when absent."
+            }
+        },
+        "required": ["reason"],
+        "not": {"required": ["pattern", "command"]},
+        "additionalProperties": false
+    });
+    let quota = json!({
+        "type": "object",
+        "properties": {
+            "pattern": {"type": "string"},
+            "name": {"type": "string"},
+            "max": {"type": "integer", "minimum": 0},
+            "per": {"enum": ["minute", "hour", "day", "session"], "default": "hour"},
+            "exceed": {"enum": ["ask", "deny"], "default": "ask"}
+        },
+        "required": ["pattern", "name", "max"],
+        "additionalProperties": false
+    });
+    let tools = json!({
+        "type": "object",
+        "description": "Deny and allow rules for a tool other than Bash, or every tool a name glob \
+         such as mcp__* matches, matched against the path or URL a call touches or its input.",
+        "additionalProperties": {
+            "type": "object",
+            "properties": {
+                "deny": {"type": "array", "items": rule},
+                "allow": {"type": "array", "items": rule}
+            },
+            "additionalProperties": false
+        }
+    });
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "safe-bash-hook config",
+        "type": "object",
+        "properties": {
+            "$schema": {"type": "string"},
+            "version": {"type": "integer", "minimum": 0},
+            "extends": files,
+            "include": files,
+            "locked": {
+                "type": "array",
+                "items": {"type": "string"},
+                "description": "Settings later layers may not change."
+            },
+            "paths": {
+                "type": "object",
+                "description": "Settings merged on top when the hook's cwd is in a tree, keyed by \
+                 directory glob.",
+                "additionalProperties": {"type": "object"}
+            },
+            "roles": {
+                "type": "object",
+                "description": "Settings merged on top for the roles of whoever runs the hook, \
+                 keyed by role name.",
+                "additionalProperties": {"type": "object"}
+            },
+            "identity": {
+                "type": "object",
+                "description": "Take roles only from a signed identity file instead of \
+                 SAFE_BASH_ROLE.",
+                "properties": {"file": {"type": "string"}, "public_key": {"type": "string"}},
+                "required": ["file", "public_key"],
+                "additionalProperties": false
+            },
+            "deny": {"type": "array", "items": rule},
+            "allow": {"type": "array", "items": rule},
+            "tools": tools,
+            "root_escalation": {"type": "boolean", "default": true},
+            "profile": {"enum": ["strict", "standard", "permissive"], "default": "standard"},
+            "mode": {
+                "enum": ["enforce", "report"],
+                "default": "enforce",
+                "description": "report: never block or ask, only warn and audit what would have \
+                 been decided."
+            },
+            "category_severity": {
+                "type": "object",
+                "propertyNames": {"enum": categories},
+                "additionalProperties": {"enum": severities}
+            },
+            "rule_severity": {"type": "object", "additionalProperties": {"enum": severities}},
+            "suppressible_rules": strings,
+            "rewrite_rules": {"type": "array", "items": {"enum": crate::rewrite::REWRITABLE}},
+            "policy": {
+                "type": "object",
+                "properties": {"name": {"type": "string"}, "contact": {"type": "string"}},
+                "additionalProperties": false
+            },
+            "wrappers": strings,
+            "rollback_hints": {"type": "boolean"},
+            "alert_webhook": {
+                "type": "object",
+                "properties": {
+                    "url": {"type": "string"},
+                    "decisions": {
+                        "type": "array",
+                        "items": {"enum": ["deny", "ask", "warn", "audit"]},
+                        "default": ["deny"]
+                    }
+                },
+                "required": ["url"],
+                "additionalProperties": false
+            },
+            "glob_delete_threshold": {"type": "integer", "minimum": 0},
+            "audit_retention": {
+                "type": "object",
+                "properties": {
+                    "max_age_days": {"type": "integer", "minimum": 0, "default": 90},
+                    "max_bytes": {"type": "integer", "minimum": 0, "default": 10485760}
+                },
+                "additionalProperties": false
+            },
+            "remote_escalation": {"type": "boolean"},
+            "unprotected_paths": strings,
+            "protected_paths": strings,
+            "protected_branches": strings,
+            "artifact_dirs": strings,
+            "suggest_safe_prefix": {"type": "boolean"},
+            "trash_deletes": {"type": "boolean"},
+            "sed_backups": {
+                "type": "object",
+                "properties": {
+                    "enabled": {"type": "boolean"},
+                    "max_age_days": {"type": "integer", "minimum": 0}
+                },
+                "additionalProperties": false
+            },
+            "snapshots": {"type": "boolean"},
+            "retry_loop": {
+                "type": "object",
+                "properties": {
+                    "max_repeats": {"type": "integer", "minimum": 0, "default": 3},
+                    "escalate": {"type": "boolean"}
+                },
+                "additionalProperties": false
+            },
+            "quotas": {"type": "array", "items": quota},
+            "source_pinning": {"enum": ["warn", "deny"], "default": "warn"},
+            "ask_unresolved_scripts": {"type": "boolean"},
+            "inspect_makefiles": {"type": "boolean"},
+            "check_descriptions": {"type": "boolean", "default": true},
+            "normalization": {
+                "type": "object",
+                "propertyNames": {"enum": stages},
+                "additionalProperties": {"type": "boolean"}
+            },
+            "session_summary": {"type": "boolean"},
+            "regex_engine": {"enum": ["regex", "fancy"], "default": "regex"}
+        },
+        "additionalProperties": false
+    })
+}
@@ -0,0 +1,207 @@
+use crate::patterns::{CheckResult, Hit};
+use crate::severity::{Category, Severities};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What an `rm -r` of relative globs would delete, counted on disk.
+#[derive(Debug, PartialEq)]
+struct Scope {
+    /// The globs, as written.
+    targets: Vec<String>,
+    /// Files and directories under the expanded globs, counted up to `threshold + 1`.
+    entries: usize,
+}
+
+/// Decide an `rm -r` of relative globs by how much it deletes: over `threshold` entries it
+/// is weighed like the generic rm rules (blocked by default), within it only warns.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    wrappers: &[String],
+    threshold: usize,
+    severities: &Severities,
+) -> Option<CheckResult> {
+    let scope = estimate(cmd, cwd, wrappers, threshold)?;
+    let targets = scope.targets.join(" ");
+    Some(if scope.entries > threshold {
+        let reason = format!(
+            "Destructive: rm -r {} deletes more than {} files and directories \
+             (glob_delete_threshold)",
+            targets, threshold
+        );
+        CheckResult::weigh(
+            Hit {
+                id: "FS101",
+                reason,
+                category: Category::FileDestructive,
+            },
+            severities,
+        )
+    } else {
+        let reason = format!(
+            "rm -r {} deletes {} files and directories (within glob_delete_threshold {})",
+            targets, scope.entries, threshold
+        );
+        CheckResult::Warn(Hit {
+            id: "FS101",
+            reason,
+            category: Category::FileDestructive,
+        })
+    })
+}
+
+/// Expand the globs of a command made only of `rm -r` calls on relative globs
+/// (`rm -r build/* tmp/*`) against `cwd` and count every file and directory they would
+/// delete, stopping once the count passes `threshold`. None when any part of the command
+/// is something else — a plain path, an absolute or `~`/`$`/`..` path, another command —
+/// so those keep the generic rm rules.
+fn estimate(cmd: &str, cwd: &Path, wrappers: &[String], threshold: usize) -> Option<Scope> {
+    let ast = crate::shell::parse(cmd);
+    let mut targets = Vec::new();
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().and_then(|p| p.rsplit('/').next()) != Some("rm") {
+            return None;
+        }
+        let mut options_done = false;
+        let mut recursive = false;
+        let mut globs = Vec::new();
+        for arg in &args[1..] {
+            if !options_done && arg.starts_with('-') {
+                options_done = *arg == "--";
+                recursive |=
+                    *arg == "--recursive" || (!arg.starts_with("--") && arg.contains(['r', 'R']));
+            } else if is_relative_glob(arg) {
+                globs.push(arg.to_string());
+            } else {
+                return None;
+            }
+        }
+        if !recursive || globs.is_empty() {
+            return None;
+        }
+        targets.extend(globs);
+    }
+    if targets.is_empty() {
+        return None;
+    }
+
+    let roots: HashSet<PathBuf> = targets
+        .iter()
+        .flat_map(|t| crate::blast::glob_paths(cwd, t))
+        .collect();
+    let mut entries = 0;
+    for root in roots {
+        entries += count_tree(&root, threshold + 1 - entries);
+        if entries > threshold {
+            break;
+        }
+    }
+    Some(Scope { targets, entries })
+}
+
+fn is_relative_glob(arg: &str) -> bool {
+    arg.contains(['*', '?', '['])
+        && !arg.starts_with(['/', '~', '$', '`'])
+        && !arg.split('/').any(|c| c == "..")
+}
+
+/// `path` and everything under it, without following symlinks, up to `limit` entries.
+pub fn count_tree(path: &Path, limit: usize) -> usize {
+    let mut count = 0;
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(p) = stack.pop() {
+        count += 1;
+        if count >= limit {
+            break;
+        }
+        if p.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+            if let Ok(entries) = fs::read_dir(&p) {
+                stack.extend(entries.flatten().map(|e| e.path()));
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for d in ["build/obj", "tmp", "src"] {
+            fs::create_dir_all(dir.path().join(d)).unwrap();
+        }
+        for f in [
+            "build/a.o",
+            "build/obj/b.o",
+            "build/obj/c.o",
+            "tmp/x",
+            "src/main.rs",
+        ] {
+            fs::write(dir.path().join(f), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn counts_everything_under_expanded_globs() {
+        let dir = tree();
+        let scope = estimate("rm -r build/* tmp/*", dir.path(), &[], 100).unwrap();
+        // build/a.o, build/obj, build/obj/b.o, build/obj/c.o, tmp/x
+        assert_eq!(scope.entries, 5);
+        assert_eq!(scope.targets, ["build/*", "tmp/*"]);
+        let result = check(
+            "rm -r build/* tmp/*",
+            dir.path(),
+            &[],
+            100,
+            &Severities::default(),
+        );
+        let Some(CheckResult::Warn(hit)) = result else {
+            panic!("{:?}", result)
+        };
+        assert_eq!(
+            hit.reason,
+            "rm -r build/* tmp/* deletes 5 files and directories (within glob_delete_threshold \
+             100)"
+        );
+    }
+
+    #[test]
+    fn stops_counting_past_threshold() {
+        let dir = tree();
+        let scope = estimate("rm -rf ./*", dir.path(), &[], 3).unwrap();
+        assert_eq!(scope.entries, 4);
+        let result = check("rm -rf ./*", dir.path(), &[], 3, &Severities::default());
+        let Some(CheckResult::Deny(hit)) = result else {
+            panic!("{:?}", result)
+        };
+        assert!(
+            hit.reason
+                .contains("deletes more than 3 files and directories"),
+            "{}",
+            hit.reason
+        );
+    }
+
+    #[test]
+    fn other_commands_and_paths_keep_generic_rules() {
+        let dir = tree();
+        for cmd in [
+            "rm -rf build",
+            "rm -rf /tmp/*",
+            "rm -rf ../*",
+            "rm -rf $OUT/*",
+            "rm build/*",
+            "rm -rf build/* && make",
+            "rm -rf ~/*",
+        ] {
+            assert_eq!(estimate(cmd, dir.path(), &[], 100), None, "{}", cmd);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use crate::patterns::{CheckResult, DenyPattern, Hit};
+use crate::severity::Severities;
+use std::path::{Path, PathBuf};
+
+/// How deep `npm run a` → `npm run b` → … is followed.
+const MAX_DEPTH: usize = 4;
+
+/// yarn and pnpm commands that are not scripts: `yarn add`, `pnpm install`.
+const BUILTINS: &[&str] = &[
+    "add",
+    "audit",
+    "bin",
+    "cache",
+    "config",
+    "create",
+    "dedupe",
+    "dlx",
+    "exec",
+    "explain",
+    "global",
+    "help",
+    "import",
+    "info",
+    "init",
+    "install",
+    "i",
+    "licenses",
+    "link",
+    "list",
+    "ls",
+    "login",
+    "logout",
+    "node",
+    "outdated",
+    "owner",
+    "pack",
+    "patch",
+    "plugin",
+    "prune",
+    "publish",
+    "rebuild",
+    "remove",
+    "rm",
+    "set",
+    "store",
+    "tag",
+    "team",
+    "unlink",
+    "up",
+    "update",
+    "upgrade",
+    "version",
+    "versions",
+    "why",
+    "workspace",
+    "workspaces",
+];
+
+/// A package.json script a command runs.
+#[derive(Debug, PartialEq)]
+pub struct ScriptRun {
+    /// `npm`, `yarn` or `pnpm`.
+    pub manager: String,
+    pub name: String,
+}
+
+/// The script a simple command runs: `npm run clean`, `npm test`, `yarn run build`,
+/// `yarn build`, `pnpm lint`. Options before the script name are skipped.
+pub fn script_run(args: &[&str]) -> Option<ScriptRun> {
+    let manager = args.first()?.rsplit('/').next()?;
+    let mut operands = args[1..].iter().filter(|a| !a.starts_with('-'));
+    let first = *operands.next()?;
+    let name = match (manager, first) {
+        ("npm", "run" | "run-script" | "rum" | "urn") => *operands.next()?,
+        ("npm", "test" | "t" | "tst" | "start" | "stop" | "restart") => match first {
+            "t" | "tst" => "test",
+            other => other,
+        },
+        ("yarn" | "pnpm", "run" | "run-script") => *operands.next()?,
+        ("yarn" | "pnpm", other) if !BUILTINS.contains(&other) => other,
+        _ => return None,
+    };
+    Some(ScriptRun {
+        manager: manager.to_string(),
+        name: name.to_string(),
+    })
+}
+
+/// The nearest package.json at or above `cwd`, as npm finds it.
+fn package_json(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|d| d.join("package.json"))
+        .find(|p| p.is_file())
+}
+
+/// The scripts in a package.json.
+fn scripts(path: &Path) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    v.get("scripts")?.as_object().cloned()
+}
+
+/// The script bodies a run executes, in order: npm also runs `pre<name>` and `post<name>`.
+fn bodies(
+    run: &ScriptRun,
+    scripts: &serde_json::Map<String, serde_json::Value>,
+) -> Option<Vec<(String, String)>> {
+    let body = |name: &str| {
+        scripts
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(|b| (name.to_string(), b.to_string()))
+    };
+    let main = body(&run.name)?;
+    if run.manager != "npm" {
+        return Some(vec![main]);
+    }
+    let pre = body(&format!("pre{}", run.name));
+    let post = body(&format!("post{}", run.name));
+    Some(pre.into_iter().chain(Some(main)).chain(post).collect())
+}
+
+/// Check the package.json scripts that `npm run`/`yarn`/`pnpm` invocations resolve to against
+/// the hardcoded patterns and the root-wildcard rule, following scripts that run other
+/// scripts. The reason names the script and its body.
+pub fn check(
+    cmd: &str,
+    cwd: &Path,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+) -> CheckResult {
+    let Some(scripts) = package_json(cwd).and_then(|p| scripts(&p)) else {
+        return CheckResult::Allow;
+    };
+    check_in(cmd, cwd, &scripts, patterns, severities, wrappers, 0)
+}
+
+fn check_in(
+    cmd: &str,
+    cwd: &Path,
+    scripts: &serde_json::Map<String, serde_json::Value>,
+    patterns: &[DenyPattern],
+    severities: &Severities,
+    wrappers: &[String],
+    depth: usize,
+) -> CheckResult {
+    let mut result = CheckResult::Allow;
+    if depth >= MAX_DEPTH {
+        return result;
+    }
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some(run) = script_run(&args[crate::patterns::unwrap_index(&args, wrappers)..])
+            else {
+                continue;
+            };
+            for (name, body) in bodies(&run, scripts).unwrap_or_default() {
+                let label = |hit: Hit| Hit {
+                    reason: format!(
+                        "{} script {:?} runs `{}`: {}",
+                        run.manager, name, body, hit.reason
+                    ),
+                    ..hit
+                };
+                if let Some(hit) = crate::wildcard::check(&body, cwd, wrappers) {
+                    return CheckResult::Deny(label(hit));
+                }
+                let direct = crate::patterns::check_command(&body, patterns, severities, wrappers);
+                let nested = check_in(
+                    &body,
+                    cwd,
+                    scripts,
+                    patterns,
+                    severities,
+                    wrappers,
+                    depth + 1,
+                );
+                let found = direct.or_worse(nested).relabel(label);
+                result = result.or_worse(found);
+            }
+        }
+    }
+    result
+}
+
+/// The first script run in `cmd` whose body cannot be read — no package.json above `cwd`, or
+/// no such script in it — for `ask_unresolved_scripts`.
+pub fn unresolved(cmd: &str, cwd: &Path, wrappers: &[String]) -> Option<String> {
+    let package = package_json(cwd);
+    let scripts = package.as_deref().and_then(scripts).unwrap_or_default();
+    for segment in crate::patterns::split_command(cmd, wrappers) {
+        let ast = crate::shell::parse(segment.trim_start_matches("| "));
+        for c in ast.simple_commands() {
+            let args = c.args();
+            let Some(run) = script_run(&args[crate::patterns::unwrap_index(&args, wrappers)..])
+            else {
+                continue;
+            };
+            if scripts.get(&run.name).and_then(|v| v.as_str()).is_none() {
+                let place = match &package {
+                    Some(p) => format!("not defined in {}", p.display()),
+                    None => format!("no package.json at or above {}", cwd.display()),
+                };
+                return Some(format!(
+                    "Cannot check {} script {:?}: {}",
+                    run.manager, run.name, place
+                ));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::hardcoded_deny_patterns;
+    use crate::severity::Category;
+
+    fn run(cmd: &str) -> Option<(String, String)> {
+        let ast = crate::shell::parse(cmd);
+        let c = ast.simple_commands().into_iter().next().unwrap();
+        script_run(&c.args()).map(|r| (r.manager, r.name))
+    }
+
+    #[test]
+    fn finds_script_runs() {
+        let pair = |m: &str, n: &str| Some((m.to_string(), n.to_string()));
+        assert_eq!(run("npm run clean"), pair("npm", "clean"));
+        assert_eq!(
+            run("npm --silent run-script build -- --watch"),
+            pair("npm", "build")
+        );
+        assert_eq!(run("npm t"), pair("npm", "test"));
+        assert_eq!(run("yarn run lint"), pair("yarn", "lint"));
+        assert_eq!(run("yarn build"), pair("yarn", "build"));
+        assert_eq!(run("pnpm -r deploy"), pair("pnpm", "deploy"));
+        assert_eq!(run("npm install"), None);
+        assert_eq!(run("yarn add -D typescript"), None);
+        assert_eq!(run("yarn"), None);
+    }
+
+    #[test]
+    fn script_bodies_checked_with_script_in_reason() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let sub = dir.path().join("src");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts":{"clean":"rm -rf dist","reset":"npm run clean && git reset --hard",
+                "build":"tsc","prebuild":"sed -i s/x/y/ tsconfig.json","nuke":"rm -rf /"}}"#,
+        )
+        .unwrap();
+        let check_in = |cmd: &str| {
+            check(
+                cmd,
+                &sub,
+                &hardcoded_deny_patterns(),
+                &Severities::default(),
+                &[],
+            )
+        };
+        let CheckResult::Deny(hit) = check_in("npm run clean") else {
+            panic!()
+        };
+        assert_eq!(
+            hit.reason,
+            "npm script \"clean\" runs `rm -rf dist`: Destructive: rm -rf"
+        );
+        let CheckResult::Deny(hit) = check_in("yarn reset") else {
+            panic!()
+        };
+        assert!(
+            hit.reason
+                .starts_with("yarn script \"reset\" runs `npm run clean && git reset --hard`: "),
+            "{}",
+            hit.reason
+        );
+        let CheckResult::Ask(hit) = check_in("npm run build") else {
+            panic!()
+        };
+        assert!(
+            hit.reason.starts_with("npm script \"prebuild\" runs"),
+            "{}",
+            hit.reason
+        );
+        assert_eq!(check_in("pnpm build"), CheckResult::Allow);
+        let CheckResult::Deny(hit) = check_in("timeout 60 npm run nuke") else {
+            panic!()
+        };
+        assert_eq!(hit.category, Category::RootWildcard);
+        assert_eq!(check_in("npm run missing"), CheckResult::Allow);
+
+        assert_eq!(unresolved("npm run build", &sub, &[]), None);
+        let reason = unresolved("npm test", &sub, &[]).unwrap();
+        assert!(
+            reason.starts_with("Cannot check npm script \"test\": not defined in "),
+            "{}",
+            reason
+        );
+        assert!(unresolved("npm test", Path::new("/"), &[])
+            .unwrap()
+            .contains("no package.json at or above /"));
+    }
+}
@@ -0,0 +1,344 @@
+use crate::shell::{Node, SimpleCommand};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Appended to each edited file's name for its backup.
+pub const SUFFIX: &str = ".safe-bash.bak";
+
+/// The rules a backed-up `sed -i` answers, so the rewritten command is decided without them.
+pub const COVERED: &[&str] = &["IPE001"];
+
+/// Which sed's option syntax to write: GNU's `-i` takes an attached suffix or none, BSD's takes
+/// the next word (`-i ''` for none). `-i<suffix>` means the same to both.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flavor {
+    Gnu,
+    Bsd,
+}
+
+impl Flavor {
+    /// The sed on this platform: BSD's on macOS and the BSDs, GNU's elsewhere.
+    pub fn host() -> Self {
+        if cfg!(any(
+            target_os = "macos",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        )) {
+            Flavor::Bsd
+        } else {
+            Flavor::Gnu
+        }
+    }
+}
+
+/// A command whose in-place seds now keep backups, and where the backups go.
+#[derive(Debug, PartialEq)]
+pub struct Backed {
+    pub command: String,
+    /// One per file operand, absolute; globs stay globs (`/work/src/*.rs.safe-bash.bak`).
+    pub backups: Vec<String>,
+}
+
+/// What one sed call does to its files.
+enum InPlace {
+    No,
+    /// Already keeps a backup under a suffix of its own.
+    WithBackup,
+    /// The edits that give it one, and its file operands.
+    Bare(Vec<(Range<usize>, String)>, Vec<String>),
+}
+
+/// `command` with every in-place sed that keeps no backup given one
+/// (`sed -i 's/a/b/' f` → `sed -i.safe-bash.bak 's/a/b/' f`, BSD's `sed -i '' …` likewise,
+/// `--in-place` → `--in-place=.safe-bash.bak`), editing only those words. None when there is
+/// nothing to back up, or an in-place sed it cannot rewrite (inside a subshell, group or
+/// function, or with options it cannot follow).
+pub fn rewrite(command: &str, cwd: &Path, wrappers: &[String], flavor: Flavor) -> Option<Backed> {
+    let Node::List(items) = crate::shell::parse(command) else {
+        return None;
+    };
+    let mut edits = Vec::new();
+    let mut backups = Vec::new();
+    for item in &items {
+        let Node::Simple(simple) = &item.node else {
+            let nested = item.node.simple_commands();
+            if nested.iter().any(|c| {
+                matches!(
+                    in_place(c, command, 0, wrappers, flavor),
+                    Some(InPlace::Bare(..)) | None
+                )
+            }) {
+                return None;
+            }
+            continue;
+        };
+        match in_place(simple, command, item.start, wrappers, flavor)? {
+            InPlace::No | InPlace::WithBackup => {}
+            InPlace::Bare(found, files) => {
+                edits.extend(found);
+                backups.extend(files.iter().map(|f| {
+                    format!(
+                        "{}{}",
+                        crate::perms::resolve(f, cwd, None).display(),
+                        SUFFIX
+                    )
+                }));
+            }
+        }
+    }
+    if edits.is_empty() {
+        return None;
+    }
+    let mut out = command.to_string();
+    for (range, text) in edits.into_iter().rev() {
+        out.replace_range(range, &text);
+    }
+    Some(Backed {
+        command: out,
+        backups,
+    })
+}
+
+/// How a simple command found at or after byte `from` of `command` edits in place. None for a
+/// sed whose options it cannot follow.
+fn in_place(
+    simple: &SimpleCommand,
+    command: &str,
+    from: usize,
+    wrappers: &[String],
+    flavor: Flavor,
+) -> Option<InPlace> {
+    let args = simple.args();
+    let at = crate::patterns::unwrap_index(&args, wrappers);
+    if args.get(at).and_then(|p| p.rsplit('/').next()) != Some("sed") {
+        return Some(InPlace::No);
+    }
+    // Spans of the words, found in order
+    let mut spans = Vec::new();
+    let mut cursor = from;
+    for word in simple.assignments.iter().chain(&simple.argv) {
+        let start = cursor + command.get(cursor..)?.find(&word.raw)?;
+        cursor = start + word.raw.len();
+        spans.push(start..cursor);
+    }
+    let spans = &spans[simple.assignments.len()..];
+
+    let (mut edits, mut operands) = (Vec::new(), Vec::new());
+    let (mut in_place, mut backed, mut script_given, mut options_done) =
+        (false, false, false, false);
+    let mut i = at + 1;
+    while i < args.len() {
+        let (arg, span) = (args[i], spans[i].clone());
+        i += 1;
+        if options_done || !arg.starts_with('-') || arg == "-" {
+            operands.push(arg.to_string());
+            continue;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            match long.split_once('=') {
+                _ if long.is_empty() => options_done = true,
+                None if long == "in-place" => {
+                    in_place = true;
+                    edits.push((span, format!("--in-place={}", SUFFIX)));
+                }
+                Some(("in-place", "")) => {
+                    in_place = true;
+                    edits.push((span, format!("--in-place={}", SUFFIX)));
+                }
+                Some(("in-place", _)) => (in_place, backed) = (true, true),
+                None if long == "expression" || long == "file" => (script_given, i) = (true, i + 1),
+                Some(("expression" | "file", _)) => script_given = true,
+                _ => {}
+            }
+            continue;
+        }
+        // A cluster: the first option taking a value takes the rest of the word, or the next one
+        for (k, c) in arg.char_indices().skip(1) {
+            let rest = &arg[k + c.len_utf8()..];
+            match c {
+                'i' | 'I' if c == 'i' || flavor == Flavor::Bsd => {
+                    in_place = true;
+                    if !rest.is_empty() {
+                        backed = true;
+                    } else if flavor == Flavor::Gnu {
+                        edits.push((span.end..span.end, SUFFIX.to_string()));
+                    } else if args.get(i) == Some(&"") {
+                        // BSD's `-i ''`: the empty suffix word goes, the suffix is attached instead
+                        edits.push((span.start..spans[i].end, format!("{}{}", arg, SUFFIX)));
+                        i += 1;
+                    } else {
+                        (backed, i) = (true, i + 1);
+                    }
+                    break;
+                }
+                'e' | 'f' | 'l' => {
+                    script_given |= c != 'l';
+                    if rest.is_empty() {
+                        i += 1;
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+    if !in_place {
+        return Some(InPlace::No);
+    }
+    if backed {
+        return Some(InPlace::WithBackup);
+    }
+    let files = if script_given {
+        operands
+    } else {
+        operands.into_iter().skip(1).collect()
+    };
+    Some(InPlace::Bare(edits, files))
+}
+
+/// Backups handed out, by path (or glob), with when.
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-sed-backups.state")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Remember the backups an edit is about to make and delete those of edits older than
+/// `max_age_days`. A file edited again since keeps its backup until that edit ages out.
+pub fn track(state_dir: &Path, backups: &[String], max_age_days: u64) {
+    track_at(state_dir, backups, max_age_days, now_secs());
+}
+
+fn track_at(state_dir: &Path, backups: &[String], max_age_days: u64, now: u64) {
+    let path = state_path(state_dir);
+    let mut made: HashMap<String, u64> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    made.retain(|backup, ts| {
+        let expired = now.saturating_sub(*ts) > max_age_days * 24 * 3600;
+        if expired {
+            remove_backups(Path::new(backup));
+        }
+        !expired
+    });
+    made.extend(backups.iter().map(|b| (b.clone(), now)));
+    if let Err(e) = fs::write(&path, serde_json::to_string(&made).unwrap_or_default()) {
+        crate::warnings::warn(
+            "sed-backups",
+            &format!("could not write {}: {}", path.display(), e),
+        );
+    }
+}
+
+/// Delete the backup files a recorded path (or glob in its last component) names.
+fn remove_backups(backup: &Path) {
+    let (Some(dir), Some(pattern)) = (backup.parent(), backup.file_name().and_then(|n| n.to_str()))
+    else {
+        return;
+    };
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(SUFFIX) && crate::blast::glob_match(pattern, &name) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn backed(command: &str, flavor: Flavor) -> Option<(String, Vec<String>)> {
+        rewrite(command, Path::new("/work"), &[], flavor).map(|b| (b.command, b.backups))
+    }
+
+    fn gnu(command: &str) -> Option<String> {
+        backed(command, Flavor::Gnu).map(|(c, _)| c)
+    }
+
+    #[test]
+    fn gnu_in_place_edits_get_a_suffix() {
+        assert_eq!(
+            backed("sed -i 's/a/b/' f.txt src/*.rs", Flavor::Gnu),
+            Some((
+                "sed -i.safe-bash.bak 's/a/b/' f.txt src/*.rs".to_string(),
+                vec![
+                    "/work/f.txt.safe-bash.bak".to_string(),
+                    "/work/src/*.rs.safe-bash.bak".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            gnu("sed -Ei -e s/a/b/ -e s/c/d/ f").as_deref(),
+            Some("sed -Ei.safe-bash.bak -e s/a/b/ -e s/c/d/ f")
+        );
+        assert_eq!(
+            gnu("sed --in-place -n p f").as_deref(),
+            Some("sed --in-place=.safe-bash.bak -n p f")
+        );
+        assert_eq!(
+            gnu("cd src && sed -i s/a/b/ f && git diff").as_deref(),
+            Some("cd src && sed -i.safe-bash.bak s/a/b/ f && git diff")
+        );
+        assert_eq!(
+            backed("sed -i -f fix.sed f", Flavor::Gnu).unwrap().1,
+            ["/work/f.safe-bash.bak"]
+        );
+    }
+
+    #[test]
+    fn bsd_empty_suffix_replaced() {
+        assert_eq!(
+            backed("sed -i '' 's/a/b/' f", Flavor::Bsd),
+            Some((
+                "sed -i.safe-bash.bak 's/a/b/' f".to_string(),
+                vec!["/work/f.safe-bash.bak".to_string()]
+            ))
+        );
+        assert_eq!(backed("sed -i.orig s/a/b/ f", Flavor::Bsd), None);
+        assert_eq!(backed("sed -i .orig s/a/b/ f", Flavor::Bsd), None);
+    }
+
+    #[test]
+    fn nothing_to_back_up_or_out_of_reach() {
+        assert_eq!(gnu("sed -i.orig s/a/b/ f"), None);
+        assert_eq!(gnu("sed --in-place=.orig s/a/b/ f"), None);
+        assert_eq!(gnu("sed -n p f"), None);
+        assert_eq!(gnu("(sed -i s/a/b/ f)"), None);
+        assert_eq!(gnu("ls; (sed -n p f)").as_deref(), None);
+    }
+
+    #[test]
+    fn expired_backups_removed() {
+        let (state, work) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let pattern = format!("{}/*.rs{}", work.path().display(), SUFFIX);
+        for name in [
+            "a.rs.safe-bash.bak",
+            "b.rs.safe-bash.bak",
+            "a.rs",
+            "c.txt.safe-bash.bak",
+        ] {
+            fs::write(work.path().join(name), "x").unwrap();
+        }
+        track_at(state.path(), std::slice::from_ref(&pattern), 1, 1000);
+        track_at(state.path(), &[], 1, 1000 + 24 * 3600);
+        assert!(work.path().join("a.rs.safe-bash.bak").exists());
+        track_at(state.path(), &[], 1, 1001 + 24 * 3600);
+        assert!(!work.path().join("a.rs.safe-bash.bak").exists());
+        assert!(!work.path().join("b.rs.safe-bash.bak").exists());
+        assert!(work.path().join("a.rs").exists());
+        assert!(work.path().join("c.txt.safe-bash.bak").exists());
+    }
+}
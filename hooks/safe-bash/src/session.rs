@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tallies of sessions idle longer than this (ended without a SessionEnd hook) are dropped.
+const SESSION_TTL_SECS: u64 = 24 * 3600;
+
+/// How many reasons of each kind the summary line names.
+const SUMMARY_REASONS: usize = 3;
+
+/// What the hook decided during one session.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Tally {
+    pub commands: u32,
+    pub allowed: u32,
+    pub warned: u32,
+    pub asked: u32,
+    pub blocked: u32,
+    /// Block reasons and how often each came up.
+    pub blocks: BTreeMap<String, u32>,
+    /// Warn reasons: commands that ran because their category was lowered to warn.
+    pub exceptions: BTreeMap<String, u32>,
+    #[serde(default)]
+    last: u64,
+}
+
+/// Running tallies keyed by session id.
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-sessions.state")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn load(path: &Path) -> HashMap<String, Tally> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Count one decision (`allow`, `audit`, `warn`, `ask` or `deny`) in `session`; an audited
+/// command counts as allowed.
+pub fn record(state_dir: &Path, session: &str, decision: &str, reason: &str) {
+    record_at(state_dir, session, decision, reason, now_secs())
+}
+
+fn record_at(state_dir: &Path, session: &str, decision: &str, reason: &str, now: u64) {
+    let path = state_path(state_dir);
+    let mut tallies = load(&path);
+    tallies.retain(|_, t| now.saturating_sub(t.last) < SESSION_TTL_SECS);
+    let tally = tallies.entry(session.to_string()).or_default();
+    tally.commands += 1;
+    tally.last = now;
+    match decision {
+        "warn" => {
+            tally.warned += 1;
+            *tally.exceptions.entry(reason.to_string()).or_default() += 1;
+        }
+        "ask" => tally.asked += 1,
+        "deny" => {
+            tally.blocked += 1;
+            *tally.blocks.entry(reason.to_string()).or_default() += 1;
+        }
+        _ => tally.allowed += 1,
+    }
+    // A lost tally only makes the summary short; decisions are unaffected
+    if let Err(e) = fs::write(&path, serde_json::to_string(&tallies).unwrap_or_default()) {
+        crate::warnings::warn(
+            "session-write",
+            &format!("could not write {}: {}", path.display(), e),
+        );
+    }
+}
+
+/// Remove and return a session's tally, or None if the hook saw no command in it.
+pub fn take(state_dir: &Path, session: &str) -> Option<Tally> {
+    let path = state_path(state_dir);
+    let mut tallies = load(&path);
+    let tally = tallies.remove(session)?;
+    let _ = fs::write(&path, serde_json::to_string(&tallies).unwrap_or_default());
+    Some(tally)
+}
+
+/// The most frequent reasons first: `Destructive: rm -rf (×3), …`.
+fn top(reasons: &BTreeMap<String, u32>) -> String {
+    let mut sorted: Vec<(&String, &u32)> = reasons.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let mut named: Vec<String> = sorted
+        .iter()
+        .take(SUMMARY_REASONS)
+        .map(|(r, n)| format!("{} (×{})", r, n))
+        .collect();
+    if sorted.len() > SUMMARY_REASONS {
+        named.push(format!("{} more", sorted.len() - SUMMARY_REASONS));
+    }
+    named.join(", ")
+}
+
+/// One line for the user: counts per decision, then the block and exception reasons.
+pub fn summary(tally: &Tally) -> String {
+    let mut line = format!(
+        "safe-bash: {} command{} this session — {} allowed, {} warned, {} asked, {} blocked",
+        tally.commands,
+        if tally.commands == 1 { "" } else { "s" },
+        tally.allowed,
+        tally.warned,
+        tally.asked,
+        tally.blocked
+    );
+    if !tally.blocks.is_empty() {
+        line.push_str(&format!(". Blocked: {}", top(&tally.blocks)));
+    }
+    if !tally.exceptions.is_empty() {
+        line.push_str(&format!(". Exceptions used: {}", top(&tally.exceptions)));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn decisions_tallied_per_session() {
+        let dir = TempDir::new().unwrap();
+        record_at(dir.path(), "s1", "allow", "", 1000);
+        record_at(dir.path(), "s1", "deny", "Destructive: rm -rf", 1001);
+        record_at(dir.path(), "s1", "deny", "Destructive: rm -rf", 1002);
+        record_at(dir.path(), "s1", "warn", "Sensitive: bare env", 1003);
+        record_at(dir.path(), "s2", "ask", "Permissions: chmod -R", 1004);
+        let tally = take(dir.path(), "s1").unwrap();
+        assert_eq!(
+            (
+                tally.commands,
+                tally.allowed,
+                tally.warned,
+                tally.asked,
+                tally.blocked
+            ),
+            (4, 1, 1, 0, 2)
+        );
+        assert_eq!(
+            summary(&tally),
+            "safe-bash: 4 commands this session — 1 allowed, 1 warned, 0 asked, 2 blocked. \
+             Blocked: Destructive: rm -rf (×2). Exceptions used: Sensitive: bare env (×1)"
+        );
+        assert_eq!(take(dir.path(), "s1"), None);
+        assert_eq!(take(dir.path(), "s2").unwrap().asked, 1);
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let dir = TempDir::new().unwrap();
+        record_at(dir.path(), "old", "allow", "", 1000);
+        record_at(dir.path(), "new", "allow", "", 1000 + SESSION_TTL_SECS);
+        assert_eq!(take(dir.path(), "old"), None);
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+
+/// What the hook does when a pattern matches. Ordered from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Ignore matches: the category is disabled.
+    Off,
+    /// Allow silently; only the audit log records the match.
+    Audit,
+    /// Allow, but print the reason to stderr and audit it.
+    Warn,
+    /// Ask the user to confirm.
+    Ask,
+    /// Block (exit 2).
+    Deny,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "audit" => Some(Self::Audit),
+            "warn" => Some(Self::Warn),
+            "ask" => Some(Self::Ask),
+            "deny" | "block" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Audit => "audit",
+            Self::Warn => "warn",
+            Self::Ask => "ask",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+/// Groups of hardcoded patterns. Config can change a category's default severity, down to its
+/// floor, or turn the category off where the floor allows it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    FileDestructive,
+    RootWildcard,
+    UnguardedVariable,
+    GitDestructive,
+    Permissions,
+    ShellInjection,
+    Exfiltration,
+    SensitiveRead,
+    InPlaceEdit,
+    EditorWrite,
+    System,
+    PrivilegeEscalation,
+    Persistence,
+    CommandShadowing,
+    ContainerEscape,
+    NetworkShell,
+    Obfuscation,
+    Archive,
+    SelfProtection,
+    EditorState,
+    ProtectedPath,
+    EnvDestruction,
+    RustToolchain,
+    Network,
+    PackageManager,
+    Root,
+}
+
+pub const ALL_CATEGORIES: &[Category] = &[
+    Category::FileDestructive,
+    Category::RootWildcard,
+    Category::UnguardedVariable,
+    Category::GitDestructive,
+    Category::Permissions,
+    Category::ShellInjection,
+    Category::Exfiltration,
+    Category::SensitiveRead,
+    Category::InPlaceEdit,
+    Category::EditorWrite,
+    Category::System,
+    Category::PrivilegeEscalation,
+    Category::Persistence,
+    Category::CommandShadowing,
+    Category::ContainerEscape,
+    Category::NetworkShell,
+    Category::Obfuscation,
+    Category::Archive,
+    Category::SelfProtection,
+    Category::EditorState,
+    Category::ProtectedPath,
+    Category::EnvDestruction,
+    Category::RustToolchain,
+    Category::Network,
+    Category::PackageManager,
+    Category::Root,
+];
+
+impl Category {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_CATEGORIES.iter().copied().find(|c| c.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FileDestructive => "file-destructive",
+            Self::RootWildcard => "root-wildcard",
+            Self::UnguardedVariable => "unguarded-variable",
+            Self::GitDestructive => "git-destructive",
+            Self::Permissions => "permissions",
+            Self::ShellInjection => "shell-injection",
+            Self::Exfiltration => "exfiltration",
+            Self::SensitiveRead => "sensitive-read",
+            Self::InPlaceEdit => "in-place-edit",
+            Self::EditorWrite => "editor-write",
+            Self::System => "system",
+            Self::PrivilegeEscalation => "privilege-escalation",
+            Self::Persistence => "persistence",
+            Self::CommandShadowing => "command-shadowing",
+            Self::ContainerEscape => "container-escape",
+            Self::NetworkShell => "network-shell",
+            Self::Obfuscation => "obfuscation",
+            Self::Archive => "archive",
+            Self::SelfProtection => "self-protection",
+            Self::EditorState => "editor-state",
+            Self::ProtectedPath => "protected-path",
+            Self::EnvDestruction => "env-destruction",
+            Self::RustToolchain => "rust-toolchain",
+            Self::Network => "network",
+            Self::PackageManager => "package-manager",
+            Self::Root => "root",
+        }
+    }
+
+    /// The lowest severity config may set. Wiping `/`, home or a system directory and hiding a
+    /// command from the patterns always block; deleting files and disabling the hook itself
+    /// always need a confirmation. Any other category can be turned off.
+    pub fn floor(&self) -> Severity {
+        match self {
+            Self::RootWildcard | Self::Obfuscation => Severity::Deny,
+            Self::FileDestructive | Self::SelfProtection => Severity::Ask,
+            _ => Severity::Off,
+        }
+    }
+
+    /// The severity without a config override under the standard profile. Deleting a package
+    /// environment or cache and changing the Rust toolchain only warn: how costly that is varies
+    /// a lot between teams. Network access and package installs are only locked down by the
+    /// strict profile.
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Self::EnvDestruction | Self::RustToolchain => Severity::Warn,
+            Self::Network | Self::PackageManager => Severity::Off,
+            _ => Severity::Deny,
+        }
+    }
+}
+
+/// Hardcoded rules that ask instead of blocking by default, below the strict profile: losing
+/// uncommitted changes, untracked files, a file's previous text or files a sync no longer
+/// finds is often what was meant, and the user can tell from the prompt. `rule_severity` or
+/// `category_severity` still set them.
+pub const ASK_BY_DEFAULT: &[&str] = &["GIT002", "GIT005", "IPE001", "FS018"];
+
+/// A built-in set of category defaults, chosen with `profile` in the config or the
+/// `SAFE_BASH_PROFILE` environment variable. Ordered from least to most strict.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Profile {
+    /// Only block what cannot be undone; confirm or warn about the rest.
+    Permissive,
+    #[default]
+    Standard,
+    /// Block every category, network access and package installs included.
+    Strict,
+}
+
+impl Profile {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "permissive" => Some(Self::Permissive),
+            "standard" => Some(Self::Standard),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Permissive => "permissive",
+            Self::Standard => "standard",
+            Self::Strict => "strict",
+        }
+    }
+
+    /// A category's severity under this profile, before config overrides; never below its floor.
+    pub fn severity(&self, category: Category) -> Severity {
+        let severity = match self {
+            Self::Standard => category.default_severity(),
+            Self::Strict => Severity::Deny,
+            Self::Permissive => match category {
+                // Lost work, leaked data and a taken-over machine cannot be undone
+                Category::UnguardedVariable
+                | Category::Exfiltration
+                | Category::System
+                | Category::NetworkShell
+                | Category::ProtectedPath
+                | Category::Root => Severity::Deny,
+                Category::GitDestructive
+                | Category::ShellInjection
+                | Category::PrivilegeEscalation
+                | Category::Persistence
+                | Category::ContainerEscape
+                | Category::Archive => Severity::Ask,
+                Category::Network | Category::PackageManager => Severity::Off,
+                _ => Severity::Warn,
+            },
+        };
+        severity.max(category.floor())
+    }
+}
+
+/// Per-category severity, and per-rule overrides keyed by a hardcoded rule's ID.
+/// Categories without an override use their profile's default (mostly deny).
+#[derive(Default, Debug)]
+pub struct Severities {
+    profile: Profile,
+    overrides: HashMap<Category, Severity>,
+    /// Each rule's override, with the rule's category.
+    rules: HashMap<String, (Category, Severity)>,
+}
+
+impl Severities {
+    /// No overrides, with the category defaults of `profile`.
+    pub fn for_profile(profile: Profile) -> Self {
+        Self {
+            profile,
+            ..Self::default()
+        }
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// The severities that apply as root: at least the standard profile, and every override
+    /// raised to at least its standard default. Nothing is lowered, so a stricter override holds.
+    pub fn escalated(&self) -> Self {
+        let standard = Self::for_profile(Profile::Standard);
+        let overrides = self
+            .overrides
+            .iter()
+            .map(|(c, s)| (*c, (*s).max(standard.get(*c))))
+            .collect();
+        let rules = self
+            .rules
+            .iter()
+            .map(|(id, (c, s))| (id.clone(), (*c, (*s).max(standard.rule(*c, id)))))
+            .collect();
+        Self {
+            profile: self.profile.max(Profile::Standard),
+            overrides,
+            rules,
+        }
+    }
+
+    pub fn get(&self, category: Category) -> Severity {
+        self.overrides
+            .get(&category)
+            .copied()
+            .unwrap_or_else(|| self.profile.severity(category))
+    }
+
+    /// The severity of one hardcoded rule: its own override, else its category's override,
+    /// else its category's default, lowered to ask for the rules in `ASK_BY_DEFAULT`.
+    pub fn rule(&self, category: Category, id: &str) -> Severity {
+        if let Some(severity) = self
+            .rules
+            .get(id)
+            .map(|(_, s)| s)
+            .or_else(|| self.overrides.get(&category))
+        {
+            return *severity;
+        }
+        let default = self.profile.severity(category);
+        if self.profile != Profile::Strict && ASK_BY_DEFAULT.contains(&id) {
+            return default.min(Severity::Ask).max(category.floor());
+        }
+        default
+    }
+
+    /// Override one hardcoded rule's severity, clamped to its category's floor.
+    /// Returns Err with a description when the requested level was raised to the floor.
+    pub fn set_rule(
+        &mut self,
+        category: Category,
+        id: &str,
+        severity: Severity,
+    ) -> Result<(), String> {
+        let floor = category.floor();
+        self.rules
+            .insert(id.to_string(), (category, severity.max(floor)));
+        if severity < floor {
+            return Err(format!(
+                "{} is {}, which cannot be lowered below {}, using {}",
+                id,
+                category.as_str(),
+                floor.as_str(),
+                floor.as_str()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Override a category's severity, clamped to its floor.
+    /// Returns Err with a description when the requested level was raised to the floor.
+    pub fn set(&mut self, category: Category, severity: Severity) -> Result<(), String> {
+        let floor = category.floor();
+        self.overrides.insert(category, severity.max(floor));
+        if severity < floor {
+            return Err(format!(
+                "{} cannot be lowered below {}, using {}",
+                category.as_str(),
+                floor.as_str(),
+                floor.as_str()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for c in ALL_CATEGORIES {
+            assert_eq!(Category::from_name(c.as_str()), Some(*c));
+        }
+        assert_eq!(Category::from_name("bogus"), None);
+        assert_eq!(Severity::from_name("block"), Some(Severity::Deny));
+        assert_eq!(Severity::from_name("audit"), Some(Severity::Audit));
+        assert_eq!(Severity::from_name("silent"), None);
+    }
+
+    #[test]
+    fn defaults_to_deny() {
+        assert_eq!(
+            Severities::default().get(Category::SensitiveRead),
+            Severity::Deny
+        );
+        assert_eq!(
+            Severities::default().get(Category::EnvDestruction),
+            Severity::Warn
+        );
+    }
+
+    #[test]
+    fn override_raises_warn_default() {
+        let mut s = Severities::default();
+        assert!(s.set(Category::EnvDestruction, Severity::Deny).is_ok());
+        assert_eq!(s.get(Category::EnvDestruction), Severity::Deny);
+    }
+
+    #[test]
+    fn override_lowers_severity() {
+        let mut s = Severities::default();
+        assert!(s.set(Category::SensitiveRead, Severity::Ask).is_ok());
+        assert!(s.set(Category::InPlaceEdit, Severity::Warn).is_ok());
+        assert_eq!(s.get(Category::SensitiveRead), Severity::Ask);
+        assert_eq!(s.get(Category::InPlaceEdit), Severity::Warn);
+    }
+
+    #[test]
+    fn categories_turned_off_above_floor_only() {
+        let mut s = Severities::default();
+        assert_eq!(Severity::from_name("off"), Some(Severity::Off));
+        assert!(s.set(Category::GitDestructive, Severity::Off).is_ok());
+        assert_eq!(s.get(Category::GitDestructive), Severity::Off);
+        assert!(s.set(Category::FileDestructive, Severity::Off).is_err());
+        assert_eq!(s.get(Category::FileDestructive), Severity::Ask);
+    }
+
+    #[test]
+    fn rule_override_wins_over_category_within_floor() {
+        let mut s = Severities::default();
+        s.set(Category::GitDestructive, Severity::Ask).unwrap();
+        assert!(s
+            .set_rule(Category::GitDestructive, "GIT002", Severity::Audit)
+            .is_ok());
+        assert_eq!(s.rule(Category::GitDestructive, "GIT002"), Severity::Audit);
+        assert_eq!(s.rule(Category::GitDestructive, "GIT001"), Severity::Ask);
+        assert!(s
+            .set_rule(Category::FileDestructive, "FS001", Severity::Warn)
+            .is_err());
+        assert_eq!(s.rule(Category::FileDestructive, "FS001"), Severity::Ask);
+    }
+
+    #[test]
+    fn medium_risk_rules_ask_by_default() {
+        let s = Severities::default();
+        assert_eq!(s.rule(Category::GitDestructive, "GIT002"), Severity::Ask);
+        assert_eq!(s.rule(Category::GitDestructive, "GIT001"), Severity::Deny);
+        assert_eq!(
+            Severities::for_profile(Profile::Permissive).rule(Category::InPlaceEdit, "IPE001"),
+            Severity::Warn
+        );
+        assert_eq!(
+            Severities::for_profile(Profile::Strict).rule(Category::GitDestructive, "GIT005"),
+            Severity::Deny
+        );
+        // An explicit category or rule severity wins
+        let mut s = Severities::default();
+        s.set(Category::GitDestructive, Severity::Deny).unwrap();
+        assert_eq!(s.rule(Category::GitDestructive, "GIT002"), Severity::Deny);
+        s.set_rule(Category::GitDestructive, "GIT002", Severity::Warn)
+            .unwrap();
+        assert_eq!(s.rule(Category::GitDestructive, "GIT002"), Severity::Warn);
+    }
+
+    #[test]
+    fn escalation_raises_overrides_and_keeps_stricter_ones() {
+        let mut severities = Severities::for_profile(Profile::Permissive);
+        severities
+            .set(Category::EnvDestruction, Severity::Deny)
+            .unwrap();
+        severities
+            .set(Category::InPlaceEdit, Severity::Warn)
+            .unwrap();
+        severities
+            .set_rule(Category::GitDestructive, "GIT002", Severity::Deny)
+            .unwrap();
+        severities
+            .set_rule(Category::GitDestructive, "GIT001", Severity::Audit)
+            .unwrap();
+        let root = severities.escalated();
+        assert_eq!(root.profile(), Profile::Standard);
+        assert_eq!(root.get(Category::EnvDestruction), Severity::Deny);
+        assert_eq!(root.get(Category::InPlaceEdit), Severity::Deny);
+        assert_eq!(root.get(Category::Archive), Severity::Deny);
+        assert_eq!(
+            root.rule(Category::GitDestructive, "GIT002"),
+            Severity::Deny
+        );
+        assert_eq!(
+            root.rule(Category::GitDestructive, "GIT001"),
+            Severity::Deny
+        );
+        assert_eq!(root.rule(Category::GitDestructive, "GIT005"), Severity::Ask);
+        assert_eq!(
+            Severities::for_profile(Profile::Strict)
+                .escalated()
+                .profile(),
+            Profile::Strict
+        );
+    }
+
+    #[test]
+    fn profiles_change_category_defaults() {
+        let (strict, permissive) = (
+            Severities::for_profile(Profile::Strict),
+            Severities::for_profile(Profile::Permissive),
+        );
+        assert_eq!(Severities::default().get(Category::Network), Severity::Off);
+        assert_eq!(strict.get(Category::Network), Severity::Deny);
+        assert_eq!(strict.get(Category::EnvDestruction), Severity::Deny);
+        assert_eq!(permissive.get(Category::SensitiveRead), Severity::Warn);
+        assert_eq!(permissive.get(Category::GitDestructive), Severity::Ask);
+        // Floors hold under every profile
+        assert_eq!(permissive.get(Category::FileDestructive), Severity::Ask);
+        assert_eq!(permissive.get(Category::RootWildcard), Severity::Deny);
+        assert_eq!(Profile::from_name("strict"), Some(Profile::Strict));
+        assert_eq!(Profile::from_name("lax"), None);
+    }
+
+    #[test]
+    fn floors_clamp_core_categories() {
+        let mut s = Severities::default();
+        assert!(s.set(Category::FileDestructive, Severity::Warn).is_err());
+        assert!(s.set(Category::SelfProtection, Severity::Warn).is_err());
+        assert_eq!(s.get(Category::FileDestructive), Severity::Ask);
+        assert_eq!(s.get(Category::SelfProtection), Severity::Ask);
+        assert!(s.set(Category::RootWildcard, Severity::Ask).is_err());
+        assert_eq!(s.get(Category::RootWildcard), Severity::Deny);
+    }
+}
@@ -0,0 +1,1343 @@
+//! A small, error-tolerant POSIX shell parser.
+//!
+//! It understands quoting (`'…'`, `"…"`, `$'…'`, backslashes), command and process
+//! substitution, arithmetic and parameter expansion, redirections (including heredoc
+//! bodies), pipelines, `&&`/`||`/`;`/`&` lists, subshells, brace groups, function
+//! definitions and the control-flow keywords. It never fails: unexpected tokens are
+//! skipped so that every command the shell could run still shows up in the AST.
+
+/// A shell word after lexing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Word {
+    /// Source text, including quotes.
+    pub raw: String,
+    /// Text after quote removal. Expansions (`$HOME`, `$(...)`) are kept verbatim.
+    pub value: String,
+    /// Bodies of `$(...)`, backtick, `<(...)` and `>(...)` substitutions in this word.
+    pub substitutions: Vec<String>,
+    /// True if any part of the word was quoted or escaped.
+    pub quoted: bool,
+}
+
+/// A redirection such as `> file`, `2>&1` or `<<EOF`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    /// The operator without any fd prefix: `>`, `>>`, `<`, `<<`, `<<-`, `<<<`, `>&`, `&>`, ...
+    pub op: String,
+    pub target: Word,
+    /// Heredoc body (for `<<` and `<<-`), without the terminating delimiter line.
+    pub heredoc: Option<String>,
+}
+
+/// A simple command: `FOO=1 cmd arg > out`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SimpleCommand {
+    pub assignments: Vec<Word>,
+    pub argv: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+    /// Source text of this command (heredoc bodies excluded).
+    pub text: String,
+    /// True when stdin comes from a preceding `|`.
+    pub piped: bool,
+}
+
+impl SimpleCommand {
+    /// The argv values after quote removal.
+    pub fn args(&self) -> Vec<&str> {
+        self.argv.iter().map(|w| w.value.as_str()).collect()
+    }
+
+    /// The command rebuilt from its words after quote removal and `$'...'` decoding, re-quoted
+    /// only where needed: `r'm' -rf /` and `$'\x72\x6d' -rf /` both become `rm -rf /`,
+    /// while `grep 'rm -rf' f` keeps its quotes.
+    pub fn canonical(&self) -> String {
+        let words = self
+            .assignments
+            .iter()
+            .chain(&self.argv)
+            .map(|w| quote(&w.value));
+        let redirects = self
+            .redirects
+            .iter()
+            .map(|r| format!("{}{}", r.op, quote(&r.target.value)));
+        words.chain(redirects).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Bodies of every command/process substitution in assignments, argv and redirect targets.
+    pub fn substitutions(&self) -> Vec<&str> {
+        self.assignments
+            .iter()
+            .chain(&self.argv)
+            .chain(self.redirects.iter().map(|r| &r.target))
+            .flat_map(|w| w.substitutions.iter().map(|s| s.as_str()))
+            .collect()
+    }
+}
+
+/// The operator following an item in a list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    /// `;` or a newline
+    Seq,
+    /// `&`
+    Background,
+    And,
+    Or,
+    /// `|` or `|&`
+    Pipe,
+}
+
+/// An entry in a command list together with the operator that follows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub node: Node,
+    pub connector: Option<Connector>,
+    /// Byte span of this item in the source string.
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Simple(SimpleCommand),
+    /// Commands joined by `;`, `&`, `&&`, `||` and `|`.
+    List(Vec<Item>),
+    /// `( ... )`
+    Subshell(Vec<Item>),
+    /// `{ ...; }`
+    Group(Vec<Item>),
+    /// `name() { ... }` or `function name { ... }`
+    Function {
+        name: String,
+        body: Vec<Item>,
+    },
+}
+
+impl Node {
+    /// All simple commands in source order, including those nested in subshells,
+    /// groups, function bodies and control structures.
+    pub fn simple_commands(&self) -> Vec<&SimpleCommand> {
+        let mut out = Vec::new();
+        collect_simple(self, &mut out);
+        out
+    }
+}
+
+fn collect_simple<'a>(node: &'a Node, out: &mut Vec<&'a SimpleCommand>) {
+    match node {
+        Node::Simple(cmd) => out.push(cmd),
+        Node::List(items) | Node::Subshell(items) | Node::Group(items) => {
+            for item in items {
+                collect_simple(&item.node, out);
+            }
+        }
+        Node::Function { body, .. } => {
+            for item in body {
+                collect_simple(&item.node, out);
+            }
+        }
+    }
+}
+
+/// Quote a word only when needed, so rebuilt commands read like typed ones.
+pub fn quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || "-_./=:@%+,~^".contains(c))
+    {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Remove backslash-newline line continuations the way the shell does before tokenizing,
+/// so `docker run \<newline>  --rm` reads as one line. Continuations inside single
+/// quotes and comments are literal and kept.
+pub fn join_continuations(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let (mut single, mut double, mut comment) = (false, false, false);
+    let mut prev: Option<char> = None;
+    while let Some(c) = chars.next() {
+        if comment {
+            comment = c != '\n';
+        } else if single {
+            single = c != '\'';
+        } else if c == '\\' {
+            match chars.next() {
+                Some('\n') => continue,
+                Some(next) => {
+                    out.push(c);
+                    out.push(next);
+                    prev = Some(next);
+                    continue;
+                }
+                None => {}
+            }
+        } else if c == '\'' && !double {
+            single = true;
+        } else if c == '"' {
+            double = !double;
+        } else if c == '#'
+            && !double
+            && prev.is_none_or(|p| p.is_whitespace() || ";&|(".contains(p))
+        {
+            comment = true;
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// Bodies of the `$(...)` and backtick substitutions in text that the shell expands but
+/// does not split into words — an unquoted heredoc body.
+pub fn expansion_substitutions(text: &str) -> Vec<String> {
+    let mut lexer = Lexer::new(text);
+    let mut word = Word::default();
+    while let Some(c) = lexer.peek(0) {
+        match c {
+            '\\' => lexer.i += 2,
+            '$' if lexer.peek(1) == Some('(') => lexer.lex_dollar_paren(&mut word),
+            '`' => {
+                lexer.i += 1;
+                let body = lexer.read_backtick();
+                word.substitutions.push(body);
+            }
+            _ => lexer.i += 1,
+        }
+    }
+    word.substitutions
+}
+
+/// Parse a command string into a top-level list.
+pub fn parse(src: &str) -> Node {
+    let tokens = Lexer::new(src).tokenize();
+    let mut parser = Parser {
+        src,
+        tokens,
+        pos: 0,
+    };
+    Node::List(parser.parse_list(&[]))
+}
+
+/// The byte ranges of the pipelines in a top-level command list, split at `;`, `&`, `&&`,
+/// `||` and newlines: `a && b | c; d` → `a`, `b | c`, `d`.
+pub fn pipelines(src: &str) -> Vec<std::ops::Range<usize>> {
+    let Node::List(items) = parse(src) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    let mut start = None;
+    for item in &items {
+        let first = *start.get_or_insert(item.start);
+        if item.connector != Some(Connector::Pipe) {
+            let text = src.get(first..item.end.max(first)).unwrap_or("");
+            let trimmed = text.trim_start();
+            let begin = first + text.len() - trimmed.len();
+            if !trimmed.trim_end().is_empty() {
+                out.push(begin..begin + trimmed.trim_end().len());
+            }
+            start = None;
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Lexer
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Word(Word),
+    /// Control operator: && || ;; ; & | |& ( )
+    Op(&'static str),
+    Newline,
+    Redirect(Redirect),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+/// The commands substituted anywhere in the body of an arithmetic or parameter expansion,
+/// outside single quotes.
+fn nested_substitutions(body: &str) -> Vec<String> {
+    let mut lexer = Lexer::new(body);
+    let mut word = Word::default();
+    while let Some(c) = lexer.peek(0) {
+        match c {
+            '\\' => lexer.i += 2,
+            '\'' => {
+                lexer.i += 1;
+                while lexer.peek(0).is_some_and(|c| c != '\'') {
+                    lexer.i += 1;
+                }
+                lexer.i += 1;
+            }
+            '"' => {
+                lexer.i += 1;
+                lexer.lex_double_quoted(&mut word);
+            }
+            '$' if lexer.peek(1) == Some('(') => lexer.lex_dollar_paren(&mut word),
+            '$' if lexer.peek(1) == Some('{') => lexer.lex_dollar_brace(&mut word),
+            '`' => {
+                lexer.i += 1;
+                let body = lexer.read_backtick();
+                word.substitutions.push(body);
+            }
+            _ => lexer.i += 1,
+        }
+    }
+    word.substitutions
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    chars: Vec<(usize, char)>,
+    i: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            chars: src.char_indices().collect(),
+            i: 0,
+        }
+    }
+
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.i + ahead).map(|&(_, c)| c)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars
+            .get(self.i)
+            .map(|&(o, _)| o)
+            .unwrap_or(self.src.len())
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.src[self.offset()..].starts_with(s)
+    }
+
+    fn tokenize(mut self) -> Vec<Token> {
+        let mut tokens: Vec<Token> = Vec::new();
+        // Indices of heredoc redirect tokens whose bodies start after the next newline
+        let mut pending_heredocs: Vec<usize> = Vec::new();
+
+        while let Some(c) = self.peek(0) {
+            let start = self.offset();
+            match c {
+                '\\' if self.peek(1) == Some('\n') => self.i += 2,
+                ' ' | '\t' | '\r' => self.i += 1,
+                '\n' => {
+                    self.i += 1;
+                    tokens.push(Token {
+                        tok: Tok::Newline,
+                        start,
+                        end: start + 1,
+                    });
+                    for idx in pending_heredocs.drain(..) {
+                        let body = self.read_heredoc_body(&tokens[idx]);
+                        if let Tok::Redirect(r) = &mut tokens[idx].tok {
+                            r.heredoc = Some(body);
+                        }
+                    }
+                }
+                '#' => {
+                    while let Some(c) = self.peek(0) {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.i += 1;
+                    }
+                }
+                _ => {
+                    if let Some(op) = self.control_op() {
+                        self.i += op.len();
+                        tokens.push(Token {
+                            tok: Tok::Op(op),
+                            start,
+                            end: self.offset(),
+                        });
+                    } else if let Some(op) = self.redirect_op() {
+                        self.i += op.chars().count();
+                        let target = self.next_word();
+                        let bare = op.trim_start_matches(|c: char| c.is_ascii_digit());
+                        let is_heredoc = bare == "<<" || bare == "<<-";
+                        tokens.push(Token {
+                            tok: Tok::Redirect(Redirect {
+                                op,
+                                target,
+                                heredoc: None,
+                            }),
+                            start,
+                            end: self.offset(),
+                        });
+                        if is_heredoc {
+                            pending_heredocs.push(tokens.len() - 1);
+                        }
+                    } else {
+                        let word = self.lex_word();
+                        if self.offset() == start {
+                            // Never stall on a character no rule consumes
+                            self.i += 1;
+                            continue;
+                        }
+                        tokens.push(Token {
+                            tok: Tok::Word(word),
+                            start,
+                            end: self.offset(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Heredocs without a trailing newline have no body
+        for idx in pending_heredocs {
+            if let Tok::Redirect(r) = &mut tokens[idx].tok {
+                r.heredoc = Some(String::new());
+            }
+        }
+        tokens
+    }
+
+    fn control_op(&self) -> Option<&'static str> {
+        const OPS: &[&str] = &["&&", "||", ";;", "|&", ";", "|", "(", ")"];
+        if self.starts_with("&>") {
+            return None;
+        }
+        if self.starts_with("&") {
+            return Some(if self.starts_with("&&") { "&&" } else { "&" });
+        }
+        OPS.iter().find(|op| self.starts_with(op)).copied()
+    }
+
+    /// Recognize a redirect operator at the current position, including an fd prefix (`2>`).
+    fn redirect_op(&self) -> Option<String> {
+        const OPS: &[&str] = &[
+            "<<<", "<<-", "<<", "<&", "<>", "<", "&>>", "&>", ">>", ">&", ">|", ">",
+        ];
+        if self.starts_with("<(") || self.starts_with(">(") {
+            return None;
+        }
+        let rest = &self.src[self.offset()..];
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        let after = &rest[digits..];
+        let op = OPS.iter().find(|op| after.starts_with(*op))?;
+        if digits > 0 && op.starts_with('&') {
+            return None;
+        }
+        Some(format!("{}{}", &rest[..digits], op))
+    }
+
+    /// Skip blanks and lex the next word (redirect target). Returns an empty word at an operator.
+    fn next_word(&mut self) -> Word {
+        while matches!(self.peek(0), Some(' ') | Some('\t')) {
+            self.i += 1;
+        }
+        match self.peek(0) {
+            None | Some('\n') => Word::default(),
+            _ if self.control_op().is_some() => Word::default(),
+            _ => self.lex_word(),
+        }
+    }
+
+    fn read_heredoc_body(&mut self, token: &Token) -> String {
+        let (delimiter, strip_tabs) = match &token.tok {
+            Tok::Redirect(r) => (r.target.value.clone(), r.op.ends_with("<<-")),
+            _ => return String::new(),
+        };
+        let mut lines: Vec<&str> = Vec::new();
+        loop {
+            let start = self.offset();
+            if start >= self.src.len() {
+                break;
+            }
+            let line_end = self.src[start..]
+                .find('\n')
+                .map(|n| start + n)
+                .unwrap_or(self.src.len());
+            let line = &self.src[start..line_end];
+            while self.offset() < line_end {
+                self.i += 1;
+            }
+            if self.peek(0) == Some('\n') {
+                self.i += 1;
+            }
+            let compare = if strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if compare == delimiter {
+                break;
+            }
+            lines.push(line);
+        }
+        let mut body = lines.join("\n");
+        if !lines.is_empty() {
+            body.push('\n');
+        }
+        body
+    }
+
+    fn is_word_end(&self) -> bool {
+        match self.peek(0) {
+            None => true,
+            Some(c) if c.is_whitespace() => true,
+            Some(';') | Some('&') | Some('|') | Some(')') => true,
+            Some('(') => true,
+            Some('<') | Some('>') => self.peek(1) != Some('('),
+            _ => false,
+        }
+    }
+
+    fn lex_word(&mut self) -> Word {
+        let start = self.offset();
+        let mut word = Word::default();
+
+        // Process substitution at the start of a word
+        if (self.starts_with("<(") || self.starts_with(">(")) && self.peek(0).is_some() {
+            let marker = self.peek(0).unwrap();
+            self.i += 2;
+            let body = self.read_balanced(')');
+            word.value.push_str(&format!("{}({})", marker, body));
+            word.substitutions.push(body);
+        }
+
+        while !self.is_word_end() {
+            let c = self.peek(0).unwrap();
+            match c {
+                '\\' => {
+                    self.i += 1;
+                    match self.peek(0) {
+                        Some('\n') => self.i += 1,
+                        Some(next) => {
+                            word.value.push(next);
+                            word.quoted = true;
+                            self.i += 1;
+                        }
+                        None => word.value.push('\\'),
+                    }
+                }
+                '\'' => {
+                    self.i += 1;
+                    word.quoted = true;
+                    while let Some(c) = self.peek(0) {
+                        self.i += 1;
+                        if c == '\'' {
+                            break;
+                        }
+                        word.value.push(c);
+                    }
+                }
+                '"' => {
+                    self.i += 1;
+                    word.quoted = true;
+                    self.lex_double_quoted(&mut word);
+                }
+                '$' if self.peek(1) == Some('\'') => {
+                    self.i += 2;
+                    word.quoted = true;
+                    let decoded = self.lex_ansi_c();
+                    word.value.push_str(&decoded);
+                }
+                '$' if self.peek(1) == Some('(') => self.lex_dollar_paren(&mut word),
+                '$' if self.peek(1) == Some('{') => self.lex_dollar_brace(&mut word),
+                '`' => {
+                    self.i += 1;
+                    let body = self.read_backtick();
+                    word.value.push_str(&format!("`{}`", body));
+                    word.substitutions.push(body);
+                }
+                _ => {
+                    word.value.push(c);
+                    self.i += 1;
+                }
+            }
+        }
+
+        word.raw = self.src[start..self.offset()].to_string();
+        word
+    }
+
+    fn lex_double_quoted(&mut self, word: &mut Word) {
+        while let Some(c) = self.peek(0) {
+            match c {
+                '"' => {
+                    self.i += 1;
+                    return;
+                }
+                '\\' => {
+                    let next = self.peek(1);
+                    match next {
+                        Some('$') | Some('`') | Some('"') | Some('\\') => {
+                            word.value.push(next.unwrap());
+                            self.i += 2;
+                        }
+                        Some('\n') => self.i += 2,
+                        _ => {
+                            word.value.push('\\');
+                            self.i += 1;
+                        }
+                    }
+                }
+                '$' if self.peek(1) == Some('(') => self.lex_dollar_paren(word),
+                '$' if self.peek(1) == Some('{') => self.lex_dollar_brace(word),
+                '`' => {
+                    self.i += 1;
+                    let body = self.read_backtick();
+                    word.value.push_str(&format!("`{}`", body));
+                    word.substitutions.push(body);
+                }
+                _ => {
+                    word.value.push(c);
+                    self.i += 1;
+                }
+            }
+        }
+    }
+
+    /// `$(...)` command substitution or `$((...))` arithmetic expansion. Like bash, a `$((`
+    /// whose parentheses do not close with `))` is a command substitution starting with a
+    /// subshell (`$((cd a); ls)`). Commands substituted inside arithmetic run too.
+    fn lex_dollar_paren(&mut self, word: &mut Word) {
+        if self.peek(2) == Some('(') {
+            let opener = self.i;
+            self.i += 3;
+            let body = self.read_balanced(')');
+            if self.peek(0) == Some(')') {
+                self.i += 1;
+                word.value.push_str(&format!("$(({}))", body));
+                word.substitutions.extend(nested_substitutions(&body));
+                return;
+            }
+            self.i = opener;
+        }
+        self.i += 2;
+        let body = self.read_balanced(')');
+        word.value.push_str(&format!("$({})", body));
+        word.substitutions.push(body);
+    }
+
+    /// `${...}` parameter expansion, whose operands may substitute commands (`${x:-$(cmd)}`).
+    fn lex_dollar_brace(&mut self, word: &mut Word) {
+        self.i += 2;
+        let body = self.read_balanced('}');
+        word.value.push_str(&format!("${{{}}}", body));
+        word.substitutions.extend(nested_substitutions(&body));
+    }
+
+    /// Read until the matching `close` (the opener has been consumed), honoring quotes
+    /// and nesting. Returns the body without the closer.
+    fn read_balanced(&mut self, close: char) -> String {
+        let open = if close == ')' { '(' } else { '{' };
+        let start = self.offset();
+        let mut depth = 1;
+        while let Some(c) = self.peek(0) {
+            match c {
+                '\\' => self.i += 1,
+                '\'' => {
+                    self.i += 1;
+                    while let Some(c) = self.peek(0) {
+                        if c == '\'' {
+                            break;
+                        }
+                        self.i += 1;
+                    }
+                }
+                '"' => {
+                    self.i += 1;
+                    while let Some(c) = self.peek(0) {
+                        if c == '\\' {
+                            self.i += 1;
+                        } else if c == '"' {
+                            break;
+                        }
+                        self.i += 1;
+                    }
+                }
+                c if c == open => depth += 1,
+                c if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let body = self.src[start..self.offset()].to_string();
+                        self.i += 1;
+                        return body;
+                    }
+                }
+                _ => {}
+            }
+            self.i += 1;
+        }
+        self.src[start..].to_string()
+    }
+
+    fn read_backtick(&mut self) -> String {
+        let mut body = String::new();
+        while let Some(c) = self.peek(0) {
+            self.i += 1;
+            match c {
+                '`' => break,
+                '\\' if matches!(self.peek(0), Some('`') | Some('\\') | Some('$')) => {
+                    body.push(self.peek(0).unwrap());
+                    self.i += 1;
+                }
+                _ => body.push(c),
+            }
+        }
+        body
+    }
+
+    /// Decode the body of an ANSI-C quoted string (`$'...'`); the opener has been consumed.
+    fn lex_ansi_c(&mut self) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek(0) {
+            self.i += 1;
+            match c {
+                '\'' => break,
+                '\\' => {
+                    let Some(e) = self.peek(0) else { break };
+                    self.i += 1;
+                    match e {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        'a' => out.push('\x07'),
+                        'b' => out.push('\x08'),
+                        'e' | 'E' => out.push('\x1b'),
+                        'f' => out.push('\x0c'),
+                        'v' => out.push('\x0b'),
+                        'x' => {
+                            let hex = self.take_while_max(2, |c| c.is_ascii_hexdigit());
+                            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                                Some(ch) => out.push(ch),
+                                None => out.push_str("\\x"),
+                            }
+                        }
+                        'u' | 'U' => {
+                            let max = if e == 'u' { 4 } else { 8 };
+                            let hex = self.take_while_max(max, |c| c.is_ascii_hexdigit());
+                            if let Some(ch) =
+                                u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                            {
+                                out.push(ch);
+                            }
+                        }
+                        '0'..='7' => {
+                            let mut oct = e.to_string();
+                            oct.push_str(&self.take_while_max(2, |c| ('0'..='7').contains(&c)));
+                            if let Some(ch) =
+                                u32::from_str_radix(&oct, 8).ok().and_then(char::from_u32)
+                            {
+                                out.push(ch);
+                            }
+                        }
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    fn take_while_max(&mut self, max: usize, pred: impl Fn(char) -> bool) -> String {
+        let mut s = String::new();
+        while s.len() < max {
+            match self.peek(0) {
+                Some(c) if pred(c) => {
+                    s.push(c);
+                    self.i += 1;
+                }
+                _ => break,
+            }
+        }
+        s
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parser
+// ---------------------------------------------------------------------------
+
+/// Reserved words that introduce a command list and are otherwise transparent.
+const PREFIX_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "do", "while", "until", "!", "time",
+];
+/// Reserved words that close a construct.
+const CLOSING_KEYWORDS: &[&str] = &["fi", "done", "esac"];
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Tok::Word(w)) if !w.quoted => Some(w.value.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Parse items until EOF or one of the `terminators` (op or word in command position).
+    fn parse_list(&mut self, terminators: &[&str]) -> Vec<Item> {
+        let mut items: Vec<Item> = Vec::new();
+        let mut piped = false;
+
+        loop {
+            // Skip blank lines and stray separators
+            while matches!(
+                self.peek(),
+                Some(Tok::Newline) | Some(Tok::Op(";")) | Some(Tok::Op("&"))
+            ) {
+                self.pos += 1;
+            }
+            let at_terminator = match self.peek() {
+                None => true,
+                Some(Tok::Op(op)) => terminators.contains(op),
+                Some(Tok::Word(_)) => self.peek_word().is_some_and(|w| terminators.contains(&w)),
+                _ => false,
+            };
+            if at_terminator {
+                break;
+            }
+
+            let start = self.tokens[self.pos].start;
+            let node = match self.parse_command(piped, terminators) {
+                Some(n) => n,
+                None => continue,
+            };
+            let last = self.pos.min(self.tokens.len()).saturating_sub(1);
+            let end = self.tokens[last].end.max(start);
+
+            let connector = match self.peek() {
+                Some(Tok::Op("&&")) => Some(Connector::And),
+                Some(Tok::Op("||")) => Some(Connector::Or),
+                Some(Tok::Op("|")) | Some(Tok::Op("|&")) => Some(Connector::Pipe),
+                Some(Tok::Op(";")) | Some(Tok::Newline) => Some(Connector::Seq),
+                Some(Tok::Op("&")) => Some(Connector::Background),
+                _ => None,
+            };
+            if connector.is_some() {
+                self.pos += 1;
+            }
+            piped = connector == Some(Connector::Pipe);
+            items.push(Item {
+                node,
+                connector,
+                start,
+                end,
+            });
+        }
+        items
+    }
+
+    /// Parse one command. Returns None when only keywords/stray tokens were consumed.
+    fn parse_command(&mut self, piped: bool, terminators: &[&str]) -> Option<Node> {
+        match self.peek()? {
+            Tok::Op("(") => {
+                self.pos += 1;
+                let body = self.parse_list(&[")"]);
+                self.expect_op(")");
+                self.skip_redirects();
+                return Some(Node::Subshell(body));
+            }
+            Tok::Op(_) => {
+                // Stray operator or unbalanced closer outside its construct
+                self.pos += 1;
+                return None;
+            }
+            _ => {}
+        }
+
+        if let Some(word) = self.peek_word() {
+            match word {
+                "{" => {
+                    self.pos += 1;
+                    let body = self.parse_list(&["}"]);
+                    if self.peek_word() == Some("}") {
+                        self.pos += 1;
+                    }
+                    self.skip_redirects();
+                    return Some(Node::Group(body));
+                }
+                "}" if !terminators.contains(&"}") => {
+                    self.pos += 1;
+                    return None;
+                }
+                w if PREFIX_KEYWORDS.contains(&w) => {
+                    self.pos += 1;
+                    return None;
+                }
+                w if CLOSING_KEYWORDS.contains(&w) => {
+                    self.pos += 1;
+                    self.skip_redirects();
+                    return None;
+                }
+                "for" | "select" => {
+                    // Skip the loop header up to `do`; the body is parsed as ordinary commands
+                    while let Some(tok) = self.peek() {
+                        if matches!(tok, Tok::Word(w) if w.value == "do" && !w.quoted) {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                    return None;
+                }
+                "case" => return Some(self.parse_case()),
+                "function" => {
+                    self.pos += 1;
+                    let name = match self.peek() {
+                        Some(Tok::Word(w)) => w.value.clone(),
+                        _ => return None,
+                    };
+                    self.pos += 1;
+                    if self.peek() == Some(&Tok::Op("(")) {
+                        self.pos += 1;
+                        self.expect_op(")");
+                    }
+                    return Some(self.parse_function_body(name));
+                }
+                _ => {}
+            }
+        }
+
+        // name() { ... }
+        if let (Some(Tok::Word(w)), Some(Tok::Op("(")), Some(Tok::Op(")"))) = (
+            self.tokens.get(self.pos).map(|t| &t.tok),
+            self.tokens.get(self.pos + 1).map(|t| &t.tok),
+            self.tokens.get(self.pos + 2).map(|t| &t.tok),
+        ) {
+            let name = w.value.clone();
+            self.pos += 3;
+            return Some(self.parse_function_body(name));
+        }
+
+        Some(Node::Simple(self.parse_simple(piped)))
+    }
+
+    fn parse_function_body(&mut self, name: String) -> Node {
+        while self.peek() == Some(&Tok::Newline) {
+            self.pos += 1;
+        }
+        let body = match self.parse_command(false, &[]) {
+            Some(Node::Group(items)) | Some(Node::Subshell(items)) => items,
+            Some(node) => vec![Item {
+                node,
+                connector: None,
+                start: 0,
+                end: 0,
+            }],
+            None => Vec::new(),
+        };
+        Node::Function { name, body }
+    }
+
+    /// `case WORD in pattern) list ;; ... esac` — the arm bodies become a flat list.
+    fn parse_case(&mut self) -> Node {
+        self.pos += 1; // case
+        while self.peek().is_some() {
+            let is_in = self.peek_word() == Some("in");
+            self.pos += 1;
+            if is_in {
+                break;
+            }
+        }
+        let mut items = Vec::new();
+        loop {
+            while matches!(self.peek(), Some(Tok::Newline) | Some(Tok::Op(";;"))) {
+                self.pos += 1;
+            }
+            match self.peek() {
+                None => break,
+                Some(Tok::Word(w)) if w.value == "esac" && !w.quoted => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => {}
+            }
+            // Skip the pattern up to `)`
+            while let Some(tok) = self.peek() {
+                let is_close = *tok == Tok::Op(")");
+                self.pos += 1;
+                if is_close {
+                    break;
+                }
+            }
+            items.extend(self.parse_list(&[";;", "esac"]));
+        }
+        Node::Group(items)
+    }
+
+    fn parse_simple(&mut self, piped: bool) -> SimpleCommand {
+        let mut cmd = SimpleCommand {
+            piped,
+            ..Default::default()
+        };
+        let start = self.tokens[self.pos].start;
+        let mut end = start;
+
+        while let Some(token) = self.tokens.get(self.pos) {
+            match &token.tok {
+                Tok::Word(w) => {
+                    if cmd.argv.is_empty() && is_assignment(w) {
+                        cmd.assignments.push(w.clone());
+                    } else {
+                        cmd.argv.push(w.clone());
+                    }
+                }
+                Tok::Redirect(r) => cmd.redirects.push(r.clone()),
+                Tok::Op(_) | Tok::Newline => break,
+            }
+            end = token.end;
+            self.pos += 1;
+        }
+
+        cmd.text = self.src[start..end].trim().to_string();
+        cmd
+    }
+
+    fn expect_op(&mut self, op: &str) {
+        if matches!(self.peek(), Some(Tok::Op(o)) if *o == op) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_redirects(&mut self) {
+        while matches!(self.peek(), Some(Tok::Redirect(_))) {
+            self.pos += 1;
+        }
+    }
+}
+
+/// `NAME=value` (or `NAME+=value`) with a valid, unquoted identifier before `=`.
+fn is_assignment(w: &Word) -> bool {
+    let Some(eq) = w.raw.find('=') else {
+        return false;
+    };
+    let name = w.raw[..eq].trim_end_matches('+');
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argvs(src: &str) -> Vec<Vec<String>> {
+        parse(src)
+            .simple_commands()
+            .iter()
+            .map(|c| c.args().iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn simple_argv_with_quotes() {
+        assert_eq!(
+            argvs(r#"git commit -m 'a b' --author="x y" c\ d"#),
+            vec![vec!["git", "commit", "-m", "a b", "--author=x y", "c d"]]
+        );
+    }
+
+    #[test]
+    fn lists_and_pipelines() {
+        assert_eq!(
+            argvs("a 1 && b 2 || c; d | e & f"),
+            vec![
+                vec!["a", "1"],
+                vec!["b", "2"],
+                vec!["c"],
+                vec!["d"],
+                vec!["e"],
+                vec!["f"]
+            ]
+        );
+    }
+
+    #[test]
+    fn piped_flag_set_after_pipe() {
+        let ast = parse("cat x | sh");
+        let cmds = ast.simple_commands();
+        assert!(!cmds[0].piped);
+        assert!(cmds[1].piped);
+    }
+
+    #[test]
+    fn subshell_and_group() {
+        assert_eq!(
+            argvs("(cd /tmp && rm -rf x)"),
+            vec![vec!["cd", "/tmp"], vec!["rm", "-rf", "x"]]
+        );
+        assert_eq!(
+            argvs("{ echo a; rm -rf y; }"),
+            vec![vec!["echo", "a"], vec!["rm", "-rf", "y"]]
+        );
+    }
+
+    #[test]
+    fn operators_inside_quotes_do_not_split() {
+        assert_eq!(
+            argvs("echo 'a && b; c | d'"),
+            vec![vec!["echo", "a && b; c | d"]]
+        );
+    }
+
+    #[test]
+    fn redirects_are_separated_from_argv() {
+        let ast = parse("echo hi > out.txt 2>&1");
+        let cmd = ast.simple_commands()[0];
+        assert_eq!(cmd.args(), vec!["echo", "hi"]);
+        assert_eq!(cmd.redirects.len(), 2);
+        assert_eq!(cmd.redirects[0].op, ">");
+        assert_eq!(cmd.redirects[0].target.value, "out.txt");
+        assert_eq!(cmd.redirects[1].op, "2>&");
+        assert_eq!(cmd.text, "echo hi > out.txt 2>&1");
+    }
+
+    #[test]
+    fn bare_redirect_command() {
+        let ast = parse("> /etc/passwd");
+        let cmd = ast.simple_commands()[0];
+        assert!(cmd.argv.is_empty());
+        assert_eq!(cmd.text, "> /etc/passwd");
+    }
+
+    #[test]
+    fn assignments_split_from_argv() {
+        let ast = parse("FOO=1 BAR=\"a b\" env");
+        let cmd = ast.simple_commands()[0];
+        assert_eq!(cmd.assignments.len(), 2);
+        assert_eq!(cmd.args(), vec!["env"]);
+    }
+
+    #[test]
+    fn command_substitution_bodies_captured() {
+        let ast = parse("echo $(rm -rf /) `ls ~` <(cat x)");
+        let cmd = ast.simple_commands()[0];
+        let subs: Vec<&String> = cmd.argv.iter().flat_map(|w| &w.substitutions).collect();
+        assert_eq!(subs, vec!["rm -rf /", "ls ~", "cat x"]);
+    }
+
+    #[test]
+    fn substitutions_include_assignments_and_redirects() {
+        let ast = parse("X=$(whoami) cat > `date` ");
+        assert_eq!(
+            ast.simple_commands()[0].substitutions(),
+            vec!["whoami", "date"]
+        );
+    }
+
+    #[test]
+    fn canonical_resolves_quoting_and_escapes() {
+        let canon = |src: &str| parse(src).simple_commands()[0].canonical();
+        assert_eq!(canon("r'm' -rf /"), "rm -rf /");
+        assert_eq!(canon("'r'm -rf /"), "rm -rf /");
+        assert_eq!(canon("$'\\x72\\x6d' -rf /"), "rm -rf /");
+        assert_eq!(canon("\\rm \"-\"rf /"), "rm -rf /");
+        assert_eq!(
+            canon("grep 'rm -rf' notes.txt > out"),
+            "grep 'rm -rf' notes.txt >out"
+        );
+        assert_eq!(canon("echo \"it's\""), "echo 'it'\\''s'");
+    }
+
+    #[test]
+    fn nested_substitution_is_balanced() {
+        let ast = parse("echo $(echo $(date) ')' ) done");
+        let cmd = ast.simple_commands()[0];
+        assert_eq!(cmd.argv.len(), 3);
+        assert_eq!(cmd.argv[1].substitutions, vec!["echo $(date) ')' "]);
+    }
+
+    #[test]
+    fn arithmetic_is_not_a_substitution() {
+        let ast = parse("echo $((1 + 2)) $(( (1 + 2) * 3 ))");
+        assert!(ast.simple_commands()[0].argv[1].substitutions.is_empty());
+        assert!(ast.simple_commands()[0].argv[2].substitutions.is_empty());
+    }
+
+    #[test]
+    fn substitutions_inside_expansions_found() {
+        let subs = |src: &str| {
+            parse(src).simple_commands()[0].argv[1]
+                .substitutions
+                .clone()
+        };
+        // No closing `))`: bash reads a command substitution starting with a subshell
+        assert_eq!(subs("echo $((rm -rf ~); true)"), ["(rm -rf ~); true"]);
+        assert_eq!(subs("x $(( $(rm -rf ~) ))"), ["rm -rf ~"]);
+        assert_eq!(subs("echo ${x:-$(rm -rf ~)}"), ["rm -rf ~"]);
+        assert_eq!(subs("echo \"${x:-`rm -rf ~`}\""), ["rm -rf ~"]);
+        assert_eq!(subs("echo ${x:-${y:-$(rm -rf ~)}}"), ["rm -rf ~"]);
+        assert!(subs("echo ${x:-'$(rm -rf ~)'}").is_empty());
+    }
+
+    #[test]
+    fn ansi_c_quotes_are_decoded() {
+        assert_eq!(argvs(r"$'\x72\x6d' -rf /"), vec![vec!["rm", "-rf", "/"]]);
+        assert_eq!(argvs(r"$'\162m' x"), vec![vec!["rm", "x"]]);
+    }
+
+    #[test]
+    fn heredoc_body_captured_and_not_parsed_as_commands() {
+        let ast = parse("bash <<'EOF'\nrm -rf /\nEOF\necho done");
+        let cmds = ast.simple_commands();
+        assert_eq!(cmds.len(), 2);
+        assert_eq!(cmds[0].redirects[0].heredoc.as_deref(), Some("rm -rf /\n"));
+        assert_eq!(cmds[1].args(), vec!["echo", "done"]);
+    }
+
+    #[test]
+    fn expansion_substitutions_in_heredoc_text() {
+        let subs =
+            expansion_substitutions("user: $(whoami)\nhost: `hostname`\nsum: $((1+2)) \\$(not)\n");
+        assert_eq!(subs, vec!["whoami", "hostname"]);
+    }
+
+    #[test]
+    fn heredoc_dash_strips_tabs_from_delimiter() {
+        let ast = parse("cat <<-END\n\tx\n\tEND\nls");
+        assert_eq!(ast.simple_commands().len(), 2);
+    }
+
+    #[test]
+    fn control_flow_keywords_are_transparent() {
+        assert_eq!(
+            argvs("if test -d x; then rm -rf x; else echo no; fi"),
+            vec![
+                vec!["test", "-d", "x"],
+                vec!["rm", "-rf", "x"],
+                vec!["echo", "no"]
+            ]
+        );
+        assert_eq!(
+            argvs("for f in *.log; do rm \"$f\"; done"),
+            vec![vec!["rm", "$f"]]
+        );
+        assert_eq!(
+            argvs("while true; do sleep 1; done"),
+            vec![vec!["true"], vec!["sleep", "1"]]
+        );
+    }
+
+    #[test]
+    fn case_arms_parsed() {
+        assert_eq!(
+            argvs("case $x in a) rm -rf /;; b|c) echo b;; esac"),
+            vec![vec!["rm", "-rf", "/"], vec!["echo", "b"]]
+        );
+    }
+
+    #[test]
+    fn function_definitions_parsed() {
+        let ast = parse("f() { rm -rf \"$1\"; }; f /srv");
+        let Node::List(items) = &ast else { panic!() };
+        assert!(matches!(&items[0].node, Node::Function { name, .. } if name == "f"));
+        assert_eq!(argvs("function g { ls; }"), vec![vec!["ls"]]);
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert_eq!(argvs("ls # rm -rf /"), vec![vec!["ls"]]);
+        assert_eq!(argvs("echo a#b"), vec![vec!["echo", "a#b"]]);
+    }
+
+    #[test]
+    fn line_continuations_join_words() {
+        assert_eq!(argvs("rm \\\n  -rf /"), vec![vec!["rm", "-rf", "/"]]);
+    }
+
+    #[test]
+    fn join_continuations_outside_single_quotes() {
+        assert_eq!(
+            join_continuations("docker run \\\n  --rm \\\n  alpine"),
+            "docker run   --rm   alpine"
+        );
+        assert_eq!(join_continuations("echo \"a\\\nb\""), "echo \"ab\"");
+        assert_eq!(join_continuations("echo 'a\\\nb'"), "echo 'a\\\nb'");
+        assert_eq!(
+            join_continuations("ls # note \\\nrm x"),
+            "ls # note \\\nrm x"
+        );
+        assert_eq!(join_continuations("echo \\\\\nls"), "echo \\\\\nls");
+    }
+
+    #[test]
+    fn item_spans_and_connectors() {
+        let src = "cargo build && rm -rf x; ls";
+        let Node::List(items) = parse(src) else {
+            panic!()
+        };
+        assert_eq!(&src[items[0].start..items[0].end], "cargo build");
+        assert_eq!(items[0].connector, Some(Connector::And));
+        assert_eq!(&src[items[1].start..items[1].end], "rm -rf x");
+        assert_eq!(items[2].connector, None);
+    }
+
+    #[test]
+    fn pipelines_split_at_list_operators() {
+        let slices = |src: &'static str| {
+            pipelines(src)
+                .into_iter()
+                .map(|r| &src[r])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            slices("npm ci && npm test | tee log; rm -rf dist &\nls"),
+            ["npm ci", "npm test | tee log", "rm -rf dist", "ls"]
+        );
+        assert_eq!(
+            slices("(cd a && make) || echo failed"),
+            ["(cd a && make)", "echo failed"]
+        );
+    }
+
+    #[test]
+    fn unbalanced_input_does_not_panic() {
+        for src in [
+            "(",
+            ")",
+            "{",
+            "}",
+            "echo $(",
+            "echo '",
+            "a && && b",
+            "cat <<",
+            "f() {",
+            "case x in",
+            "echo `",
+            "function",
+            "function f",
+            "}~if=esac\nindone{~done*&-esacdofor|do<$-=;function",
+            "(a ;; b)",
+        ] {
+            let _ = parse(src).simple_commands();
+        }
+    }
+
+    #[test]
+    fn non_ascii_preserved() {
+        assert_eq!(
+            argvs("echo héllo 'wörld'"),
+            vec![vec!["echo", "héllo", "wörld"]]
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use crate::trash::{Batch, Kind};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Past this many files and directories, the deleted paths are not copied and the command
+/// runs without a snapshot.
+const MAX_ENTRIES: usize = 5000;
+
+/// What a command puts at risk that a snapshot can save.
+#[derive(Debug, PartialEq)]
+enum AtRisk {
+    /// Uncommitted changes of the repository the command runs in (`git reset --hard`,
+    /// `git checkout -- <path>`, `git restore <path>`).
+    Changes,
+    /// Files and directories inside the workspace that an `rm` deletes, globs expanded.
+    Paths(Vec<PathBuf>),
+}
+
+fn at_risk(cmd: &str, cwd: &Path, root: &Path, wrappers: &[String]) -> Option<AtRisk> {
+    let ast = crate::shell::parse(cmd);
+    let mut paths = Vec::new();
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        match args.first().and_then(|p| p.rsplit('/').next()) {
+            Some("git") => {
+                let Some(sub) = args
+                    .iter()
+                    .skip(1)
+                    .position(|a| !a.starts_with('-'))
+                    .map(|i| i + 1)
+                else {
+                    continue;
+                };
+                let rest = &args[sub + 1..];
+                let discards = match args[sub] {
+                    "reset" => rest.contains(&"--hard"),
+                    "checkout" => rest.contains(&"--") || rest.contains(&"."),
+                    "restore" => !rest.iter().any(|a| *a == "--staged" || *a == "-S"),
+                    _ => false,
+                };
+                if discards {
+                    return Some(AtRisk::Changes);
+                }
+            }
+            Some("rm") => {
+                let mut options_done = false;
+                for arg in &args[1..] {
+                    if !options_done && arg.starts_with('-') && *arg != "-" {
+                        options_done = *arg == "--";
+                        continue;
+                    }
+                    if arg.starts_with(['~', '$', '`']) {
+                        continue;
+                    }
+                    let found = if arg.contains(['*', '?', '[']) {
+                        crate::blast::glob_paths(cwd, arg)
+                    } else {
+                        vec![cwd.join(arg)]
+                    };
+                    paths.extend(
+                        found
+                            .into_iter()
+                            .map(|p| crate::perms::resolve(&p.to_string_lossy(), cwd, None))
+                            .filter(|p| {
+                                p.starts_with(root)
+                                    && p != root
+                                    && !p.starts_with(root.join(".git"))
+                            })
+                            .filter(|p| p.symlink_metadata().is_ok()),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    // A path under another one is copied with it
+    paths.sort();
+    paths.dedup();
+    let nested: Vec<bool> = paths
+        .iter()
+        .map(|p| paths.iter().any(|q| q != p && p.starts_with(q)))
+        .collect();
+    let mut nested = nested.into_iter();
+    paths.retain(|_| !nested.next().unwrap_or(false));
+    (!paths.is_empty()).then_some(AtRisk::Paths(paths))
+}
+
+/// Save what `cmd` may destroy into a batch of the session's trash before it runs: the
+/// repository's uncommitted changes as a stash commit (also listed in `git stash list`), or a
+/// copy of the workspace paths an `rm` deletes. Returns the batch id, or why no snapshot was
+/// taken although one was called for; None when the command risks nothing a snapshot can
+/// save (no `rm` in the workspace, a clean work tree, not a repository).
+pub fn take(
+    state_dir: &Path,
+    cmd: &str,
+    cwd: &Path,
+    root: &Path,
+    wrappers: &[String],
+    session: &str,
+) -> Option<Result<String, String>> {
+    let risk = at_risk(cmd, cwd, root, wrappers)?;
+    let now = crate::trash::now_secs();
+    crate::trash::prune(state_dir, now);
+    let id = format!("{}-{}", now, std::process::id());
+    let dir = crate::trash::batch_dir(state_dir, session, &id);
+    let batch = match risk {
+        AtRisk::Changes => {
+            let repo = PathBuf::from(git(cwd, &["rev-parse", "--show-toplevel"])?);
+            let commit = git(&repo, &["stash", "create"])?;
+            let message = format!("safe-bash snapshot {}", id);
+            if git(&repo, &["stash", "store", "-m", &message, &commit]).is_none()
+                && !stored(&repo, &commit)
+            {
+                return Some(Err(format!(
+                    "no snapshot: could not store stash {}",
+                    commit
+                )));
+            }
+            Batch {
+                id: id.clone(),
+                session: session.to_string(),
+                ts: now,
+                paths: vec![repo],
+                kind: Kind::Stash,
+                stash: Some(commit),
+            }
+        }
+        AtRisk::Paths(paths) => {
+            let mut entries = 0;
+            for path in &paths {
+                entries += crate::scope::count_tree(path, MAX_ENTRIES + 1 - entries);
+                if entries > MAX_ENTRIES {
+                    return Some(Err(format!(
+                        "no snapshot: more than {} files and directories to copy",
+                        MAX_ENTRIES
+                    )));
+                }
+            }
+            for path in &paths {
+                if let Err(e) = copy_tree(path, &dir.join(path.strip_prefix("/").unwrap_or(path))) {
+                    let _ = fs::remove_dir_all(&dir);
+                    return Some(Err(format!(
+                        "no snapshot: could not copy {}: {}",
+                        path.display(),
+                        e
+                    )));
+                }
+            }
+            Batch {
+                id: id.clone(),
+                session: session.to_string(),
+                ts: now,
+                paths,
+                kind: Kind::Snapshot,
+                stash: None,
+            }
+        }
+    };
+    Some(match crate::trash::save(&dir, &batch) {
+        Ok(()) => Ok(id),
+        Err(e) => Err(format!(
+            "no snapshot: could not write {}: {}",
+            dir.display(),
+            e
+        )),
+    })
+}
+
+/// `git stash store` prints nothing on success, so check the stash list instead.
+fn stored(repo: &Path, commit: &str) -> bool {
+    git(repo, &["stash", "list", "--format=%H"])
+        .is_some_and(|list| list.lines().any(|l| l == commit))
+}
+
+/// Apply a snapshot's stash commit to the work tree of `repo`.
+pub fn apply_stash(repo: &Path, commit: &str) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["stash", "apply", commit])
+        .output()
+        .map_err(|e| format!("could not run git: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "git stash apply {} failed in {}: {}",
+            commit,
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Run git in `dir` and return its trimmed output, if it succeeded and printed something.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let out = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!out.is_empty()).then_some(out)
+}
+
+/// Copy `src` to `dest`, directories recursively and symlinks as symlinks.
+pub fn copy_tree(src: &Path, dest: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(src)?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if meta.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if meta.file_type().is_symlink() {
+        copy_link(src, dest)
+    } else {
+        fs::copy(src, dest).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_link(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dest)
+}
+
+#[cfg(not(unix))]
+fn copy_link(src: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::Error::other(format!(
+        "cannot copy symlink {}",
+        src.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git_ok(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@t",
+                "-c",
+                "commit.gpgsign=false",
+            ])
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    fn workspace() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/old")).unwrap();
+        fs::write(root.join("src/old/a.rs"), "a").unwrap();
+        fs::write(root.join("src/b.rs"), "b").unwrap();
+        fs::write(root.join("notes.txt"), "n").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn finds_what_a_command_risks() {
+        let (_dir, root) = workspace();
+        let risk = |cmd: &str| at_risk(cmd, &root, &root, &[]);
+        assert_eq!(risk("git reset --hard HEAD~1"), Some(AtRisk::Changes));
+        assert_eq!(risk("git checkout -- src/b.rs"), Some(AtRisk::Changes));
+        assert_eq!(risk("git reset --soft HEAD~1"), None);
+        assert_eq!(
+            risk("rm -rf src/old src/old/a.rs src/*.rs gone"),
+            Some(AtRisk::Paths(vec![
+                root.join("src/b.rs"),
+                root.join("src/old")
+            ]))
+        );
+        assert_eq!(risk("rm -rf /tmp/x ~/y $Z ."), None);
+        assert_eq!(risk("ls src"), None);
+    }
+
+    #[test]
+    fn deleted_paths_copied_and_restored() {
+        let (_dir, root) = workspace();
+        let state = TempDir::new().unwrap();
+        let id = take(
+            state.path(),
+            "rm -r src/old notes.txt",
+            &root,
+            &root,
+            &[],
+            "s1",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(root.join("src/old/a.rs").exists());
+        fs::remove_dir_all(root.join("src/old")).unwrap();
+        fs::remove_file(root.join("notes.txt")).unwrap();
+        let (restored, errors) = crate::trash::restore(state.path(), &id).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read_to_string(root.join("src/old/a.rs")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(root.join("notes.txt")).unwrap(), "n");
+        assert_eq!(
+            take(state.path(), "rm -rf gone", &root, &root, &[], "s1"),
+            None
+        );
+    }
+
+    #[test]
+    fn uncommitted_changes_stashed_and_restored() {
+        let (_dir, root) = workspace();
+        if !git_ok(&root, &["init", "-q"]) {
+            return; // git not installed
+        }
+        assert!(git_ok(&root, &["add", "."]));
+        assert!(git_ok(&root, &["commit", "-qm", "init"]));
+        let state = TempDir::new().unwrap();
+        assert_eq!(
+            take(state.path(), "git reset --hard", &root, &root, &[], "s1"),
+            None
+        );
+        fs::write(root.join("notes.txt"), "edited").unwrap();
+        let id = take(state.path(), "git reset --hard", &root, &root, &[], "s1")
+            .unwrap()
+            .unwrap();
+        assert!(git_ok(&root, &["reset", "-q", "--hard"]));
+        assert_eq!(fs::read_to_string(root.join("notes.txt")).unwrap(), "n");
+        let (restored, errors) = crate::trash::restore(state.path(), &id).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(restored, std::slice::from_ref(&root));
+        assert_eq!(
+            fs::read_to_string(root.join("notes.txt")).unwrap(),
+            "edited"
+        );
+    }
+}
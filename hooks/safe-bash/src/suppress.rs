@@ -0,0 +1,230 @@
+use crate::config::CompiledConfig;
+use crate::patterns::DenyPattern;
+use crate::project::ProjectPolicy;
+use crate::severity::Severity;
+
+/// Prefix assignment that lifts rules for one command: `SAFE_BASH_ALLOW=FS001 rm -rf ./scratch`.
+pub const ALLOW_VAR: &str = "SAFE_BASH_ALLOW";
+
+/// The rule IDs a command asks to lift: those its `# safe-bash: allow ID[, ID…]` comments
+/// name, and those in a `SAFE_BASH_ALLOW=ID[,ID…]` assignment before any of its simple
+/// commands (`env` included). A comment's list ends at the first word not after a comma, so
+/// the rest of the comment can say why.
+pub fn requested(cmd: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut add = |id: &str| {
+        if !id.is_empty() && !ids.iter().any(|have| have == id) {
+            ids.push(id.to_string());
+        }
+    };
+    for comment in crate::normalize::comments(cmd) {
+        let Some(rest) = comment.trim().strip_prefix("safe-bash:") else {
+            continue;
+        };
+        let Some(list) = rest
+            .trim_start()
+            .strip_prefix("allow")
+            .filter(|l| l.starts_with(char::is_whitespace))
+        else {
+            continue;
+        };
+        for item in list.split(',') {
+            let mut words = item.split_whitespace();
+            let Some(id) = words.next() else { break };
+            add(id);
+            if words.next().is_some() {
+                break;
+            }
+        }
+    }
+    let prefix = format!("{}=", ALLOW_VAR);
+    for c in crate::shell::parse(cmd).simple_commands() {
+        let args = c.args();
+        let wrappers = &args[..crate::patterns::unwrap_index(&args, &[])];
+        for value in c
+            .assignments
+            .iter()
+            .map(|w| w.value.as_str())
+            .chain(wrappers.iter().copied())
+            .filter_map(|w| w.strip_prefix(&prefix))
+        {
+            value.split(',').map(str::trim).for_each(&mut add);
+        }
+    }
+    ids
+}
+
+/// What a command's suppression comments did.
+#[derive(Debug, Default, PartialEq)]
+pub struct Suppression {
+    /// Rules lifted for this command, cited as "GIT002 (Destructive: git reset --hard)".
+    pub lifted: Vec<String>,
+    /// IDs named that are not suppressible rules.
+    pub refused: Vec<String>,
+}
+
+impl Suppression {
+    /// What to add to the decision's reason, so the warning and the audit log say what was
+    /// suppressed; None when the command asked for nothing.
+    pub fn note(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if !self.lifted.is_empty() {
+            parts.push(format!("suppressed inline: {}", self.lifted.join(", ")));
+        }
+        if !self.refused.is_empty() {
+            parts.push(format!(
+                "cannot be suppressed inline: {}",
+                self.refused.join(", ")
+            ));
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
+}
+
+/// Lift the rules `ids` names for this command: hardcoded rules listed in
+/// `suppressible_rules` are turned off (or down to their category's floor), and config and
+/// project deny rules marked `suppressible` are dropped. Locked rules are never lifted.
+pub fn apply(
+    ids: &[String],
+    config: &mut CompiledConfig,
+    project: Option<&mut ProjectPolicy>,
+    hardcoded: &[DenyPattern],
+) -> Suppression {
+    let mut suppression = Suppression::default();
+    let mut project = project;
+    for id in ids {
+        let mut lifted = Vec::new();
+        if config.suppressible_rules.contains(id) {
+            for p in hardcoded.iter().filter(|p| p.id == id) {
+                // Down to the category's floor: a file-destructive rule asks instead of blocking
+                let _ = config.severities.set_rule(p.category, p.id, Severity::Off);
+                lifted.push(p.reason.to_string());
+            }
+        }
+        let mut lift = |rules: &mut Vec<crate::config::CompiledPattern>| {
+            rules.retain(|p| {
+                let matched = p.suppressible && p.id == *id;
+                if matched {
+                    lifted.push(p.reason.clone());
+                }
+                !matched
+            })
+        };
+        lift(&mut config.deny);
+        if let Some(policy) = project.as_deref_mut() {
+            lift(&mut policy.deny);
+        }
+        match lifted.first() {
+            Some(reason) => suppression.lifted.push(format!("{} ({})", id, reason)),
+            None => suppression.refused.push(id.clone()),
+        }
+    }
+    suppression
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+
+    fn rule(id: &str, reason: &str, suppressible: bool) -> crate::config::CompiledPattern {
+        crate::config::CompiledPattern {
+            id: id.to_string(),
+            matcher: Matcher::Contains(reason.to_string()),
+            reason: reason.to_string(),
+            suggestion: None,
+            severity: Severity::Deny,
+            unless: Vec::new(),
+            suppressible,
+            field: None,
+            schedule: None,
+            zones: None,
+            origin: Default::default(),
+        }
+    }
+
+    #[test]
+    fn reads_ids_from_comments_only() {
+        assert_eq!(
+            requested("git reset --hard HEAD~1  # safe-bash: allow GIT002"),
+            ["GIT002"]
+        );
+        assert_eq!(
+            requested(
+                "rm -rf x # safe-bash: allow FS001,CFG002 scratch dir\nls #safe-bash: allow GIT002"
+            ),
+            ["FS001", "CFG002", "GIT002"]
+        );
+        assert!(requested("ls # safe-bash: allowed GIT002").is_empty());
+        assert!(requested("echo '# safe-bash: allow GIT002'").is_empty());
+        assert!(requested("git reset --hard # safe-bash allow GIT002").is_empty());
+        assert!(requested("ls # safe-bash: deny GIT002").is_empty());
+    }
+
+    #[test]
+    fn reads_ids_from_allow_assignments() {
+        assert_eq!(
+            requested("SAFE_BASH_ALLOW=FS001 rm -rf ./scratch"),
+            ["FS001"]
+        );
+        assert_eq!(
+            requested("cd app && sudo env SAFE_BASH_ALLOW='GIT002, CFG001' git reset --hard"),
+            ["GIT002", "CFG001"]
+        );
+        assert!(requested("echo SAFE_BASH_ALLOW=FS001 && rm -rf ./scratch").is_empty());
+        assert!(requested("OTHER=FS001 rm -rf ./scratch").is_empty());
+    }
+
+    #[test]
+    fn lifts_only_suppressible_rules() {
+        let hardcoded = crate::patterns::hardcoded_deny_patterns();
+        let mut config = CompiledConfig {
+            deny: vec![
+                rule("CFG001", "deploys go through CI", true),
+                rule("CFG002", "no prod db", false),
+            ],
+            suppressible_rules: vec!["GIT002".to_string(), "FS001".to_string()],
+            ..CompiledConfig::default()
+        };
+        let ids: Vec<String> = ["GIT002", "CFG001", "CFG002", "SEC001"]
+            .map(String::from)
+            .to_vec();
+        let suppression = apply(&ids, &mut config, None, &hardcoded);
+        assert_eq!(
+            suppression.lifted,
+            [
+                "GIT002 (Destructive: git reset --hard)",
+                "CFG001 (deploys go through CI)"
+            ]
+        );
+        assert_eq!(suppression.refused, ["CFG002", "SEC001"]);
+        assert_eq!(
+            config
+                .severities
+                .rule(crate::severity::Category::GitDestructive, "GIT002"),
+            Severity::Off
+        );
+        // A rule in a category with a floor goes down to it
+        apply(&["FS001".to_string()], &mut config, None, &hardcoded);
+        assert_eq!(
+            config
+                .severities
+                .rule(crate::severity::Category::FileDestructive, "FS001"),
+            Severity::Ask
+        );
+        assert_eq!(
+            config
+                .deny
+                .iter()
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>(),
+            ["CFG002"]
+        );
+        assert_eq!(
+            suppression.note().unwrap(),
+            "suppressed inline: GIT002 (Destructive: git reset --hard), CFG001 (deploys go \
+             through CI); cannot be suppressed inline: CFG002, SEC001"
+        );
+        assert_eq!(Suppression::default().note(), None);
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The remote policy source as the update fetch saw it: the URL and the server's public key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub url: String,
+    /// `sha256//<base64>` of the server's DER public key, as curl's `--pinnedpubkey` takes it.
+    pub key: String,
+}
+
+impl Source {
+    fn parse(text: &str) -> Option<Self> {
+        let (url, key) = text.trim().split_once(' ')?;
+        Some(Self {
+            url: url.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("{} (key {})", self.url, self.key)
+    }
+}
+
+/// What the last update fetch saw, written by the background fetch.
+pub fn observed_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-patterns.source")
+}
+
+/// The source seen on first use (or last accepted with `trust-source`), with the fingerprint
+/// of the machine that accepted it.
+pub fn trusted_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-patterns.trusted")
+}
+
+fn read(path: &Path) -> Option<Source> {
+    Source::parse(&fs::read_to_string(path).ok()?)
+}
+
+/// The source on record as trusted.
+#[derive(Debug, PartialEq)]
+enum Pin {
+    /// Accepted on this machine.
+    Local(Source),
+    /// Accepted on another machine, whose fingerprint it carries.
+    Foreign(Source),
+}
+
+/// The trusted source on record. A `url key` pin written before pins carried the machine's
+/// fingerprint was accepted here, and is rewritten with it.
+fn read_trusted(path: &Path) -> Option<Pin> {
+    let text = fs::read_to_string(path).ok()?;
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    match fields.as_slice() {
+        [url, key] => {
+            let source = Source {
+                url: url.to_string(),
+                key: key.to_string(),
+            };
+            write(path, &source);
+            Some(Pin::Local(source))
+        }
+        [url, key, host] => {
+            let source = Source {
+                url: url.to_string(),
+                key: key.to_string(),
+            };
+            Some(if *host == crate::host::fingerprint() {
+                Pin::Local(source)
+            } else {
+                Pin::Foreign(source)
+            })
+        }
+        _ => None,
+    }
+}
+
+fn write(path: &Path, source: &Source) {
+    if let Err(e) = fs::write(
+        path,
+        format!(
+            "{} {} {}\n",
+            source.url,
+            source.key,
+            crate::host::fingerprint()
+        ),
+    ) {
+        crate::warnings::warn(
+            "source-write",
+            &format!("could not write {}: {}", path.display(), e),
+        );
+    }
+}
+
+/// The trusted source, recording the observed one as trusted when there is none yet. Err when
+/// the one on record was accepted on another machine: nothing is trusted, and nothing newly
+/// observed either, until `trust-source` confirms the source on this one.
+pub fn trusted(state_dir: &Path) -> Result<Option<Source>, String> {
+    match read_trusted(&trusted_path(state_dir)) {
+        Some(Pin::Local(source)) => Ok(Some(source)),
+        Some(Pin::Foreign(source)) => Err(format!(
+            "the trusted remote policy source {} was accepted on another machine",
+            source.describe()
+        )),
+        None => {
+            let Some(observed) = read(&observed_path(state_dir)) else {
+                return Ok(None);
+            };
+            write(&trusted_path(state_dir), &observed);
+            Ok(Some(observed))
+        }
+    }
+}
+
+/// A warning when the last fetch saw a different URL or server key than the first one did.
+/// `refuse` says whether updates from the changed source are being refused.
+pub fn check(state_dir: &Path, refuse: bool) -> Option<String> {
+    let updates = if refuse {
+        "updates from it are refused (source_pinning: deny)"
+    } else {
+        "its patterns are still applied — set \"source_pinning\": \"deny\" to refuse them"
+    };
+    let trusted = match trusted(state_dir) {
+        Ok(trusted) => trusted?,
+        Err(why) => {
+            return Some(format!(
+                "{}; {}. Run `safe-bash-hook trust-source` to confirm the source on this machine",
+                why, updates
+            ))
+        }
+    };
+    let observed = read(&observed_path(state_dir))?;
+    if observed == trusted {
+        return None;
+    }
+    Some(format!(
+        "remote policy source changed since first use: was {}, now {}; {}. If the change is \
+         expected, run `safe-bash-hook trust-source` to accept it",
+        trusted.describe(),
+        observed.describe(),
+        updates
+    ))
+}
+
+/// Accept the last observed source as the trusted one. Returns it, or None if nothing has been
+/// observed yet.
+pub fn trust(state_dir: &Path) -> Option<Source> {
+    let observed = read(&observed_path(state_dir))?;
+    write(&trusted_path(state_dir), &observed);
+    Some(observed)
+}
+
+/// Shell that records the source's public key to the observed file. Needs `openssl`; without
+/// it nothing is recorded and no pin applies.
+pub fn observe_script(url: &str, observed: &Path) -> String {
+    let host = url
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or("");
+    let tmp = format!("{}.tmp", observed.display());
+    format!(
+        "key=$(openssl s_client -connect {host}:443 -servername {host} </dev/null 2>/dev/null | \
+         openssl x509 -pubkey -noout 2>/dev/null | openssl pkey -pubin -outform der 2>/dev/null | \
+         openssl dgst -sha256 -binary 2>/dev/null | openssl base64 2>/dev/null); [ -n \"$key\" ] \
+         && printf '%s sha256//%s\\n' {url} \"$key\" > {tmp} && mv {tmp} {observed}",
+        host = crate::autoupdate::shell_quote(host.split(':').next().unwrap_or(host)),
+        url = crate::autoupdate::shell_quote(url),
+        tmp = crate::autoupdate::shell_quote(&tmp),
+        observed = crate::autoupdate::shell_quote(&observed.display().to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn observe(dir: &Path, url: &str, key: &str) {
+        fs::write(observed_path(dir), format!("{} {}\n", url, key)).unwrap();
+    }
+
+    #[test]
+    fn first_observation_becomes_trusted() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(check(dir.path(), false), None);
+        observe(
+            dir.path(),
+            "https://raw.example.test/p.json",
+            "sha256//AAAA",
+        );
+        assert_eq!(check(dir.path(), false), None);
+        let trusted = fs::read_to_string(trusted_path(dir.path())).unwrap();
+        assert_eq!(
+            trusted,
+            format!(
+                "https://raw.example.test/p.json sha256//AAAA {}\n",
+                crate::host::fingerprint()
+            )
+        );
+    }
+
+    #[test]
+    fn pin_from_before_fingerprints_is_kept_and_upgraded() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            trusted_path(dir.path()),
+            "https://raw.example.test/p.json sha256//AAAA\n",
+        )
+        .unwrap();
+        observe(
+            dir.path(),
+            "https://evil.example.test/p.json",
+            "sha256//EEEE",
+        );
+        assert!(check(dir.path(), true)
+            .unwrap()
+            .contains("was https://raw.example.test/p.json (key sha256//AAAA)"));
+        let trusted = fs::read_to_string(trusted_path(dir.path())).unwrap();
+        assert_eq!(
+            trusted,
+            format!(
+                "https://raw.example.test/p.json sha256//AAAA {}\n",
+                crate::host::fingerprint()
+            )
+        );
+    }
+
+    #[test]
+    fn source_trusted_on_another_machine_needs_confirming() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            trusted_path(dir.path()),
+            "https://raw.example.test/p.json sha256//AAAA 0123456789abcdef\n",
+        )
+        .unwrap();
+        observe(
+            dir.path(),
+            "https://evil.example.test/p.json",
+            "sha256//EEEE",
+        );
+        let warning = check(dir.path(), true).unwrap();
+        assert!(
+            warning.contains("accepted on another machine")
+                && warning.contains("refused")
+                && warning.contains("trust-source"),
+            "{}",
+            warning
+        );
+        assert!(trusted(dir.path()).is_err());
+        // The observed source is not taken on trust
+        assert!(fs::read_to_string(trusted_path(dir.path()))
+            .unwrap()
+            .ends_with("0123456789abcdef\n"));
+        assert_eq!(
+            trust(dir.path()).unwrap().url,
+            "https://evil.example.test/p.json"
+        );
+        assert_eq!(check(dir.path(), true), None);
+    }
+
+    #[test]
+    fn changed_key_or_url_warns_until_trusted() {
+        let dir = TempDir::new().unwrap();
+        observe(
+            dir.path(),
+            "https://raw.example.test/p.json",
+            "sha256//AAAA",
+        );
+        assert_eq!(check(dir.path(), false), None);
+        observe(
+            dir.path(),
+            "https://raw.example.test/p.json",
+            "sha256//BBBB",
+        );
+        let warning = check(dir.path(), true).unwrap();
+        assert!(
+            warning.contains(
+                "was https://raw.example.test/p.json (key sha256//AAAA), \
+                 now https://raw.example.test/p.json (key sha256//BBBB)"
+            ),
+            "{}",
+            warning
+        );
+        assert!(warning.contains("refused"), "{}", warning);
+        observe(
+            dir.path(),
+            "https://mirror.example.test/p.json",
+            "sha256//AAAA",
+        );
+        assert!(check(dir.path(), false).unwrap().contains("still applied"));
+        assert_eq!(
+            trust(dir.path()).unwrap().url,
+            "https://mirror.example.test/p.json"
+        );
+        assert_eq!(check(dir.path(), false), None);
+    }
+
+    #[test]
+    fn observe_script_targets_the_url_host() {
+        let script = observe_script(
+            "https://raw.example.test/org/p.json",
+            Path::new("/h/safe-bash-patterns.source"),
+        );
+        assert!(
+            script.contains("-connect 'raw.example.test':443 -servername 'raw.example.test'"),
+            "{}",
+            script
+        );
+        assert!(
+            script.contains("mv '/h/safe-bash-patterns.source.tmp' '/h/safe-bash-patterns.source'"),
+            "{}",
+            script
+        );
+    }
+}
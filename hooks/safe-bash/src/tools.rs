@@ -0,0 +1,383 @@
+use crate::config::{CompiledConfig, CompiledPattern, RuleMatch, ToolRules};
+use crate::severity::Severity;
+use serde_json::Value;
+
+/// The `tool_input` fields a tool's config rules are matched against: the file a file tool
+/// reads or writes, the URL WebFetch fetches, the query WebSearch sends. Tools not listed are
+/// matched against their whole input as compact JSON.
+const SUBJECT_FIELDS: &[(&str, &[&str])] = &[
+    ("Read", &["file_path"]),
+    ("Write", &["file_path"]),
+    ("Edit", &["file_path"]),
+    ("MultiEdit", &["file_path"]),
+    ("NotebookEdit", &["notebook_path"]),
+    ("Glob", &["path", "pattern"]),
+    ("Grep", &["path"]),
+    ("WebFetch", &["url"]),
+    ("WebSearch", &["query"]),
+];
+
+/// What a call of `tool` touches, as its rules see it. A path under the home directory comes a
+/// second time in its `~/` form, so both `/\.ssh/` and `^~/\.ssh/` match `/home/me/.ssh/config`.
+pub fn subjects(tool: &str, input: &Value) -> Vec<String> {
+    let Some((_, fields)) = SUBJECT_FIELDS.iter().find(|(name, _)| *name == tool) else {
+        return vec![input.to_string()];
+    };
+    let home = std::env::var("HOME").unwrap_or_default();
+    let mut subjects = Vec::new();
+    for value in fields
+        .iter()
+        .filter_map(|f| input.get(*f).and_then(Value::as_str))
+    {
+        subjects.push(value.to_string());
+        if let Some(rest) = value
+            .strip_prefix(home.as_str())
+            .filter(|r| !home.is_empty() && (r.is_empty() || r.starts_with('/')))
+        {
+            subjects.push(format!("~{}", rest));
+        }
+    }
+    subjects
+}
+
+/// A call's input as an example of a rule for `tool` gives it (`should_block`/`should_allow`): a
+/// JSON object as is, anything else as the value of the tool's subject field, or for a tool
+/// without one as the subject itself.
+pub fn example_input(tool: &str, example: &str) -> Value {
+    match serde_json::from_str::<Value>(example) {
+        Ok(input @ Value::Object(_)) => input,
+        _ => match SUBJECT_FIELDS.iter().find(|(name, _)| *name == tool) {
+            Some((_, fields)) => serde_json::json!({ fields[0]: example }),
+            None => Value::String(example.to_string()),
+        },
+    }
+}
+
+/// The rules that apply to calls of `tool`: those under its own name and under every glob
+/// matching it (`mcp__*`, `mcp__github__*`).
+pub fn rules_for<'a>(tool: &str, config: &'a CompiledConfig) -> Vec<&'a ToolRules> {
+    let mut names: Vec<&String> = config
+        .tools
+        .keys()
+        .filter(|name| {
+            *name == tool
+                || (name.contains('*')
+                    && crate::matcher::glob_regex(name).is_ok_and(|re| re.is_match(tool)))
+        })
+        .collect();
+    names.sort();
+    names.into_iter().map(|name| &config.tools[name]).collect()
+}
+
+/// Check a call of `tool` with `input` against the config rules for that tool: locked deny
+/// rules first, then the allow rules, any of which lets the call through, then the other deny
+/// rules, the most severe of which wins. The error carries the policy note for the message.
+pub fn check(
+    tool: &str,
+    input: &Value,
+    config: &CompiledConfig,
+) -> Result<(), (RuleMatch, String)> {
+    let rules = rules_for(tool, config);
+    let subjects = match input {
+        // An example's bare subject (`example_input`); calls always send an object
+        Value::String(subject) => vec![subject.clone()],
+        _ => subjects(tool, input),
+    };
+    let matches = |p: &&CompiledPattern| {
+        let matched = match &p.field {
+            Some(path) => path
+                .select(input)
+                .iter()
+                .any(|s| p.matcher.is_match(s, &[])),
+            None => subjects.iter().any(|s| p.matcher.is_match(s, &[])),
+        };
+        matched && p.in_force()
+    };
+    if let Some(found) = worst(rules.iter().flat_map(|r| &r.locked_deny).filter(matches)) {
+        let note = config.locked_note(&found);
+        return Err((found, note));
+    }
+    if rules.iter().flat_map(|r| &r.allow).any(|p| matches(&p)) {
+        return Ok(());
+    }
+    match worst(rules.iter().flat_map(|r| &r.deny).filter(matches)) {
+        Some(found) => {
+            let note = config.layer_note(&found);
+            Err((found, note))
+        }
+        None => Ok(()),
+    }
+}
+
+/// The most severe of the matching deny rules; on a tie the first one.
+fn worst<'a>(matching: impl Iterator<Item = &'a CompiledPattern>) -> Option<RuleMatch> {
+    let mut worst: Option<&CompiledPattern> = None;
+    for p in matching {
+        if p.severity == Severity::Deny {
+            worst = Some(p);
+            break;
+        }
+        if worst.is_none_or(|w| p.severity > w.severity) {
+            worst = Some(p);
+        }
+    }
+    worst.map(CompiledPattern::found)
+}
+
+/// One step of a `JsonPath`.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Any,
+}
+
+/// A rule's `field`: a JSON path such as `$.repo`, `$.files[*].path` or `$['dry-run']` (the `$`
+/// is optional) picking the values in a tool call's input the rule matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath(Vec<Step>);
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<JsonPath, String> {
+        let bad = |why: &str| Err(format!("{:?}: {}", path, why));
+        let mut rest = path.strip_prefix('$').unwrap_or(path);
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('[') {
+                let Some(end) = after.find(']') else {
+                    return bad("unclosed [");
+                };
+                let inner = &after[..end];
+                steps.push(match inner {
+                    "*" => Step::Any,
+                    _ if inner.len() >= 2
+                        && (inner.starts_with('\'') && inner.ends_with('\'')
+                            || inner.starts_with('"') && inner.ends_with('"')) =>
+                    {
+                        Step::Key(inner[1..inner.len() - 1].to_string())
+                    }
+                    _ => match inner.parse() {
+                        Ok(i) => Step::Index(i),
+                        Err(_) => return bad("expected [N], [*] or ['key']"),
+                    },
+                });
+                rest = &after[end + 1..];
+                continue;
+            }
+            // The first key may leave out the dot: "repo.name"
+            let after = match rest.strip_prefix('.') {
+                Some(after) => after,
+                None if steps.is_empty() => rest,
+                None => return bad("expected . or ["),
+            };
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            steps.push(match &after[..end] {
+                "" => return bad("empty key"),
+                "*" => Step::Any,
+                key => Step::Key(key.to_string()),
+            });
+            rest = &after[end..];
+        }
+        Ok(JsonPath(steps))
+    }
+
+    /// The values at the path in `input`: strings as they are, anything else as compact JSON.
+    pub fn select(&self, input: &Value) -> Vec<String> {
+        let mut values = vec![input];
+        for step in &self.0 {
+            values = values
+                .into_iter()
+                .flat_map(|v| -> Vec<&Value> {
+                    match (step, v) {
+                        (Step::Key(key), _) => v.get(key.as_str()).into_iter().collect(),
+                        (Step::Index(i), _) => v.get(*i).into_iter().collect(),
+                        (Step::Any, Value::Array(items)) => items.iter().collect(),
+                        (Step::Any, Value::Object(fields)) => fields.values().collect(),
+                        (Step::Any, _) => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        values
+            .into_iter()
+            .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn config(json: &str) -> CompiledConfig {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(json.as_bytes()).unwrap();
+        crate::config::load_config(f.path())
+    }
+
+    #[test]
+    fn subjects_by_tool() {
+        let home = std::env::var("HOME").unwrap();
+        let input =
+            serde_json::json!({"file_path": format!("{}/.ssh/config", home), "content": "x"});
+        assert_eq!(
+            subjects("Write", &input),
+            [format!("{}/.ssh/config", home), "~/.ssh/config".to_string()]
+        );
+        assert_eq!(
+            subjects(
+                "WebFetch",
+                &serde_json::json!({"url": "https://example.test", "prompt": "p"})
+            ),
+            ["https://example.test"]
+        );
+        assert_eq!(
+            subjects("Read", &serde_json::json!({"file_path": "/etc/hosts"})),
+            ["/etc/hosts"]
+        );
+        assert_eq!(
+            subjects("TodoWrite", &serde_json::json!({"todos": []})),
+            [r#"{"todos":[]}"#]
+        );
+    }
+
+    #[test]
+    fn rules_apply_to_their_tools_only() {
+        let config = config(
+            r#"{
+                "deny": [{"pattern": "\\.env$", "reason": "no env files", "tools": ["Write",
+                "Edit"]}],
+                "tools": {
+                    "Read": {
+                        "deny": [{"type": "glob", "pattern": "~/.ssh/*", "reason": "keys stay put",
+                        "locked": true},
+                                 {"pattern": "/secrets/", "reason": "secrets", "severity": "ask"}],
+                        "allow": [{"pattern": "/secrets/README", "reason": "docs"}]
+                    },
+                    "WebFetch": {"deny": [{"pattern": "^http://", "reason": "https only",
+                    "severity": "warn"}]}
+                }
+            }"#,
+        );
+        let check = |tool: &str, input: Value| {
+            check(tool, &input, &config).map_err(|(found, _)| (found.reason, found.severity))
+        };
+        let path = |p: &str| serde_json::json!({"file_path": p});
+        assert_eq!(
+            check("Write", path("/app/.env")),
+            Err(("no env files".to_string(), Severity::Deny))
+        );
+        assert_eq!(
+            check("Edit", path("/app/.env")),
+            Err(("no env files".to_string(), Severity::Deny))
+        );
+        assert_eq!(check("Read", path("/app/.env")), Ok(()));
+        let key = format!("{}/.ssh/id_ed25519", std::env::var("HOME").unwrap());
+        assert_eq!(
+            check("Read", path(&key)),
+            Err(("keys stay put".to_string(), Severity::Deny))
+        );
+        assert_eq!(
+            check("Read", path("/srv/secrets/token")),
+            Err(("secrets".to_string(), Severity::Ask))
+        );
+        assert_eq!(check("Read", path("/srv/secrets/README")), Ok(()));
+        assert_eq!(
+            check(
+                "WebFetch",
+                serde_json::json!({"url": "http://example.test"})
+            ),
+            Err(("https only".to_string(), Severity::Warn))
+        );
+        // The Bash rules are untouched by the others
+        assert!(config.deny.is_empty() && config.allow.is_empty());
+        assert!(crate::config::check_config("cat .env", &config).is_ok());
+    }
+
+    #[test]
+    fn json_paths() {
+        let input = serde_json::json!({
+            "owner": "apollo",
+            "files": [{"path": "a.rs"}, {"path": "b.rs"}],
+            "dry-run": false,
+            "n": 3,
+        });
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&input);
+        assert_eq!(select("$.owner"), ["apollo"]);
+        assert_eq!(select("owner"), ["apollo"]);
+        assert_eq!(select("$.files[*].path"), ["a.rs", "b.rs"]);
+        assert_eq!(select("$.files[1].path"), ["b.rs"]);
+        assert_eq!(select("$['dry-run']"), ["false"]);
+        assert_eq!(select("$.n"), ["3"]);
+        assert!(select("$.missing.deeper").is_empty());
+        for bad in ["$.files[", "$.files[x]", "$..owner", "$.files[0]path"] {
+            assert!(JsonPath::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn mcp_rules_by_name_glob_and_field() {
+        let config = config(
+            r#"{
+                "tools": {
+                    "mcp__github__delete_*": {"deny": [{"reason": "no deleting on GitHub"}]},
+                    "mcp__github__*": {
+                        "deny": [{"field": "$.owner", "type": "exact", "pattern": "apollo-com-ph",
+                        "reason": "org repos by PR", "severity": "ask"}]
+                    },
+                    "mcp__*": {"deny": [{"field": "$.query", "pattern": "(?i)\\bdrop\\s+table\\b",
+                    "reason": "no DDL"}]}
+                }
+            }"#,
+        );
+        let check = |tool: &str, input: Value| {
+            check(tool, &input, &config).map_err(|(found, _)| (found.reason, found.severity))
+        };
+        assert_eq!(
+            check(
+                "mcp__github__delete_repo",
+                serde_json::json!({"owner": "me", "repo": "x"})
+            ),
+            Err(("no deleting on GitHub".to_string(), Severity::Deny))
+        );
+        assert_eq!(
+            check(
+                "mcp__github__create_issue",
+                serde_json::json!({"owner": "apollo-com-ph"})
+            ),
+            Err(("org repos by PR".to_string(), Severity::Ask))
+        );
+        assert_eq!(
+            check(
+                "mcp__github__create_issue",
+                serde_json::json!({"owner": "me"})
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check(
+                "mcp__postgres__query",
+                serde_json::json!({"query": "DROP TABLE users"})
+            ),
+            Err(("no DDL".to_string(), Severity::Deny))
+        );
+        assert_eq!(
+            check(
+                "mcp__postgres__query",
+                serde_json::json!({"query": "select 1"})
+            ),
+            Ok(())
+        );
+        assert!(rules_for("Write", &config).is_empty());
+        // Examples give the input as JSON, or the subject of a tool that has one
+        assert_eq!(
+            example_input("mcp__x__y", r#"{"owner": "me"}"#),
+            serde_json::json!({"owner": "me"})
+        );
+        assert_eq!(
+            example_input("Write", "/app/.env"),
+            serde_json::json!({"file_path": "/app/.env"})
+        );
+    }
+}
@@ -0,0 +1,726 @@
+use crate::shell::{Connector, Node};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The rm rules a trash move answers: the files stay recoverable, so `rm -rf build` is decided
+/// without them.
+pub const COVERED: &[&str] = &["FS001", "FS002", "FS010"];
+
+/// Trashed batches older than this are deleted for good the next time something is trashed.
+const TRASH_TTL_SECS: u64 = 7 * 24 * 3600;
+
+/// How long a rewritten `rm` has to run before its approval lapses.
+const APPROVAL_TTL_SECS: u64 = 600;
+
+/// One `rm` turned into a move, or a snapshot taken before a risky command (see snapshot.rs):
+/// where its paths were, recorded next to them in the trash.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Batch {
+    pub id: String,
+    pub session: String,
+    pub ts: u64,
+    /// Absolute paths the moved or copied files and directories came from; for a stash, the
+    /// repository.
+    pub paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub kind: Kind,
+    /// The commit `git stash create` made of the repository's uncommitted changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stash: Option<String>,
+}
+
+/// How a batch holds what it saved.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// Moved here instead of deleted.
+    #[default]
+    Trash,
+    /// Copied here before a command that may delete them.
+    Snapshot,
+    /// Uncommitted changes kept as a stash commit.
+    Stash,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Trash => "trash",
+            Kind::Snapshot => "snapshot",
+            Kind::Stash => "stash",
+        }
+    }
+}
+
+/// What `safe-bash-hook trash` was asked to move, parsed from its arguments.
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub session: String,
+    pub id: String,
+    /// The workspace root nothing may be moved from outside of, found from the working
+    /// directory when the call runs rather than taken from its arguments.
+    pub root: PathBuf,
+    pub recursive: bool,
+    pub force: bool,
+    pub paths: Vec<String>,
+}
+
+/// What the hook approved when it rewrote an `rm` into batch `id`: `trash` runs only with a
+/// matching approval, which it uses up, so a call the hook did not write itself moves nothing.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Approval {
+    pub cwd: PathBuf,
+    pub root: PathBuf,
+    pub recursive: bool,
+    pub force: bool,
+    pub ts: u64,
+}
+
+/// Per-session trash directories, each holding one directory per batch.
+pub fn trash_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-trash")
+}
+
+/// Where batch `id` of `session` keeps its files and `batch.json`.
+pub fn batch_dir(state_dir: &Path, session: &str, id: &str) -> PathBuf {
+    trash_dir(state_dir).join(session).join(id)
+}
+
+/// Where the approval of batch `id` of `session` waits for its `trash` call.
+fn approval_path(state_dir: &Path, session: &str, id: &str) -> PathBuf {
+    state_dir
+        .join("safe-bash-trash-approvals")
+        .join(format!("{}.{}.json", session, id))
+}
+
+/// A fresh batch id: the time and the hook's pid.
+pub fn new_id() -> String {
+    format!("{}-{}", now_secs(), std::process::id())
+}
+
+/// Record that the hook rewrote an `rm` into the `trash` call for batch `id`.
+pub fn approve(state_dir: &Path, session: &str, id: &str, approval: &Approval) -> io::Result<()> {
+    let path = approval_path(state_dir, session, id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(approval).unwrap_or_default())
+}
+
+/// Use up the approval of `request`: true if the hook wrote it for this call, run from `cwd`
+/// in workspace `request.root` with the same options, and it has not lapsed.
+pub fn take_approval(state_dir: &Path, request: &Request, cwd: &Path, now: u64) -> bool {
+    let path = approval_path(state_dir, &request.session, &request.id);
+    let approval: Option<Approval> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok());
+    let _ = fs::remove_file(&path);
+    // The hook saw the cwd Claude Code reported, which may go through a symlink
+    let same = |a: &Path, b: &Path| {
+        a == b
+            || a.canonicalize()
+                .ok()
+                .zip(b.canonicalize().ok())
+                .is_some_and(|(a, b)| a == b)
+    };
+    approval.is_some_and(|a| {
+        same(&a.cwd, cwd)
+            && same(&a.root, &request.root)
+            && a.recursive == request.recursive
+            && a.force == request.force
+            && now.saturating_sub(a.ts) <= APPROVAL_TTL_SECS
+    })
+}
+
+/// Write a batch's record into its directory, creating it if need be.
+pub fn save(dir: &Path, batch: &Batch) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(
+        dir.join("batch.json"),
+        serde_json::to_string_pretty(batch).unwrap_or_default(),
+    )
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The `trash` call for batch `id` standing in for a command that is a single `rm` of paths
+/// inside `root` (`rm -rf build src/old.rs` → `<exe> trash --session … --id … -r -f -- build
+/// src/old.rs`), keeping the operands' quoting and globs for the shell to expand and any redirects
+/// after them. None for anything else: other commands, wrappers or assignments, options rm has
+/// beyond `-r`/`-f`/`-v` (`-i` included), `$`, `~` or substituted paths, and paths outside
+/// `root`, equal to it, or in its `.git`. Comes with the approval the call needs to run.
+pub fn rewrite(
+    cmd: &str,
+    cwd: &Path,
+    root: &Path,
+    exe: &str,
+    session: &str,
+    id: &str,
+) -> Option<(String, Approval)> {
+    let Node::List(items) = crate::shell::parse(cmd) else {
+        return None;
+    };
+    let [item] = items.as_slice() else {
+        return None;
+    };
+    let Node::Simple(simple) = &item.node else {
+        return None;
+    };
+    if item.connector == Some(Connector::Background)
+        || !simple.assignments.is_empty()
+        || !simple.substitutions().is_empty()
+    {
+        return None;
+    }
+    let (program, args) = simple.argv.split_first()?;
+    if program.value.rsplit('/').next() != Some("rm") {
+        return None;
+    }
+    let (mut recursive, mut force, mut options_done) = (false, false, false);
+    let mut operands = Vec::new();
+    for word in args {
+        let arg = word.value.as_str();
+        if !options_done && arg.starts_with('-') && arg != "-" {
+            match arg {
+                "--" => options_done = true,
+                "--recursive" => recursive = true,
+                "--force" => force = true,
+                "--verbose" => {}
+                _ if !arg.starts_with("--") && arg[1..].chars().all(|c| "rRfv".contains(c)) => {
+                    recursive |= arg.contains(['r', 'R']);
+                    force |= arg.contains('f');
+                }
+                _ => return None,
+            }
+        } else if inside(arg, cwd, root) {
+            operands.push(word.raw.as_str());
+        } else {
+            return None;
+        }
+    }
+    if operands.is_empty() {
+        return None;
+    }
+    // The words must sit together so nothing between them is dropped
+    let start = cmd.find(&program.raw)?;
+    let mut end = start + program.raw.len();
+    for word in args {
+        let at = end + cmd[end..].find(&word.raw)?;
+        if !cmd[end..at].trim().is_empty() {
+            return None;
+        }
+        end = at + word.raw.len();
+    }
+    let mut call = format!(
+        "{} trash --session {} --id {}",
+        crate::shell::quote(exe),
+        crate::shell::quote(session),
+        crate::shell::quote(id)
+    );
+    if recursive {
+        call.push_str(" -r");
+    }
+    if force {
+        call.push_str(" -f");
+    }
+    call.push_str(" -- ");
+    call.push_str(&operands.join(" "));
+    let approval = Approval {
+        cwd: cwd.to_path_buf(),
+        root: root.to_path_buf(),
+        recursive,
+        force,
+        ts: now_secs(),
+    };
+    Some((
+        format!("{}{}{}", &cmd[..start], call, &cmd[end..]),
+        approval,
+    ))
+}
+
+/// Whether an rm operand, as written, names something strictly inside `root` and outside its
+/// `.git`.
+fn inside(arg: &str, cwd: &Path, root: &Path) -> bool {
+    if arg.is_empty() || arg.starts_with('~') || arg.contains(['$', '`']) {
+        return false;
+    }
+    let path = crate::perms::resolve(arg, cwd, None);
+    path.starts_with(root) && path != root && !path.starts_with(root.join(".git"))
+}
+
+/// Parse `trash --session <s> --id <id> [-r] [-f] -- <path>…`; the root is left for the caller.
+pub fn parse_request(args: &[String]) -> Option<Request> {
+    let mut request = Request {
+        session: String::new(),
+        id: String::new(),
+        root: PathBuf::new(),
+        recursive: false,
+        force: false,
+        paths: Vec::new(),
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--session" => request.session = iter.next()?.clone(),
+            "--id" => request.id = iter.next()?.clone(),
+            "-r" => request.recursive = true,
+            "-f" => request.force = true,
+            "--" => {
+                request.paths = iter.cloned().collect();
+                break;
+            }
+            _ => return None,
+        }
+    }
+    let valid = |s: &str| !s.is_empty() && !s.contains(['/', '\\']) && s != "." && s != "..";
+    (valid(&request.session) && valid(&request.id)).then_some(request)
+}
+
+/// Move the requested paths (relative to `cwd`) into the session's trash as one batch, under
+/// their absolute path, and record the batch. Returns the paths moved and an rm-style error
+/// per path that was not; with `-f`, missing paths are skipped quietly. Paths whose real
+/// location is outside the root (`..` after a glob, a symlinked parent) are refused.
+pub fn move_to_trash(
+    state_dir: &Path,
+    request: &Request,
+    cwd: &Path,
+) -> (Vec<PathBuf>, Vec<String>) {
+    prune(state_dir, now_secs());
+    let batch_dir = batch_dir(state_dir, &request.session, &request.id);
+    let real_root = request
+        .root
+        .canonicalize()
+        .unwrap_or_else(|_| request.root.clone());
+    let (mut moved, mut errors) = (Vec::new(), Vec::new());
+    for arg in &request.paths {
+        let path = crate::perms::resolve(arg, cwd, None);
+        let real_parent = path.parent().and_then(|p| p.canonicalize().ok());
+        let Some(real) = real_parent
+            .zip(path.file_name())
+            .map(|(parent, name)| parent.join(name))
+        else {
+            if !request.force {
+                errors.push(format!(
+                    "cannot remove '{}': No such file or directory",
+                    arg
+                ));
+            }
+            continue;
+        };
+        if !real.starts_with(&real_root)
+            || real == real_root
+            || real.starts_with(real_root.join(".git"))
+        {
+            errors.push(format!(
+                "refusing to trash '{}': outside {}",
+                arg,
+                request.root.display()
+            ));
+            continue;
+        }
+        let Ok(meta) = fs::symlink_metadata(&real) else {
+            if !request.force {
+                errors.push(format!(
+                    "cannot remove '{}': No such file or directory",
+                    arg
+                ));
+            }
+            continue;
+        };
+        if meta.is_dir() && !request.recursive {
+            errors.push(format!("cannot remove '{}': Is a directory", arg));
+            continue;
+        }
+        let dest = batch_dir.join(real.strip_prefix("/").unwrap_or(&real));
+        let result = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| move_path(&real, &dest));
+        match result {
+            Ok(()) => moved.push(real),
+            Err(e) => errors.push(format!("cannot move '{}' to the trash: {}", arg, e)),
+        }
+    }
+    if !moved.is_empty() {
+        let batch = Batch {
+            id: request.id.clone(),
+            session: request.session.clone(),
+            ts: now_secs(),
+            paths: moved.clone(),
+            kind: Kind::Trash,
+            stash: None,
+        };
+        if let Err(e) = save(&batch_dir, &batch) {
+            errors.push(format!(
+                "could not record the batch in {}: {}",
+                batch_dir.display(),
+                e
+            ));
+        }
+    }
+    (moved, errors)
+}
+
+/// Move `from` to `to`. The trash lives in the state directory, which need not be on the
+/// workspace's filesystem (`/tmp`, a bind mount, a container volume), so across filesystems
+/// the path is copied and then removed.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = crate::snapshot::copy_tree(from, to) {
+                let _ = remove_path(to);
+                return Err(e);
+            }
+            remove_path(from)
+        }
+        moved => moved,
+    }
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Every trashed batch, oldest first, with its directory.
+pub fn batches(state_dir: &Path) -> Vec<(PathBuf, Batch)> {
+    let mut out: Vec<(PathBuf, Batch)> = fs::read_dir(trash_dir(state_dir))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flat_map(|session| fs::read_dir(session.path()).into_iter().flatten().flatten())
+        .filter_map(|entry| {
+            let batch = fs::read_to_string(entry.path().join("batch.json"))
+                .ok()
+                .and_then(|s| serde_json::from_str(&s).ok())?;
+            Some((entry.path(), batch))
+        })
+        .collect();
+    out.sort_by(|a, b| (a.1.ts, &a.1.id).cmp(&(b.1.ts, &b.1.id)));
+    out
+}
+
+/// Move a batch's paths back where they came from, or apply its stash to the repository.
+/// Returns the paths restored and a message per path that was not because something took its
+/// place since, which stays in the batch; the batch is removed from the trash once nothing is
+/// left in it. None if there is no batch `id`.
+pub fn restore(state_dir: &Path, id: &str) -> Option<(Vec<PathBuf>, Vec<String>)> {
+    let (dir, mut batch) = batches(state_dir).into_iter().find(|(_, b)| b.id == id)?;
+    if let (Some(commit), Some(repo)) = (&batch.stash, batch.paths.first()) {
+        return Some(match crate::snapshot::apply_stash(repo, commit) {
+            Ok(()) => {
+                let _ = fs::remove_dir_all(&dir);
+                (vec![repo.clone()], Vec::new())
+            }
+            Err(e) => (Vec::new(), vec![e]),
+        });
+    }
+    let (mut restored, mut errors) = (Vec::new(), Vec::new());
+    for path in &batch.paths {
+        let trashed = dir.join(path.strip_prefix("/").unwrap_or(path));
+        if fs::symlink_metadata(path).is_ok() {
+            errors.push(format!(
+                "{} exists, left in the trash at {}",
+                path.display(),
+                trashed.display()
+            ));
+            continue;
+        }
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| move_path(&trashed, path));
+        match result {
+            Ok(()) => restored.push(path.clone()),
+            Err(e) => errors.push(format!("could not restore {}: {}", path.display(), e)),
+        }
+    }
+    batch.paths.retain(|p| !restored.contains(p));
+    if batch.paths.is_empty() {
+        let _ = fs::remove_dir_all(&dir);
+    } else {
+        let _ = save(&dir, &batch);
+    }
+    Some((restored, errors))
+}
+
+/// Delete batches older than the TTL, and session directories left empty.
+pub fn prune(state_dir: &Path, now: u64) {
+    for (dir, batch) in batches(state_dir) {
+        if now.saturating_sub(batch.ts) > TRASH_TTL_SECS {
+            let _ = fs::remove_dir_all(&dir);
+            if let Some(session) = dir.parent() {
+                // Only succeeds when empty
+                let _ = fs::remove_dir(session);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn trash(cmd: &str) -> Option<String> {
+        rewrite(
+            cmd,
+            Path::new("/work/app/src"),
+            Path::new("/work/app"),
+            "/bin/safe-bash-hook",
+            "s1",
+            "1-2",
+        )
+        .map(|(call, _)| call)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn moves_across_filesystems() {
+        use std::os::unix::fs::MetadataExt;
+        let state = TempDir::new().unwrap();
+        let Ok(other) = tempfile::Builder::new().tempdir_in("/dev/shm") else {
+            return;
+        };
+        if fs::metadata(other.path()).unwrap().dev() == fs::metadata(state.path()).unwrap().dev() {
+            return; // no second filesystem to move across
+        }
+        fs::create_dir_all(other.path().join("old/nested")).unwrap();
+        fs::write(other.path().join("old/nested/f.txt"), "data").unwrap();
+        let dest = state.path().join("batch/old");
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        move_path(&other.path().join("old"), &dest).unwrap();
+        assert!(!other.path().join("old").exists());
+        assert_eq!(
+            fs::read_to_string(dest.join("nested/f.txt")).unwrap(),
+            "data"
+        );
+        move_path(&dest, &other.path().join("old")).unwrap();
+        assert_eq!(
+            fs::read_to_string(other.path().join("old/nested/f.txt")).unwrap(),
+            "data"
+        );
+    }
+
+    #[test]
+    fn rm_of_project_files_becomes_a_trash_call() {
+        assert_eq!(
+            trash("rm -rf old").as_deref(),
+            Some("/bin/safe-bash-hook trash --session s1 --id 1-2 -r -f -- old")
+        );
+        assert_eq!(
+            trash("rm a.txt 'b c.txt' ../docs/*.md 2>/dev/null").as_deref(),
+            Some(
+                "/bin/safe-bash-hook trash --session s1 --id 1-2 -- a.txt 'b c.txt' ../docs/*.md \
+                 2>/dev/null"
+            )
+        );
+        assert_eq!(
+            trash("rm -v --recursive -- -x").as_deref(),
+            Some("/bin/safe-bash-hook trash --session s1 --id 1-2 -r -- -x")
+        );
+        assert_eq!(
+            trash("/bin/rm -R /work/app/build").as_deref(),
+            Some("/bin/safe-bash-hook trash --session s1 --id 1-2 -r -- /work/app/build")
+        );
+    }
+
+    #[test]
+    fn other_deletes_are_left_alone() {
+        for cmd in [
+            "rm -rf ../..",
+            "rm -rf ..",
+            "rm -rf ../.git",
+            "rm -rf /tmp/x",
+            "rm -rf ~/notes",
+            "rm -rf $DIR",
+            "rm -ri old",
+            "rm -rf --no-preserve-root old",
+            "rm -rf",
+            "sudo rm -rf old",
+            "X=1 rm old",
+            "rm -rf old && ls",
+            "rm -rf old &",
+            "rm -rf $(cat list)",
+            "rmdir old",
+        ] {
+            assert_eq!(trash(cmd), None, "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let args: Vec<String> = ["--session", "s1", "--id", "1-2", "-r", "--", "-x", "y"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let request = parse_request(&args).unwrap();
+        assert_eq!(request.paths, ["-x", "y"]);
+        assert!(request.recursive && !request.force);
+        let bad: Vec<String> = ["--session", "../s", "--id", "1", "--", "x"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(parse_request(&bad), None);
+        let rooted: Vec<String> = ["--session", "s1", "--id", "1", "--root", "/", "--", "x"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(parse_request(&rooted), None);
+    }
+
+    #[test]
+    fn only_an_approved_call_runs_once() {
+        let state = TempDir::new().unwrap();
+        let request = Request {
+            session: "s1".to_string(),
+            id: "1-2".to_string(),
+            root: PathBuf::from("/work/app"),
+            recursive: true,
+            force: true,
+            paths: vec!["old".to_string()],
+        };
+        let cwd = Path::new("/work/app/src");
+        assert!(!take_approval(state.path(), &request, cwd, 1000));
+        let approval = Approval {
+            cwd: cwd.to_path_buf(),
+            root: request.root.clone(),
+            recursive: true,
+            force: true,
+            ts: 1000,
+        };
+        approve(state.path(), "s1", "1-2", &approval).unwrap();
+        assert!(take_approval(state.path(), &request, cwd, 1000));
+        assert!(!take_approval(state.path(), &request, cwd, 1000));
+
+        // Another workspace, other options or a lapsed approval are refused, and use it up too
+        for (root, recursive, now) in [
+            ("/", true, 1000),
+            ("/work/app", false, 1000),
+            ("/work/app", true, 1001 + APPROVAL_TTL_SECS),
+        ] {
+            approve(state.path(), "s1", "1-2", &approval).unwrap();
+            let other = Request {
+                session: "s1".to_string(),
+                id: "1-2".to_string(),
+                root: PathBuf::from(root),
+                recursive,
+                force: true,
+                paths: Vec::new(),
+            };
+            assert!(
+                !take_approval(state.path(), &other, cwd, now),
+                "{} {} {}",
+                root,
+                recursive,
+                now
+            );
+            assert!(!take_approval(state.path(), &request, cwd, 1000));
+        }
+    }
+
+    #[test]
+    fn trashed_paths_restore_to_where_they_were() {
+        let (state, root) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let root_path = root.path().canonicalize().unwrap();
+        fs::create_dir_all(root_path.join("build/out")).unwrap();
+        fs::write(root_path.join("build/out/app"), "bin").unwrap();
+        fs::write(root_path.join("notes.txt"), "keep").unwrap();
+        let request = |paths: &[&str], recursive: bool, force: bool| Request {
+            session: "s1".to_string(),
+            id: "100-1".to_string(),
+            root: root_path.clone(),
+            recursive,
+            force,
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+        };
+
+        let (moved, errors) =
+            move_to_trash(state.path(), &request(&["build"], false, false), &root_path);
+        assert!(moved.is_empty());
+        assert_eq!(errors, ["cannot remove 'build': Is a directory"]);
+        let (_, errors) = move_to_trash(
+            state.path(),
+            &request(&["gone", "../x"], true, false),
+            &root_path,
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors[1].starts_with("refusing to trash '../x'"),
+            "{:?}",
+            errors
+        );
+        let (moved, errors) = move_to_trash(
+            state.path(),
+            &request(&["build", "notes.txt", "gone"], true, true),
+            &root_path,
+        );
+        assert_eq!(
+            moved,
+            [root_path.join("build"), root_path.join("notes.txt")]
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(!root_path.join("build").exists());
+        assert_eq!(batches(state.path()).len(), 1);
+
+        fs::write(root_path.join("notes.txt"), "new").unwrap();
+        let (restored, errors) = restore(state.path(), "100-1").unwrap();
+        assert_eq!(restored, [root_path.join("build")]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            fs::read_to_string(root_path.join("build/out/app")).unwrap(),
+            "bin"
+        );
+        assert_eq!(
+            fs::read_to_string(root_path.join("notes.txt")).unwrap(),
+            "new"
+        );
+        assert_eq!(batches(state.path()).len(), 1);
+        fs::remove_file(root_path.join("notes.txt")).unwrap();
+        let (restored, errors) = restore(state.path(), "100-1").unwrap();
+        assert_eq!(restored, [root_path.join("notes.txt")]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            fs::read_to_string(root_path.join("notes.txt")).unwrap(),
+            "keep"
+        );
+        assert!(batches(state.path()).is_empty());
+        assert_eq!(restore(state.path(), "100-1"), None);
+    }
+
+    #[test]
+    fn old_batches_pruned() {
+        let state = TempDir::new().unwrap();
+        let dir = trash_dir(state.path()).join("s1/1-1");
+        fs::create_dir_all(&dir).unwrap();
+        let batch = Batch {
+            id: "1-1".to_string(),
+            session: "s1".to_string(),
+            ts: 1000,
+            paths: Vec::new(),
+            kind: Kind::Trash,
+            stash: None,
+        };
+        fs::write(
+            dir.join("batch.json"),
+            serde_json::to_string(&batch).unwrap(),
+        )
+        .unwrap();
+        prune(state.path(), 1000 + TRASH_TTL_SECS);
+        assert_eq!(batches(state.path()).len(), 1);
+        prune(state.path(), 1001 + TRASH_TTL_SECS);
+        assert!(batches(state.path()).is_empty());
+        assert!(!trash_dir(state.path()).join("s1").exists());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+
+/// Variables the shell always sets, so an rm path starting with them can't collapse to `/`.
+const ALWAYS_SET: &[&str] = &["HOME", "PWD", "USER"];
+
+/// Detect `rm -r` whose path argument starts with a variable that could be unset or empty —
+/// `rm -rf "$BUILD_DIR"/` removes `/` when BUILD_DIR is unset. `${VAR:?}`, a non-empty
+/// default (`${VAR:-dist}`) or an earlier `set -u` / `set -o nounset` count as guarded.
+/// Variables assigned earlier in the string should already be substituted (indirection.rs).
+pub fn check(cmd: &str, wrappers: &[String]) -> Option<Hit> {
+    let ast = crate::shell::parse(cmd);
+    let mut nounset = false;
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let start = crate::patterns::unwrap_index(&args, wrappers);
+        let Some(program) = args.get(start) else {
+            continue;
+        };
+        match program.rsplit('/').next() {
+            Some("set") => nounset |= sets_nounset(&args[start + 1..]),
+            Some("rm") if !nounset => {
+                let words = &command.argv[start + 1..];
+                if !words.iter().any(|w| is_recursive_flag(&w.value)) {
+                    continue;
+                }
+                let mut options_done = false;
+                for word in words {
+                    if !options_done && word.value.starts_with('-') {
+                        options_done = word.value == "--";
+                        continue;
+                    }
+                    if let Some((name, after)) = unguarded_variable(&word.raw) {
+                        let removes = if after.trim_start_matches('"').starts_with('/') {
+                            "paths under /"
+                        } else {
+                            "the wrong path"
+                        };
+                        return Some(Hit {
+                            id: "UV101",
+                            reason: format!(
+                                "Destructive: rm -r {} — if {} is unset or empty this removes {}; \
+                                 use \"${{{}:?}}\" or set -u",
+                                word.raw, name, removes, name
+                            ),
+                            category: Category::UnguardedVariable,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_recursive_flag(arg: &str) -> bool {
+    arg == "--recursive"
+        || (arg.starts_with('-') && !arg.starts_with("--") && arg.contains(['r', 'R']))
+}
+
+/// `set -u`, `set -eu`, `set -o nounset`. `set +u` turns it back off but is rare enough to ignore.
+fn sets_nounset(args: &[&str]) -> bool {
+    args.iter()
+        .any(|a| a.starts_with('-') && !a.starts_with("--") && a.contains('u'))
+        || args.windows(2).any(|w| w[0] == "-o" && w[1] == "nounset")
+}
+
+/// The variable a raw word starts with (after an opening quote) and the text after the
+/// reference, unless it is guarded.
+fn unguarded_variable(raw: &str) -> Option<(String, &str)> {
+    let rest = raw.trim_start_matches('"').strip_prefix('$')?;
+    let (name, guarded, after) = if let Some(braced) = rest.strip_prefix('{') {
+        let close = braced.find('}')?;
+        let inner = &braced[..close];
+        let (name, op) = inner.split_at(identifier_len(inner));
+        let guarded = op.starts_with('?')
+            || op.starts_with(":?")
+            || ["-", ":-", "=", ":="]
+                .iter()
+                .any(|g| op.strip_prefix(g).is_some_and(|d| !d.is_empty()));
+        (name, guarded, &braced[close + 1..])
+    } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        (&rest[..1], false, &rest[1..])
+    } else {
+        let len = identifier_len(rest);
+        (&rest[..len], false, &rest[len..])
+    };
+    if name.is_empty() || guarded || ALWAYS_SET.contains(&name) {
+        return None;
+    }
+    Some((name.to_string(), after))
+}
+
+fn identifier_len(s: &str) -> usize {
+    s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(s.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(cmd: &str) -> Option<String> {
+        check(cmd, &[]).map(|h| h.reason)
+    }
+
+    #[test]
+    fn flags_unguarded_variable_paths() {
+        let r = reason("rm -rf \"$BUILD_DIR\"/").unwrap();
+        assert!(
+            r.contains("if BUILD_DIR is unset or empty this removes paths under /"),
+            "{}",
+            r
+        );
+        assert!(r.contains("${BUILD_DIR:?}"), "{}", r);
+        assert!(reason("rm -rf $STAGING/*").is_some());
+        assert!(reason("rm -r ${OUT}/cache").is_some());
+        assert!(reason("rm -fr -- \"$1\"/").is_some());
+        assert!(reason("rm -rf ${TMP:-}/x").is_some());
+        assert!(reason("sudo rm -rf \"$PREFIX/lib\"").is_some());
+    }
+
+    #[test]
+    fn guarded_or_known_variables_pass() {
+        assert!(reason("rm -rf \"${BUILD_DIR:?}\"/").is_none());
+        assert!(reason("rm -rf \"${BUILD_DIR?not set}/out\"").is_none());
+        assert!(reason("rm -rf ${OUT:-dist}/").is_none());
+        assert!(reason("set -eu; rm -rf \"$BUILD_DIR\"/").is_none());
+        assert!(reason("set -o nounset && rm -rf $D/").is_none());
+        assert!(reason("rm -rf \"$HOME/.cache/pip\"").is_none());
+        assert!(reason("rm -f \"$LOG\"").is_none());
+        assert!(reason("rm -rf build/$TARGET").is_none());
+    }
+}
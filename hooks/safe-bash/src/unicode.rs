@@ -0,0 +1,132 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Invisible code points that render as nothing but split a word for the regex engine:
+/// zero-width spaces/joiners, word joiner, invisible operators, BOM, soft hyphen,
+/// Mongolian vowel separator, and bidi embedding/isolate controls.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+/// Latin letters that common Cyrillic and Greek homoglyphs are mistaken for.
+fn latin_lookalike(c: char) -> Option<char> {
+    let latin = match c {
+        // Cyrillic lowercase
+        'а' => 'a',
+        'в' => 'b',
+        'г' => 'r',
+        'ԁ' => 'd',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'к' => 'k',
+        'ӏ' => 'l',
+        'м' => 'm',
+        'н' => 'h',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'т' => 't',
+        'с' => 'c',
+        'у' => 'y',
+        'ԝ' => 'w',
+        'х' => 'x',
+        // Cyrillic uppercase
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'Х' => 'X',
+        'І' => 'I',
+        'Ј' => 'J',
+        'Ѕ' => 'S',
+        // Greek
+        'α' => 'a',
+        'ε' => 'e',
+        'ι' => 'i',
+        'κ' => 'k',
+        'ν' => 'v',
+        'ο' => 'o',
+        'ρ' => 'p',
+        'τ' => 't',
+        'υ' => 'u',
+        'χ' => 'x',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Χ' => 'X',
+        'Υ' => 'Y',
+        'Ζ' => 'Z',
+        _ => return None,
+    };
+    Some(latin)
+}
+
+/// Fold a command into the plain-ASCII shape the patterns are written for: strip invisible
+/// code points, apply NFKC (fullwidth `ｒｍ` → `rm`, no-break space → space), then map
+/// homoglyphs to the Latin letters they imitate. ASCII input is returned unchanged.
+pub fn fold(s: &str) -> String {
+    if s.is_ascii() {
+        return s.to_string();
+    }
+    s.chars()
+        .filter(|c| !is_invisible(*c))
+        .nfkc()
+        .map(|c| latin_lookalike(c).unwrap_or(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_is_unchanged() {
+        assert_eq!(fold("rm -rf /tmp/x"), "rm -rf /tmp/x");
+    }
+
+    #[test]
+    fn zero_width_characters_are_stripped() {
+        assert_eq!(fold("r\u{200B}m -rf /"), "rm -rf /");
+        assert_eq!(fold("su\u{200D}do\u{FEFF} ls"), "sudo ls");
+        assert_eq!(fold("sh\u{00AD}red x"), "shred x");
+        assert_eq!(fold("\u{202E}rm -rf /"), "rm -rf /");
+    }
+
+    #[test]
+    fn homoglyphs_map_to_latin() {
+        assert_eq!(fold("гm -rf /"), "rm -rf /");
+        assert_eq!(fold("ѕudо ls"), "sudo ls");
+        assert_eq!(fold("chmοd 777 /"), "chmod 777 /");
+    }
+
+    #[test]
+    fn nfkc_folds_fullwidth_and_spaces() {
+        assert_eq!(fold("ｒｍ -ｒｆ /"), "rm -rf /");
+        assert_eq!(fold("rm\u{00A0}-rf\u{3000}/"), "rm -rf /");
+    }
+}
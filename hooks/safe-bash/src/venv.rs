@@ -0,0 +1,94 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+
+/// Directory names a Python virtual environment is conventionally created under.
+const VENV_DIRS: &[&str] = &[".venv", "venv", ".virtualenv"];
+
+/// Classify a command made only of `rm -r` calls on project virtualenvs (`rm -rf .venv`,
+/// `rm -r backend/venv`) as env-destruction instead of a generic recursive delete, so it is
+/// decided by that category's severity (warn by default). None when any part of the command
+/// is something else — another directory, an absolute, `~`, `$` or `..` path, a wildcard,
+/// another command — so those keep the generic rm rules.
+pub fn check(cmd: &str, wrappers: &[String]) -> Option<Hit> {
+    let ast = crate::shell::parse(cmd);
+    let mut targets = Vec::new();
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().and_then(|p| p.rsplit('/').next()) != Some("rm") {
+            return None;
+        }
+        let mut options_done = false;
+        let mut recursive = false;
+        for arg in &args[1..] {
+            if !options_done && arg.starts_with('-') {
+                options_done = *arg == "--";
+                recursive |=
+                    *arg == "--recursive" || (!arg.starts_with("--") && arg.contains(['r', 'R']));
+            } else if is_project_venv(arg) {
+                targets.push(*arg);
+            } else {
+                return None;
+            }
+        }
+        if !recursive {
+            return None;
+        }
+    }
+    if targets.is_empty() {
+        return None;
+    }
+    let reason = format!(
+        "Env destruction: rm -r {} (deletes a Python virtual environment)",
+        targets.join(" ")
+    );
+    Some(Hit {
+        id: "ENV101",
+        reason,
+        category: Category::EnvDestruction,
+    })
+}
+
+fn is_project_venv(arg: &str) -> bool {
+    let path = arg.trim_end_matches('/');
+    !path.starts_with(['/', '~', '$', '`'])
+        && !path.contains(['*', '?', '[', '{'])
+        && !path.split('/').any(|c| c == "..")
+        && path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| VENV_DIRS.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn venv_deletes_are_env_destruction() {
+        let hit = check("rm -rf .venv", &[]).unwrap();
+        assert_eq!(hit.category, Category::EnvDestruction);
+        assert_eq!(
+            hit.reason,
+            "Env destruction: rm -r .venv (deletes a Python virtual environment)"
+        );
+        assert!(check("rm -r -f ./backend/venv/ && rm -rf .virtualenv", &[]).is_some());
+        assert!(check("sudo rm -rf .venv", &[]).is_some());
+    }
+
+    #[test]
+    fn other_targets_keep_generic_rules() {
+        for cmd in [
+            "rm -rf .venv src",
+            "rm -rf /opt/venv",
+            "rm -rf ~/.venv",
+            "rm -rf ../.venv",
+            "rm -rf $VENV",
+            "rm -rf .venv*",
+            "rm .venv",
+            "rm -rf .venv && make",
+        ] {
+            assert_eq!(check(cmd, &[]), None, "{}", cmd);
+        }
+    }
+}
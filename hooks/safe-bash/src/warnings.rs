@@ -0,0 +1,153 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A warning of a given kind is printed at most once per interval; repeats go to the debug log
+/// only.
+const WARN_INTERVAL_SECS: u64 = 3600;
+/// The debug log is rotated to `.1` past this size.
+const DEBUG_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+thread_local! {
+    /// Warnings collected by `capture` instead of printed.
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    /// Warnings reported so far, printed, suppressed or captured.
+    static REPORTED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Every warning, printed or not (`<ts> <kind>: <message>` per line).
+pub fn debug_log_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-debug.log")
+}
+
+/// When each kind of warning was last printed.
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-warnings.state")
+}
+
+/// Set where the debug log and suppression state live. Before this (and in unit tests)
+/// every warning is printed.
+pub fn init(state_dir: &Path) {
+    let _ = STATE_DIR.set(state_dir.to_path_buf());
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Report a non-fatal problem. `kind` names the warning type (`config-json`, `audit-write`)
+/// so the same failure on every Bash call prints once per interval instead of every time.
+pub fn warn(kind: &str, message: &str) {
+    REPORTED.set(REPORTED.get() + 1);
+    if CAPTURED
+        .with_borrow_mut(|captured| captured.as_mut().map(|c| c.push(message.to_string())))
+        .is_some()
+    {
+        return;
+    }
+    let print = match STATE_DIR.get() {
+        Some(dir) => record(dir, kind, message, now_secs()),
+        None => true,
+    };
+    if print {
+        eprintln!("safe-bash-hook: warn: {}", message);
+    }
+}
+
+/// How many warnings have been reported so far, so a caller can tell whether some step had
+/// any.
+pub fn reported() -> usize {
+    REPORTED.get()
+}
+
+/// Run `f`, collecting the warnings it reports instead of printing or logging them.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    CAPTURED.set(Some(Vec::new()));
+    let out = f();
+    (out, CAPTURED.take().unwrap_or_default())
+}
+
+/// Append the warning to the debug log and return whether it should be printed.
+fn record(state_dir: &Path, kind: &str, message: &str, now: u64) -> bool {
+    let log = debug_log_path(state_dir);
+    if fs::metadata(&log).is_ok_and(|m| m.len() > DEBUG_LOG_MAX_BYTES) {
+        let _ = fs::rename(&log, log.with_extension("log.1"));
+    }
+    let _ = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .and_then(|mut f| writeln!(f, "{} {}: {}", now, kind, message));
+
+    let path = state_path(state_dir);
+    let mut last: HashMap<String, u64> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    if last
+        .get(kind)
+        .is_some_and(|t| now.saturating_sub(*t) < WARN_INTERVAL_SECS)
+    {
+        return false;
+    }
+    last.insert(kind.to_string(), now);
+    // If the state can't be saved, keep printing rather than go quiet
+    let _ = fs::write(&path, serde_json::to_string(&last).unwrap_or_default());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn repeats_are_suppressed_per_kind_within_interval() {
+        let dir = TempDir::new().unwrap();
+        assert!(record(dir.path(), "config-json", "malformed JSON", 1000));
+        assert!(!record(
+            dir.path(),
+            "config-json",
+            "malformed JSON",
+            1000 + WARN_INTERVAL_SECS - 1
+        ));
+        assert!(record(
+            dir.path(),
+            "audit-write",
+            "could not write audit log",
+            1001
+        ));
+        assert!(record(
+            dir.path(),
+            "config-json",
+            "malformed JSON",
+            1000 + WARN_INTERVAL_SECS
+        ));
+    }
+
+    #[test]
+    fn every_warning_reaches_the_debug_log() {
+        let dir = TempDir::new().unwrap();
+        record(dir.path(), "config-json", "first", 1000);
+        record(dir.path(), "config-json", "second", 1001);
+        let log = fs::read_to_string(debug_log_path(dir.path())).unwrap();
+        assert_eq!(log, "1000 config-json: first\n1001 config-json: second\n");
+    }
+
+    #[test]
+    fn unwritable_dir_still_prints() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("missing/hooks");
+        assert!(record(&missing, "audit-write", "x", 1000));
+        assert!(record(&missing, "audit-write", "x", 1001));
+    }
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Events per POST.
+const BATCH_SIZE: usize = 50;
+/// Backoff after the first failed delivery, doubled per consecutive failure.
+const BACKOFF_BASE_SECS: u64 = 30;
+const BACKOFF_MAX_SECS: u64 = 3600;
+/// A flush lock older than this belongs to a flusher that died.
+const STALE_LOCK_SECS: u64 = 120;
+const CURL_TIMEOUT_SECS: &str = "10";
+
+/// Events waiting to be delivered (one JSON object per line, appended by every decision).
+pub fn queue_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-alerts.jsonl")
+}
+
+/// Events taken off the queue by a flush and not yet acknowledged by the endpoint.
+fn sending_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-alerts.sending.jsonl")
+}
+
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-alerts.state")
+}
+
+fn lock_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("safe-bash-alerts.lock")
+}
+
+/// Consecutive delivery failures and when the next attempt is due.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct State {
+    failures: u32,
+    next_attempt: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn load_state(state_dir: &Path) -> State {
+    fs::read_to_string(state_path(state_dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state_dir: &Path, state: &State) {
+    let path = state_path(state_dir);
+    if let Err(e) = fs::write(&path, serde_json::to_string(state).unwrap_or_default()) {
+        crate::warnings::warn(
+            "alert-state",
+            &format!("could not write {}: {}", path.display(), e),
+        );
+    }
+}
+
+fn backoff_secs(failures: u32) -> u64 {
+    BACKOFF_BASE_SECS
+        .saturating_mul(1u64 << failures.saturating_sub(1).min(16))
+        .min(BACKOFF_MAX_SECS)
+}
+
+/// Append an event to the durable queue. Failures warn but never affect the decision.
+pub fn enqueue(state_dir: &Path, event: &serde_json::Value) {
+    let path = queue_path(state_dir);
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| writeln!(f, "{}", event));
+    if let Err(e) = result {
+        crate::warnings::warn(
+            "alert-queue",
+            &format!("could not queue alert in {}: {}", path.display(), e),
+        );
+    }
+}
+
+/// True when there is something to deliver and no backoff is pending.
+fn flush_due(state_dir: &Path) -> bool {
+    let pending = [queue_path(state_dir), sending_path(state_dir)]
+        .iter()
+        .any(|p| fs::metadata(p).is_ok_and(|m| m.len() > 0));
+    pending && load_state(state_dir).next_attempt <= now_secs()
+}
+
+/// If events are pending and the backoff has expired, spawn `safe-bash-hook flush-alerts`
+/// detached so delivery never delays the decision.
+pub fn maybe_flush(state_dir: &Path) {
+    if !flush_due(state_dir) {
+        return;
+    }
+    let result = std::env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .arg("flush-alerts")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    if let Err(e) = result {
+        crate::warnings::warn(
+            "alert-spawn",
+            &format!("could not spawn alert flush: {}", e),
+        );
+    }
+}
+
+/// Deliver queued events to `url` in batches, one flusher at a time. The queue is moved
+/// aside before sending, so decisions keep appending to a fresh queue; a failed batch stays
+/// in the sending file for the next flush and schedules an exponential backoff.
+pub fn flush(state_dir: &Path, send: &mut dyn FnMut(&str) -> bool) {
+    let lock = lock_path(state_dir);
+    let stale = fs::metadata(&lock)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age.as_secs() > STALE_LOCK_SECS);
+    if stale {
+        let _ = fs::remove_file(&lock);
+    }
+    if OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock)
+        .is_err()
+    {
+        return; // another flush is running
+    }
+
+    let mut state = load_state(state_dir);
+    if state.next_attempt <= now_secs() {
+        deliver(state_dir, &mut state, send);
+        save_state(state_dir, &state);
+    }
+    let _ = fs::remove_file(&lock);
+}
+
+fn deliver(state_dir: &Path, state: &mut State, send: &mut dyn FnMut(&str) -> bool) {
+    let sending = sending_path(state_dir);
+    loop {
+        let pending = fs::read_to_string(&sending).unwrap_or_default();
+        let lines: Vec<&str> = pending.lines().filter(|l| !l.trim().is_empty()).collect();
+        if lines.is_empty() {
+            // Take the next chunk of the queue; a missing queue means everything is delivered
+            if fs::rename(queue_path(state_dir), &sending).is_err() {
+                return;
+            }
+            continue;
+        }
+        let batch = &lines[..lines.len().min(BATCH_SIZE)];
+        let body = format!("[{}]", batch.join(","));
+        if !send(&body) {
+            state.failures += 1;
+            state.next_attempt = now_secs() + backoff_secs(state.failures);
+            return;
+        }
+        *state = State::default();
+        let rest = lines[batch.len()..].join("\n");
+        let result = if rest.is_empty() {
+            fs::remove_file(&sending)
+        } else {
+            fs::write(&sending, rest + "\n")
+        };
+        if let Err(e) = result {
+            crate::warnings::warn(
+                "alert-queue",
+                &format!("could not update {}: {}", sending.display(), e),
+            );
+            return;
+        }
+    }
+}
+
+/// POST a JSON array of events with curl. True on a 2xx response.
+pub fn post(url: &str, body: &str) -> bool {
+    let child = Command::new("curl")
+        .args(["-fsS", "-o", "/dev/null", "--max-time", CURL_TIMEOUT_SECS])
+        .args([
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+        ])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else { return false };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(body.as_bytes());
+    }
+    child.wait().is_ok_and(|s| s.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn queue_events(dir: &Path, n: usize) {
+        for i in 0..n {
+            enqueue(dir, &json!({"id": i.to_string(), "decision": "deny"}));
+        }
+    }
+
+    #[test]
+    fn flush_delivers_in_batches_and_empties_queue() {
+        let dir = TempDir::new().unwrap();
+        queue_events(dir.path(), BATCH_SIZE + 5);
+        let mut batches = Vec::new();
+        flush(dir.path(), &mut |body| {
+            batches.push(
+                serde_json::from_str::<Vec<serde_json::Value>>(body)
+                    .unwrap()
+                    .len(),
+            );
+            true
+        });
+        assert_eq!(batches, vec![BATCH_SIZE, 5]);
+        assert!(!flush_due(dir.path()));
+        assert!(!lock_path(dir.path()).exists());
+    }
+
+    #[test]
+    fn failed_delivery_keeps_events_and_backs_off() {
+        let dir = TempDir::new().unwrap();
+        queue_events(dir.path(), 3);
+        flush(dir.path(), &mut |_| false);
+        let state = load_state(dir.path());
+        assert_eq!(state.failures, 1);
+        assert!(state.next_attempt > now_secs());
+        // Still offline: the backoff skips the attempt entirely
+        let mut attempts = 0;
+        flush(dir.path(), &mut |_| {
+            attempts += 1;
+            true
+        });
+        assert_eq!(attempts, 0);
+
+        // Events from later decisions queue behind the unsent batch; all arrive once back online
+        queue_events(dir.path(), 2);
+        save_state(
+            dir.path(),
+            &State {
+                failures: 1,
+                next_attempt: 0,
+            },
+        );
+        let mut ids = Vec::new();
+        flush(dir.path(), &mut |body| {
+            let events: Vec<serde_json::Value> = serde_json::from_str(body).unwrap();
+            ids.extend(events.iter().map(|e| e["id"].as_str().unwrap().to_string()));
+            true
+        });
+        assert_eq!(ids, ["0", "1", "2", "0", "1"]);
+        assert_eq!(load_state(dir.path()), State::default());
+    }
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        assert_eq!(backoff_secs(1), 30);
+        assert_eq!(backoff_secs(2), 60);
+        assert_eq!(backoff_secs(4), 240);
+        assert_eq!(backoff_secs(40), BACKOFF_MAX_SECS);
+    }
+
+    #[test]
+    fn concurrent_flush_is_skipped_while_locked() {
+        let dir = TempDir::new().unwrap();
+        queue_events(dir.path(), 1);
+        fs::write(lock_path(dir.path()), "").unwrap();
+        flush(dir.path(), &mut |_| {
+            panic!("must not send while another flush holds the lock")
+        });
+        assert!(flush_due(dir.path()));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::patterns::Hit;
+use crate::severity::Category;
+use std::path::{Path, PathBuf};
+
+/// Top-level directories whose loss breaks the system.
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/lib", "/lib64", "/opt", "/proc", "/root", "/sbin", "/srv",
+    "/sys", "/usr", "/var",
+];
+
+/// Where a recursive delete lands, from worst to least bad.
+#[derive(Debug, PartialEq)]
+enum Zone {
+    FilesystemRoot,
+    Home,
+    SystemDir(PathBuf),
+}
+
+/// Classify the targets of `rm -r` and flag the ones that wipe the filesystem root, the home
+/// directory or a top-level system directory — directly (`rm -rf /`, `rm -rf ~`) or through a
+/// wildcard (`rm -rf /*`, `rm -rf "$HOME"/.*`, `rm -rf ./*` run from `/`). These always block.
+pub fn check(cmd: &str, cwd: &Path, wrappers: &[String]) -> Option<Hit> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let ast = crate::shell::parse(cmd);
+    for command in ast.simple_commands() {
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        if args.first().and_then(|p| p.rsplit('/').next()) != Some("rm") {
+            continue;
+        }
+        let mut options_done = false;
+        let mut recursive = false;
+        let mut targets = Vec::new();
+        for arg in &args[1..] {
+            if !options_done && arg.starts_with('-') {
+                options_done = *arg == "--";
+                recursive |=
+                    *arg == "--recursive" || (!arg.starts_with("--") && arg.contains(['r', 'R']));
+            } else {
+                targets.push(*arg);
+            }
+        }
+        if !recursive {
+            continue;
+        }
+        for target in targets {
+            if let Some(zone) = classify(target, cwd, home.as_deref()) {
+                return Some(Hit {
+                    id: "RW101",
+                    reason: reason(target, cwd, &zone),
+                    category: Category::RootWildcard,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The zone a target deletes, if it is one of the catastrophic ones.
+fn classify(target: &str, cwd: &Path, home: Option<&Path>) -> Option<Zone> {
+    // A trailing wildcard deletes the contents of its directory: classify the directory
+    let base = match target.trim_end_matches('/').rsplit_once('/') {
+        Some(("", last)) if is_wildcard(last) => "/",
+        Some((dir, last)) if is_wildcard(last) => dir,
+        None if is_wildcard(target) => ".",
+        _ => target,
+    };
+    let resolved = crate::perms::resolve(base, cwd, home);
+    if resolved == Path::new("/") {
+        return Some(Zone::FilesystemRoot);
+    }
+    if home == Some(resolved.as_path()) {
+        return Some(Zone::Home);
+    }
+    if SYSTEM_DIRS.iter().any(|d| resolved == Path::new(d)) {
+        return Some(Zone::SystemDir(resolved));
+    }
+    // Anything deeper is project-local enough for the generic rm rules
+    None
+}
+
+/// `*`, `.*`, `{*,.*}`, `?*` — a last component that matches everything in its directory.
+fn is_wildcard(component: &str) -> bool {
+    !component.is_empty()
+        && component
+            .chars()
+            .all(|c| matches!(c, '*' | '?' | '.' | '{' | '}' | ','))
+        && component.contains(['*', '?'])
+}
+
+fn reason(target: &str, cwd: &Path, zone: &Zone) -> String {
+    let from = if target.starts_with('/') || target.starts_with('~') || target.starts_with('$') {
+        String::new()
+    } else {
+        format!(" (run from {})", cwd.display())
+    };
+    match zone {
+        Zone::FilesystemRoot => format!(
+            "Catastrophic: rm -r {}{} deletes everything under the filesystem root",
+            target, from
+        ),
+        Zone::Home => format!(
+            "Catastrophic: rm -r {}{} deletes the entire home directory",
+            target, from
+        ),
+        Zone::SystemDir(dir) => format!(
+            "Catastrophic: rm -r {}{} deletes the system directory {}",
+            target,
+            from,
+            dir.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason_in(cmd: &str, cwd: &str) -> Option<String> {
+        check(cmd, Path::new(cwd), &[]).map(|h| h.reason)
+    }
+
+    #[test]
+    fn root_wildcards_are_catastrophic() {
+        let r = reason_in("rm -rf /*", "/tmp").unwrap();
+        assert_eq!(
+            r,
+            "Catastrophic: rm -r /* deletes everything under the filesystem root"
+        );
+        let r = reason_in("rm -rf ./*", "/").unwrap();
+        assert!(
+            r.contains("(run from /) deletes everything under the filesystem root"),
+            "{}",
+            r
+        );
+        assert!(reason_in("sudo rm -rf -- /", "/tmp").is_some());
+        assert!(reason_in("rm -fr ../../*", "/a/b").is_some());
+    }
+
+    #[test]
+    fn home_and_system_dirs_are_catastrophic() {
+        let home = std::env::var("HOME").unwrap_or_default();
+        if !home.is_empty() && home != "/" {
+            assert!(reason_in("rm -rf ~/*", "/tmp")
+                .unwrap()
+                .contains("entire home directory"));
+            assert!(reason_in("rm -rf \"$HOME\"/.*", "/tmp").is_some());
+        }
+        assert!(reason_in("rm -rf /usr/*", "/tmp")
+            .unwrap()
+            .contains("system directory /usr"));
+        assert!(reason_in("rm -r /etc", "/tmp").is_some());
+    }
+
+    #[test]
+    fn project_local_deletes_are_left_to_generic_rules() {
+        assert!(reason_in("rm -rf ./*", "/home/me/project").is_none());
+        assert!(reason_in("rm -rf build/*", "/").is_none());
+        assert!(reason_in("rm -rf /usr/local/share/myapp", "/tmp").is_none());
+        assert!(reason_in("rm /*", "/tmp").is_none());
+        assert!(reason_in("rm -rf *.log", "/").is_none());
+    }
+}
@@ -0,0 +1,425 @@
+use std::path::{Path, PathBuf};
+
+/// Where a path lies, from the point of view of the command's workspace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Zone {
+    /// `/`, `/etc`, `/usr`, `/var` and the other directories the OS owns.
+    System,
+    /// Dotfiles and dotdirs in the home directory: `~/.ssh`, `~/.bashrc`, `~/.config`.
+    HomeConfig,
+    /// `/tmp`, `/var/tmp`, `/dev/shm`, `$TMPDIR`.
+    Temp,
+    /// The workspace root the command runs in (the nearest `.git` above the cwd, else the cwd).
+    Project,
+    /// Anything else: other repositories, `~/Documents`, mounted volumes.
+    OutsideProject,
+}
+
+pub const ALL_ZONES: &[Zone] = &[
+    Zone::System,
+    Zone::HomeConfig,
+    Zone::Temp,
+    Zone::Project,
+    Zone::OutsideProject,
+];
+
+impl Zone {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_ZONES.iter().copied().find(|z| z.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::HomeConfig => "home-config",
+            Self::Temp => "temp",
+            Self::Project => "project",
+            Self::OutsideProject => "outside-project",
+        }
+    }
+}
+
+/// What a command does to a path argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Delete,
+}
+
+impl Access {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            "delete" => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+const SYSTEM_DIRS: &[&str] = &[
+    "/etc",
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/boot",
+    "/var",
+    "/opt",
+    "/srv",
+    "/sys",
+    "/proc",
+    "/dev",
+    "/root",
+    "/System",
+    "/Library",
+    "/Applications",
+    "/private/etc",
+    "/private/var",
+];
+
+const TEMP_DIRS: &[&str] = &[
+    "/tmp",
+    "/var/tmp",
+    "/dev/shm",
+    "/private/tmp",
+    "/private/var/folders",
+];
+
+/// Commands whose arguments are text, not paths.
+const TEXT_ONLY: &[&str] = &["echo", "printf", "true", "false", ":"];
+
+/// Commands that delete their operands.
+const REMOVERS: &[&str] = &["rm", "unlink", "rmdir", "shred", "trash", "trash-put"];
+
+/// Commands that create or change their operands.
+const WRITERS: &[&str] = &[
+    "tee", "touch", "truncate", "mkdir", "chmod", "chown", "chgrp",
+];
+
+/// Commands whose last operand is written and whose other operands are read (or, for `mv`,
+/// taken away).
+const COPIERS: &[&str] = &["cp", "mv", "install", "ln", "rsync", "scp"];
+
+/// Commands whose first operand is a script or pattern unless one is given with an option.
+const SCRIPTED: &[&str] = &["sed", "perl", "awk", "gawk", "grep", "egrep", "fgrep", "rg"];
+
+/// The options of `SCRIPTED` commands that take the script or pattern as their next word.
+const SCRIPT_OPTIONS: &[&str] = &["-e", "--expression", "-f", "--file", "--regexp"];
+
+/// One path a command touches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathArg {
+    /// The program, or `redirect >` for a redirect target.
+    pub what: String,
+    /// The argument as written.
+    pub arg: String,
+    /// Absolute, with `~` expanded, globs expanded on disk and `.`/`..` folded; see `real_path`
+    /// for the symlinks resolved.
+    pub path: PathBuf,
+    pub access: Access,
+}
+
+/// Every path the simple commands of `cmd` read, write or delete, resolved the way the shell
+/// and the kernel would: `~` and `$HOME` expanded, relative paths joined to the directory the
+/// command runs in (following `cd`/`pushd` earlier in the command), `.`/`..` segments folded
+/// and globs expanded on disk. The value after `=` in `--file=PATH` and `of=PATH` counts too.
+pub fn path_args(cmd: &str, cwd: &Path, wrappers: &[String]) -> Vec<PathArg> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let resolve = |arg: &str, dir: &Path| -> Vec<PathBuf> {
+        let arg = arg.replace("${HOME}", "$HOME");
+        if arg.contains(['*', '?', '[']) {
+            crate::blast::glob_paths(dir, &arg.replacen("$HOME/", "~/", 1))
+        } else {
+            vec![crate::perms::resolve(&arg, dir, home.as_deref())]
+        }
+    };
+    let mut out = Vec::new();
+    let mut push = |what: &str, arg: &str, target: &str, dir: &Path, access: Access| {
+        for path in resolve(target, dir) {
+            out.push(PathArg {
+                what: what.to_string(),
+                arg: arg.to_string(),
+                path,
+                access,
+            });
+        }
+    };
+
+    let mut dir = cwd.to_path_buf();
+    let ast = crate::shell::parse(cmd.trim_start_matches("| "));
+    for command in ast.simple_commands() {
+        for redirect in &command.redirects {
+            let target = redirect.target.value.as_str();
+            // `2>&1` and `<&-` name descriptors, not files
+            if redirect.op.ends_with('&')
+                || target.bytes().all(|b| b.is_ascii_digit())
+                || target == "-"
+            {
+                continue;
+            }
+            let access = if redirect.op.contains('>') {
+                Access::Write
+            } else {
+                Access::Read
+            };
+            push(
+                &format!("redirect {}", redirect.op),
+                target,
+                target,
+                &dir,
+                access,
+            );
+        }
+
+        let args = command.args();
+        let args = &args[crate::patterns::unwrap_index(&args, wrappers)..];
+        let Some(first) = args.first() else { continue };
+        let program = first.rsplit('/').next().unwrap_or(first);
+        match program {
+            "cd" | "pushd" => {
+                match args.iter().skip(1).find(|a| !a.starts_with('-')) {
+                    Some(target) => dir = crate::perms::resolve(target, &dir, home.as_deref()),
+                    None if args.len() == 1 => dir = home.clone().unwrap_or(dir),
+                    None => {}
+                }
+                continue;
+            }
+            p if TEXT_ONLY.contains(&p) => continue,
+            _ => {}
+        }
+        // A script run from a path is read
+        if first.contains('/') {
+            push(program, first, first, &dir, Access::Read);
+        }
+        let in_place = matches!(program, "sed" | "perl")
+            && args[1..].iter().any(|a| {
+                a.starts_with("-i")
+                    || a.starts_with("--in-place")
+                    || (a.starts_with("-p") && a.contains('i'))
+            });
+        let last_operand = args[1..]
+            .iter()
+            .rposition(|a| !a.starts_with('-'))
+            .map(|i| i + 1);
+        let script = SCRIPTED
+            .contains(&program)
+            .then(|| script_index(args))
+            .flatten();
+        for (i, arg) in args.iter().enumerate().skip(1) {
+            if Some(i) == script
+                || (i > 1 && SCRIPTED.contains(&program) && SCRIPT_OPTIONS.contains(&args[i - 1]))
+            {
+                continue;
+            }
+            let operand_access = match program {
+                p if REMOVERS.contains(&p) => Access::Delete,
+                p if WRITERS.contains(&p) => Access::Write,
+                _ if in_place => Access::Write,
+                p if COPIERS.contains(&p) && Some(i) == last_operand => Access::Write,
+                "mv" => Access::Delete,
+                _ => Access::Read,
+            };
+            // `--file=PATH`, `of=PATH`: the value after `=` is the path
+            let assigned = arg.split_once('=').filter(|(key, _)| !key.contains('/'));
+            if !arg.starts_with('-') && assigned.is_none() {
+                push(program, arg, arg, &dir, operand_access);
+            }
+            if let Some((key, value)) = assigned.filter(|(_, v)| !v.is_empty()) {
+                let access = if program == "dd" && key == "of" {
+                    Access::Write
+                } else {
+                    Access::Read
+                };
+                push(program, arg, value, &dir, access);
+            }
+        }
+    }
+    out
+}
+
+/// Where the script of a `SCRIPTED` command is among its words, when no option gives it.
+fn script_index(args: &[&str]) -> Option<usize> {
+    if args.iter().any(|a| SCRIPT_OPTIONS.contains(a)) {
+        return None;
+    }
+    args.iter()
+        .skip(1)
+        .position(|a| !a.starts_with('-'))
+        .map(|i| i + 1)
+}
+
+/// The zone of an absolute, already resolved `path` for a command run in `cwd`.
+pub fn classify(path: &Path, cwd: &Path, home: Option<&Path>) -> Zone {
+    let project = crate::blast::workspace_root(cwd).unwrap_or_else(|| cwd.to_path_buf());
+    let project = real_path(&project);
+    // A dotdir of home stays home-config even when the workspace is home itself, unless the
+    // workspace lives inside it (a dotfiles repository in ~/.config/nvim)
+    if let Some(dotdir) = home.and_then(|h| home_dotdir(path, h)) {
+        if !project.starts_with(&dotdir) {
+            return Zone::HomeConfig;
+        }
+    }
+    if project != Path::new("/") && path.starts_with(&project) {
+        return Zone::Project;
+    }
+    let tmpdir = std::env::var_os("TMPDIR").map(PathBuf::from);
+    if TEMP_DIRS.iter().any(|t| path.starts_with(t))
+        || tmpdir.is_some_and(|t| t.is_absolute() && path.starts_with(t))
+    {
+        return Zone::Temp;
+    }
+    if path == Path::new("/") || SYSTEM_DIRS.iter().any(|d| path.starts_with(d)) {
+        return Zone::System;
+    }
+    Zone::OutsideProject
+}
+
+/// The home dotfile or dotdir `path` is in, e.g. `~/.ssh` for `~/.ssh/config`.
+fn home_dotdir(path: &Path, home: &Path) -> Option<PathBuf> {
+    let first = path.strip_prefix(home).ok()?.components().next()?;
+    first
+        .as_os_str()
+        .to_string_lossy()
+        .starts_with('.')
+        .then(|| home.join(first))
+}
+
+/// `path` with the symlinks in its longest existing prefix resolved.
+pub fn real_path(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(real) = ancestor.canonicalize() {
+            return real.join(path.strip_prefix(ancestor).unwrap_or(Path::new("")));
+        }
+    }
+    path.to_path_buf()
+}
+
+/// A config rule's `zones` and `access`: the rule only matches commands that touch paths in
+/// those zones, that way.
+#[derive(Debug, Clone)]
+pub struct ZoneRule {
+    pub zones: Vec<Zone>,
+    /// None for any access.
+    pub access: Option<Access>,
+    /// Where relative paths start: the hook input's cwd.
+    pub cwd: PathBuf,
+    /// An allow rule needs every path it looks at in the zones; a deny rule any one.
+    pub every: bool,
+}
+
+impl ZoneRule {
+    pub fn holds(&self, text: &str, wrappers: &[String]) -> bool {
+        let home = std::env::var("HOME").ok().map(PathBuf::from);
+        let mut paths = path_args(text, &self.cwd, wrappers)
+            .into_iter()
+            .filter(|p| self.access.is_none_or(|a| p.access == a))
+            .peekable();
+        let inside = |p: PathArg| {
+            self.zones
+                .contains(&classify(&real_path(&p.path), &self.cwd, home.as_deref()))
+        };
+        if self.every {
+            paths.peek().is_some() && paths.all(inside)
+        } else {
+            paths.any(inside)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_by_access() {
+        let cwd = Path::new("/work/app");
+        let found = |cmd: &str| {
+            path_args(cmd, cwd, &[])
+                .into_iter()
+                .map(|p| (p.path.to_string_lossy().to_string(), p.access))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            found("rm -rf build"),
+            [("/work/app/build".to_string(), Access::Delete)]
+        );
+        assert_eq!(
+            found("mv a ../b && cat < in > out"),
+            [
+                ("/work/app/a".to_string(), Access::Delete),
+                ("/work/b".to_string(), Access::Write),
+                ("/work/app/in".to_string(), Access::Read),
+                ("/work/app/out".to_string(), Access::Write),
+            ]
+        );
+        assert_eq!(
+            found("cd /etc && sed -i s/a/b/ hosts"),
+            [("/etc/hosts".to_string(), Access::Write)]
+        );
+        assert_eq!(
+            found("dd if=/dev/zero of=/dev/sda"),
+            [
+                ("/dev/zero".to_string(), Access::Read),
+                ("/dev/sda".to_string(), Access::Write)
+            ]
+        );
+        assert_eq!(
+            found("grep -e ssh -r ~/.ssh-old notes"),
+            [
+                (
+                    format!("{}/.ssh-old", std::env::var("HOME").unwrap()),
+                    Access::Read
+                ),
+                ("/work/app/notes".to_string(), Access::Read)
+            ]
+        );
+        assert!(found("echo /etc/passwd 2>&1").is_empty());
+    }
+
+    #[test]
+    fn zones_of_paths() {
+        let home = Path::new("/home/ana");
+        let repo = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(repo.path().join(".git")).unwrap();
+        let cwd = real_path(&repo.path().join("src"));
+        let zone = |path: &str| classify(Path::new(path), &cwd, Some(home));
+        assert_eq!(zone("/etc/hosts"), Zone::System);
+        assert_eq!(zone("/"), Zone::System);
+        assert_eq!(zone("/var/tmp/x"), Zone::Temp);
+        assert_eq!(zone("/home/ana/.ssh/id_rsa"), Zone::HomeConfig);
+        assert_eq!(zone("/home/ana/Documents/cv.pdf"), Zone::OutsideProject);
+        assert_eq!(
+            zone(&cwd.parent().unwrap().join("README.md").to_string_lossy()),
+            Zone::Project
+        );
+        // Home as the workspace keeps its dotfiles apart
+        assert_eq!(
+            classify(Path::new("/home/ana/.aws/credentials"), home, Some(home)),
+            Zone::HomeConfig
+        );
+        assert_eq!(
+            classify(Path::new("/home/ana/notes.md"), home, Some(home)),
+            Zone::Project
+        );
+    }
+
+    #[test]
+    fn deny_any_allow_every() {
+        let cwd = real_path(&std::env::temp_dir()).join("zones-test");
+        let rule = |every| ZoneRule {
+            zones: vec![Zone::System],
+            access: Some(Access::Write),
+            cwd: cwd.clone(),
+            every,
+        };
+        assert!(rule(false).holds("cp notes /etc/motd", &[]));
+        assert!(!rule(false).holds("cat /etc/motd", &[]));
+        assert!(!rule(true).holds("cp notes /etc/motd && touch scratch", &[]));
+        assert!(rule(true).holds("tee /etc/motd /etc/issue", &[]));
+        assert!(!rule(true).holds("ls", &[]), "nothing written");
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::path::Path;
 use std::process::{Command, Stdio};
 
 /// Path to the compiled test binary.
@@ -24,9 +25,39 @@ fn bash_input(cmd: &str) -> String {
     .to_string()
 }
 
+/// Run the binary and return (exit_code, stdout) — for JSON hook output.
+fn run_stdout(input: &str) -> (i32, String) {
+    let output = Command::new(binary())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .expect("failed to run safe-bash-hook binary — run `cargo build` first");
+    (
+        output.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    )
+}
+
 /// Run the binary with the given stdin, return (exit_code, stderr).
 fn run(input: &str) -> (i32, String) {
+    run_with_args(&[], input)
+}
+
+/// Run the binary with extra command-line arguments and the given stdin.
+fn run_with_args(args: &[&str], input: &str) -> (i32, String) {
+    run_with_env(args, &[], input)
+}
+
+/// Run the binary with extra arguments and environment variables.
+fn run_with_env(args: &[&str], env: &[(&str, &str)], input: &str) -> (i32, String) {
     let mut child = Command::new(binary())
+        .args(args)
+        .envs(env.iter().copied())
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::piped())
@@ -121,6 +152,60 @@ fn blocks_rm_rf_slash() {
     assert!(stderr.contains("Blocked"), "stderr should contain reason");
 }
 
+#[test]
+fn blocks_also_print_a_json_deny_decision() {
+    let (code, stdout) = run_stdout(&bash_input("rm -rf /"));
+    assert_eq!(code, 2);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["hookEventName"], "PreToolUse");
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "deny");
+    let reason = v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap();
+    assert!(
+        reason.starts_with("Blocked: ") && reason.contains("\nPolicy: "),
+        "{}",
+        reason
+    );
+}
+
+#[test]
+fn block_and_ask_messages_suggest_safer_alternatives() {
+    let (code, stderr) = run(&bash_input("rm -r ./src"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("\nInstead: move it to the trash (`trash <path>`"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout) = run_stdout(&bash_input("git push --force origin main"));
+    assert_eq!(code, 2);
+    assert!(
+        stdout.contains("Instead: use `git push --force-with-lease`"),
+        "stdout: {}",
+        stdout
+    );
+    let (code, stdout) = run_stdout(&bash_input("sed -i 's/a/b/' f.txt"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains("Instead: write the result to a new file"),
+        "stdout: {}",
+        stdout
+    );
+    // Config rules give their own
+    let home = home_with_config(
+        r#"{"deny":[{"pattern":"^terraform\\s+destroy\\b","reason":"CI only",
+            "suggestion":"run terraform plan -destroy and open a PR"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("terraform destroy"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("\nInstead: run terraform plan -destroy and open a PR"),
+        "stderr: {}",
+        stderr
+    );
+}
+
 #[test]
 fn blocks_rm_r() {
     let (code, _) = run(&bash_input("rm -r ./src"));
@@ -134,6 +219,79 @@ fn blocks_compound_and() {
     assert!(stderr.contains("Blocked"));
 }
 
+#[test]
+fn blocks_rm_rf_in_subshell() {
+    let (code, _) = run(&bash_input("(rm -rf ./data)"));
+    assert_eq!(code, 2, "commands inside a subshell should be checked");
+}
+
+#[test]
+fn blocks_flags_split_by_line_continuation() {
+    let (code, stderr) = run(&bash_input("rm \\\n  -rf /"));
+    assert_eq!(code, 2);
+    assert!(stderr.contains("rm -r"), "stderr: {}", stderr);
+    let (code, _) = run(&bash_input("git push \\\n  --force origin main"));
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn allows_multiline_docker_build() {
+    let (code, _) = run(&bash_input("docker build \\\n  -t app:latest \\\n  ."));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn blocks_heredoc_piped_into_shell() {
+    let (code, stderr) = run(&bash_input(
+        "cat <<'EOF' | bash\necho cleanup\nshred -u id_rsa\nEOF",
+    ));
+    assert_eq!(code, 2);
+    assert!(stderr.contains("shred"), "stderr: {}", stderr);
+}
+
+#[test]
+fn allows_heredoc_written_to_file() {
+    let (code, _) = run(&bash_input("cat > Makefile.inc <<'EOF'\nCFLAGS = -O2\nEOF"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn blocks_quote_obfuscated_rm() {
+    for cmd in ["r'm' -rf /", "'r'm -rf /", "$'\\x72\\x6d' -rf /"] {
+        let (code, stderr) = run(&bash_input(cmd));
+        assert_eq!(code, 2, "{}", cmd);
+        assert!(stderr.contains("rm -r"), "stderr: {}", stderr);
+    }
+}
+
+#[test]
+fn blocks_unicode_evasion() {
+    for cmd in [
+        "r\u{200B}m -rf /",
+        "гm -rf /",
+        "ｒｍ -rf /",
+        "rm\u{00A0}-rf /",
+        "ѕudo ls",
+    ] {
+        let (code, _) = run(&bash_input(cmd));
+        assert_eq!(code, 2, "{:?}", cmd);
+    }
+}
+
+#[test]
+fn allows_non_latin_text_in_arguments() {
+    let (code, _) = run(&bash_input("echo 'привет мир' > greeting.txt"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn blocks_rm_rf_in_command_substitution() {
+    let (code, _) = run(&bash_input("echo $(rm -rf /)"));
+    assert_eq!(code, 2);
+    let (code, _) = run(&bash_input("echo `rm -rf ~`"));
+    assert_eq!(code, 2);
+}
+
 #[test]
 fn blocks_compound_semicolon() {
     let (code, _) = run(&bash_input("echo hello; rm -rf /"));
@@ -153,9 +311,17 @@ fn blocks_git_push_force() {
 }
 
 #[test]
-fn blocks_git_reset_hard() {
-    let (code, _) = run(&bash_input("git reset --hard HEAD~5"));
-    assert_eq!(code, 2);
+fn asks_before_git_reset_hard_and_clean() {
+    for cmd in ["git reset --hard HEAD~5", "git clean -fdx"] {
+        let (code, stdout) = run_stdout(&bash_input(cmd));
+        assert_eq!(code, 0, "{}", cmd);
+        assert!(
+            stdout.contains(r#""permissionDecision":"ask""#),
+            "{}: {}",
+            cmd,
+            stdout
+        );
+    }
 }
 
 #[test]
@@ -183,9 +349,14 @@ fn blocks_truncation_redirect() {
 }
 
 #[test]
-fn blocks_sed_i() {
-    let (code, _) = run(&bash_input("sed -i 's/a/b/' file.txt"));
-    assert_eq!(code, 2);
+fn asks_before_sed_i() {
+    let (code, stdout) = run_stdout(&bash_input("sed -i 's/a/b/' file.txt"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#) && stdout.contains("sed -i"),
+        "stdout: {}",
+        stdout
+    );
 }
 
 #[test]
@@ -207,7 +378,7 @@ fn blocks_kill_all() {
 }
 
 // ---------------------------------------------------------------------------
-// Non-Bash tool_name — should always pass through (exit 0)
+// Non-Bash tool_name — passes through unless a config rule is scoped to the tool
 // ---------------------------------------------------------------------------
 
 #[test]
@@ -221,6 +392,127 @@ fn non_bash_tool_allowed() {
     assert_eq!(code, 0, "Non-Bash tool should always pass");
 }
 
+#[test]
+fn tool_rules_check_file_and_fetch_calls() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"tools":{
+            "Write":{"deny":[{"pattern":"\\.env$","reason":"No writing env files"}]},
+            "WebFetch":{"deny":[{"pattern":"^http://","reason":"Fetch over https",
+            "severity":"ask"}]}}}"#,
+    );
+    let call = |tool: &str, input: serde_json::Value| {
+        serde_json::json!({"tool_name": tool, "tool_input": input}).to_string()
+    };
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &call(
+            "Write",
+            serde_json::json!({"file_path": "/app/.env", "content": "A=1"}),
+        ),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: No writing env files [CFG002]"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &call("Write", serde_json::json!({"file_path": "/app/main.rs"}))
+        )
+        .0,
+        0
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &call("Read", serde_json::json!({"file_path": "/app/.env"}))
+        )
+        .0,
+        0
+    );
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &call(
+            "WebFetch",
+            serde_json::json!({"url": "http://example.test"}),
+        ),
+    );
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains("\"permissionDecision\":\"ask\""),
+        "stdout: {}",
+        stdout
+    );
+    // The rules do not apply to shell commands
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("echo ok > notes.env")).0,
+        0
+    );
+}
+
+#[test]
+fn tool_rules_check_mcp_calls_by_name_glob_and_field() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"tools":{
+            "mcp__github__delete_*":{"deny":[{"reason":"No deleting on GitHub"}]},
+            "mcp__github__*":{"deny":[{"field":"$.owner","type":"exact","pattern":"apollo-com-ph",
+            "reason":"Org repos change by PR","severity":"ask"}]}}}"#,
+    );
+    let call = |tool: &str, input: serde_json::Value| {
+        serde_json::json!({"tool_name": tool, "tool_input": input}).to_string()
+    };
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &call(
+            "mcp__github__delete_repo",
+            serde_json::json!({"owner": "me", "repo": "scratch"}),
+        ),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: No deleting on GitHub"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &call(
+            "mcp__github__create_or_update_file",
+            serde_json::json!({"owner": "apollo-com-ph", "path": "README.md"}),
+        ),
+    );
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains("\"permissionDecision\":\"ask\""),
+        "stdout: {}",
+        stdout
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &call(
+                "mcp__github__create_or_update_file",
+                serde_json::json!({"owner": "me"})
+            )
+        )
+        .0,
+        0
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &call(
+                "mcp__slack__post_message",
+                serde_json::json!({"owner": "apollo-com-ph"})
+            )
+        )
+        .0,
+        0
+    );
+}
+
 // ---------------------------------------------------------------------------
 // New patterns
 // ---------------------------------------------------------------------------
@@ -274,28 +566,3034 @@ fn allows_tee_append() {
 }
 
 // ---------------------------------------------------------------------------
-// Edge cases
+// Input adapters
 // ---------------------------------------------------------------------------
 
 #[test]
-fn malformed_json_exits_0() {
-    let (code, _) = run("not json at all {{{{");
-    assert_eq!(code, 0, "Malformed JSON should exit 0 (allow, not crash)");
+fn generic_payload_auto_detected() {
+    let (code, stderr) = run(r#"{"command":"rm -rf /"}"#);
+    assert_eq!(code, 2, "generic {{command}} payload should be checked");
+    assert!(stderr.contains("Blocked"));
 }
 
 #[test]
-fn missing_command_field_exits_0() {
+fn open_interpreter_shell_payload_blocked() {
+    let (code, _) = run(r#"{"language":"shell","code":"git push --force origin main"}"#);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn other_harnesses_get_blocks_instead_of_asks_and_rewrites() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"trash_deletes":true,"sed_backups":{"enabled":true}}"#,
+    );
+    let project = tempfile::TempDir::new().unwrap();
+    let root = project.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("old")).unwrap();
+    std::fs::write(root.join("f.txt"), "a").unwrap();
+    let generic = |cmd: &str| {
+        serde_json::json!({"command": cmd, "cwd": root, "session_id": "s1"}).to_string()
+    };
+    let interpreter = |cmd: &str| {
+        serde_json::json!({"language": "shell", "code": cmd, "cwd": root, "session_id": "s1"})
+            .to_string()
+    };
+    for input in [&generic as &dyn Fn(&str) -> String, &interpreter] {
+        for cmd in [
+            "git reset --hard",
+            "chmod -R 755 ~",
+            "rm -rf old",
+            "sed -i s/a/b/ f.txt",
+        ] {
+            let (code, stdout, stderr) = run_stdout_with_home(home.path(), &input(cmd));
+            assert_eq!(code, 2, "{}: stdout: {} stderr: {}", cmd, stdout, stderr);
+            assert!(
+                stderr.starts_with("Blocked: ") && !stdout.contains("updatedInput"),
+                "{}: stdout: {} stderr: {}",
+                cmd,
+                stdout,
+                stderr
+            );
+        }
+    }
+    let (_, _, stderr) = run_stdout_with_home(home.path(), &generic("git reset --hard"));
+    assert!(
+        stderr.contains("(needs confirmation, which this harness cannot ask for)"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(root.join("old").exists());
+}
+
+#[test]
+fn open_interpreter_code_blocks_are_checked_as_one_liners() {
+    let (code, stderr) =
+        run(r#"{"language":"python","code":"import shutil\nshutil.rmtree(\"/\")"}"#);
+    assert_eq!(code, 2, "stderr: {}", stderr);
+    let (code, stderr) =
+        run(r#"{"language":"javascript","code":"require('child_process').execSync('rm -rf /')"}"#);
+    assert_eq!(code, 2, "stderr: {}", stderr);
+    assert_eq!(run(r#"{"language":"python","code":"print(1)"}"#).0, 0);
+}
+
+#[test]
+fn explicit_format_flag() {
+    let (code, _) = run_with_args(&["--format", "generic"], r#"{"cmd":["rm","-rf","/"]}"#);
+    assert_eq!(code, 2);
+    let (code, _) = run_with_args(&["--format=generic"], r#"{"command":"git status"}"#);
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn block_message_includes_blast_radius_context() {
+    let dir = tempfile::TempDir::new().unwrap();
+    for name in ["a.o", "b.o", "c.o"] {
+        std::fs::write(dir.path().join(name), "").unwrap();
+    }
     let input = serde_json::json!({
         "tool_name": "Bash",
-        "tool_input": {}
+        "tool_input": {"command": "rm -rf ./*.o"},
+        "cwd": dir.path().to_str().unwrap()
     })
     .to_string();
-    let (code, _) = run(&input);
-    assert_eq!(code, 0, "Missing command field should exit 0");
+    let (code, stderr) = run(&input);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("matches 3 entries"), "stderr: {}", stderr);
+}
+
+/// The audit log files under a HOME, one per project.
+fn audit_logs(home: &Path) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(home.join(".claude/hooks/safe-bash-audit"))
+        .map(|d| d.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default()
 }
 
 #[test]
-fn empty_stdin_exits_0() {
-    let (code, _) = run("");
-    assert_eq!(code, 0, "Empty stdin should exit 0");
+fn block_is_recorded_in_audit_log_with_privilege() {
+    let home = tempfile::TempDir::new().unwrap();
+    let hooks = home.path().join(".claude/hooks");
+    std::fs::create_dir_all(&hooks).unwrap();
+    let (code, _) = run_with_env(
+        &[],
+        &[("HOME", home.path().to_str().unwrap())],
+        &bash_input("rm -rf /"),
+    );
+    assert_eq!(code, 2);
+    let logs = audit_logs(home.path());
+    assert_eq!(logs.len(), 1);
+    let log = std::fs::read_to_string(&logs[0]).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["decision"], "deny");
+    assert!(["user", "root", "container-root"].contains(&entry["privilege"].as_str().unwrap()));
+}
+
+#[test]
+fn audit_logs_are_per_project_and_purgeable() {
+    let home = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(home.path().join(".claude/hooks")).unwrap();
+    let (a, b) = (
+        tempfile::TempDir::new().unwrap(),
+        tempfile::TempDir::new().unwrap(),
+    );
+    std::fs::create_dir(a.path().join(".git")).unwrap();
+    std::fs::create_dir(a.path().join("src")).unwrap();
+    for cwd in [a.path().join("src").as_path(), a.path(), b.path()] {
+        let input = serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": "rm -rf src"},
+            "cwd": cwd,
+        })
+        .to_string();
+        let (code, _, _) = run_stdout_with_home(home.path(), &input);
+        assert_eq!(code, 2);
+    }
+    // A subdirectory of a workspace shares the workspace root's log
+    assert_eq!(audit_logs(home.path()).len(), 2);
+
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("purge")
+        .arg(a.path().join("src"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("Purged 2 audit entries for "),
+        "stdout: {}",
+        stdout
+    );
+    let logs = audit_logs(home.path());
+    assert_eq!(logs.len(), 1);
+    let log = std::fs::read_to_string(&logs[0]).unwrap();
+    assert!(
+        log.contains(&format!("\"project\":{}", serde_json::json!(b.path()))),
+        "log: {}",
+        log
+    );
+
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("purge")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Nothing to purge"));
+}
+
+// ---------------------------------------------------------------------------
+// Ask decisions (exit 0 + permissionDecision JSON)
+// ---------------------------------------------------------------------------
+
+/// A HOME with ~/.claude/hooks/safe-bash-patterns.json and a fresh update timestamp,
+/// so the background updater leaves the config alone.
+fn home_with_config(config: &str) -> tempfile::TempDir {
+    let home = tempfile::TempDir::new().unwrap();
+    let hooks = home.path().join(".claude/hooks");
+    std::fs::create_dir_all(&hooks).unwrap();
+    std::fs::write(hooks.join("safe-bash-patterns.json"), config).unwrap();
+    std::fs::write(hooks.join("safe-bash-patterns.last_update"), "0").unwrap();
+    home
+}
+
+fn run_stdout_with_home(home: &Path, input: &str) -> (i32, String, String) {
+    let output = Command::new(binary())
+        .env("HOME", home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input.as_bytes())?;
+            child.wait_with_output()
+        })
+        .expect("failed to run safe-bash-hook binary — run `cargo build` first");
+    (
+        output.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn category_severity_ask_and_warn() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"sensitive-read":"ask",
+            "in-place-edit":"warn"}}"#,
+    );
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &bash_input("cat .env"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+
+    let (code, stdout, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("sed -i 's/a/b/' f.txt"));
+    assert_eq!(code, 0);
+    assert!(stdout.is_empty());
+    assert!(
+        stderr.contains("Warning: Destructive: sed -i"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn category_severity_off_disables_category() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"in-place-edit":"off",
+            "git-destructive":"off"}}"#,
+    );
+    for cmd in ["sed -i 's/a/b/' f.txt", "git reset --hard HEAD~1"] {
+        let (code, stdout, stderr) = run_stdout_with_home(home.path(), &bash_input(cmd));
+        assert_eq!(
+            (code, stdout.as_str(), stderr.as_str()),
+            (0, "", ""),
+            "{}",
+            cmd
+        );
+    }
+    // Other categories in the same command still apply
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("sed -i s/a/b/ f && cat .env")).0,
+        2
+    );
+}
+
+#[test]
+fn root_escalation_keeps_stricter_severity_overrides() {
+    // Only the hook running as root escalates
+    if unsafe { libc::geteuid() } != 0 {
+        return;
+    }
+    let home = home_with_config(
+        r#"{"category_severity":{"env-destruction":"deny","git-destructive":"deny",
+            "in-place-edit":"off"}}"#,
+    );
+    for cmd in [
+        "conda remove --all -n dev -y",
+        "git reset --hard",
+        "sed -i s/a/b/ f.txt",
+    ] {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input(cmd));
+        assert_eq!(code, 2, "{}: {}", cmd, stderr);
+    }
+}
+
+#[test]
+fn rule_severities_map_to_hook_outcomes() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"rule_severity":{"Destructive: git reset --hard":"audit"},
+            "deny":[
+            {"pattern":"^terraform\\s+plan\\b","reason":"Plans are reviewed later",
+            "severity":"warn"},
+            {"pattern":"^terraform\\s+apply\\b","reason":"Apply needs a look","severity":"ask"}]}"#,
+    );
+    // audit: allowed with nothing on stderr, recorded in the audit log
+    let (code, stdout, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("git reset --hard HEAD~1"));
+    assert_eq!((code, stdout.as_str(), stderr.as_str()), (0, "", ""));
+    let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+    assert!(
+        log.contains(r#""decision":"audit""#) && log.contains("git reset --hard"),
+        "log: {}",
+        log
+    );
+    // warn: allowed with the reason on stderr
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("terraform plan"));
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("Warning: Plans are reviewed later"),
+        "stderr: {}",
+        stderr
+    );
+    // ask: the user confirms
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &bash_input("terraform apply"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#) && stdout.contains("Apply needs a look"),
+        "stdout: {}",
+        stdout
+    );
+    // Other git-destructive rules keep their category's severity
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("git push --force origin main")).0,
+        2
+    );
+}
+
+#[test]
+fn inline_comment_suppresses_suppressible_rules() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"suppressible_rules":["GIT002"],
+            "rule_severity":{"GIT002":"deny"},"deny":[
+            {"id":"DEPLOY","pattern":"\\bdeploy\\.sh\\b","reason":"Deploys go through CI",
+            "suppressible":true},
+            {"pattern":"^kubectl\\s+delete\\b","reason":"Cluster changes go through CI"}]}"#,
+    );
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("git reset --hard HEAD~1"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with("Blocked: Destructive: git reset --hard [GIT002]"),
+        "stderr: {}",
+        stderr
+    );
+    // Lifted for this command only, with a warning the audit log records
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("git reset --hard HEAD~1 # safe-bash: allow GIT002 undoing my own commit"),
+    );
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    assert!(
+        stderr.contains("Warning: suppressed inline: GIT002 (Destructive: git reset --hard)"),
+        "stderr: {}",
+        stderr
+    );
+    let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+    assert!(
+        log.contains(r#""decision":"warn""#) && log.contains("suppressed inline: GIT002"),
+        "log: {}",
+        log
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &bash_input("./deploy.sh staging # safe-bash: allow DEPLOY")
+        )
+        .0,
+        0
+    );
+    // Rules not marked suppressible still block, and the block says the comment did nothing
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("kubectl delete ns app # safe-bash: allow CFG002"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with(
+            "Blocked: Cluster changes go through CI [CFG002]; cannot be suppressed inline: CFG002"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &bash_input("git push --force origin main # safe-bash: allow GIT001")
+        )
+        .0,
+        2
+    );
+    // A quoted comment marker is not a comment
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &bash_input("git reset --hard HEAD~1; echo '# safe-bash: allow GIT002'")
+        )
+        .0,
+        2
+    );
+}
+
+#[test]
+fn allow_variable_lifts_rules_for_one_command() {
+    let home =
+        home_with_config(r#"{"root_escalation":false,"suppressible_rules":["GIT002","FS001"]}"#);
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("SAFE_BASH_ALLOW=GIT002 git reset --hard HEAD~1"),
+    );
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    assert!(
+        stderr.contains("Warning: suppressed inline: GIT002 (Destructive: git reset --hard)"),
+        "stderr: {}",
+        stderr
+    );
+    // file-destructive cannot be turned off, so its rules go down to ask: the user approves
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input("SAFE_BASH_ALLOW=FS001 rm -rf ./scratch"),
+    );
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#)
+            && stdout.contains("suppressed inline: FS001"),
+        "stdout: {}",
+        stdout
+    );
+    // Unknown IDs are named in the warning and the audit log, and lift nothing
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("SAFE_BASH_ALLOW=GIT001 git push --force origin main"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("cannot be suppressed inline: GIT001"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("SAFE_BASH_ALLOW=NOPE ls"));
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("Warning: cannot be suppressed inline: NOPE"),
+        "stderr: {}",
+        stderr
+    );
+    let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+    assert!(
+        log.contains("cannot be suppressed inline: NOPE"),
+        "log: {}",
+        log
+    );
+}
+
+#[test]
+fn profile_chosen_by_config_or_env_var() {
+    let home = home_with_config(r#"{"root_escalation":false,"profile":"permissive"}"#);
+    let with = |profile: &str, cmd: &str| {
+        run_with_env(
+            &[],
+            &[
+                ("HOME", home.path().to_str().unwrap()),
+                ("SAFE_BASH_PROFILE", profile),
+            ],
+            &bash_input(cmd),
+        )
+    };
+    let (code, stderr) = run_with_env(
+        &[],
+        &[("HOME", home.path().to_str().unwrap())],
+        &bash_input("cat .env"),
+    );
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("Warning: Sensitive: reading .env file"),
+        "stderr: {}",
+        stderr
+    );
+    // The variable wins over the config file
+    assert_eq!(with("standard", "cat .env").0, 2);
+    assert_eq!(with("standard", "npm install left-pad").0, 0);
+    let (code, stderr) = with("strict", "npm install left-pad");
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Package manager: JavaScript package install"),
+        "stderr: {}",
+        stderr
+    );
+    // Irreversible operations block under every profile
+    assert_eq!(with("permissive", "rm -rf /").0, 2);
+
+    let locked = home_with_config(r#"{"locked":["profile"],"profile":"strict"}"#);
+    let (code, _) = run_with_env(
+        &[],
+        &[
+            ("HOME", locked.path().to_str().unwrap()),
+            ("SAFE_BASH_PROFILE", "permissive"),
+        ],
+        &bash_input("curl https://example.test"),
+    );
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn report_mode_warns_and_audits_instead_of_blocking() {
+    let home = home_with_config(r#"{"root_escalation":false,"mode":"report"}"#);
+    let (code, stdout, stderr) = run_stdout_with_home(home.path(), &bash_input("rm -rf /"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+    assert!(
+        stderr.starts_with("Warning: report only, would block: "),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("git push --force origin main"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+    assert!(
+        stderr.contains("Warning: report only, would "),
+        "stderr: {}",
+        stderr
+    );
+    let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+    let decisions: Vec<String> = log
+        .lines()
+        .map(|l| {
+            serde_json::from_str::<serde_json::Value>(l).unwrap()["decision"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(decisions[0], "would-deny");
+    assert!(
+        decisions[1].starts_with("would-"),
+        "decisions: {:?}",
+        decisions
+    );
+
+    // The variable turns it on or off over the config file, unless a layer locks the mode
+    let enforcing = home_with_config(r#"{"root_escalation":false}"#);
+    let with = |home: &tempfile::TempDir, mode: &str| {
+        run_with_env(
+            &[],
+            &[
+                ("HOME", home.path().to_str().unwrap()),
+                ("SAFE_BASH_MODE", mode),
+            ],
+            &bash_input("rm -rf /"),
+        )
+    };
+    assert_eq!(with(&enforcing, "report").0, 0);
+    assert_eq!(with(&home, "enforce").0, 2);
+    let locked = home_with_config(r#"{"locked":["mode"],"mode":"enforce"}"#);
+    assert_eq!(with(&locked, "report").0, 2);
+}
+
+#[test]
+fn report_mode_only_reports_trash_and_backup_rewrites() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"mode":"report","trash_deletes":true,
+            "sed_backups":{"enabled":true}}"#,
+    );
+    let project = tempfile::TempDir::new().unwrap();
+    let root = project.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("old")).unwrap();
+    std::fs::write(root.join("f.txt"), "a").unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": root,
+            "session_id": "s1",
+        })
+        .to_string()
+    };
+    for (cmd, would) in [
+        ("rm -rf old", "would-trash"),
+        ("sed -i s/a/b/ f.txt", "would-backup"),
+    ] {
+        let (code, stdout, stderr) = run_stdout_with_home(home.path(), &input(cmd));
+        assert_eq!((code, stdout.as_str()), (0, ""), "{}", cmd);
+        assert!(
+            stderr.starts_with("Warning: report only, would run `"),
+            "{}: {}",
+            cmd,
+            stderr
+        );
+        let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(log.lines().last().unwrap()).unwrap();
+        assert_eq!(entry["decision"], would, "{}", cmd);
+    }
+    // Nothing was approved for the trash call to run
+    assert!(
+        std::fs::read_dir(home.path().join(".claude/hooks/safe-bash-trash-approvals"))
+            .map_or(true, |mut d| d.next().is_none())
+    );
+}
+
+#[test]
+fn role_overlays_chosen_by_env_var_unless_identity_is_signed() {
+    let config = r#"{"root_escalation":false,"deny":[{"pattern":"^make release",
+        "reason":"Releases need the release-manager role"}],
+        "roles":{"release-manager":{"allow":[{"pattern":"^make release",
+        "reason":"release manager"}]},
+                 "junior-dev":{"category_severity":{"git-destructive":"deny"}}}}"#;
+    let home = home_with_config(config);
+    let home_var = ("HOME", home.path().to_str().unwrap());
+    let (code, stderr) = run_with_env(&[], &[home_var], &bash_input("make release"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Releases need the release-manager role"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_with_env(
+            &[],
+            &[home_var, ("SAFE_BASH_ROLE", "junior-dev, release-manager")],
+            &bash_input("make release")
+        )
+        .0,
+        0
+    );
+
+    // With an identity the variable is ignored, and a missing signature gives no roles
+    let signed = home_with_config(&config.replacen(
+        '{',
+        r#"{"identity":{"file":"~/identity.json","public_key":"~/org.pem"},"locked":["identity"],"#,
+        1,
+    ));
+    let (code, stderr) = run_with_env(
+        &[],
+        &[
+            ("HOME", signed.path().to_str().unwrap()),
+            ("SAFE_BASH_ROLE", "release-manager"),
+        ],
+        &bash_input("make release"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("ignoring SAFE_BASH_ROLE"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn hooks_dir_and_user_config_relocated_by_env_vars() {
+    // Without the variables the HOME's own config applies
+    let home = home_with_config(
+        r#"{"root_escalation":false,"deny":[{"pattern":"^make deploy","reason":"home rule"}]}"#,
+    );
+    let elsewhere = home_with_config(
+        r#"{"root_escalation":false,"deny":[{"pattern":"^make release",
+            "reason":"relocated rule"}]}"#,
+    );
+    let dir = elsewhere.path().join(".claude/hooks");
+    let user = elsewhere.path().join("candidate.toml");
+    std::fs::write(
+        &user,
+        "[[deny]]\npattern = '^make publish'\nreason = \"candidate rule\"\n",
+    )
+    .unwrap();
+    let home_var = ("HOME", home.path().to_str().unwrap());
+    let dir_var = ("SAFE_BASH_HOOKS_DIR", dir.to_str().unwrap());
+    assert_eq!(
+        run_with_env(&[], &[home_var], &bash_input("make deploy")).0,
+        2
+    );
+    assert_eq!(
+        run_with_env(&[], &[home_var], &bash_input("make release")).0,
+        0
+    );
+
+    let (code, stderr) = run_with_env(&[], &[home_var, dir_var], &bash_input("make release"));
+    assert_eq!(code, 2);
+    assert!(stderr.contains("relocated rule"), "stderr: {}", stderr);
+    assert_eq!(
+        run_with_env(&[], &[home_var, dir_var], &bash_input("make deploy")).0,
+        0
+    );
+    assert!(
+        !audit_logs(elsewhere.path()).is_empty(),
+        "audit log written under the relocated directory"
+    );
+
+    let (code, stderr) = run_with_env(
+        &[],
+        &[
+            home_var,
+            dir_var,
+            ("SAFE_BASH_CONFIG", user.to_str().unwrap()),
+        ],
+        &bash_input("make publish"),
+    );
+    assert_eq!(code, 2);
+    assert!(stderr.contains("candidate rule"), "stderr: {}", stderr);
+}
+
+#[test]
+fn protected_paths_match_after_resolving_the_path() {
+    let home = home_with_config(r#"{"root_escalation":false,"protected_paths":["~/.ssh","/etc"]}"#);
+    std::fs::create_dir_all(home.path().join(".ssh")).unwrap();
+    std::fs::write(home.path().join(".ssh/id_rsa"), "").unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": home.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("cat ~/.s?h/id_rsa"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Blocked: Protected path: cat ~/.s?h/id_rsa reaches ~/.ssh (protected_paths) [PATH101]"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &input("cd .ssh && cat id_rsa")).0,
+        2
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &input("sed -n 1p ../../../../../../etc/passwd")
+        )
+        .0,
+        2
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &input("cat notes.txt")).0,
+        0
+    );
+}
+
+#[test]
+fn zone_rules_match_where_paths_lie() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"deny":[
+            {"id":"SYSW","zones":["system"],"access":"write","reason":"No writes to system paths"},
+            {"command":"rm","zones":["system","home-config","outside-project"],"access":"delete",
+            "reason":"Deletes stay in the project or temp"}
+        ]}"#,
+    );
+    let project = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(project.path().join(".git")).unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": project.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("cd /etc && touch motd"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("No writes to system paths [SYSW]"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &input("cp notes.txt ../../../../../../usr/local/notes.txt")
+        )
+        .0,
+        2
+    );
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("rm ~/.bashrc"));
+    assert_eq!(code, 2, "stderr: {}", stderr);
+    assert!(
+        stderr.contains("Deletes stay in the project or temp"),
+        "stderr: {}",
+        stderr
+    );
+    for cmd in [
+        "rm notes.txt",
+        "rm /tmp/scratch.log",
+        "cat /etc/hosts",
+        "cp /etc/hosts hosts.bak",
+    ] {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &input(cmd));
+        assert_eq!(code, 0, "{}: {}", cmd, stderr);
+    }
+}
+
+#[test]
+fn sha256_allow_entries_approve_one_exact_command() {
+    let approved = "./deploy.sh --env prod --tag v1.4";
+    let output = Command::new(binary())
+        .arg("hash")
+        .arg(approved)
+        .output()
+        .unwrap();
+    let entry: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let digest = entry["value"].as_str().unwrap().to_string();
+    assert_eq!(entry["type"], "sha256");
+    let home = home_with_config(&format!(
+        r#"{{"root_escalation":false,"deny":[{{"pattern":"^\\./deploy\\.sh\\b",
+            "reason":"Deploys go through CI"}}],"allow":[{{"type":"sha256","value":"{}",
+            "reason":"the v1.4 rollout"}}]}}"#,
+        digest.to_uppercase()
+    ));
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input(approved)).0,
+        0
+    );
+    for cmd in [
+        "./deploy.sh --env prod --tag v1.5",
+        "./deploy.sh --env prod --tag v1.4 --force",
+    ] {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input(cmd));
+        assert_eq!(code, 2, "{}", cmd);
+        assert!(
+            stderr.contains("Deploys go through CI"),
+            "stderr: {}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn protected_branches_decide_force_pushes_by_destination() {
+    let home =
+        home_with_config(r#"{"root_escalation":false,"protected_branches":["main","release/*"]}"#);
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &bash_input("git push --force origin ana/feature")
+        )
+        .0,
+        0
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("git push origin +HEAD:release/2.0"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr
+            .contains("Blocked: Destructive: force push to protected branch release/2.0 [GIT101]"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("git push origin --delete main")).0,
+        2
+    );
+    // A destination the refspecs do not name keeps the generic rule
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("git push --all --force origin"));
+    assert_eq!(code, 2);
+    assert!(stderr.contains("[GIT001]"), "stderr: {}", stderr);
+    // An unprotected push beside it does not let off a protected one hidden in a script or variable
+    for cmd in [
+        r#"git push --force origin feature; bash -c "git push --force origin main""#,
+        "git push -f origin feature; c=git; $c push --force origin main",
+        "git push -f origin feature && echo $(git push -f origin main)",
+    ] {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input(cmd));
+        assert_eq!(code, 2, "{}", cmd);
+        assert!(stderr.contains("[GIT101]"), "{}: {}", cmd, stderr);
+    }
+
+    // Lowering the category does not reach protected branches
+    let lowered = home_with_config(
+        r#"{"root_escalation":false,"protected_branches":["main"],
+            "category_severity":{"git-destructive":"warn"}}"#,
+    );
+    assert_eq!(
+        run_stdout_with_home(lowered.path(), &bash_input("git push -f origin main")).0,
+        2
+    );
+}
+
+#[test]
+fn command_rule_matches_parsed_words() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"git-destructive":"off"},
+            "deny":[{"command":"git","subcommand":"push","flags_any":["--force","-f"],
+            "reason":"No force pushes"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("timeout 60 git -C app push origin main -fq"),
+    );
+    assert_eq!(code, 2);
+    assert!(stderr.contains("No force pushes"), "stderr: {}", stderr);
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("git push --force-with-lease")).0,
+        0
+    );
+}
+
+#[test]
+fn glob_rule_matches_whole_segment() {
+    let home = home_with_config(
+        r#"{"deny":[{"type":"glob","pattern":"kubectl --context=prod* *",
+            "reason":"Prod clusters go through CI"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("make build && kubectl --context=prod-eu delete pod api-0"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Prod clusters go through CI"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("kubectl --context=dev get pods")).0,
+        0
+    );
+}
+
+#[test]
+fn contains_and_exact_rules_take_text_literally() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"deny":[{"type":"contains","pattern":"prod-db.internal",
+            "reason":"No direct prod database access"},{"type":"contains",
+            "pattern":"npm run deploy:","reason":"Deploys go through CI"}],
+            "allow":[{"type":"exact","pattern":"npm run deploy:staging",
+            "reason":"Staging deploys are fine"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("psql -h prod-db.internal -c 'select 1'"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("No direct prod database access"),
+        "stderr: {}",
+        stderr
+    );
+    // `.` is not a wildcard
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("psql -h prod-dbXinternal")).0,
+        0
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("npm run deploy:staging")).0,
+        0
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("npm run deploy:staging --prod")).0,
+        2
+    );
+}
+
+#[test]
+fn unless_exempts_matching_targets_only() {
+    let home = home_with_config(
+        r#"{"deny":[{"command":"git","subcommand":"branch","flags_any":["-D"],
+            "unless":[{"args_match":"^scratch/"}],
+            "reason":"Only scratch branches may be force-deleted"}]}"#,
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &bash_input("git branch -D scratch/a scratch/b")
+        )
+        .0,
+        0
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("git branch -D scratch/a && git branch -D main"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Only scratch branches may be force-deleted"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn scheduled_rules_apply_outside_their_windows() {
+    // Today's and tomorrow's weekday in UTC: a window on today lifts the rule, one on tomorrow does
+    // not
+    const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let today = ((secs / 86_400 + 3) % 7) as usize;
+    let config = |day: &str| {
+        format!(
+            r#"{{"deny":[{{"pattern":"^terraform\\s+apply\\b",
+                "reason":"Applies only in supervised hours",
+                "schedule":{{"windows":["{} 00:00-24:00"],"timezone":"UTC"}}}}]}}"#,
+            day
+        )
+    };
+    let home = home_with_config(&config(DAYS[today]));
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("terraform apply")).0,
+        0
+    );
+    let home = home_with_config(&config(DAYS[(today + 1) % 7]));
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("terraform apply"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(&format!(
+            "Applies only in supervised hours (outside {} 00:00-24:00 UTC)",
+            DAYS[(today + 1) % 7]
+        )),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn config_deny_matches_normalized_whitespace() {
+    let home =
+        home_with_config(r#"{"deny":[{"pattern":"^npm publish\\b","reason":"no publishing"}]}"#);
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("npm \t publish \\\n  --access public"),
+    );
+    assert_eq!(code, 2);
+    assert!(stderr.contains("no publishing"), "stderr: {}", stderr);
+}
+
+#[test]
+fn block_message_cites_policy_layer() {
+    let (_, stderr) = run(&bash_input("rm -rf src"));
+    assert!(
+        stderr.contains("Policy: built-in rule (file-destructive)"),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"policy":{"name":"Acme org policy","contact":"https://wiki.acme.test/exceptions"},
+            "deny":[{"pattern":"\\bterraform\\s+apply\\b","reason":"Deploy: terraform apply"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("terraform apply"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Policy: rule from Acme org policy — request an exception via \
+             https://wiki.acme.test/exceptions"
+        ),
+        "stderr: {}",
+        stderr
+    );
+
+    // The built-in rule's hint names the user config the hook loaded
+    let user = home.path().join("my-safe-bash.toml");
+    std::fs::write(&user, "root_escalation = false\n").unwrap();
+    let (code, stderr) = run_with_env(
+        &[],
+        &[
+            ("HOME", home.path().to_str().unwrap()),
+            ("SAFE_BASH_CONFIG", user.to_str().unwrap()),
+        ],
+        &bash_input("rm -rf src"),
+    );
+    assert_eq!(code, 2);
+    let hint = format!(
+        "set \"category_severity\": {{\"file-destructive\": \"ask\"}} in {} to confirm",
+        user.display()
+    );
+    assert!(stderr.contains(&hint), "stderr: {}", stderr);
+}
+
+#[test]
+fn category_severity_cannot_lower_file_destructive_below_ask() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"file-destructive":"warn"}}"#,
+    );
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &bash_input("rm -rf src"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+}
+
+#[test]
+fn blocks_extracting_archive_with_traversal_entry() {
+    let dir = tempfile::TempDir::new().unwrap();
+    // Build the archive with tar itself: a member renamed to escape the destination
+    std::fs::write(dir.path().join("payload"), "x").unwrap();
+    let built = Command::new("tar")
+        .current_dir(dir.path())
+        .args(["-cf", "evil.tar", "--transform", "s,^,../,", "payload"])
+        .status();
+    if !built.is_ok_and(|s| s.success()) {
+        return; // GNU tar not installed
+    }
+    let input = serde_json::json!({
+        "tool_name": "Bash",
+        "tool_input": {"command": "tar -xf evil.tar"},
+        "cwd": dir.path().to_str().unwrap()
+    })
+    .to_string();
+    let (code, stderr) = run(&input);
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("path-traversal entry ../payload"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Policy: built-in rule (archive)"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn variable_indirection_resolved_or_asked() {
+    let (code, stderr) = run(&bash_input("c=rm; $c -rf build"));
+    assert_eq!(code, 2);
+    assert!(stderr.contains("Destructive: rm -rf"), "stderr: {}", stderr);
+
+    let (code, stdout) = run_stdout(&bash_input("$CLEANER -rf build"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+    assert!(v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap()
+        .contains("Indirect execution: $CLEANER -rf"));
+}
+
+#[test]
+fn rm_with_unguarded_variable_blocks_even_when_rm_is_asked() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"file-destructive":"ask"}}"#,
+    );
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("rm -rf \"$BUILD_DIR\"/"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("if BUILD_DIR is unset or empty"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Policy: built-in rule (unguarded-variable)"),
+        "stderr: {}",
+        stderr
+    );
+
+    let (code, stdout, _) =
+        run_stdout_with_home(home.path(), &bash_input("rm -rf \"${BUILD_DIR:?}\"/"));
+    assert_eq!(code, 0);
+    assert!(stdout.contains("\"ask\""), "stdout: {}", stdout);
+}
+
+#[test]
+fn ask_records_rollback_hint_for_rollback_info() {
+    let repo = tempfile::TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(repo.path())
+            .args([
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@t",
+                "-c",
+                "commit.gpgsign=false",
+            ])
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    };
+    if !git(&["init", "-q"]) {
+        return; // git not installed
+    }
+    assert!(git(&["commit", "-q", "--allow-empty", "-m", "init"]));
+    let head = Command::new("git")
+        .arg("-C")
+        .arg(repo.path())
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .unwrap();
+    let head = String::from_utf8_lossy(&head.stdout).trim().to_string();
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"rollback_hints":true,
+            "category_severity":{"git-destructive":"ask"}}"#,
+    );
+    let input = serde_json::json!({
+        "tool_name": "Bash",
+        "tool_input": {"command": "git reset --hard HEAD~1"},
+        "cwd": repo.path().to_str().unwrap()
+    })
+    .to_string();
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &input);
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    let reason = v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap();
+    let expected = format!(
+        "Rollback: git reset --hard {} (safe-bash-hook rollback-info ",
+        head
+    );
+    assert!(reason.contains(&expected), "reason: {}", reason);
+    let id = reason.rsplit(' ').next().unwrap().trim_end_matches(')');
+
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .args(["rollback-info", id])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("Rollback: git reset --hard {}", head)),
+        "stdout: {}",
+        stdout
+    );
+
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .args(["rollback-info", "0-0"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn root_wildcard_blocks_even_when_rm_is_asked() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"file-destructive":"ask",
+            "root-wildcard":"warn"}}"#,
+    );
+    let input = serde_json::json!({
+        "tool_name": "Bash",
+        "tool_input": {"command": "rm -rf ./*"},
+        "cwd": "/"
+    })
+    .to_string();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input);
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Catastrophic: rm -r ./* (run from /) deletes everything under the filesystem root"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Policy: built-in rule (root-wildcard), always enforced"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("root-wildcard cannot be lowered below deny"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn glob_delete_threshold_warns_small_and_blocks_broad_deletes() {
+    let home = home_with_config(r#"{"root_escalation":false,"glob_delete_threshold":3}"#);
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("build/obj")).unwrap();
+    std::fs::create_dir_all(dir.path().join("tmp")).unwrap();
+    std::fs::write(dir.path().join("build/a.o"), "").unwrap();
+    std::fs::write(dir.path().join("tmp/x"), "").unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": dir.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("rm -rf build/* tmp/*"));
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    assert!(
+        stderr.contains(
+            "Warning: rm -r build/* tmp/* deletes 3 files and directories (within \
+             glob_delete_threshold 3)"
+        ),
+        "stderr: {}",
+        stderr
+    );
+
+    std::fs::write(dir.path().join("build/obj/b.o"), "").unwrap();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("rm -rf build/* tmp/*"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("deletes more than 3 files and directories (glob_delete_threshold)"),
+        "stderr: {}",
+        stderr
+    );
+
+    // Plain paths keep the generic rule
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("rm -rf tmp"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: Destructive: rm -rf"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn build_artifact_deletes_inside_cwd_are_allowed() {
+    let home = home_with_config(r#"{"root_escalation":false,"artifact_dirs":["vendor"]}"#);
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("web/node_modules")).unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": dir.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+    for cmd in [
+        "rm -rf node_modules",
+        "rm -rf target/",
+        "rm -rf dist build .next",
+        "rm -rf web/node_modules vendor",
+    ] {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &input(cmd));
+        assert_eq!(code, 0, "{}: {}", cmd, stderr);
+    }
+    for cmd in [
+        "rm -rf node_modules src",
+        "rm -rf /tmp/build",
+        "rm -rf ../dist",
+        "rm -rf build; rm -rf ~",
+    ] {
+        assert_eq!(
+            run_stdout_with_home(home.path(), &input(cmd)).0,
+            2,
+            "{}",
+            cmd
+        );
+    }
+}
+
+#[test]
+fn env_destruction_warns_by_default_and_is_tunable() {
+    let home = home_with_config(r#"{"root_escalation":false}"#);
+    let (code, stdout, stderr) = run_stdout_with_home(home.path(), &bash_input("rm -rf .venv"));
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    assert!(stdout.is_empty());
+    assert!(
+        stderr.contains("Warning: Env destruction: rm -r .venv"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("pip uninstall -y -r requirements.txt"),
+    );
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("Warning: Env destruction: pip uninstall -y -r"),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"env-destruction":"deny"}}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("rm -rf .venv"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Policy: built-in rule (env-destruction)"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn rust_toolchain_changes_warn_until_raised() {
+    let home = home_with_config(r#"{"root_escalation":false}"#);
+    let (code, stdout, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("rustup self uninstall -y"));
+    assert_eq!(code, 0);
+    assert!(stdout.is_empty());
+    assert!(
+        stderr.contains("Warning: Rust toolchain: rustup self uninstall"),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"rust-toolchain":"ask"}}"#,
+    );
+    let (code, stdout, _) =
+        run_stdout_with_home(home.path(), &bash_input("cargo yank --version 1.2.3"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+}
+
+#[test]
+fn interpreter_one_liners_checked_as_shell_commands() {
+    let (code, stderr) = run(&bash_input(
+        r#"ruby -e 'require "fileutils"; FileUtils.rm_rf("/srv")'"#,
+    ));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: ruby -e one-liner runs `rm -r /srv` (FileUtils.rm_rf)"),
+        "stderr: {}",
+        stderr
+    );
+
+    let (code, stderr) = run(&bash_input(r#"awk '{print "rm -rf " $1 | "sh"}' dirs.txt"#));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: awk one-liner runs `print | sh` (print |): Shell injection"),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"deny":[{"pattern":"^terraform destroy\\b",
+            "reason":"no destroys"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input(r#"perl -e 'system("terraform destroy -auto-approve")'"#),
+    );
+    assert_eq!(code, 2, "stderr: {}", stderr);
+    assert!(stderr.contains("no destroys"), "stderr: {}", stderr);
+
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input(r#"python3 -c "import os, sys; os.system(sys.argv[1])" ls"#),
+    );
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+}
+
+#[test]
+fn encoded_payloads_decoded_and_checked() {
+    let (code, stderr) = run(&bash_input("echo cm0gLXJmIC8= | base64 -d | sh"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: Decoded payload (base64 -d | sh) runs `rm -rf /`"),
+        "stderr: {}",
+        stderr
+    );
+
+    // With pipe-to-shell lowered, the decoded command still decides
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"shell-injection":"warn"}}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("echo Z2l0IHB1c2ggLWY= | base64 -d | bash"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("runs `git push -f`: Destructive: git force push"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("echo bHMgLWxh | base64 -d | sh"));
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("Warning: Shell injection"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn editor_shell_escapes_checked() {
+    let (code, stderr) = run(&bash_input("vim -c '!git push --force origin main' -c q"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Blocked: vim -c shell escape runs `git push --force origin main`: Destructive: git \
+             force push"
+        ),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"deny":[{"pattern":"^terraform\\b","reason":"no terraform"}]}"#,
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("nvim --headless +'silent !terraform apply' +qa"),
+    );
+    assert_eq!(code, 2);
+    assert!(stderr.contains("no terraform"), "stderr: {}", stderr);
+}
+
+#[test]
+fn compound_commands_report_each_segment() {
+    let (code, stderr) = run(&bash_input(
+        "cargo build && git push --force origin main; cargo test",
+    ));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Segments (resubmit the allowed ones"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            "\n  allow  cargo build\n  deny   git push --force origin main — Destructive: git \
+             force push"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("\n  allow  cargo test"),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"git-destructive":"ask"}}"#,
+    );
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input("git stash && git reset --hard HEAD~1"),
+    );
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    let reason = v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap();
+    assert!(
+        reason.contains("\n  allow  git stash\n  ask    git reset --hard HEAD~1"),
+        "reason: {}",
+        reason
+    );
+
+    let (code, stderr) = run(&bash_input("git push --force origin main"));
+    assert_eq!(code, 2);
+    assert!(!stderr.contains("Segments"), "stderr: {}", stderr);
+}
+
+#[test]
+fn safe_prefix_offered_as_updated_input_when_enabled() {
+    let cmd = "cargo build && git push --force origin main && ./deploy.sh";
+    let home = home_with_config(r#"{"root_escalation":false,"suggest_safe_prefix":true}"#);
+    let input = r#"{"tool_name":"Bash",
+        "tool_input":{"command":"cargo build && git push --force origin main && ./deploy.sh",
+        "description":"ship"}}"#;
+    let (code, stdout, _) = run_stdout_with_home(home.path(), input);
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+    assert_eq!(
+        v["hookSpecificOutput"]["updatedInput"]["command"],
+        "cargo build"
+    );
+    assert_eq!(
+        v["hookSpecificOutput"]["updatedInput"]["description"],
+        "ship"
+    );
+    let reason = v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap();
+    assert!(
+        reason.starts_with("Blocked: Destructive: git force push"),
+        "reason: {}",
+        reason
+    );
+    assert!(
+        reason.contains("Suggested: approve to run only `cargo build`"),
+        "reason: {}",
+        reason
+    );
+    let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["decision"], "deny");
+
+    // Nothing safe before the blocked segment, or the option off: a plain block
+    let (code, _, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input("git push --force origin main && cargo build"),
+    );
+    assert_eq!(code, 2);
+    let (code, _) = run(&bash_input(cmd));
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn opted_in_rules_rewrite_the_command_via_updated_input() {
+    let home =
+        home_with_config(r#"{"root_escalation":false,"rewrite_rules":["GIT001","FS001","FS018"]}"#);
+    let decide = |cmd: &str| {
+        let input = serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "session_id": "s1",
+        })
+        .to_string();
+        let (code, stdout, _) = run_stdout_with_home(home.path(), &input);
+        assert_eq!(code, 0, "{}", cmd);
+        serde_json::from_str::<serde_json::Value>(stdout.trim()).unwrap()["hookSpecificOutput"]
+            .clone()
+    };
+
+    let out = decide("git push --force origin feature");
+    assert_eq!(out["permissionDecision"], "ask");
+    assert_eq!(
+        out["updatedInput"]["command"],
+        "git push --force-with-lease origin feature"
+    );
+    let reason = out["permissionDecisionReason"].as_str().unwrap();
+    assert!(
+        reason.starts_with("Rewritten: Destructive: git force push [GIT001]"),
+        "reason: {}",
+        reason
+    );
+    assert_eq!(
+        decide("rm -rf ~/notes")["updatedInput"]["command"],
+        "rm -i -r ~/notes"
+    );
+
+    // rsync --delete is previewed the first time, then asked about as usual
+    let cmd = "rsync -av --delete dist/ /srv/www/";
+    assert_eq!(
+        decide(cmd)["updatedInput"]["command"],
+        "rsync --dry-run -av --delete dist/ /srv/www/"
+    );
+    let again = decide(cmd);
+    assert_eq!(again["permissionDecision"], "ask");
+    assert!(again.get("updatedInput").is_none());
+
+    let log = std::fs::read_to_string(&audit_logs(home.path())[0]).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["decision"], "ask");
+    assert_eq!(entry["command"], "git push --force origin feature");
+    assert_eq!(
+        entry["rewritten"],
+        "git push --force-with-lease origin feature"
+    );
+
+    // Not opted in, or nothing to rewrite: decided as usual
+    let (code, _, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input("git reset --hard HEAD~1 && git push --force"),
+    );
+    assert_eq!(code, 2);
+    let (code, _) = run(&bash_input("git push --force origin feature"));
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn trash_deletes_moves_workspace_files_and_restores_them() {
+    let home = home_with_config(r#"{"root_escalation":false,"trash_deletes":true}"#);
+    let project = tempfile::TempDir::new().unwrap();
+    let root = project.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("old/nested")).unwrap();
+    std::fs::write(root.join("old/nested/f.txt"), "data").unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": root,
+            "session_id": "s1",
+        })
+        .to_string()
+    };
+
+    let (code, stdout, stderr) = run_stdout_with_home(home.path(), &input("rm -rf old"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert!(
+        v["hookSpecificOutput"].get("permissionDecision").is_none(),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stderr.contains("rm moved to the trash"),
+        "stderr: {}",
+        stderr
+    );
+    let call = v["hookSpecificOutput"]["updatedInput"]["command"]
+        .as_str()
+        .unwrap();
+    assert!(call.contains(" trash --session s1 --id "), "call: {}", call);
+    let entry: serde_json::Value = serde_json::from_str(
+        std::fs::read_to_string(&audit_logs(home.path())[0])
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(entry["decision"], "trash");
+    assert_eq!(entry["command"], "rm -rf old");
+    assert_eq!(entry["rewritten"], call);
+
+    // Run the rewritten command as Claude Code would
+    let out = Command::new("sh")
+        .arg("-c")
+        .arg(call)
+        .current_dir(&root)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    let printed = String::from_utf8_lossy(&out.stdout).to_string();
+    assert!(
+        out.status.success(),
+        "stdout: {} stderr: {}",
+        printed,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(!root.join("old").exists());
+    let id = printed.trim().rsplit(' ').next().unwrap();
+
+    let listed = Command::new(binary())
+        .arg("restore")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    let listed = String::from_utf8_lossy(&listed.stdout).to_string();
+    assert!(
+        listed.starts_with(id) && listed.contains(&root.join("old").display().to_string()),
+        "list: {}",
+        listed
+    );
+    let restored = Command::new(binary())
+        .args(["restore", id])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(restored.status.success());
+    assert_eq!(
+        std::fs::read_to_string(root.join("old/nested/f.txt")).unwrap(),
+        "data"
+    );
+
+    // The approval was used up: running the call again, or one the hook did not write, moves
+    // nothing
+    let out = Command::new("sh")
+        .arg("-c")
+        .arg(call)
+        .current_dir(&root)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(root.join("old/nested/f.txt").exists());
+    let forged = Command::new(binary())
+        .args([
+            "trash",
+            "--session",
+            "a",
+            "--id",
+            "b",
+            "-r",
+            "-f",
+            "--",
+            "old",
+        ])
+        .current_dir(&root)
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!forged.status.success());
+    assert!(root.join("old/nested/f.txt").exists());
+
+    // Calling the subcommands through Bash is blocked
+    for cmd in [
+        "safe-bash-hook trash --session a --id b --root / -r -f -- /etc",
+        "~/.claude/hooks/safe-bash-hook restore 1-2",
+    ] {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &input(cmd));
+        assert_eq!(code, 2, "{}", cmd);
+        assert!(stderr.contains("SELF003"), "stderr: {}", stderr);
+    }
+
+    // Outside the workspace the rm rules decide as usual
+    let (code, _, _) = run_stdout_with_home(home.path(), &input("rm -rf /tmp/elsewhere"));
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn sed_backups_run_in_place_edits_with_a_backup() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"sed_backups":{"enabled":true,"max_age_days":7}}"#,
+    );
+    let project = tempfile::TempDir::new().unwrap();
+    let root = project.path().canonicalize().unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({"tool_name": "Bash", "tool_input": {"command": cmd}, "cwd": root})
+            .to_string()
+    };
+
+    let (code, stdout, stderr) =
+        run_stdout_with_home(home.path(), &input("sed -i 's/a/b/' notes.txt"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert!(
+        v["hookSpecificOutput"].get("permissionDecision").is_none(),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stderr.contains("sed -i keeps a backup"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        v["hookSpecificOutput"]["updatedInput"]["command"],
+        "sed -i.safe-bash.bak 's/a/b/' notes.txt"
+    );
+    let backup = format!("{}/notes.txt.safe-bash.bak", root.display());
+    let entry: serde_json::Value = serde_json::from_str(
+        std::fs::read_to_string(&audit_logs(home.path())[0])
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(entry["decision"], "backup");
+    assert_eq!(entry["command"], "sed -i 's/a/b/' notes.txt");
+    assert_eq!(entry["backups"], serde_json::json!([backup]));
+
+    // A sed that already keeps a backup, or off by default: asked about as usual
+    let (_, stdout, _) =
+        run_stdout_with_home(home.path(), &input("sed -i.orig 's/a/b/' notes.txt"));
+    assert!(
+        stdout.contains("\"permissionDecision\":\"ask\""),
+        "stdout: {}",
+        stdout
+    );
+    let (_, stdout) = run_stdout(&input("sed -i 's/a/b/' notes.txt"));
+    assert!(
+        stdout.contains("\"permissionDecision\":\"ask\"") && !stdout.contains("updatedInput"),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[test]
+fn snapshots_save_what_an_allowed_rm_deletes() {
+    let home = home_with_config(r#"{"root_escalation":false,"snapshots":true}"#);
+    let project = tempfile::TempDir::new().unwrap();
+    let root = project.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("notes")).unwrap();
+    std::fs::write(root.join("notes/a.txt"), "a").unwrap();
+    std::fs::write(root.join("notes/b.txt"), "b").unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": root,
+            "session_id": "s1",
+        })
+        .to_string()
+    };
+
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &input("rm notes/*.txt"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+    let entry: serde_json::Value = serde_json::from_str(
+        std::fs::read_to_string(&audit_logs(home.path())[0])
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(entry["decision"], "audit");
+    let reason = entry["reason"].as_str().unwrap();
+    assert!(reason.starts_with("snapshot "), "reason: {}", reason);
+    let id = reason.trim_end_matches(')').rsplit(' ').next().unwrap();
+    for name in ["a.txt", "b.txt"] {
+        std::fs::remove_file(root.join("notes").join(name)).unwrap();
+    }
+
+    let listed = Command::new(binary())
+        .arg("restore")
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    let listed = String::from_utf8_lossy(&listed.stdout).to_string();
+    assert!(
+        listed.starts_with(&format!(
+            "{}  snapshot  session s1  {}",
+            id,
+            root.join("notes/a.txt").display()
+        )),
+        "list: {}",
+        listed
+    );
+    let restored = Command::new(binary())
+        .args(["restore", id])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(restored.status.success());
+    assert_eq!(
+        std::fs::read_to_string(root.join("notes/b.txt")).unwrap(),
+        "b"
+    );
+
+    // Nothing there to save: no snapshot, nothing audited
+    let (code, _, _) = run_stdout_with_home(home.path(), &input("rm gone.txt"));
+    assert_eq!(code, 0);
+    assert_eq!(
+        std::fs::read_to_string(&audit_logs(home.path())[0])
+            .unwrap()
+            .lines()
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn editor_state_protected_with_opt_outs() {
+    let (code, stderr) = run(&bash_input("truncate -s 0 .git/index"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: Editor state: truncate .git/index touches .git/index"),
+        "stderr: {}",
+        stderr
+    );
+
+    let (code, _) = run(&bash_input("cp /tmp/index.bak .git/index"));
+    assert_eq!(code, 2);
+
+    let home = home_with_config(r#"{"root_escalation":false,"unprotected_paths":[".git/index"]}"#);
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("cp /tmp/index.bak .git/index"));
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("echo '{}' > .vscode/settings.json"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Editor state: redirect > .vscode/settings.json"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn ssh_remote_command_checked_and_escalated() {
+    let (code, stderr) = run(&bash_input("ssh prod 'rm -rf /var/www'"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: Remote on prod: Destructive: rm -rf"),
+        "stderr: {}",
+        stderr
+    );
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"remote_escalation":true,
+            "category_severity":{"in-place-edit":"warn"}}"#,
+    );
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input("ssh prod \"sed -i s/a/b/ app.conf\""),
+    );
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+    let (code, stdout, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("sed -i s/a/b/ app.conf"));
+    assert_eq!(code, 0);
+    assert!(stdout.is_empty());
+    assert!(
+        stderr.contains("Warning: Destructive: sed -i"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn docker_container_commands_and_bind_mounts_checked() {
+    let input = |cmd: &str| {
+        serde_json::json!({"tool_name": "Bash", "tool_input": {"command": cmd}, "cwd": "/work/app"})
+            .to_string()
+    };
+    let (code, stderr) = run(&input("docker run -v $PWD:/w alpine rm -rf /w"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Blocked: In container alpine (bind mount /w → host /work/app): Destructive: rm -rf"
+        ),
+        "stderr: {}",
+        stderr
+    );
+
+    let (code, stderr) = run(&input("docker run --rm -v /:/host alpine ls /host"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Container escape: docker run mounts host / at /host"),
+        "stderr: {}",
+        stderr
+    );
+
+    let (code, stderr) = run(&input("docker run --rm -v $PWD:/w -w /w node:20 npm test"));
+    assert_eq!(code, 0, "stderr: {}", stderr);
+}
+
+#[test]
+fn alerts_queue_while_webhook_is_unreachable() {
+    // Port 9 (discard) refuses connections: delivery fails and the event must stay queued
+    let home = home_with_config(
+        r#"{"root_escalation":false,"alert_webhook":{"url":"http://127.0.0.1:9/alerts"}}"#,
+    );
+    let (code, _, _) =
+        run_stdout_with_home(home.path(), &bash_input("git push --force origin main"));
+    assert_eq!(code, 2);
+    let (code, _, _) = run_stdout_with_home(home.path(), &bash_input("ls -la"));
+    assert_eq!(code, 0);
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("flush-alerts")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+
+    // The queue is moved aside while a flush runs: read it before the file it is moved to
+    let hooks = home.path().join(".claude/hooks");
+    let queued = ["safe-bash-alerts.jsonl", "safe-bash-alerts.sending.jsonl"]
+        .iter()
+        .map(|f| std::fs::read_to_string(hooks.join(f)).unwrap_or_default())
+        .collect::<String>();
+    let events: Vec<serde_json::Value> = queued
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1, "queued: {}", queued);
+    assert_eq!(events[0]["decision"], "deny");
+    assert_eq!(events[0]["command"], "git push --force origin main");
+}
+
+#[test]
+fn asks_for_chmod_r_over_repo_root() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join(".git")).unwrap();
+    let input = serde_json::json!({
+        "tool_name": "Bash",
+        "tool_input": {"command": "chmod -R 755 ."},
+        "cwd": dir.path().to_str().unwrap()
+    })
+    .to_string();
+    let (code, stdout) = run_stdout(&input);
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+    assert!(v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap()
+        .contains("repository root"));
+}
+
+// ---------------------------------------------------------------------------
+// Edge cases
+// ---------------------------------------------------------------------------
+
+#[test]
+fn malformed_json_exits_0() {
+    let (code, _) = run("not json at all {{{{");
+    assert_eq!(code, 0, "Malformed JSON should exit 0 (allow, not crash)");
+}
+
+#[test]
+fn repeated_config_warning_prints_once_but_is_always_logged() {
+    let home = home_with_config("{ not json");
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("ls"));
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("safe-bash-hook: warn: malformed JSON in"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("ls"));
+    assert_eq!(code, 0);
+    assert!(!stderr.contains("malformed JSON"), "stderr: {}", stderr);
+
+    let log =
+        std::fs::read_to_string(home.path().join(".claude/hooks/safe-bash-debug.log")).unwrap();
+    assert_eq!(
+        log.matches("config-json: malformed JSON in").count(),
+        2,
+        "log: {}",
+        log
+    );
+}
+
+#[test]
+fn missing_command_field_exits_0() {
+    let input = serde_json::json!({
+        "tool_name": "Bash",
+        "tool_input": {}
+    })
+    .to_string();
+    let (code, _) = run(&input);
+    assert_eq!(code, 0, "Missing command field should exit 0");
+}
+
+#[test]
+fn empty_stdin_exits_0() {
+    let (code, _) = run("");
+    assert_eq!(code, 0, "Empty stdin should exit 0");
+}
+
+#[test]
+fn repeated_blocks_in_a_session_say_stop_retrying() {
+    let home = home_with_config(r#"{"root_escalation":false,"retry_loop":{"max_repeats":2}}"#);
+    let input = |session: &str| {
+        format!(
+            r#"{{"tool_name":"Bash","tool_input":{{"command":"git push --force origin main"}},
+                "session_id":"{}"}}"#,
+            session
+        )
+    };
+    for _ in 0..2 {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &input("s1"));
+        assert_eq!(code, 2);
+        assert!(
+            stderr.starts_with("Blocked: Destructive: git force push"),
+            "stderr: {}",
+            stderr
+        );
+    }
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("s1"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with(
+            "Blocked: Stop retrying: this command (or an equivalent form) has been blocked 3 times"
+        ),
+        "stderr: {}",
+        stderr
+    );
+
+    // Another session, or no session id at all, starts from scratch
+    let (_, _, stderr) = run_stdout_with_home(home.path(), &input("s2"));
+    assert!(
+        stderr.starts_with("Blocked: Destructive: git force push"),
+        "stderr: {}",
+        stderr
+    );
+    for _ in 0..3 {
+        let (_, _, stderr) =
+            run_stdout_with_home(home.path(), &bash_input("git push --force origin main"));
+        assert!(
+            stderr.starts_with("Blocked: Destructive: git force push"),
+            "stderr: {}",
+            stderr
+        );
+    }
+
+    let home = home_with_config(
+        r#"{"root_escalation":false,"retry_loop":{"max_repeats":1,"escalate":true}}"#,
+    );
+    run_stdout_with_home(home.path(), &input("s1"));
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &input("s1"));
+    assert_eq!(code, 0);
+    let v: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "ask");
+    let reason = v["hookSpecificOutput"]["permissionDecisionReason"]
+        .as_str()
+        .unwrap();
+    assert!(
+        reason.starts_with("The agent has submitted this command 2 times"),
+        "reason: {}",
+        reason
+    );
+
+    // Wiping / and locked rules stay blocked, with the stop-retrying advice
+    let home = home_with_config(
+        r#"{"root_escalation":false,"retry_loop":{"max_repeats":1,"escalate":true},
+            "deny":[{"pattern":"^terraform apply","reason":"CI only","locked":true}]}"#,
+    );
+    for (cmd, reason) in [("rm -rf /", "[RW101]"), ("terraform apply", "CI only")] {
+        let input = format!(
+            r#"{{"tool_name":"Bash","tool_input":{{"command":"{}"}},"session_id":"s1"}}"#,
+            cmd
+        );
+        run_stdout_with_home(home.path(), &input);
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &input);
+        assert_eq!(code, 2, "{}", cmd);
+        assert!(
+            stderr.starts_with(
+                "Blocked: Stop retrying: this command (or an equivalent form) has been blocked 2 \
+                 times"
+            ),
+            "stderr: {}",
+            stderr
+        );
+        assert!(stderr.contains(reason), "stderr: {}", stderr);
+    }
+}
+
+#[test]
+fn aliases_and_functions_expanded_at_call_sites() {
+    let (code, stderr) = run(&bash_input("alias nuke='rm -rf'; nuke /"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Catastrophic: rm -r / deletes everything under the filesystem root"),
+        "stderr: {}",
+        stderr
+    );
+
+    // With file-destructive lowered the body alone only asks; the call with /srv still blocks
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"file-destructive":"ask",
+            "unguarded-variable":"ask"}}"#,
+    );
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input(r#"f(){ rm -rf "$1"; }; f /srv"#));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Catastrophic: rm -r /srv"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, _) =
+        run_stdout_with_home(home.path(), &bash_input(r#"f(){ rm -rf "$1"; }; f build"#));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#),
+        "stdout: {}",
+        stdout
+    );
+
+    let (code, stderr) = run(&bash_input("alias gp='git push'; gp --force origin main"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Destructive: git force push"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _) = run(&bash_input("alias ll='ls -la'; ll /"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn quotas_ask_or_block_once_used_up() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"quotas":[
+            {"pattern":"^git\\s+push\\b","name":"git push","max":2,"per":"hour"},
+            {"pattern":"^terraform\\s+apply\\b","name":"terraform apply","max":1,"per":"session",
+            "exceed":"deny"}
+        ]}"#,
+    );
+    for _ in 0..2 {
+        let (code, stdout, _) =
+            run_stdout_with_home(home.path(), &bash_input("git push origin main"));
+        assert_eq!((code, stdout.as_str()), (0, ""));
+    }
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &bash_input("git push origin main"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Quota: git push has run 2 of 2 allowed times in the last hour"),
+        "stdout: {}",
+        stdout
+    );
+
+    let input = r#"{"tool_name":"Bash","tool_input":{"command":"terraform apply -auto-approve"},
+        "session_id":"s1"}"#;
+    let (code, _, _) = run_stdout_with_home(home.path(), input);
+    assert_eq!(code, 0);
+    let (code, _, stderr) = run_stdout_with_home(home.path(), input);
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with(
+            "Blocked: Quota: terraform apply has run 1 of 1 allowed times this session"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _, _) = run_stdout_with_home(home.path(), &input.replace("s1", "s2"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn detached_commands_raised_one_level() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"git-destructive":"ask"}}"#,
+    );
+    let (code, stdout, _) =
+        run_stdout_with_home(home.path(), &bash_input("git reset --hard origin/main"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#),
+        "stdout: {}",
+        stdout
+    );
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input("nohup git reset --hard origin/main &"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with("Blocked: Detached (nohup + &): Destructive: git reset --hard"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &bash_input("nohup npm run dev > dev.log 2>&1 &"),
+    );
+    assert_eq!((code, stdout.as_str()), (0, ""));
+}
+
+#[test]
+fn claude_md_safe_bash_block_adds_project_rules() {
+    let repo = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(repo.path().join(".git")).unwrap();
+    std::fs::create_dir(repo.path().join("infra")).unwrap();
+    std::fs::write(
+        repo.path().join("CLAUDE.md"),
+        "# Project\n\n```safe-bash\ndeny ^terraform\\s+apply\\b -- Terraform runs in CI \
+         only\nallow ^terraform\\s+apply\\s+-target=module\\.scratch\\b\n```\n",
+    )
+    .unwrap();
+    let home = home_with_config(
+        r#"{"root_escalation":false,"deny":[{"pattern":"-target=module\\.scratch",
+            "reason":"no scratch"}]}"#,
+    );
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": repo.path().join("infra").to_str().unwrap(),
+        })
+        .to_string()
+    };
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &input("terraform apply -auto-approve"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with(
+            "Blocked: Terraform runs in CI only [PRJ001]\nPolicy: rule from project policy in "
+        ),
+        "stderr: {}",
+        stderr
+    );
+    // A project allow does not lift a config deny
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &input("terraform apply -target=module.scratch"),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with("Blocked: no scratch"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _, _) = run_stdout_with_home(home.path(), &input("terraform plan"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn project_config_file_adds_rules_and_checks() {
+    let repo = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(repo.path().join(".git")).unwrap();
+    std::fs::create_dir(repo.path().join(".claude")).unwrap();
+    std::fs::write(
+        repo.path().join(".claude/safe-bash-patterns.json"),
+        r#"{"deny":[{"pattern":"^kubectl\\s+delete\\b","reason":"Cluster changes go through CI"}],
+            "category_severity":{"git-destructive":"warn"}}"#,
+    )
+    .unwrap();
+    // Project rules see through the user's wrappers
+    let home = home_with_config(r#"{"root_escalation":false,"wrappers":["with-lock"]}"#);
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": repo.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &input("with-lock kubectl delete ns app"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Blocked: Cluster changes go through CI [PRJ001]\nPolicy: rule from project policy in "
+        ),
+        "stderr: {}",
+        stderr
+    );
+    // Severities stay with the user config
+    assert!(
+        stderr.contains("ignoring \"category_severity\": only the user config may set it"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &input("git push --force origin main")).0,
+        2
+    );
+}
+
+#[test]
+fn project_config_cannot_add_wrappers() {
+    let repo = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(repo.path().join(".git")).unwrap();
+    std::fs::write(
+        repo.path().join(".safe-bash.json"),
+        r#"{"wrappers":["rm"]}"#,
+    )
+    .unwrap();
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"file-destructive":"ask"}}"#,
+    );
+    let input = serde_json::json!({
+        "tool_name": "Bash",
+        "tool_input": {"command": "rm -rf /"},
+        "cwd": repo.path().to_str().unwrap(),
+    })
+    .to_string();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("[RW101]"), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("ignoring \"wrappers\": only the user config may set it"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn locked_org_rule_survives_user_allow() {
+    let home = home_with_config(
+        r#"{"policy":{"name":"Org policy"},"locked":["category_severity"],
+            "deny":[{"pattern":"^kubectl\\s+delete\\b","reason":"Cluster changes go through CI",
+            "locked":true},{"pattern":"^helm\\s+uninstall\\b",
+            "reason":"Use the release pipeline"}]}"#,
+    );
+    let hooks = home.path().join(".claude/hooks");
+    std::fs::write(
+        hooks.join("safe-bash-user.json"),
+        r#"{"root_escalation":false,
+        "category_severity":{"git-destructive":"warn"},"allow":[{"pattern":"^(kubectl|helm)\\b",
+        "reason":"my cluster"}]}"#,
+    )
+    .unwrap();
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("kubectl delete ns scratch"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Blocked: Cluster changes go through CI [CFG001]\nPolicy: locked rule from Org policy \
+             — allow patterns cannot override it"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("ignoring \"category_severity\": locked by an earlier config layer"),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("helm uninstall scratch")).0,
+        0
+    );
+    assert_eq!(
+        run_stdout_with_home(home.path(), &bash_input("git push --force origin main")).0,
+        2
+    );
+}
+
+#[test]
+fn user_layer_read_from_toml() {
+    let home = home_with_config("{}");
+    let hooks = home.path().join(".claude/hooks");
+    std::fs::write(
+        hooks.join("safe-bash-user.toml"),
+        "[[deny]]\npattern = '\\bdeploy\\.sh\\b'\nreason = \"Run deploy.sh manually\"\n",
+    )
+    .unwrap();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("./deploy.sh prod"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("Blocked: Run deploy.sh manually [CFG001]\nPolicy: rule from config "),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("add an allow pattern in ")
+            && stderr.contains("safe-bash-user.toml to override"),
+        "stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn ifs_obfuscation_always_blocked() {
+    let home = home_with_config(
+        r#"{"root_escalation":false,"category_severity":{"obfuscation":"warn",
+            "shell-injection":"warn"}}"#,
+    );
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &bash_input("IFS=,;$(echo rm,-rf,build)"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains(
+            "Blocked: Obfuscation: IFS set to \",\" before running $(echo rm,-rf,build) as a \
+             command"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("built-in rule (obfuscation), always enforced"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stderr) = run(&bash_input("cat${IFS}README.md"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.contains("$IFS used as a word separator"),
+        "stderr: {}",
+        stderr
+    );
+    let (code, _) = run(&bash_input(
+        "while IFS=, read -r a b; do echo \"$a\"; done < data.csv",
+    ));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn changed_policy_source_warns_until_trusted() {
+    let home = home_with_config(r#"{"root_escalation":false,"source_pinning":"deny"}"#);
+    let hooks = home.path().join(".claude/hooks");
+    let host = Command::new(binary()).arg("host-id").output().unwrap();
+    let host = String::from_utf8_lossy(&host.stdout).trim().to_string();
+    assert_eq!(host.len(), 16, "host-id: {}", host);
+    std::fs::write(
+        hooks.join("safe-bash-patterns.trusted"),
+        format!("https://raw.example.test/p.json sha256//AAAA {}\n", host),
+    )
+    .unwrap();
+    std::fs::write(
+        hooks.join("safe-bash-patterns.source"),
+        "https://raw.example.test/p.json sha256//BBBB\n",
+    )
+    .unwrap();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("git status"));
+    assert_eq!(code, 0);
+    assert!(
+        stderr.contains("WARNING: remote policy source changed since first use"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains("updates from it are refused"),
+        "stderr: {}",
+        stderr
+    );
+
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("trust-source")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("sha256//BBBB"));
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("git status"));
+    assert_eq!((code, stderr.as_str()), (0, ""));
+
+    // A pin from before fingerprints is still this machine's
+    std::fs::write(
+        hooks.join("safe-bash-patterns.trusted"),
+        "https://raw.example.test/p.json sha256//AAAA\n",
+    )
+    .unwrap();
+    let (_, _, stderr) = run_stdout_with_home(home.path(), &bash_input("git status"));
+    assert!(
+        stderr.contains("changed since first use"),
+        "stderr: {}",
+        stderr
+    );
+
+    // One accepted on another machine is not used, and the observed source is not trusted in its
+    // place
+    std::fs::write(
+        hooks.join("safe-bash-patterns.trusted"),
+        "https://raw.example.test/p.json sha256//AAAA 0123456789abcdef\n",
+    )
+    .unwrap();
+    for _ in 0..2 {
+        let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("git status"));
+        assert_eq!(code, 0);
+        assert!(
+            stderr.contains("was accepted on another machine; updates from it are refused"),
+            "stderr: {}",
+            stderr
+        );
+    }
+    Command::new(binary())
+        .env("HOME", home.path())
+        .arg("trust-source")
+        .output()
+        .unwrap();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("git status"));
+    assert_eq!((code, stderr.as_str()), (0, ""));
+}
+
+#[test]
+fn stats_group_audited_commands_by_shape() {
+    let home = home_with_config(r#"{"root_escalation":false}"#);
+    for (cmd, expected) in [
+        ("rm -rf ./a", 2),
+        ("rm -fr './b'", 2),
+        ("sed -i s/a/b/ f.txt", 0),
+    ] {
+        let (code, _, _) = run_stdout_with_home(home.path(), &bash_input(cmd));
+        assert_eq!(code, expected, "{}", cmd);
+    }
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("stats")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "stdout: {}", stdout);
+    assert!(
+        lines[0].trim_start().starts_with("2  deny 2") && lines[0].ends_with("rm -f -r"),
+        "stdout: {}",
+        stdout
+    );
+    assert!(lines[1].ends_with("sed -i"), "stdout: {}", stdout);
+}
+
+#[test]
+fn doctor_reports_stage_timings() {
+    let home = home_with_config(r#"{"deny":[{"pattern":"^git push","reason":"no pushes"}]}"#);
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("doctor")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "safe-bash-patterns.json\n  profile: standard; 1 deny, 0 allow patterns; \
+             normalization: unicode, continuations, whitespace\n"
+        ),
+        "stdout: {}",
+        stdout
+    );
+    for stage in ["config load", "pattern compile", "evaluation"] {
+        assert!(
+            stdout.contains(&format!("\n  {} ", stage)),
+            "stdout: {}",
+            stdout
+        );
+    }
+}
+
+#[test]
+fn schema_and_lint_check_config_files() {
+    let output = Command::new(binary()).arg("schema").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        schema["properties"]["deny"]["items"]["required"],
+        serde_json::json!(["reason"])
+    );
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let good = dir.path().join("good.json");
+    std::fs::write(
+        &good,
+        r#"{"deny":[{"pattern":"^terraform apply","reason":"CI only"}]}"#,
+    )
+    .unwrap();
+    let output = Command::new(binary())
+        .arg("lint")
+        .arg(&good)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("good.json: OK\n"));
+
+    let bad = dir.path().join("bad.yaml");
+    std::fs::write(
+        &bad,
+        "deny:\n  - pattern: \"(\"\n    reason: broken\n    lockd: true\n",
+    )
+    .unwrap();
+    let output = Command::new(binary())
+        .arg("lint")
+        .arg(&bad)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("bad.yaml: deny[0]: unknown key \"lockd\"\n"),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("bad.yaml: invalid deny regex \"(\""),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_config_runs_rule_examples() {
+    let rules = r#"{"deny":[{"pattern":"^make deploy\\b","reason":"CI deploys",
+        "should_block":["make deploy","cd app && make deploy"],"should_allow":["make test"]}],
+        "allow":[{"pattern":"^git reset --hard\\b","reason":"resets are fine",
+        "should_allow":["git reset --hard HEAD"]}]}"#;
+    let home = home_with_config(rules);
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("test-config")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "stdout: {}", stdout);
+    // Config allow rules do not lift hardcoded ones
+    assert!(
+        stdout.starts_with(
+            "FAIL allow rule \"resets are fine\": should allow \"git reset --hard HEAD\", but it \
+             is blocked: "
+        ),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\n3 passed, 1 failed ("),
+        "stdout: {}",
+        stdout
+    );
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("team.json");
+    std::fs::write(
+        &file,
+        r#"{"deny":[{"pattern":"^make deploy\\b","reason":"CI deploys",
+        "should_block":["make deploy"]}]}"#,
+    )
+    .unwrap();
+    let output = Command::new(binary())
+        .env("HOME", home.path())
+        .arg("test-config")
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("1 passed, 0 failed ("));
+}
+
+#[test]
+fn package_scripts_resolved_and_checked() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("package.json"),
+        r#"{"scripts":{"clean":"rm -rf dist node_modules","build":"tsc -p ."}}"#,
+    )
+    .unwrap();
+    let home = home_with_config(r#"{"root_escalation":false,"ask_unresolved_scripts":true}"#);
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": dir.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("npm run clean"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with("Blocked: npm script \"clean\" runs `rm -rf dist node_modules`: "),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &input("npm run build"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &input("yarn deploy"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains(r#"Cannot check yarn script \"deploy\": not defined in "#),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[test]
+fn read_only_description_on_changing_command_asks() {
+    let input = |description: &str, cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd, "description": description},
+        })
+        .to_string()
+    };
+    let home = home_with_config("{}");
+    let (code, stdout, _) =
+        run_stdout_with_home(home.path(), &input("List files", "ls -la > files.txt"));
+    assert_eq!(code, 0);
+    assert!(
+        stdout.contains(r#""permissionDecision":"ask""#),
+        "stdout: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains(r#"Described as \"List files\", but the command writes to files.txt"#),
+        "stdout: {}",
+        stdout
+    );
+    let (code, stdout, _) = run_stdout_with_home(
+        home.path(),
+        &input("Save the file list", "ls -la > files.txt"),
+    );
+    assert_eq!((code, stdout.as_str()), (0, ""));
+    let off = home_with_config(r#"{"check_descriptions":false}"#);
+    let (code, stdout, _) =
+        run_stdout_with_home(off.path(), &input("List files", "ls -la > files.txt"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+}
+
+#[test]
+fn git_aliases_expanded_from_user_config() {
+    let home = home_with_config("{}");
+    std::fs::write(
+        home.path().join(".gitconfig"),
+        "[alias]\n\tnuke = push --force\n\tlg = log --oneline\n",
+    )
+    .unwrap();
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &bash_input("git nuke origin main"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with("Blocked: git alias \"nuke\" runs `git push --force 'origin' 'main'`: "),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &bash_input("git lg -5"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+}
+
+#[test]
+fn make_recipes_checked_when_enabled() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("Makefile"),
+        "build:\n\tcargo build\n\ndistclean: clean\n\trm -rf $(HOME)\nclean:\n\t@echo cleaning\n",
+    )
+    .unwrap();
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "cwd": dir.path().to_str().unwrap(),
+        })
+        .to_string()
+    };
+    let off = home_with_config("{}");
+    assert_eq!(
+        run_stdout_with_home(off.path(), &input("make distclean")).0,
+        0
+    );
+    let home = home_with_config(r#"{"inspect_makefiles":true}"#);
+    let (code, _, stderr) = run_stdout_with_home(home.path(), &input("make distclean"));
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with("Blocked: make target \"distclean\" runs `rm -rf $HOME`: "),
+        "stderr: {}",
+        stderr
+    );
+    let (code, stdout, _) = run_stdout_with_home(home.path(), &input("make build"));
+    assert_eq!((code, stdout.as_str()), (0, ""));
+}
+
+#[test]
+fn written_script_checked_when_run() {
+    let home = home_with_config("{}");
+    let (code, _, stderr) = run_stdout_with_home(
+        home.path(),
+        &bash_input(
+            "cat > run.sh <<'EOF'\nset -e\ngit push --force origin main\nEOF\nchmod +x run.sh && \
+             ./run.sh",
+        ),
+    );
+    assert_eq!(code, 2);
+    assert!(
+        stderr.starts_with(
+            "Blocked: Script ./run.sh written earlier in this command runs: Destructive: git \
+             force push"
+        ),
+        "stderr: {}",
+        stderr
+    );
+    assert_eq!(
+        run_stdout_with_home(
+            home.path(),
+            &bash_input("printf 'cargo test\\n' > t.sh && sh t.sh")
+        )
+        .0,
+        0
+    );
+}
+
+#[test]
+fn concatenated_payloads_use_first_envelope() {
+    let first = bash_input("rm -rf /");
+    let home = home_with_config("{}");
+    let (code, _, stderr) =
+        run_stdout_with_home(home.path(), &format!("{}{}", first, bash_input("ls")));
+    assert_eq!(code, 2);
+    assert!(stderr.contains("Blocked: "), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("checked the envelope"),
+        "stderr: {}",
+        stderr
+    );
+
+    let (code, stderr) = run(&format!("{}\n\0\0trailing garbage {{", first));
+    assert_eq!(code, 2, "stderr: {}", stderr);
+    let (code, _) = run(&format!("{}\n{}\n", r#"{"event":"PreToolUse"}"#, first));
+    assert_eq!(code, 2);
+    let (code, _) = run(&format!("{}\n{}", bash_input("git status"), "not json"));
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn session_end_summarizes_session_decisions() {
+    let home = home_with_config(r#"{"session_summary":true}"#);
+    let input = |cmd: &str| {
+        serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": cmd},
+            "session_id": "sess-1",
+        })
+        .to_string()
+    };
+    for cmd in ["ls", "rm -rf /", "git status"] {
+        run_stdout_with_home(home.path(), &input(cmd));
+    }
+    let end = |session: &str| {
+        let output = Command::new(binary())
+            .env("HOME", home.path())
+            .arg("session-end")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                let payload = serde_json::json!({
+                    "hook_event_name": "SessionEnd",
+                    "session_id": session,
+                    "cwd": "/tmp",
+                });
+                child
+                    .stdin
+                    .take()
+                    .unwrap()
+                    .write_all(payload.to_string().as_bytes())?;
+                child.wait_with_output()
+            })
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+        )
+    };
+    let (code, stdout) = end("sess-1");
+    assert_eq!(code, Some(0));
+    let message: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let summary = message["systemMessage"].as_str().unwrap();
+    assert!(
+        summary.starts_with(
+            "safe-bash: 3 commands this session — 2 allowed, 0 warned, 0 asked, 1 blocked. \
+             Blocked: "
+        ),
+        "{}",
+        summary
+    );
+    let logs = std::fs::read_dir(home.path().join(".claude/hooks/safe-bash-audit")).unwrap();
+    let audit: String = logs
+        .flatten()
+        .map(|e| std::fs::read_to_string(e.path()).unwrap())
+        .collect();
+    assert!(
+        audit.contains(r#""decision":"session-summary""#)
+            && audit.contains(r#""session":"sess-1""#),
+        "audit: {}",
+        audit
+    );
+    // The tally is consumed: a second SessionEnd has nothing to report
+    assert_eq!(end("sess-1"), (Some(0), String::new()));
+}
+
+#[test]
+fn threat_checklist_is_covered() {
+    let checklist: serde_json::Value = serde_json::from_str(include_str!("threats.json")).unwrap();
+    let scenarios = checklist["scenarios"].as_array().unwrap();
+    for threat in checklist["required"].as_array().unwrap() {
+        assert!(
+            scenarios.iter().any(|s| s["threat"] == *threat),
+            "no scenario covers {}",
+            threat
+        );
+    }
+    let home = home_with_config(r#"{"root_escalation":false}"#);
+    for scenario in scenarios {
+        let command = scenario["command"].as_str().unwrap();
+        let reason = scenario["reason"].as_str().unwrap();
+        let (code, stdout, stderr) = run_stdout_with_home(home.path(), &bash_input(command));
+        let flagged = code == 2 || stdout.contains(r#""permissionDecision":"ask""#);
+        assert!(
+            flagged && format!("{}{}", stdout, stderr).contains(reason),
+            "{} scenario not covered: {}\nstdout: {}\nstderr: {}",
+            scenario["threat"],
+            command,
+            stdout,
+            stderr
+        );
+    }
 }
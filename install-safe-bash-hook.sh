@@ -13,7 +13,7 @@ set -euo pipefail
 #   4. Validates the binary (non-empty, executable format, not an HTML error page)
 #   5. Installs to ~/.claude/hooks/safe-bash-hook (atomic: tmpfile -> mv)
 #   6. Downloads initial safe-bash-patterns.json to ~/.claude/hooks/
-#   7. Merges PreToolUse hook config into ~/.claude/settings.json
+#   7. Merges PreToolUse and SessionEnd hook config into ~/.claude/settings.json
 
 # ---------------------------------------------------------------------------
 # Constants
@@ -201,7 +201,7 @@ else
 fi
 
 # ---------------------------------------------------------------------------
-# Step 7: Merge PreToolUse hook config into ~/.claude/settings.json
+# Step 7: Merge PreToolUse and SessionEnd hook config into ~/.claude/settings.json
 # ---------------------------------------------------------------------------
 
 info "Updating $SETTINGS_JSON..."
@@ -209,7 +209,7 @@ info "Updating $SETTINGS_JSON..."
 HOOK_CONFIG='{
   "PreToolUse": [
     {
-      "matcher": "Bash",
+      "matcher": "Bash|Read|Write|Edit|MultiEdit|NotebookEdit|WebFetch|mcp__.*",
       "hooks": [
         {
           "type": "command",
@@ -217,6 +217,16 @@ HOOK_CONFIG='{
         }
       ]
     }
+  ],
+  "SessionEnd": [
+    {
+      "hooks": [
+        {
+          "type": "command",
+          "command": "~/.claude/hooks/safe-bash-hook session-end"
+        }
+      ]
+    }
   ]
 }'
 
@@ -300,7 +310,8 @@ if [ -f "$SETTINGS_JSON" ]; then
     # Merge hooks config and permission deny list (additive)
     jq --argjson hooks "$HOOK_CONFIG" \
        --argjson deny "$DENY_LIST" \
-      '.hooks.PreToolUse = ((.hooks.PreToolUse // []) + $hooks.PreToolUse | unique_by(.hooks[0].command)) |
+      '.hooks.PreToolUse = ($hooks.PreToolUse + (.hooks.PreToolUse // []) | unique_by(.hooks[0].command)) |
+       .hooks.SessionEnd = ((.hooks.SessionEnd // []) + $hooks.SessionEnd | unique_by(.hooks[0].command)) |
        .permissions.deny = ((.permissions.deny // []) + $deny | unique)' \
       "$SETTINGS_JSON" > "$tmpjson" \
       || fail "Failed to update settings.json."
@@ -332,7 +343,7 @@ printf '  Binary:        %s\n' "$BINARY_TARGET"
 if [ -f "$PATTERNS_TARGET" ]; then
   printf '  Patterns:      %s\n' "$PATTERNS_TARGET"
 fi
-printf '  settings.json: %s (PreToolUse hook + deny list merged)\n' "$SETTINGS_JSON"
+printf '  settings.json: %s (PreToolUse + SessionEnd hooks, deny list merged)\n' "$SETTINGS_JSON"
 printf '\n'
 printf 'The hook inspects every Bash command before execution, blocking destructive\n'
 printf 'compound commands that bypass the deny list (e.g. "git status && rm -rf /").\n'
@@ -23,7 +23,7 @@ HELPER_PATH="/home/jessie/.claude/apollotech-otel-headers.sh"
 HOOK_CONFIG='{
   "PreToolUse": [
     {
-      "matcher": "Bash",
+      "matcher": "Bash|Read|Write|Edit|MultiEdit|NotebookEdit|WebFetch|mcp__.*",
       "hooks": [
         {
           "type": "command",
@@ -31,6 +31,16 @@ HOOK_CONFIG='{
         }
       ]
     }
+  ],
+  "SessionEnd": [
+    {
+      "hooks": [
+        {
+          "type": "command",
+          "command": "~/.claude/hooks/safe-bash-hook session-end"
+        }
+      ]
+    }
   ]
 }'
 
@@ -96,12 +106,13 @@ _jq_hook_merge() {
   local _file="$1"
   jq --argjson hooks "$HOOK_CONFIG" \
      --argjson deny "$DENY_LIST" \
-    '.hooks.PreToolUse = ((.hooks.PreToolUse // []) + $hooks.PreToolUse | unique_by(.hooks[0].command)) |
+    '.hooks.PreToolUse = ($hooks.PreToolUse + (.hooks.PreToolUse // []) | unique_by(.hooks[0].command)) |
+     .hooks.SessionEnd = ((.hooks.SessionEnd // []) + $hooks.SessionEnd | unique_by(.hooks[0].command)) |
      .permissions.deny = ((.permissions.deny // []) + $deny | unique)' \
     "$_file"
 }
 
-# 4b-1: Empty {} → creates hooks.PreToolUse and permissions.deny
+# 4b-1: Empty {} → creates hooks.PreToolUse, hooks.SessionEnd and permissions.deny
 _cfg="$TMPDIR_JQ/settings_4b1.json"
 printf '{}' > "$_cfg"
 result="$(_jq_hook_merge "$_cfg")"
@@ -110,6 +121,10 @@ assert_eq '4b-1: PreToolUse has 1 entry' \
   "1" \
   "$(printf '%s' "$result" | jq '.hooks.PreToolUse | length')"
 
+assert_eq '4b-1: SessionEnd runs safe-bash-hook session-end' \
+  "~/.claude/hooks/safe-bash-hook session-end" \
+  "$(printf '%s' "$result" | jq -r '.hooks.SessionEnd[0].hooks[0].command')"
+
 assert_eq '4b-1: deny has 2 entries' \
   "2" \
   "$(printf '%s' "$result" | jq '.permissions.deny | length')"
@@ -143,6 +158,19 @@ assert_eq '4b-4: deny entries deduplicated' \
   "3" \
   "$(printf '%s' "$result" | jq '.permissions.deny | length')"
 
+# 4b-5: An earlier install's Bash-only entry → replaced by the current matcher
+_cfg="$TMPDIR_JQ/settings_4b5.json"
+printf '{"hooks":{"PreToolUse":[{"matcher":"Bash","hooks":[{"type":"command","command":"~/.claude/hooks/safe-bash-hook"}]}]}}' > "$_cfg"
+result="$(_jq_hook_merge "$_cfg")"
+
+assert_eq '4b-5: safe-bash-hook matcher updated' \
+  "Bash|Read|Write|Edit|MultiEdit|NotebookEdit|WebFetch|mcp__.*" \
+  "$(printf '%s' "$result" | jq -r '.hooks.PreToolUse[0].matcher')"
+
+assert_eq '4b-5: still a single safe-bash-hook entry' \
+  "1" \
+  "$(printf '%s' "$result" | jq '.hooks.PreToolUse | length')"
+
 # ---------------------------------------------------------------------------
 # 4c. Statusline merge
 # ---------------------------------------------------------------------------